@@ -0,0 +1,4 @@
+ALTER TABLE tickets
+    ADD COLUMN received_count INT NOT NULL DEFAULT 0
+                                  CHECK (received_count >= 0
+                                         AND received_count <= count);
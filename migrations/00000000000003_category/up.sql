@@ -0,0 +1,8 @@
+ALTER TABLE tickets
+    ADD COLUMN category INT2 NOT NULL DEFAULT 4
+                             CHECK (category >= 1 AND category <= 4);
+COMMENT ON COLUMN tickets.category
+        IS '1 - IT, \
+            2 - lab supplies, \
+            3 - furniture, \
+            4 - other';
@@ -0,0 +1 @@
+DROP INDEX tickets_created_at_id_idx;
@@ -0,0 +1,2 @@
+CREATE INDEX tickets_created_at_id_idx
+          ON tickets (created_at DESC, id DESC);
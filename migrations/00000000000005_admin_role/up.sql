@@ -0,0 +1,8 @@
+ALTER TABLE users
+    DROP CONSTRAINT users_role_check,
+    ADD CONSTRAINT users_role_check CHECK (role >= 1 AND role <= 4);
+COMMENT ON COLUMN users.role
+        IS '1 - initiator, \
+            2 - purchasing manager, \
+            3 - accounting manager, \
+            4 - admin';
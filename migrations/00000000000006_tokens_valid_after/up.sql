@@ -0,0 +1,9 @@
+CREATE TABLE auth_settings (
+    id                  BOOL PRIMARY KEY DEFAULT TRUE CHECK (id),
+    tokens_valid_after  TIMESTAMPTZ NOT NULL
+);
+COMMENT ON COLUMN auth_settings.tokens_valid_after
+        IS 'Access tokens issued before this moment are rejected';
+
+INSERT INTO auth_settings (tokens_valid_after)
+VALUES ('epoch');
@@ -0,0 +1,4 @@
+ALTER TABLE users
+    ADD COLUMN password_changed_at TIMESTAMPTZ NOT NULL DEFAULT 'epoch';
+COMMENT ON COLUMN users.password_changed_at
+        IS 'Access tokens of the user issued before this moment are rejected';
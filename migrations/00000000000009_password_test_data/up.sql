@@ -0,0 +1,2 @@
+INSERT INTO users (id, name, login, password_hash, role)
+VALUES ('00000000-0000-0000-0000-000000000005', 'Eve', 'eve', 'password', 1);
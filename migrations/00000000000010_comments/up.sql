@@ -0,0 +1,13 @@
+CREATE TABLE comments (
+    id          UUID PRIMARY KEY,
+    ticket_id   UUID NOT NULL REFERENCES tickets(id)
+                              ON UPDATE RESTRICT
+                              ON DELETE CASCADE,
+    author_id   UUID NOT NULL REFERENCES users(id)
+                              ON UPDATE RESTRICT
+                              ON DELETE RESTRICT,
+    text        TEXT NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX comments_ticket_id_created_at_idx
+          ON comments (ticket_id, created_at, id);
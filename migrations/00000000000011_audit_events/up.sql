@@ -0,0 +1,13 @@
+CREATE TABLE audit_events (
+    id          BIGSERIAL PRIMARY KEY,
+    actor_id    UUID NOT NULL REFERENCES users(id)
+                              ON UPDATE RESTRICT
+                              ON DELETE RESTRICT,
+    entity      TEXT NOT NULL,
+    entity_id   UUID NOT NULL,
+    action      TEXT NOT NULL,
+    payload     JSONB NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX audit_events_entity_idx
+          ON audit_events (entity, entity_id, id);
@@ -0,0 +1,2 @@
+ALTER TABLE tickets
+    DROP COLUMN payment_reference;
@@ -0,0 +1,4 @@
+ALTER TABLE tickets
+    ADD COLUMN payment_reference TEXT;
+COMMENT ON COLUMN tickets.payment_reference
+        IS 'Reference of the payment transaction, recorded once paid';
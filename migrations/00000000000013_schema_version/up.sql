@@ -0,0 +1,10 @@
+CREATE TABLE IF NOT EXISTS schema_version (
+    version     TEXT PRIMARY KEY,
+    applied_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+COMMENT ON TABLE schema_version
+        IS 'Names of the applied migrations';
+
+INSERT INTO schema_version (version)
+VALUES ('00000000000013_schema_version')
+ON CONFLICT DO NOTHING;
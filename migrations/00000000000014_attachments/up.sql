@@ -0,0 +1,17 @@
+CREATE TABLE attachments (
+    id            UUID PRIMARY KEY,
+    ticket_id     UUID NOT NULL REFERENCES tickets(id)
+                                ON UPDATE RESTRICT
+                                ON DELETE CASCADE,
+    uploader_id   UUID NOT NULL REFERENCES users(id)
+                                ON UPDATE RESTRICT
+                                ON DELETE RESTRICT,
+    file_name     TEXT NOT NULL,
+    content_type  TEXT NOT NULL,
+    size          BIGINT NOT NULL,
+    created_at    TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX attachments_ticket_id_created_at_idx
+          ON attachments (ticket_id, created_at, id);
+COMMENT ON TABLE attachments
+        IS 'Metadata of the files attached to tickets, stored apart';
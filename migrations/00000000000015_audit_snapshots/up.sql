@@ -0,0 +1,4 @@
+ALTER TABLE audit_events
+    ADD COLUMN snapshot JSONB;
+COMMENT ON COLUMN audit_events.snapshot
+        IS 'State of the entity right after the event, if recorded';
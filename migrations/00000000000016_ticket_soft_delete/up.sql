@@ -0,0 +1,4 @@
+ALTER TABLE tickets
+    ADD COLUMN deleted_at TIMESTAMPTZ;
+COMMENT ON COLUMN tickets.deleted_at
+        IS 'Time the ticket was soft-deleted at, hiding it from listings';
@@ -0,0 +1,2 @@
+ALTER TABLE tickets
+    DROP CONSTRAINT tickets_count_check;
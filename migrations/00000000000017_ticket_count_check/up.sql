@@ -0,0 +1,2 @@
+ALTER TABLE tickets
+    ADD CONSTRAINT tickets_count_check CHECK (count > 0);
@@ -0,0 +1,6 @@
+DROP INDEX tickets_search_russian_idx;
+DROP INDEX tickets_search_simple_idx;
+
+ALTER TABLE tickets
+    DROP COLUMN search_russian,
+    DROP COLUMN search_simple;
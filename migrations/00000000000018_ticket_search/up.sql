@@ -0,0 +1,20 @@
+ALTER TABLE tickets
+    ADD COLUMN search_simple TSVECTOR
+               GENERATED ALWAYS AS (
+                   to_tsvector('simple'::REGCONFIG,
+                               title || ' ' || description)
+               ) STORED,
+    ADD COLUMN search_russian TSVECTOR
+               GENERATED ALWAYS AS (
+                   to_tsvector('russian'::REGCONFIG,
+                               title || ' ' || description)
+               ) STORED;
+COMMENT ON COLUMN tickets.search_simple
+        IS 'Words of the title and description, as they are';
+COMMENT ON COLUMN tickets.search_russian
+        IS 'Words of the title and description, stemmed';
+
+CREATE INDEX tickets_search_simple_idx
+          ON tickets USING GIN (search_simple);
+CREATE INDEX tickets_search_russian_idx
+          ON tickets USING GIN (search_russian);
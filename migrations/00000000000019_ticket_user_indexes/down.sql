@@ -0,0 +1,3 @@
+DROP INDEX tickets_accounting_manager_id_idx;
+DROP INDEX tickets_purchasing_manager_id_idx;
+DROP INDEX tickets_initiator_id_idx;
@@ -0,0 +1,6 @@
+CREATE INDEX tickets_initiator_id_idx
+          ON tickets (initiator_id);
+CREATE INDEX tickets_purchasing_manager_id_idx
+          ON tickets (purchasing_manager_id);
+CREATE INDEX tickets_accounting_manager_id_idx
+          ON tickets (accounting_manager_id);
@@ -0,0 +1,4 @@
+ALTER TABLE users
+    ADD COLUMN email TEXT;
+COMMENT ON COLUMN users.email
+        IS 'Address to send password resets and notifications to';
@@ -0,0 +1,5 @@
+DROP INDEX tickets_updated_at_idx;
+ALTER TABLE tickets
+    DROP COLUMN updated_at;
+ALTER TABLE users
+    DROP COLUMN updated_at;
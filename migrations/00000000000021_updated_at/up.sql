@@ -0,0 +1,15 @@
+ALTER TABLE users
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+COMMENT ON COLUMN users.updated_at
+        IS 'Time the user was last changed at';
+ALTER TABLE tickets
+    ADD COLUMN updated_at TIMESTAMPTZ;
+UPDATE tickets
+   SET updated_at = COALESCE(deleted_at, created_at);
+ALTER TABLE tickets
+    ALTER COLUMN updated_at SET DEFAULT now(),
+    ALTER COLUMN updated_at SET NOT NULL;
+COMMENT ON COLUMN tickets.updated_at
+        IS 'Time the ticket was last changed at, deletion included';
+CREATE INDEX tickets_updated_at_idx
+          ON tickets (updated_at, id);
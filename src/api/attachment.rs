@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{api, db};
+
+pub use crate::db::attachment::Id;
+
+/// Maximum length of [`Attachment::file_name`], in characters.
+pub const FILE_NAME_MAX_LEN: usize = 255;
+
+/// Metadata of a file attached to a ticket.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub id: Id,
+    pub file_name: String,
+    pub content_type: String,
+
+    /// Size of the file, in bytes.
+    pub size: usize,
+
+    pub uploader: api::user::Id,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+}
+
+impl From<db::Attachment> for Attachment {
+    fn from(attachment: db::Attachment) -> Self {
+        Self {
+            id: attachment.id,
+            file_name: attachment.file_name,
+            content_type: attachment.content_type,
+            size: attachment.size,
+            uploader: attachment.uploader,
+            created_at: attachment.created_at,
+        }
+    }
+}
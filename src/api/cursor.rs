@@ -0,0 +1,308 @@
+//! Opaque cursors continuing a list of tickets past its page.
+//!
+//! A cursor is the creation time and the ID of the last ticket of a page,
+//! packed into 32 bytes and encoded as base64url without padding. Cursors
+//! handed out by the server are additionally signed with an HMAC, so clients
+//! can't forge them to skip the filters of a listing.
+
+use std::fmt;
+
+use jsonwebtoken::{crypto, Algorithm, DecodingKey, EncodingKey};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::api::ticket;
+
+/// Length of a decoded cursor, in bytes.
+const LEN: usize = 32;
+
+/// Length of an encoded unsigned cursor, in characters.
+const ENCODED_LEN: usize = (LEN * 8).div_ceil(6);
+
+/// Algorithm signed cursors are signed with.
+const ALGORITHM: Algorithm = Algorithm::HS256;
+
+/// Separator of a signed cursor and its signature.
+const SIGNATURE_SEPARATOR: char = '.';
+
+const ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encodes the cursor pointing past the ticket with the given `id`, which was
+/// created at `created_at`.
+pub fn encode(created_at: OffsetDateTime, id: ticket::Id) -> String {
+    let mut bytes = [0; LEN];
+    let (time, id_bytes) = bytes.split_at_mut(LEN / 2);
+    time.copy_from_slice(&created_at.unix_timestamp_nanos().to_be_bytes());
+    id_bytes.copy_from_slice(&u128::from(id).to_be_bytes());
+    to_base64url(&bytes)
+}
+
+/// Decodes the cursor produced by [`encode()`].
+pub fn decode(
+    cursor: &str,
+) -> Result<(OffsetDateTime, ticket::Id), CursorError> {
+    let bytes = from_base64url(cursor)?;
+    let (time, id) = bytes.split_at(LEN / 2);
+    let time = i128::from_be_bytes(time.try_into().unwrap());
+    let id = u128::from_be_bytes(id.try_into().unwrap());
+    let created_at = OffsetDateTime::from_unix_timestamp_nanos(time)
+        .map_err(|_| CursorError::OutOfRange)?;
+    Ok((created_at, id.into()))
+}
+
+/// Encodes the cursor like [`encode()`] does, signing it with the `key`.
+pub fn encode_signed(
+    created_at: OffsetDateTime,
+    id: ticket::Id,
+    key: &EncodingKey,
+) -> String {
+    let cursor = encode(created_at, id);
+    let signature = crypto::sign(cursor.as_bytes(), key, ALGORITHM)
+        .expect("HMAC accepts keys of any length");
+    format!("{cursor}{SIGNATURE_SEPARATOR}{signature}")
+}
+
+/// Decodes the cursor produced by [`encode_signed()`], verifying its
+/// signature with the `key`.
+pub fn decode_signed(
+    cursor: &str,
+    key: &DecodingKey,
+) -> Result<(OffsetDateTime, ticket::Id), CursorError> {
+    let (cursor, signature) = cursor
+        .split_once(SIGNATURE_SEPARATOR)
+        .ok_or(CursorError::Unsigned)?;
+    let is_valid = crypto::verify(signature, cursor.as_bytes(), key, ALGORITHM)
+        .unwrap_or(false);
+    if !is_valid {
+        return Err(CursorError::BadSignature);
+    }
+    decode(cursor)
+}
+
+/// Encodes the `bytes` as base64url without padding.
+fn to_base64url(bytes: &[u8; LEN]) -> String {
+    let mut encoded = String::with_capacity(ENCODED_LEN);
+    for chunk in bytes.chunks(3) {
+        let mut group = [0; 3];
+        group[..chunk.len()].copy_from_slice(chunk);
+        let group = u32::from_be_bytes([0, group[0], group[1], group[2]]);
+        for i in 0..=chunk.len() {
+            let sextet = (group >> (18 - 6 * i)) & 0x3f;
+            encoded.push(char::from(ALPHABET[sextet as usize]));
+        }
+    }
+    encoded
+}
+
+/// Decodes the base64url without padding into exactly [`LEN`] bytes.
+///
+/// Rejects non-canonical encodings, whose unused trailing bits aren't zero,
+/// so every cursor has a single representation.
+fn from_base64url(encoded: &str) -> Result<[u8; LEN], CursorError> {
+    if encoded.len() != ENCODED_LEN {
+        return Err(CursorError::Length);
+    }
+
+    let mut bytes = [0; LEN];
+    let mut written = 0;
+    for chunk in encoded.as_bytes().chunks(4) {
+        let mut group = 0;
+        for (i, c) in chunk.iter().enumerate() {
+            let sextet = ALPHABET
+                .iter()
+                .position(|a| a == c)
+                .ok_or(CursorError::Encoding)?;
+            group |= (sextet as u32) << (18 - 6 * i);
+        }
+        let [_, decoded @ ..] = group.to_be_bytes();
+        let len = chunk.len() - 1;
+        if decoded[len..].iter().any(|b| *b != 0) {
+            return Err(CursorError::Encoding);
+        }
+        bytes[written..written + len].copy_from_slice(&decoded[..len]);
+        written += len;
+    }
+    Ok(bytes)
+}
+
+/// Error of decoding a cursor.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CursorError {
+    /// Cursor isn't valid base64url.
+    Encoding,
+
+    /// Cursor doesn't decode into the expected number of bytes.
+    Length,
+
+    /// Cursor refers to a time which can't be represented.
+    OutOfRange,
+
+    /// Cursor expected to be signed has no signature.
+    Unsigned,
+
+    /// Signature of the cursor doesn't match its contents.
+    BadSignature,
+}
+
+impl fmt::Display for CursorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Encoding => "cursor isn't valid base64url",
+            Self::Length => "cursor has an invalid length",
+            Self::OutOfRange => "cursor time is out of range",
+            Self::Unsigned => "cursor isn't signed",
+            Self::BadSignature => "cursor signature doesn't match",
+        })
+    }
+}
+
+/// Rejection of a list request continuing from a cursor which can't be
+/// decoded.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvalidCursor {
+    /// Always `INVALID_CURSOR`.
+    pub code: String,
+}
+
+#[cfg(test)]
+mod spec {
+    use rand::Rng as _;
+    use time::OffsetDateTime;
+
+    use super::{
+        decode, decode_signed, encode, encode_signed, CursorError, ENCODED_LEN,
+    };
+    use crate::api::ticket;
+
+    const ITERATIONS: usize = 1000;
+
+    const SECRET: &[u8] = b"secret";
+
+    /// Generates a random valid cursor position.
+    fn position(rng: &mut impl rand::Rng) -> (OffsetDateTime, ticket::Id) {
+        let midnight = |d: time::Date| d.midnight().assume_utc();
+        let min = midnight(time::Date::MIN).unix_timestamp_nanos();
+        let max = midnight(time::Date::MAX).unix_timestamp_nanos();
+        let time =
+            OffsetDateTime::from_unix_timestamp_nanos(rng.gen_range(min..max))
+                .unwrap();
+        (time, rng.gen::<u128>().into())
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..ITERATIONS {
+            let (time, id) = position(&mut rng);
+            let cursor = encode(time, id);
+
+            assert_eq!(cursor.len(), ENCODED_LEN);
+            assert_eq!(decode(&cursor), Ok((time, id)));
+        }
+    }
+
+    #[test]
+    fn round_trips_signed() {
+        let encoding = jsonwebtoken::EncodingKey::from_secret(SECRET);
+        let decoding = jsonwebtoken::DecodingKey::from_secret(SECRET);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..ITERATIONS {
+            let (time, id) = position(&mut rng);
+            let cursor = encode_signed(time, id, &encoding);
+
+            assert_eq!(decode_signed(&cursor, &decoding), Ok((time, id)));
+        }
+    }
+
+    #[test]
+    fn rejects_truncated() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..ITERATIONS {
+            let (time, id) = position(&mut rng);
+            let cursor = encode(time, id);
+            let len = rng.gen_range(0..cursor.len());
+
+            assert_eq!(decode(&cursor[..len]), Err(CursorError::Length));
+        }
+    }
+
+    #[test]
+    fn rejects_non_base64url() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..ITERATIONS {
+            let (time, id) = position(&mut rng);
+            let mut cursor = encode(time, id).into_bytes();
+            let at = rng.gen_range(0..cursor.len());
+            cursor[at] = *b"+/=.!".get(rng.gen_range(0..5)).unwrap();
+            let cursor = String::from_utf8(cursor).unwrap();
+
+            assert_eq!(decode(&cursor), Err(CursorError::Encoding));
+        }
+    }
+
+    #[test]
+    fn rejects_non_canonical() {
+        let cursor = encode(OffsetDateTime::UNIX_EPOCH, ticket::Id::default());
+        let cursor = format!("{}B", &cursor[..cursor.len() - 1]);
+
+        assert_eq!(decode(&cursor), Err(CursorError::Encoding));
+    }
+
+    #[test]
+    fn rejects_corrupted_signed() {
+        let encoding = jsonwebtoken::EncodingKey::from_secret(SECRET);
+        let decoding = jsonwebtoken::DecodingKey::from_secret(SECRET);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..ITERATIONS {
+            let (time, id) = position(&mut rng);
+            let mut cursor = encode_signed(time, id, &encoding).into_bytes();
+            let at = rng.gen_range(0..cursor.len());
+            let replacement = loop {
+                let c = super::ALPHABET[rng.gen_range(0..64)];
+                if c != cursor[at] {
+                    break c;
+                }
+            };
+            cursor[at] = replacement;
+            let cursor = String::from_utf8(cursor).unwrap();
+
+            assert!(decode_signed(&cursor, &decoding).is_err(), "{cursor}");
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_signed() {
+        let encoding = jsonwebtoken::EncodingKey::from_secret(SECRET);
+        let decoding = jsonwebtoken::DecodingKey::from_secret(SECRET);
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..ITERATIONS {
+            let (time, id) = position(&mut rng);
+            let cursor = encode_signed(time, id, &encoding);
+            let len = rng.gen_range(0..cursor.len());
+
+            assert!(decode_signed(&cursor[..len], &decoding).is_err());
+        }
+    }
+
+    #[test]
+    fn rejects_unsigned_and_foreign_signatures() {
+        let decoding = jsonwebtoken::DecodingKey::from_secret(SECRET);
+        let foreign = jsonwebtoken::EncodingKey::from_secret(b"other");
+        let time = OffsetDateTime::now_utc();
+        let id = ticket::Id::new();
+
+        assert_eq!(
+            decode_signed(&encode(time, id), &decoding),
+            Err(CursorError::Unsigned),
+        );
+        assert_eq!(
+            decode_signed(&encode_signed(time, id, &foreign), &decoding),
+            Err(CursorError::BadSignature),
+        );
+    }
+}
@@ -0,0 +1,45 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::ticket::Status;
+
+/// Summary of the tickets relevant to the current user, tagged by their
+/// role, so clients may render the landing page of the role.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(
+    rename_all = "SCREAMING_SNAKE_CASE",
+    rename_all_fields = "camelCase",
+    tag = "role"
+)]
+pub enum Dashboard {
+    /// Tickets of the initiator.
+    Initiator {
+        tickets_by_status: HashMap<Status, usize>,
+    },
+
+    PurchasingManager {
+        /// Tickets awaiting to be confirmed or denied by any purchasing
+        /// manager.
+        pending_confirmation: usize,
+
+        /// Tickets confirmed by the manager, paid or not.
+        confirmed: usize,
+
+        /// Sum of the prices of the tickets confirmed by the manager.
+        confirmed_total_price: f64,
+    },
+
+    AccountingManager {
+        /// Confirmed tickets awaiting to be paid.
+        awaiting_payment: usize,
+
+        /// Sum of the prices of the tickets awaiting to be paid.
+        awaiting_payment_total_price: f64,
+    },
+
+    /// All the tickets.
+    Admin {
+        tickets_by_status: HashMap<Status, usize>,
+    },
+}
@@ -0,0 +1,111 @@
+//! Portable dump of the users and tickets, for backing them up without
+//! access to the database itself.
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{api, db};
+
+/// Users and tickets changed at or after the requested time, or all of them.
+///
+/// Taking [`Export::exported_at`] as the time to export the changes since
+/// next time makes exports incremental. Rows changed while exporting may be
+/// exported twice, but never skipped.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Export {
+    #[serde(with = "time::serde::rfc3339")]
+    pub exported_at: OffsetDateTime,
+
+    pub users: Vec<User>,
+    pub tickets: Vec<Ticket>,
+}
+
+/// Exported user, without the password hash.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub id: api::user::Id,
+    pub name: String,
+    pub login: String,
+    pub role: api::user::Role,
+    pub email: Option<String>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub password_changed_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+}
+
+impl From<db::user::UserWithTimes> for User {
+    fn from(
+        db::user::UserWithTimes { user, updated_at }: db::user::UserWithTimes,
+    ) -> Self {
+        Self {
+            id: user.id,
+            name: user.name,
+            login: user.login,
+            role: user.role,
+            email: user.email,
+            password_changed_at: user.password_changed_at,
+            updated_at,
+        }
+    }
+}
+
+/// Exported ticket, referring to its users by their IDs.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ticket {
+    pub id: api::ticket::Id,
+    pub title: String,
+    pub description: String,
+    pub status: api::ticket::Status,
+    pub category: api::ticket::Category,
+    pub count: usize,
+    pub received_count: usize,
+    pub price: Option<f64>,
+    pub payment_reference: Option<String>,
+    pub initiator: api::user::Id,
+    pub purchasing_manager: Option<api::user::Id>,
+    pub accounting_manager: Option<api::user::Id>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub updated_at: OffsetDateTime,
+
+    /// Time the ticket was deleted at, if it was.
+    #[serde(with = "time::serde::rfc3339::option")]
+    pub deleted_at: Option<OffsetDateTime>,
+}
+
+impl From<db::ticket::TicketWithTimes> for Ticket {
+    fn from(
+        db::ticket::TicketWithTimes {
+            ticket,
+            updated_at,
+            deleted_at,
+        }: db::ticket::TicketWithTimes,
+    ) -> Self {
+        Self {
+            id: ticket.id,
+            title: ticket.title,
+            description: ticket.description,
+            status: ticket.status,
+            category: ticket.category,
+            count: ticket.count,
+            received_count: ticket.received_count,
+            price: ticket.price,
+            payment_reference: ticket.payment_reference,
+            initiator: ticket.initiator,
+            purchasing_manager: ticket.purchasing_manager,
+            accounting_manager: ticket.accounting_manager,
+            created_at: ticket.created_at,
+            updated_at,
+            deleted_at,
+        }
+    }
+}
@@ -1,4 +1,16 @@
+pub mod attachment;
+pub mod cursor;
+pub mod dashboard;
+pub mod export;
 pub mod ticket;
 pub mod user;
+pub mod validation;
+pub mod version;
 
-pub use self::{ticket::Ticket, user::User};
+pub use self::{
+    attachment::Attachment,
+    dashboard::Dashboard,
+    ticket::Ticket,
+    user::{Permissions, User},
+    version::Version,
+};
@@ -1,26 +1,303 @@
 use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
 
-use crate::api;
+use crate::{api, db};
 
-pub use crate::db::ticket::{Id, Status};
+pub use crate::db::ticket::{Category, Id, SortBy, SortDirection, Status};
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+/// Maximum length of [`Ticket::title`], in characters.
+pub const TITLE_MAX_LEN: usize = 200;
+
+/// Maximum length of [`Ticket::description`], in characters.
+pub const DESCRIPTION_MAX_LEN: usize = 5000;
+
+/// Maximum length of [`Ticket::payment_reference`], in characters.
+pub const PAYMENT_REFERENCE_MAX_LEN: usize = 100;
+
+/// Maximum number of tickets a [`BulkOp`] may be applied to at once.
+pub const BULK_TRANSITION_MAX_IDS: usize = 100;
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Ticket {
     pub id: Id,
     pub title: String,
     pub description: String,
     pub status: Status,
+    pub category: Category,
     pub count: usize,
+    pub received_count: usize,
+    pub fully_received: bool,
     pub price: Option<f64>,
+    pub payment_reference: Option<String>,
     pub initiator: api::User,
     pub purchasing_manager: Option<api::User>,
     pub accounting_manager: Option<api::User>,
+
+    /// Files attached to the ticket.
+    ///
+    /// Only returned for a single ticket, to its participants.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachments: Option<Vec<api::Attachment>>,
+}
+
+/// Converts the ticket along with its initiator and managers, in that order.
+///
+/// Leaves the [`Ticket::attachments`] out, as those are only returned for a
+/// single ticket to its participants.
+impl From<(&db::Ticket, &db::User, Option<&db::User>, Option<&db::User>)>
+    for Ticket
+{
+    fn from(
+        (ticket, initiator, purchasing_manager, accounting_manager): (
+            &db::Ticket,
+            &db::User,
+            Option<&db::User>,
+            Option<&db::User>,
+        ),
+    ) -> Self {
+        Self {
+            id: ticket.id,
+            title: ticket.title.clone(),
+            description: ticket.description.clone(),
+            status: ticket.status,
+            category: ticket.category,
+            count: ticket.count,
+            received_count: ticket.received_count,
+            fully_received: ticket.received_count == ticket.count,
+            price: ticket.price,
+            payment_reference: ticket.payment_reference.clone(),
+            initiator: initiator.into(),
+            purchasing_manager: purchasing_manager.map(Into::into),
+            accounting_manager: accounting_manager.map(Into::into),
+            attachments: None,
+        }
+    }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct List {
     pub tickets: Vec<Ticket>,
     pub total_count: usize,
+
+    /// Opaque cursor to pass as `before` to get the next page, produced by
+    /// [`api::cursor`].
+    pub next_cursor: Option<String>,
+}
+
+/// Page of the tickets a manager is assigned to.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignedList {
+    pub tickets: Vec<AssignedTicket>,
+    pub total_count: usize,
+}
+
+/// [`Ticket`] along with the role the manager listing it is assigned in.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AssignedTicket {
+    #[serde(flatten)]
+    pub ticket: Ticket,
+    pub role_on_ticket: RoleOnTicket,
+}
+
+/// Role a manager is assigned to a [`Ticket`] in.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum RoleOnTicket {
+    /// Confirmed or denied the ticket.
+    PurchasingManager,
+
+    /// Paid for the ticket.
+    AccountingManager,
+}
+
+/// Number of tickets matching the requested filters.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Count {
+    pub count: usize,
+}
+
+/// Tickets created by an import, in the order of the CSV rows.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Imported {
+    pub ids: Vec<Id>,
+}
+
+/// Rejection of an import, listing all the offending lines of the CSV, so
+/// they can be fixed at once.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ImportErrors {
+    pub lines: Vec<ImportLineErrors>,
+}
+
+/// Violations found in a row of an imported CSV.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ImportLineErrors {
+    /// Number of the line the row starts on, the header being the first.
+    pub line: usize,
+
+    /// Violations of the columns of the row, named as in the header, or of
+    /// the whole `row`.
+    #[serde(flatten)]
+    pub errors: api::validation::Errors,
+}
+
+/// Rejection of a ticket edit requesting an operation which doesn't exist.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnknownOp {
+    /// Always `UNKNOWN_OP`.
+    pub code: String,
+
+    /// Requested operation, unless it's missing or isn't a string.
+    pub op: Option<String>,
+
+    /// All the operations which are accepted instead.
+    pub valid_ops: Vec<String>,
+}
+
+/// Transition applied to several [`Ticket`]s at once.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BulkOp {
+    Confirm,
+    Deny,
+}
+
+/// Outcome of a [`BulkOp`] for a single [`Ticket`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct BulkTransitionResult {
+    pub id: Id,
+    pub ok: bool,
+
+    /// Reason the [`Ticket`] isn't transitioned, unless it is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<BulkTransitionError>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BulkTransitionError {
+    /// No [`Ticket`] has the ID.
+    NotFound,
+
+    /// Caller may not apply the [`BulkOp`] to the [`Ticket`] in its status.
+    CannotBeTransitioned,
+
+    /// [`Ticket`] is changed by a concurrent request meanwhile.
+    StatusChanged,
+}
+
+/// Recorded change of a ticket, as listed in its history.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    pub id: i64,
+    pub action: String,
+    pub actor: api::user::Id,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub created_at: OffsetDateTime,
+
+    /// Fields of the ticket altered by this change.
+    ///
+    /// Only returned on request, and only if known: the changes recorded
+    /// before the states of tickets were aren't.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changes: Option<Vec<FieldChange>>,
+}
+
+/// Field of a ticket altered by a change, along with its values before and
+/// after it.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: serde_json::Value,
+    pub after: serde_json::Value,
+}
+
+#[cfg(test)]
+mod from_spec {
+    use time::OffsetDateTime;
+
+    use super::{Category, Id, Status, Ticket};
+    use crate::{api, db};
+
+    fn user(id: u128, name: &str, role: api::user::Role) -> db::User {
+        db::User {
+            id: api::user::Id::from(id),
+            name: name.into(),
+            role,
+            login: name.to_lowercase(),
+            password_hash: db::user::PasswordHash::new("password"),
+            password_changed_at: OffsetDateTime::UNIX_EPOCH,
+            email: Some(format!("{}@example.com", name.to_lowercase())),
+        }
+    }
+
+    fn ticket() -> db::Ticket {
+        db::Ticket {
+            id: Id::new(),
+            title: "Ticket".into(),
+            description: "Description".into(),
+            status: Status::Requested,
+            category: Category::Other,
+            count: 2,
+            received_count: 2,
+            price: Some(100.0),
+            payment_reference: Some("Reference".into()),
+            initiator: api::user::Id::from(1),
+            purchasing_manager: Some(api::user::Id::from(2)),
+            accounting_manager: Some(api::user::Id::from(3)),
+            created_at: OffsetDateTime::UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn omits_user_email() {
+        let alice = user(1, "Alice", api::user::Role::Initiator);
+
+        let converted = api::User::from(&alice);
+
+        assert_eq!(
+            converted,
+            api::User {
+                id: api::user::Id::from(1),
+                name: "Alice".into(),
+                role: api::user::Role::Initiator,
+                email: None,
+            },
+        );
+    }
+
+    #[test]
+    fn converts_ticket_with_any_managers() {
+        let ticket = ticket();
+        let alice = user(1, "Alice", api::user::Role::Initiator);
+        let bob = user(2, "Bob", api::user::Role::PurchasingManager);
+        let charlie = user(3, "Charlie", api::user::Role::AccountingManager);
+
+        for (pm, am) in [
+            (None, None),
+            (Some(&bob), None),
+            (None, Some(&charlie)),
+            (Some(&bob), Some(&charlie)),
+        ] {
+            let converted = Ticket::from((&ticket, &alice, pm, am));
+
+            assert_eq!(converted.id, ticket.id);
+            assert_eq!(converted.title, ticket.title);
+            assert_eq!(converted.description, ticket.description);
+            assert_eq!(converted.price, ticket.price);
+            assert_eq!(converted.payment_reference, ticket.payment_reference);
+            assert!(converted.fully_received);
+            assert_eq!(converted.initiator, api::User::from(&alice));
+            assert_eq!(converted.purchasing_manager, pm.map(Into::into));
+            assert_eq!(converted.accounting_manager, am.map(Into::into));
+            assert_eq!(converted.attachments, None);
+        }
+    }
 }
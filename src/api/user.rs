@@ -1,10 +1,146 @@
 use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::{api, db};
 
 pub use crate::db::user::{Id, PasswordHash, Role};
 
+/// Maximum length of [`User::name`], in characters.
+pub const NAME_MAX_LEN: usize = 100;
+
+/// Maximum length of [`User::email`], in characters, as limited by SMTP.
+pub const EMAIL_MAX_LEN: usize = 254;
+
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct User {
     pub id: Id,
     pub name: String,
     pub role: Role,
+
+    /// Only returned to the user itself, never along with the tickets the
+    /// user is referred to by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+}
+
+/// Leaves the [`User::email`] out, as users are mostly shown to others.
+impl From<&db::User> for User {
+    fn from(user: &db::User) -> Self {
+        Self {
+            id: user.id,
+            name: user.name.clone(),
+            role: user.role,
+            email: None,
+        }
+    }
+}
+
+impl From<db::user::UserSummary> for User {
+    fn from(user: db::user::UserSummary) -> Self {
+        Self {
+            id: user.id,
+            name: user.name,
+            role: user.role,
+            email: None,
+        }
+    }
+}
+
+/// Indicates whether the `email` looks like an address: has no whitespace
+/// and has an at-sign with non-empty parts on both its sides.
+///
+/// Whether it's deliverable is left for the mail server to tell.
+pub fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && !domain.is_empty()
+        && !email.chars().any(char::is_whitespace)
+}
+
+/// Maximum number of tickets listed in [`UserHasTickets::ticket_ids`].
+pub const USER_TICKETS_MAX_LISTED: usize = 100;
+
+/// Rejection of deleting a user the tickets still refer to.
+///
+/// Users are never deleted along with their tickets, nor are the tickets left
+/// referring to nobody: the tickets have to be reassigned or purged first.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserHasTickets {
+    /// Always `USER_HAS_TICKETS`.
+    pub code: String,
+
+    /// Number of the tickets referring to the user in any role.
+    pub ticket_count: usize,
+
+    /// IDs of up to [`USER_TICKETS_MAX_LISTED`] of these tickets, the latest
+    /// first.
+    pub ticket_ids: Vec<api::ticket::Id>,
+}
+
+/// Rejection of signing in, or of the access token a request is made with,
+/// explaining it to the user.
+///
+/// Signing in with an unknown login is rejected exactly as with a wrong
+/// password, so it doesn't tell which logins exist.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthFailure {
+    /// One of `WRONG_CREDENTIALS`, `INVALID_TOKEN`, `UNKNOWN_CLIENT` or
+    /// `INTERNAL_ERROR`.
+    pub code: String,
+
+    /// Human-readable description of the failure.
+    pub message: String,
+}
+
+/// Access token issued on signing in, along with its lifetime.
+///
+/// Times are whole seconds, exactly as encoded into the token.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthResponse {
+    pub token: String,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub issued_at: OffsetDateTime,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub expires_at: OffsetDateTime,
+}
+
+/// Whether a login may be taken by a new user.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LoginAvailability {
+    pub available: bool,
+}
+
+/// Actions the current user may perform, so clients know which of them to
+/// offer.
+///
+/// Derived from the [`Role`] alone, so an action permitted here may still be
+/// refused for a particular ticket, like confirming an already denied one.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Permissions {
+    pub can_create_ticket: bool,
+    pub can_confirm_ticket: bool,
+    pub can_deny_ticket: bool,
+    pub can_mark_as_paid: bool,
+    pub can_manage_users: bool,
+}
+
+impl Permissions {
+    /// Returns the [`Permissions`] of a user with the `role`.
+    pub fn of(role: Role) -> Self {
+        Self {
+            can_create_ticket: role == Role::Initiator,
+            can_confirm_ticket: role == Role::PurchasingManager,
+            can_deny_ticket: role == Role::PurchasingManager,
+            can_mark_as_paid: role == Role::AccountingManager,
+            can_manage_users: role == Role::Admin,
+        }
+    }
 }
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+/// All the violations found in a request, reported at once, so every
+/// invalid input can be highlighted.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Errors {
+    pub errors: Vec<FieldError>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub code: Code,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Code {
+    InvalidFormat,
+    MustBePositive,
+    MustNotBeEmpty,
+    NotFound,
+    TooLarge,
+    TooLong,
+    TooSmall,
+    WrongRole,
+}
+
+/// Accumulates violations across all the fields of a request.
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Errors,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a violation of the `field` with the `code`, unless `valid`.
+    pub fn check(&mut self, field: &str, valid: bool, code: Code) -> &mut Self {
+        if !valid {
+            self.errors.errors.push(FieldError {
+                field: field.to_owned(),
+                code,
+            });
+        }
+        self
+    }
+
+    /// Returns all the recorded violations, if any.
+    pub fn finish(self) -> Result<(), Errors> {
+        if self.errors.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+}
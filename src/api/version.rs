@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+/// Build and uptime of the running server.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Version {
+    /// Version of the crate the server is built from.
+    pub version: String,
+
+    /// Git commit the server is built from, if it was known at build time.
+    pub git_sha: Option<String>,
+
+    #[serde(with = "time::serde::rfc3339")]
+    pub started_at: OffsetDateTime,
+
+    pub uptime_secs: u64,
+}
@@ -0,0 +1,173 @@
+use std::{
+    error::Error as StdError,
+    io,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use aws_sdk_s3::{error::SdkError, primitives::ByteStream};
+use bytes::Bytes;
+use derive_more::{Display, From};
+use tokio::fs;
+
+use crate::config;
+
+/// Storage of binary objects, like the files attached to tickets, kept
+/// apart from the database.
+#[async_trait]
+pub trait Store: Send + Sync {
+    /// Stores the `data` under the `key`, replacing the one stored before.
+    async fn put(
+        &self,
+        key: &str,
+        content_type: &str,
+        data: Bytes,
+    ) -> Result<(), Error>;
+
+    /// Returns the data stored under the `key`, if any.
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, Error>;
+}
+
+/// Creates the [`Store`] described by the `config`.
+pub async fn connect(config: &config::BlobStorage) -> Arc<dyn Store> {
+    match config {
+        config::BlobStorage::Local { dir } => Arc::new(LocalDir::new(dir)),
+        config::BlobStorage::S3 {
+            bucket,
+            endpoint_url,
+        } => Arc::new(S3::new(bucket, endpoint_url.as_deref()).await),
+    }
+}
+
+/// [`Store`] keeping every object in a file of a local directory, named after
+/// its key.
+pub struct LocalDir {
+    dir: PathBuf,
+}
+
+impl LocalDir {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Returns the path of the file the object under the `key` is stored in.
+    ///
+    /// Keys are only ever generated by this application, but are checked to
+    /// be relative anyway, so no object may escape the directory.
+    fn path(&self, key: &str) -> Result<PathBuf, Error> {
+        let relative = Path::new(key);
+        let is_plain = relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+        if !is_plain {
+            return Err(Error::InvalidKey(key.to_owned()));
+        }
+        Ok(self.dir.join(relative))
+    }
+}
+
+#[async_trait]
+impl Store for LocalDir {
+    async fn put(&self, key: &str, _: &str, data: Bytes) -> Result<(), Error> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(path, data).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, Error> {
+        match fs::read(self.path(key)?).await {
+            Ok(data) => Ok(Some(data.into())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// [`Store`] keeping objects in an S3 bucket.
+///
+/// Credentials and region are resolved from the environment, the same way
+/// the AWS CLI does.
+pub struct S3 {
+    client: aws_sdk_s3::Client,
+    bucket: String,
+}
+
+impl S3 {
+    /// Creates an [`S3`] store of the `bucket`, served at the `endpoint_url`
+    /// if it's not AWS itself.
+    pub async fn new(bucket: &str, endpoint_url: Option<&str>) -> Self {
+        let sdk_config =
+            aws_config::load_defaults(aws_config::BehaviorVersion::latest())
+                .await;
+        let mut config = aws_sdk_s3::config::Builder::from(&sdk_config);
+        if let Some(url) = endpoint_url {
+            // S3-compatible storages rarely support virtual-hosted buckets.
+            config = config.endpoint_url(url).force_path_style(true);
+        }
+        Self {
+            client: aws_sdk_s3::Client::from_conf(config.build()),
+            bucket: bucket.to_owned(),
+        }
+    }
+}
+
+#[async_trait]
+impl Store for S3 {
+    async fn put(
+        &self,
+        key: &str,
+        content_type: &str,
+        data: Bytes,
+    ) -> Result<(), Error> {
+        self.client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .body(ByteStream::from(data))
+            .send()
+            .await
+            .map_err(|e| Error::S3(e.into()))?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Bytes>, Error> {
+        let output = match self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(output) => output,
+            Err(SdkError::ServiceError(e)) if e.err().is_no_such_key() => {
+                return Ok(None);
+            }
+            Err(e) => return Err(Error::S3(e.into())),
+        };
+        let data = output
+            .body
+            .collect()
+            .await
+            .map_err(|e| Error::S3(e.into()))?;
+        Ok(Some(data.into_bytes()))
+    }
+}
+
+#[derive(Debug, Display, From)]
+pub enum Error {
+    #[display("I/O error: {_0}")]
+    #[from]
+    Io(io::Error),
+    #[display("S3 error: {_0}")]
+    S3(Box<dyn StdError + Send + Sync>),
+    #[display("invalid blob key `{_0}`")]
+    InvalidKey(String),
+}
+
+impl StdError for Error {}
@@ -1,33 +1,426 @@
-use std::{net, time};
+use std::{collections::BTreeMap, net, num::NonZeroUsize, path::PathBuf, time};
 
+use derive_more::Display;
 use serde::Deserialize;
 
+use crate::db;
+
 #[derive(Deserialize)]
 pub struct Config {
     pub db: Db,
     pub http: Http,
     pub jwt: Jwt,
+
+    /// Settings of the files attached to tickets.
+    #[serde(default)]
+    pub attachments: Attachments,
+
+    /// Limits of the created tickets.
+    #[serde(default)]
+    pub tickets: Tickets,
+
+    #[serde(default)]
+    pub maintenance: Maintenance,
+}
+
+impl Config {
+    /// Checks the invariants of this [`Config`] not enforced by its
+    /// deserialization.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self.http.cors.validate()?;
+        self.http.validate()?;
+        self.tickets.validate()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+pub enum ValidationError {
+    #[display(
+        "`http.cors.allowed_origins` cannot combine `\"*\"` with explicit \
+         origins"
+    )]
+    AnyOriginWithExplicitOrigins,
+    #[display(
+        "`http.cors.allow_credentials` cannot be enabled while any origin is \
+         allowed"
+    )]
+    CredentialsWithAnyOrigin,
+    #[display("`http.cors.expose_headers` must contain header names only")]
+    InvalidExposedHeader,
+    #[display(
+        "`http.base_path` must start with `/` and not end with it, like \
+         `/api`"
+    )]
+    InvalidBasePath,
+    #[display(
+        "`tickets.min_count` must be positive and not exceed \
+         `tickets.max_count`, which must fit into the database"
+    )]
+    InvalidTicketCountRange,
 }
 
+impl std::error::Error for ValidationError {}
+
 #[derive(Deserialize)]
 pub struct Db {
     pub url: String,
+
+    /// URL of a read replica to serve read-only queries from.
+    ///
+    /// If not specified, all queries are served by the primary.
+    pub read_url: Option<String>,
+
+    /// Number of times a read-only query failed due to a transient error
+    /// (like a dropped connection during a failover) is retried.
+    #[serde(default = "Db::default_max_retries")]
+    pub max_retries: u32,
+
+    /// Time after which a statement is aborted by the database.
+    ///
+    /// If not specified, the database's own setting applies.
+    #[serde(default, with = "humantime_serde::option")]
+    pub statement_timeout: Option<time::Duration>,
+
+    /// Name the connections report to the database, telling them apart in
+    /// `pg_stat_activity`.
+    ///
+    /// If not specified, the one of the URL applies, if any.
+    #[serde(default)]
+    pub application_name: Option<String>,
+
+    /// Session parameters set on every connection, like
+    /// `work_mem = "64MB"`.
+    ///
+    /// [`Db::statement_timeout`] takes precedence over the one specified
+    /// here. None are set by default.
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+
+    /// Indicates whether connections to the database must be encrypted.
+    ///
+    /// If set, the database certificate is verified against the root
+    /// certificates of the platform, and unencrypted connections are
+    /// refused.
+    #[serde(default)]
+    pub require_ssl: bool,
+
+    /// Number of times connecting to the database is retried on startup,
+    /// while it may be not ready yet.
+    ///
+    /// Connecting isn't retried by default.
+    #[serde(default)]
+    pub max_connect_retries: u32,
+
+    /// Time to wait before retrying to connect to the database.
+    #[serde(
+        default = "Db::default_connect_retry_delay",
+        with = "humantime_serde"
+    )]
+    pub connect_retry_delay: time::Duration,
+
+    /// Duration of a database query above which it's logged as slow.
+    #[serde(
+        default = "Db::default_slow_query_threshold",
+        with = "humantime_serde"
+    )]
+    pub slow_query_threshold: time::Duration,
+
+    /// Maximum number of connections to each database.
+    ///
+    /// If not specified, four times the number of CPUs.
+    #[serde(default)]
+    pub pool_size: Option<usize>,
+
+    /// Time to wait for a connection when all of them are in use, before
+    /// failing the query.
+    ///
+    /// 30 seconds by default, so requests fail instead of piling up forever
+    /// once the pool is exhausted.
+    #[serde(
+        default = "Db::default_acquire_timeout",
+        with = "humantime_serde::option"
+    )]
+    pub acquire_timeout: Option<time::Duration>,
+
+    /// Time to wait for a connection above which it's logged as slow, as the
+    /// pool is likely saturated.
+    #[serde(
+        default = "Db::default_slow_acquire_threshold",
+        with = "humantime_serde"
+    )]
+    pub slow_acquire_threshold: time::Duration,
+
+    /// Number of rows inserted by a single statement when writing a batch of
+    /// them, like imported tickets.
+    #[serde(default = "Db::default_write_batch_size")]
+    pub write_batch_size: NonZeroUsize,
+
+    /// Whether to skip checking the database schema to be the one expected
+    /// on startup.
+    ///
+    /// Meant for emergencies only, as queries to an incompatible schema fail
+    /// at request time instead.
+    #[serde(default)]
+    pub skip_schema_check: bool,
+}
+
+impl Db {
+    fn default_max_retries() -> u32 {
+        3
+    }
+
+    fn default_connect_retry_delay() -> time::Duration {
+        time::Duration::from_secs(1)
+    }
+
+    fn default_slow_query_threshold() -> time::Duration {
+        time::Duration::from_millis(500)
+    }
+
+    fn default_acquire_timeout() -> Option<time::Duration> {
+        Some(time::Duration::from_secs(30))
+    }
+
+    fn default_slow_acquire_threshold() -> time::Duration {
+        time::Duration::from_millis(100)
+    }
+
+    fn default_write_batch_size() -> NonZeroUsize {
+        NonZeroUsize::new(1000).unwrap()
+    }
 }
 
 #[derive(Deserialize)]
 pub struct Http {
     pub server: Server,
     pub cors: Cors,
+
+    /// TLS settings of the server.
+    ///
+    /// If not specified, the server accepts plain HTTP connections.
+    pub tls: Option<Tls>,
+
+    /// Path prefix to serve the API under, like `/api`, when deployed behind
+    /// a reverse proxy not rewriting paths.
+    ///
+    /// Health probes and version are served at the root regardless. No
+    /// prefix is used by default.
+    #[serde(default)]
+    pub base_path: Option<String>,
+
+    /// Whether to report the database error causing a
+    /// `500 Internal Server Error` in its response body.
+    ///
+    /// Meant for development only, as the errors may reveal the database
+    /// schema and data. Errors aren't reported by default.
+    #[serde(default)]
+    pub expose_db_errors: bool,
+
+    /// Whether to pretty-print JSON response bodies.
+    ///
+    /// Meant for development only, as it bloats the responses. Bodies are
+    /// compact by default.
+    #[serde(default)]
+    pub pretty_json: bool,
+
+    /// Compression of response bodies, negotiated via `Accept-Encoding`.
+    ///
+    /// If not specified, responses are sent uncompressed.
+    pub compression: Option<Compression>,
+
+    /// Checking whether a login is taken via `GET /auth/available`.
+    ///
+    /// If not specified, the endpoint is disabled, as it lets anyone probe
+    /// which users exist.
+    pub login_availability: Option<LoginAvailability>,
+
+    /// Caching the caller's user responded by `GET /user`, and the time of
+    /// its last password change checked on every request.
+    ///
+    /// If not specified, both are read from the database on every request.
+    pub user_cache: Option<UserCache>,
+}
+
+impl Http {
+    fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(path) = &self.base_path {
+            if !path.starts_with('/') || path.ends_with('/') {
+                return Err(ValidationError::InvalidBasePath);
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Deserialize)]
 pub struct Server {
     pub addr: net::SocketAddr,
+
+    /// Time given to the in-flight requests to complete once the server is
+    /// requested to shut down.
+    #[serde(
+        default = "Server::default_shutdown_timeout",
+        with = "humantime_serde"
+    )]
+    pub shutdown_timeout: time::Duration,
+}
+
+impl Server {
+    fn default_shutdown_timeout() -> time::Duration {
+        time::Duration::from_secs(30)
+    }
 }
 
 #[derive(Deserialize)]
 pub struct Cors {
+    /// Origins allowed to make cross-origin requests.
+    ///
+    /// The special `"*"` value allows any origin and is meant for
+    /// development only. No origins are allowed by default.
+    #[serde(default)]
     pub allowed_origins: Vec<String>,
+
+    /// Seconds browsers may cache the response to a preflight request for,
+    /// rather than repeating it before every cross-origin request.
+    #[serde(default = "Cors::default_preflight_max_age_seconds")]
+    pub preflight_max_age_seconds: u64,
+
+    /// Indicates whether browsers may send cookies and other credentials
+    /// with cross-origin requests.
+    ///
+    /// Requires explicit [`Cors::allowed_origins`], as browsers reject
+    /// credentialed responses allowing any origin.
+    #[serde(default)]
+    pub allow_credentials: bool,
+
+    /// Response headers JavaScript clients may read, beyond the
+    /// CORS-safelisted ones, like `ETag` or `Link`.
+    ///
+    /// No extra headers are exposed by default.
+    #[serde(default)]
+    pub expose_headers: Vec<String>,
+}
+
+impl Cors {
+    /// Value of [`Cors::allowed_origins`] allowing any origin.
+    pub const ANY_ORIGIN: &'static str = "*";
+
+    fn default_preflight_max_age_seconds() -> u64 {
+        600
+    }
+
+    /// Indicates whether any origin is allowed.
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|o| o == Self::ANY_ORIGIN)
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        if self.allows_any_origin() && self.allowed_origins.len() > 1 {
+            return Err(ValidationError::AnyOriginWithExplicitOrigins);
+        }
+        if self.allow_credentials && self.allows_any_origin() {
+            return Err(ValidationError::CredentialsWithAnyOrigin);
+        }
+        if !self.expose_headers.iter().all(|h| is_header_name(h)) {
+            return Err(ValidationError::InvalidExposedHeader);
+        }
+        Ok(())
+    }
+}
+
+/// Indicates whether the `name` is a valid HTTP header name, that is a
+/// non-empty token of [RFC 9110].
+///
+/// [RFC 9110]: https://www.rfc-editor.org/rfc/rfc9110#name-tokens
+fn is_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.bytes().all(|b| {
+            b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+        })
+}
+
+#[derive(Deserialize)]
+pub struct Tls {
+    /// Path to the PEM-encoded certificate chain.
+    pub cert_path: PathBuf,
+
+    /// Path to the PEM-encoded private key.
+    pub key_path: PathBuf,
+
+    /// Port on which plain HTTP requests are redirected to HTTPS.
+    ///
+    /// It's bound on the same IP address as [`Server::addr`].
+    #[serde(default = "Tls::default_redirect_from_port")]
+    pub redirect_from_port: u16,
+}
+
+impl Tls {
+    fn default_redirect_from_port() -> u16 {
+        80
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Compression {
+    /// Size in bytes a response body must exceed to be compressed, as
+    /// compressing smaller ones saves next to nothing.
+    #[serde(default = "Compression::default_min_size")]
+    pub min_size: u16,
+}
+
+impl Compression {
+    fn default_min_size() -> u16 {
+        1024
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LoginAvailability {
+    /// Number of checks served per [`LoginAvailability::period`] to a single
+    /// client, beyond which `429 Too Many Requests` is responded to it.
+    #[serde(default = "LoginAvailability::default_max_requests")]
+    pub max_requests: u32,
+
+    #[serde(
+        default = "LoginAvailability::default_period",
+        with = "humantime_serde"
+    )]
+    pub period: time::Duration,
+
+    /// Whether a client is told by the last address of the `X-Forwarded-For`
+    /// header, as appended by a reverse proxy in front of the server, rather
+    /// than by the address it connects from.
+    ///
+    /// Must only be set behind such a proxy, as clients can send any header
+    /// otherwise. Not set by default.
+    #[serde(default)]
+    pub trust_forwarded_for: bool,
+}
+
+impl LoginAvailability {
+    fn default_max_requests() -> u32 {
+        10
+    }
+
+    fn default_period() -> time::Duration {
+        time::Duration::from_secs(60)
+    }
+}
+
+#[derive(Deserialize)]
+pub struct UserCache {
+    /// Time a user is served from the cache for.
+    ///
+    /// Changes made through this instance are seen right away, but
+    /// changes made through other instances are only seen once it expires.
+    #[serde(default = "UserCache::default_ttl", with = "humantime_serde")]
+    pub ttl: time::Duration,
+}
+
+impl UserCache {
+    fn default_ttl() -> time::Duration {
+        time::Duration::from_secs(60)
+    }
 }
 
 #[derive(Deserialize)]
@@ -35,4 +428,188 @@ pub struct Jwt {
     pub secret: String,
     #[serde(with = "humantime_serde")]
     pub expiration_time: time::Duration,
+
+    /// Time past its expiration a token is still accepted for, tolerating
+    /// the clock skew between the instances issuing and validating tokens.
+    ///
+    /// Any leeway extends the lifetime of every token, stolen ones included,
+    /// so it should be kept as small as the skew allows. No leeway is given
+    /// by default. Rounded down to whole seconds.
+    #[serde(default, with = "humantime_serde")]
+    pub leeway: time::Duration,
+
+    /// Profiles of the tokens issued to the clients of different kinds, like
+    /// `web` or `integration`, by their names.
+    ///
+    /// Clients not naming a profile are issued tokens without an audience,
+    /// expiring after [`Jwt::expiration_time`].
+    #[serde(default)]
+    pub clients: BTreeMap<String, JwtClient>,
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct JwtClient {
+    /// `aud` claim of the issued tokens.
+    ///
+    /// Tokens of any configured audience are accepted by every endpoint.
+    pub audience: String,
+
+    /// Lifetime of the issued tokens.
+    ///
+    /// If not specified, [`Jwt::expiration_time`] applies.
+    #[serde(default, with = "humantime_serde::option")]
+    pub expiration_time: Option<time::Duration>,
+}
+
+#[derive(Deserialize)]
+pub struct Attachments {
+    /// Maximum size of an attached file, in bytes.
+    #[serde(default = "Attachments::default_max_size")]
+    pub max_size: usize,
+
+    /// Content types a file may be attached with.
+    #[serde(default = "Attachments::default_allowed_content_types")]
+    pub allowed_content_types: Vec<String>,
+
+    /// Storage the attached files are kept in.
+    ///
+    /// Files are kept in the local `attachments` directory by default.
+    #[serde(default)]
+    pub storage: BlobStorage,
+}
+
+impl Attachments {
+    fn default_max_size() -> usize {
+        10 * 1024 * 1024
+    }
+
+    fn default_allowed_content_types() -> Vec<String> {
+        ["application/pdf", "image/jpeg", "image/png", "text/plain"]
+            .map(ToOwned::to_owned)
+            .into()
+    }
+}
+
+impl Default for Attachments {
+    fn default() -> Self {
+        Self {
+            max_size: Self::default_max_size(),
+            allowed_content_types: Self::default_allowed_content_types(),
+            storage: BlobStorage::default(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct Tickets {
+    /// Least number of items a ticket may request.
+    #[serde(default = "Tickets::default_min_count")]
+    pub min_count: usize,
+
+    /// Most number of items a ticket may request.
+    ///
+    /// Limited only by the database by default.
+    #[serde(default = "Tickets::default_max_count")]
+    pub max_count: usize,
+
+    /// Full-text search of the tickets by the `q` parameter of their
+    /// listing.
+    ///
+    /// If not specified, the tickets are searched for the text with `ILIKE`
+    /// instead.
+    pub full_text_search: Option<FullTextSearch>,
+}
+
+impl Tickets {
+    fn default_min_count() -> usize {
+        1
+    }
+
+    fn default_max_count() -> usize {
+        i32::MAX as usize
+    }
+
+    fn validate(&self) -> Result<(), ValidationError> {
+        if self.min_count == 0
+            || self.min_count > self.max_count
+            || self.max_count > Self::default_max_count()
+        {
+            return Err(ValidationError::InvalidTicketCountRange);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Tickets {
+    fn default() -> Self {
+        Self {
+            min_count: Self::default_min_count(),
+            max_count: Self::default_max_count(),
+            full_text_search: None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct FullTextSearch {
+    /// Text search configuration the words are parsed with.
+    #[serde(default)]
+    pub config: db::ticket::TextSearchConfig,
+}
+
+#[derive(Deserialize)]
+pub struct Maintenance {
+    /// Whether to reject the requests changing any data with
+    /// `503 Service Unavailable`, while still serving the reading ones.
+    ///
+    /// Re-read from `config.toml` on `SIGHUP`, so it can be toggled without
+    /// restarting the server. Disabled by default.
+    #[serde(default)]
+    pub read_only: bool,
+
+    /// Time the clients are told to retry the rejected requests after.
+    #[serde(
+        default = "Maintenance::default_retry_after",
+        with = "humantime_serde"
+    )]
+    pub retry_after: time::Duration,
+}
+
+impl Maintenance {
+    fn default_retry_after() -> time::Duration {
+        time::Duration::from_secs(60)
+    }
+}
+
+impl Default for Maintenance {
+    fn default() -> Self {
+        Self {
+            read_only: false,
+            retry_after: Self::default_retry_after(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "backend")]
+pub enum BlobStorage {
+    /// Directory of the local file system.
+    Local { dir: PathBuf },
+
+    /// S3 bucket, with the credentials and region resolved from the
+    /// environment.
+    S3 {
+        bucket: String,
+
+        /// URL of an S3-compatible storage to use instead of AWS.
+        endpoint_url: Option<String>,
+    },
+}
+
+impl Default for BlobStorage {
+    fn default() -> Self {
+        Self::Local {
+            dir: PathBuf::from("attachments"),
+        }
+    }
 }
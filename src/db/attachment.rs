@@ -0,0 +1,176 @@
+use std::error::Error as StdError;
+
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio_postgres::types::{
+    accepts, private::BytesMut, to_sql_checked, FromSql, IsNull, ToSql, Type,
+};
+use uuid::Uuid;
+
+use super::{ticket, user, Client, Error, Target};
+
+/// Metadata of a file attached to a ticket.
+///
+/// The file contents are kept in a [blob store] under the
+/// [`Attachment::blob_key()`].
+///
+/// [blob store]: crate::blob::Store
+#[derive(Clone, Debug)]
+pub struct Attachment {
+    pub id: Id,
+    pub ticket_id: ticket::Id,
+    pub uploader: user::Id,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: usize,
+    pub created_at: OffsetDateTime,
+}
+
+impl Attachment {
+    /// Returns the key the contents of this [`Attachment`] are stored under.
+    pub fn blob_key(&self) -> String {
+        format!("tickets/{}/attachments/{}", self.ticket_id, self.id)
+    }
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Display,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+)]
+pub struct Id(Uuid);
+
+impl Id {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<u128> for Id {
+    fn from(value: u128) -> Self {
+        Self(Uuid::from_u128(value))
+    }
+}
+
+impl FromSql<'_> for Id {
+    accepts!(UUID);
+
+    fn from_sql(
+        ty: &Type,
+        raw: &[u8],
+    ) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+        Uuid::from_sql(ty, raw).map(Self)
+    }
+}
+
+impl ToSql for Id {
+    accepts!(UUID);
+
+    to_sql_checked!();
+
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn StdError + Sync + Send>> {
+        self.0.to_sql(ty, out)
+    }
+}
+
+impl Client {
+    pub async fn insert_attachment(
+        &self,
+        attachment: &Attachment,
+    ) -> Result<(), Error> {
+        const SQL: &str = "\
+            INSERT INTO attachments (id, ticket_id, uploader_id, file_name, \
+                                     content_type, size, created_at) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7)";
+        self.traced("insert_attachment", async move {
+            let size = i64::try_from(attachment.size).unwrap();
+            self.conn(Target::Primary)
+                .await?
+                .execute(
+                    SQL,
+                    &[
+                        &attachment.id,
+                        &attachment.ticket_id,
+                        &attachment.uploader,
+                        &attachment.file_name,
+                        &attachment.content_type,
+                        &size,
+                        &attachment.created_at,
+                    ],
+                )
+                .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn get_attachment_by_id(
+        &self,
+        id: Id,
+    ) -> Result<Option<Attachment>, Error> {
+        const SQL: &str = "\
+            SELECT id, ticket_id, uploader_id, file_name, content_type, \
+                   size, created_at \
+            FROM attachments \
+            WHERE id = $1";
+        self.traced("get_attachment_by_id", async move {
+            Ok(self
+                .read_opt(Target::Replica, SQL, &[&id])
+                .await?
+                .map(|row| Attachment {
+                    id: row.get("id"),
+                    ticket_id: row.get("ticket_id"),
+                    uploader: row.get("uploader_id"),
+                    file_name: row.get("file_name"),
+                    content_type: row.get("content_type"),
+                    size: row.get::<_, i64>("size").try_into().unwrap(),
+                    created_at: row.get("created_at"),
+                }))
+        })
+        .await
+    }
+
+    /// Returns all the [`Attachment`]s of the ticket, from the oldest to the
+    /// newest.
+    pub async fn get_attachments_for_ticket(
+        &self,
+        ticket_id: ticket::Id,
+    ) -> Result<Vec<Attachment>, Error> {
+        const SQL: &str = "\
+            SELECT id, ticket_id, uploader_id, file_name, content_type, \
+                   size, created_at \
+            FROM attachments \
+            WHERE ticket_id = $1 \
+            ORDER BY created_at ASC, \
+                     id ASC";
+        self.traced("get_attachments_for_ticket", async move {
+            Ok(self
+                .read(Target::Replica, SQL, &[&ticket_id])
+                .await?
+                .into_iter()
+                .map(|row| Attachment {
+                    id: row.get("id"),
+                    ticket_id: row.get("ticket_id"),
+                    uploader: row.get("uploader_id"),
+                    file_name: row.get("file_name"),
+                    content_type: row.get("content_type"),
+                    size: row.get::<_, i64>("size").try_into().unwrap(),
+                    created_at: row.get("created_at"),
+                })
+                .collect())
+        })
+        .await
+    }
+}
@@ -0,0 +1,401 @@
+use serde_json::Value as Json;
+use time::OffsetDateTime;
+use tokio_postgres::types::ToSql;
+
+use super::{
+    ticket,
+    user::{self, Role, UpdateUserRoleError},
+    Client, Error, Target, Ticket, Transaction, User,
+};
+
+/// Recorded change of some entity.
+#[derive(Clone, Debug)]
+pub struct Event {
+    pub id: i64,
+    pub actor: user::Id,
+    pub entity: Entity,
+    pub action: String,
+    pub payload: Json,
+
+    /// State of the entity right after this [`Event`].
+    ///
+    /// Missing for the events recorded before the snapshots were.
+    pub snapshot: Option<Json>,
+
+    pub created_at: OffsetDateTime,
+}
+
+/// Change of the status of a ticket along with the [`Event`] recording it,
+/// as applied by [`Client::update_ticket_statuses_with_events()`].
+#[derive(Clone, Debug)]
+pub struct StatusTransition {
+    /// State of the ticket right after this [`StatusTransition`].
+    pub ticket: Ticket,
+
+    /// Status the ticket must be in for this [`StatusTransition`] to apply.
+    pub expected: ticket::Status,
+
+    pub fields: ticket::StatusUpdateFields,
+    pub actor: user::Id,
+    pub action: String,
+    pub payload: Json,
+}
+
+/// Change of a ticket along with the [`Event`] recording it, as applied by
+/// [`Client::edit_ticket_with_event()`].
+#[derive(Clone, Debug)]
+pub struct TicketEdit {
+    /// State of the ticket this [`TicketEdit`] is decided upon, which the
+    /// ticket must still be in for it to apply.
+    pub original: Ticket,
+
+    /// State of the ticket right after this [`TicketEdit`].
+    pub ticket: Ticket,
+
+    /// Fields set along with the status, if this [`TicketEdit`] changes it.
+    pub status_fields: Option<ticket::StatusUpdateFields>,
+
+    pub actor: user::Id,
+    pub action: String,
+    pub payload: Json,
+}
+
+/// Entity an [`Event`] relates to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Entity {
+    Ticket(ticket::Id),
+    User(user::Id),
+}
+
+impl Entity {
+    const TICKET: &'static str = "ticket";
+    const USER: &'static str = "user";
+}
+
+impl Ticket {
+    /// Returns the state of this [`Ticket`], as recorded along with the
+    /// [`Event`]s of its changes.
+    pub fn snapshot(&self) -> Json {
+        serde_json::json!({
+            "title": self.title,
+            "description": self.description,
+            "status": self.status,
+            "category": self.category,
+            "count": self.count,
+            "receivedCount": self.received_count,
+            "price": self.price,
+            "paymentReference": self.payment_reference,
+            "initiator": self.initiator,
+            "purchasingManager": self.purchasing_manager,
+            "accountingManager": self.accounting_manager,
+        })
+    }
+}
+
+impl User {
+    /// Returns the state of this [`User`], as recorded along with the
+    /// [`Event`]s of its changes, without the credentials.
+    pub fn snapshot(&self) -> Json {
+        serde_json::json!({
+            "name": self.name,
+            "login": self.login,
+            "role": self.role,
+            "email": self.email,
+        })
+    }
+}
+
+impl Transaction<'_> {
+    /// Records an [`Event`] along with the rest of this [`Transaction`], so
+    /// it's only stored if the change itself is.
+    pub async fn insert_event(
+        &self,
+        actor: user::Id,
+        entity: Entity,
+        action: &str,
+        payload: &Json,
+        snapshot: &Json,
+    ) -> Result<(), Error> {
+        const SQL: &str = "\
+            INSERT INTO audit_events (actor_id, entity, entity_id, action, \
+                                      payload, snapshot) \
+            VALUES ($1, $2, $3, $4, $5, $6)";
+        let (entity, id): (_, &(dyn ToSql + Sync)) = match &entity {
+            Entity::Ticket(id) => (Entity::TICKET, id),
+            Entity::User(id) => (Entity::USER, id),
+        };
+        self.0
+            .execute(SQL, &[&actor, &entity, id, &action, payload, snapshot])
+            .await?;
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Writes the [`Ticket`] and records the [`Event`] of its change by the
+    /// `actor` in a single [`Transaction`].
+    pub async fn write_ticket_with_event(
+        &self,
+        ticket: &Ticket,
+        actor: user::Id,
+        action: &str,
+        payload: &Json,
+    ) -> Result<(), Error> {
+        self.traced("write_ticket_with_event", async move {
+            let mut conn = self.connection().await?;
+            let tx = conn.transaction().await?;
+            tx.write_ticket(ticket).await?;
+            tx.insert_event(
+                actor,
+                Entity::Ticket(ticket.id),
+                action,
+                payload,
+                &ticket.snapshot(),
+            )
+            .await?;
+            tx.commit().await
+        })
+        .await
+    }
+
+    /// Writes the [`TicketEdit`] and records its [`Event`] in a single
+    /// [`Transaction`], locking the ticket until then.
+    ///
+    /// Returns `false` without changing anything if the active ticket isn't
+    /// in the [`TicketEdit::original`] state anymore, as another request has
+    /// changed or deleted it meanwhile, so concurrent edits never overwrite
+    /// each other. A change of the status is written as by
+    /// [`Client::update_ticket_status()`], so it's never applied to a status
+    /// other than the original one either.
+    ///
+    /// Nothing else is acquired while the ticket is locked, so concurrent
+    /// edits of it can't exhaust the connection pool.
+    pub async fn edit_ticket_with_event(
+        &self,
+        edit: &TicketEdit,
+    ) -> Result<bool, Error> {
+        self.traced("edit_ticket_with_event", async move {
+            let id = edit.original.id;
+            let mut conn = self.connection().await?;
+            let tx = conn.transaction().await?;
+            let locked = tx.get_ticket_by_id_for_update(id).await?;
+            if locked.as_ref() != Some(&edit.original) {
+                return Ok(false);
+            }
+            match &edit.status_fields {
+                Some(fields) => {
+                    let (expected, new) =
+                        (edit.original.status, edit.ticket.status);
+                    if !tx
+                        .update_ticket_status(id, expected, new, fields)
+                        .await?
+                    {
+                        return Ok(false);
+                    }
+                }
+                None => tx.write_ticket(&edit.ticket).await?,
+            }
+            tx.insert_event(
+                edit.actor,
+                Entity::Ticket(id),
+                &edit.action,
+                &edit.payload,
+                &edit.ticket.snapshot(),
+            )
+            .await?;
+            tx.commit().await?;
+            Ok(true)
+        })
+        .await
+    }
+
+    /// Changes the role of the user with the provided `id` and records the
+    /// [`Event`] of this change by the `actor` in a single [`Transaction`],
+    /// returning the changed user, if there's such one.
+    ///
+    /// Nothing is changed or recorded if the user already has the `role`.
+    /// The last admin can't be demoted, even by concurrent changes, as all
+    /// the admins are locked before checking.
+    pub async fn update_user_role_with_event(
+        &self,
+        id: user::Id,
+        role: Role,
+        actor: user::Id,
+    ) -> Result<Option<User>, UpdateUserRoleError> {
+        self.traced("update_user_role_with_event", async move {
+            let mut conn = self.connection().await?;
+            let tx = conn.transaction().await?;
+            let locked = tx.lock_user_and_admins(id).await?;
+            let Some(user) = locked.iter().find(|u| u.id == id).cloned() else {
+                return Ok(None);
+            };
+            if user.role == role {
+                return Ok(Some(user));
+            }
+            let admins = locked.iter().filter(|u| u.role == Role::Admin);
+            if user.role == Role::Admin && admins.count() == 1 {
+                return Err(UpdateUserRoleError::LastAdmin);
+            }
+
+            tx.update_user_role(id, role).await?;
+            // Shaped as the payloads of ticket edits are.
+            let payload = serde_json::json!({
+                "op": "changeRole",
+                "data": { "role": role, "previousRole": user.role },
+            });
+            let user = User { role, ..user };
+            tx.insert_event(
+                actor,
+                Entity::User(id),
+                "changeRole",
+                &payload,
+                &user.snapshot(),
+            )
+            .await?;
+            tx.commit().await?;
+            Ok(Some(user))
+        })
+        .await
+    }
+
+    /// Changes the status of the ticket from the `expected` one to the one
+    /// of the provided [`Ticket`], as [`Client::update_ticket_status()`]
+    /// does, and records the [`Event`] of this change by the `actor` in a
+    /// single [`Transaction`].
+    ///
+    /// Returns `false` without recording anything if the ticket isn't in the
+    /// `expected` status.
+    pub async fn update_ticket_status_with_event(
+        &self,
+        ticket: &Ticket,
+        expected: ticket::Status,
+        fields: &ticket::StatusUpdateFields,
+        actor: user::Id,
+        action: &str,
+        payload: &Json,
+    ) -> Result<bool, Error> {
+        self.traced("update_ticket_status_with_event", async move {
+            let mut conn = self.connection().await?;
+            let tx = conn.transaction().await?;
+            let new = ticket.status;
+            if !tx
+                .update_ticket_status(ticket.id, expected, new, fields)
+                .await?
+            {
+                return Ok(false);
+            }
+            tx.insert_event(
+                actor,
+                Entity::Ticket(ticket.id),
+                action,
+                payload,
+                &ticket.snapshot(),
+            )
+            .await?;
+            tx.commit().await?;
+            Ok(true)
+        })
+        .await
+    }
+
+    /// Applies the [`StatusTransition`]s of several tickets within a single
+    /// [`Transaction`], returning whether each of them is applied.
+    ///
+    /// A ticket not in the expected status is skipped, as by
+    /// [`Client::update_ticket_status_with_event()`], without failing the
+    /// others.
+    pub async fn update_ticket_statuses_with_events(
+        &self,
+        transitions: &[StatusTransition],
+    ) -> Result<Vec<bool>, Error> {
+        self.traced("update_ticket_statuses_with_events", async move {
+            let mut conn = self.connection().await?;
+            let tx = conn.transaction().await?;
+            let mut applied = Vec::with_capacity(transitions.len());
+            for t in transitions {
+                let (id, new) = (t.ticket.id, t.ticket.status);
+                let updated = tx
+                    .update_ticket_status(id, t.expected, new, &t.fields)
+                    .await?;
+                if updated {
+                    tx.insert_event(
+                        t.actor,
+                        Entity::Ticket(id),
+                        &t.action,
+                        &t.payload,
+                        &t.ticket.snapshot(),
+                    )
+                    .await?;
+                }
+                applied.push(updated);
+            }
+            tx.commit().await?;
+            Ok(applied)
+        })
+        .await
+    }
+
+    /// Returns the [`Event`]s of the ticket, in the order they happened.
+    pub async fn get_events_for_ticket(
+        &self,
+        ticket_id: ticket::Id,
+    ) -> Result<Vec<Event>, Error> {
+        const SQL: &str = "\
+            SELECT id, actor_id, entity_id, action, payload, snapshot, \
+                   created_at \
+            FROM audit_events \
+            WHERE entity = $1 AND entity_id = $2 \
+            ORDER BY id";
+        self.traced("get_events_for_ticket", async move {
+            Ok(self
+                .conn(Target::Replica)
+                .await?
+                .query(SQL, &[&Entity::TICKET, &ticket_id])
+                .await?
+                .into_iter()
+                .map(|row| Event {
+                    id: row.get("id"),
+                    actor: row.get("actor_id"),
+                    entity: Entity::Ticket(row.get("entity_id")),
+                    action: row.get("action"),
+                    payload: row.get("payload"),
+                    snapshot: row.get("snapshot"),
+                    created_at: row.get("created_at"),
+                })
+                .collect())
+        })
+        .await
+    }
+
+    /// Returns the [`Event`]s of the user, in the order they happened.
+    pub async fn get_events_for_user(
+        &self,
+        user_id: user::Id,
+    ) -> Result<Vec<Event>, Error> {
+        const SQL: &str = "\
+            SELECT id, actor_id, entity_id, action, payload, snapshot, \
+                   created_at \
+            FROM audit_events \
+            WHERE entity = $1 AND entity_id = $2 \
+            ORDER BY id";
+        self.traced("get_events_for_user", async move {
+            Ok(self
+                .conn(Target::Replica)
+                .await?
+                .query(SQL, &[&Entity::USER, &user_id])
+                .await?
+                .into_iter()
+                .map(|row| Event {
+                    id: row.get("id"),
+                    actor: row.get("actor_id"),
+                    entity: Entity::User(row.get("entity_id")),
+                    action: row.get("action"),
+                    payload: row.get("payload"),
+                    snapshot: row.get("snapshot"),
+                    created_at: row.get("created_at"),
+                })
+                .collect())
+        })
+        .await
+    }
+}
@@ -0,0 +1,37 @@
+use time::OffsetDateTime;
+
+use super::{Client, Error, Target};
+
+impl Client {
+    /// Returns the moment before which all issued access tokens are
+    /// considered invalid.
+    pub async fn get_tokens_valid_after(
+        &self,
+    ) -> Result<OffsetDateTime, Error> {
+        const SQL: &str = "SELECT tokens_valid_after FROM auth_settings";
+        self.traced("get_tokens_valid_after", async move {
+            Ok(self
+                .conn(Target::Primary)
+                .await?
+                .query_one(SQL, &[])
+                .await?
+                .get("tokens_valid_after"))
+        })
+        .await
+    }
+
+    pub async fn set_tokens_valid_after(
+        &self,
+        at: OffsetDateTime,
+    ) -> Result<(), Error> {
+        const SQL: &str = "UPDATE auth_settings SET tokens_valid_after = $1";
+        self.traced("set_tokens_valid_after", async move {
+            self.conn(Target::Primary)
+                .await?
+                .execute(SQL, &[&at])
+                .await?;
+            Ok(())
+        })
+        .await
+    }
+}
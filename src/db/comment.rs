@@ -0,0 +1,183 @@
+use std::error::Error as StdError;
+
+use derive_more::{Display, From};
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use tokio_postgres::types::{
+    accepts, private::BytesMut, to_sql_checked, FromSql, IsNull, ToSql, Type,
+};
+use uuid::Uuid;
+
+use super::{bigint, count, ticket, user, Client, Error, Target};
+
+#[derive(Clone, Debug)]
+pub struct Comment {
+    pub id: Id,
+    pub ticket_id: ticket::Id,
+    pub author: user::Id,
+    pub text: String,
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Display,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+)]
+pub struct Id(Uuid);
+
+impl Id {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<u128> for Id {
+    fn from(value: u128) -> Self {
+        Self(Uuid::from_u128(value))
+    }
+}
+
+impl FromSql<'_> for Id {
+    accepts!(UUID);
+
+    fn from_sql(
+        ty: &Type,
+        raw: &[u8],
+    ) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+        Uuid::from_sql(ty, raw).map(Self)
+    }
+}
+
+impl ToSql for Id {
+    accepts!(UUID);
+
+    to_sql_checked!();
+
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn StdError + Sync + Send>> {
+        self.0.to_sql(ty, out)
+    }
+}
+
+impl Client {
+    pub async fn insert_comment(
+        &self,
+        comment: &Comment,
+    ) -> Result<(), InsertCommentError> {
+        const SQL: &str = "\
+            INSERT INTO comments (id, ticket_id, author_id, text, created_at) \
+            VALUES ($1, $2, $3, $4, $5)";
+
+        self.traced("insert_comment", async move {
+            self.conn(Target::Primary)
+                .await?
+                .execute(
+                    SQL,
+                    &[
+                        &comment.id,
+                        &comment.ticket_id,
+                        &comment.author,
+                        &comment.text,
+                        &comment.created_at,
+                    ],
+                )
+                .await
+                .map_err(|e| match Error::from(e) {
+                    Error::ConstraintViolation { constraint }
+                        if constraint == "comments_ticket_id_fkey" =>
+                    {
+                        InsertCommentError::TicketNotFound
+                    }
+                    Error::ConstraintViolation { constraint }
+                        if constraint == "comments_author_id_fkey" =>
+                    {
+                        InsertCommentError::AuthorNotFound
+                    }
+                    e => e.into(),
+                })?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Returns the requested page of comments left on the ticket, from the
+    /// oldest to the newest.
+    pub async fn get_comments_for_ticket(
+        &self,
+        ticket_id: ticket::Id,
+        offset: usize,
+        limit: usize,
+    ) -> Result<Vec<Comment>, Error> {
+        self.traced("get_comments_for_ticket", async move {
+            let offset = bigint(offset, "offset")?;
+            let limit = bigint(limit, "limit")?;
+
+            const SQL: &str = "\
+                SELECT id, ticket_id, author_id, text, created_at \
+                FROM comments \
+                WHERE ticket_id = $1 \
+                ORDER BY created_at ASC, \
+                         id ASC \
+                OFFSET $2 LIMIT $3";
+            Ok(self
+                .conn(Target::Replica)
+                .await?
+                .query(SQL, &[&ticket_id, &offset, &limit])
+                .await?
+                .into_iter()
+                .map(|row| Comment {
+                    id: row.get("id"),
+                    ticket_id: row.get("ticket_id"),
+                    author: row.get("author_id"),
+                    text: row.get("text"),
+                    created_at: row.get("created_at"),
+                })
+                .collect())
+        })
+        .await
+    }
+
+    pub async fn count_comments_for_ticket(
+        &self,
+        ticket_id: ticket::Id,
+    ) -> Result<usize, Error> {
+        const SQL: &str = "\
+            SELECT COUNT(*) \
+            FROM comments \
+            WHERE ticket_id = $1";
+        self.traced("count_comments_for_ticket", async move {
+            let n = self
+                .conn(Target::Replica)
+                .await?
+                .query_one(SQL, &[&ticket_id])
+                .await?
+                .get(0);
+            Ok(count(n))
+        })
+        .await
+    }
+}
+
+#[derive(Debug, Display, From)]
+pub enum InsertCommentError {
+    #[display("{_0}")]
+    #[from]
+    DbError(Error),
+    #[display("comment author doesn't exist")]
+    AuthorNotFound,
+    #[display("commented ticket doesn't exist")]
+    TicketNotFound,
+}
+
+impl StdError for InsertCommentError {}
@@ -0,0 +1,93 @@
+use time::OffsetDateTime;
+
+use super::{
+    user::{PasswordHash, Role},
+    Client, Error, Transaction, User,
+};
+
+/// Password of every [default user].
+///
+/// [default user]: default_users
+pub const DEFAULT_PASSWORD: &str = "password";
+
+/// Returns the users the integration tests rely on: one of each [`Role`],
+/// plus another initiator.
+pub fn default_users() -> [User; 5] {
+    [
+        (1, "Alice", "alice", Role::Initiator),
+        (2, "Bob", "bob", Role::PurchasingManager),
+        (3, "Charlie", "charlie", Role::AccountingManager),
+        (4, "Dave", "dave", Role::Admin),
+        (5, "Eve", "eve", Role::Initiator),
+    ]
+    .map(|(id, name, login, role)| User {
+        id: id.into(),
+        name: name.to_owned(),
+        login: login.to_owned(),
+        password_hash: PasswordHash::new(DEFAULT_PASSWORD),
+        role,
+        password_changed_at: OffsetDateTime::UNIX_EPOCH,
+        email: None,
+    })
+}
+
+/// Inserts the [default users], keeping the ones already present as is.
+///
+/// [default users]: default_users
+pub async fn seed_default_users(client: &Client) -> Result<(), Error> {
+    let mut conn = client.connection().await?;
+    let tx = conn.transaction().await?;
+    insert_default_users(&tx).await?;
+    tx.commit().await
+}
+
+/// Removes all the tickets along with everything referring to them, and
+/// replaces all the users with the [default ones].
+///
+/// [default ones]: default_users
+pub async fn reset(client: &Client) -> Result<(), Error> {
+    const SQL: &str = "\
+        TRUNCATE attachments, audit_events, comments, tickets, users";
+
+    let mut conn = client.connection().await?;
+    let tx = conn.transaction().await?;
+    tx.0.batch_execute(SQL).await?;
+    insert_default_users(&tx).await?;
+    tx.commit().await
+}
+
+/// Returns the current value of the session parameter with the provided
+/// `name` on a pooled connection of the `client`.
+pub async fn session_parameter(
+    client: &Client,
+    name: &str,
+) -> Result<String, Error> {
+    const SQL: &str = "SELECT current_setting($1)";
+
+    let conn = client.connection().await?;
+    Ok(conn.0.query_one(SQL, &[&name]).await?.get(0))
+}
+
+async fn insert_default_users(tx: &Transaction<'_>) -> Result<(), Error> {
+    const SQL: &str = "\
+        INSERT INTO users (id, name, login, password_hash, role, \
+                           password_changed_at) \
+        VALUES ($1, $2, $3, $4, $5, $6) \
+        ON CONFLICT (id) DO NOTHING";
+
+    for user in default_users() {
+        tx.0.execute(
+            SQL,
+            &[
+                &user.id,
+                &user.name,
+                &user.login,
+                &user.password_hash,
+                &user.role,
+                &user.password_changed_at,
+            ],
+        )
+        .await?;
+    }
+    Ok(())
+}
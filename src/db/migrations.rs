@@ -0,0 +1,91 @@
+use std::{error::Error as StdError, io, path::Path};
+
+use derive_more::{Display, From};
+use tokio::fs;
+
+use super::Client;
+
+/// Script of a migration, within its directory.
+const UP_SCRIPT: &str = "up.sql";
+
+/// Key of the advisory lock serializing concurrent runs, so a migration is
+/// never applied twice.
+const LOCK_KEY: i64 = 0x006d_6967_7261_7465;
+
+/// Applies the migrations from the `migrations_dir` not yet recorded in the
+/// `schema_version` table, in the lexicographic order of their names,
+/// returning how many of them were applied.
+///
+/// Every migration is a directory holding an `up.sql` script. Each script is
+/// run in its own transaction along with recording the migration name, so a
+/// failed migration leaves neither its changes nor its record behind.
+pub async fn run_pending(
+    client: &Client,
+    migrations_dir: &Path,
+) -> Result<usize, Error> {
+    const CREATE_SQL: &str = "\
+        CREATE TABLE IF NOT EXISTS schema_version (\
+            version     TEXT PRIMARY KEY, \
+            applied_at  TIMESTAMPTZ NOT NULL DEFAULT now()\
+        )";
+    const LOCK_SQL: &str = "SELECT pg_advisory_xact_lock($1)";
+    const IS_APPLIED_SQL: &str = "\
+        SELECT 1 \
+        FROM schema_version \
+        WHERE version = $1";
+    // Migration may record itself, if it's meant to be applied without this
+    // runner as well.
+    const RECORD_SQL: &str = "\
+        INSERT INTO schema_version (version) \
+        VALUES ($1) \
+        ON CONFLICT DO NOTHING";
+
+    let mut names = Vec::new();
+    let mut entries = fs::read_dir(migrations_dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_type().await?.is_dir() {
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    names.sort();
+
+    let mut conn = client.connection().await?;
+    conn.0.batch_execute(CREATE_SQL).await?;
+
+    let mut applied = 0;
+    for name in names {
+        let tx = conn.transaction().await?;
+        tx.0.execute(LOCK_SQL, &[&LOCK_KEY]).await?;
+        if tx.0.query_opt(IS_APPLIED_SQL, &[&name]).await?.is_some() {
+            continue;
+        }
+
+        let path = migrations_dir.join(&name).join(UP_SCRIPT);
+        let sql = fs::read_to_string(&path).await?;
+        tx.0.batch_execute(&sql).await?;
+        tx.0.execute(RECORD_SQL, &[&name]).await?;
+        tx.commit().await?;
+
+        tracing::info!(migration = name, "applied a migration");
+        applied += 1;
+    }
+    Ok(applied)
+}
+
+#[derive(Debug, Display, From)]
+pub enum Error {
+    #[display("database error: {_0}")]
+    #[from]
+    DbError(super::Error),
+    #[display("failed to read migrations: {_0}")]
+    #[from]
+    Io(io::Error),
+}
+
+impl From<tokio_postgres::Error> for Error {
+    fn from(e: tokio_postgres::Error) -> Self {
+        Self::DbError(e.into())
+    }
+}
+
+impl StdError for Error {}
@@ -1,22 +1,1279 @@
+pub mod attachment;
+pub mod audit;
+pub mod auth;
+pub mod comment;
+#[cfg(feature = "testing")]
+pub mod fixtures;
+pub mod migrations;
+pub mod schema;
+pub mod storage;
 pub mod ticket;
 pub mod user;
 
+use std::{
+    error::Error as StdError,
+    future::Future,
+    io,
+    num::NonZeroUsize,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use deadpool_postgres::{
+    ClientWrapper, Connect, CreatePoolError, Hook, HookError, Manager, Metrics,
+    Object, Pool, PoolConfig, PoolError, Runtime, SslMode,
+};
+use derive_more::{Display, From};
+use futures::{future::BoxFuture, stream, StreamExt as _};
+use rand::Rng as _;
+use serde::Serialize;
+use tokio::{task::JoinHandle, time};
+use tokio_postgres::{
+    error::{DbError, SqlState},
+    tls::{MakeTlsConnect, TlsConnect},
+    types::ToSql,
+    AsyncMessage, NoTls, Row, Socket,
+};
+use tokio_postgres_rustls::MakeRustlsConnect;
+use tracing::{field, Instrument as _, Span};
+
 use crate::config;
 
-use tokio_postgres::{tls::NoTlsStream, NoTls, Socket};
+pub use self::{
+    attachment::Attachment,
+    comment::Comment,
+    schema::{REQUIRED_COLUMNS, SCHEMA_VERSION},
+    storage::Storage,
+    ticket::Ticket,
+    user::User,
+};
+
+/// Connects to the database, retrying up to
+/// [`config::Db::max_connect_retries`] times, as it may be not ready yet
+/// when the application starts along with it.
+pub async fn connect(config: config::Db) -> Result<Client, Error> {
+    retry_connect(
+        config.max_connect_retries,
+        config.connect_retry_delay,
+        || async {
+            if config.require_ssl {
+                let tls = tls_connector()?;
+                connect_with(&config, Some(SslMode::Require), tls).await
+            } else {
+                connect_with(&config, None, NoTls).await
+            }
+        },
+    )
+    .await
+}
+
+/// Runs the `connect` until it succeeds or has been retried `max_retries`
+/// times, waiting the `delay` before every retry.
+///
+/// Unlike [`retry()`], retries any error, as the database may be not even
+/// listening yet.
+async fn retry_connect<T, F, Fut>(
+    max_retries: u32,
+    delay: Duration,
+    mut connect: F,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        match connect().await {
+            Err(e) if attempt < max_retries => {
+                attempt += 1;
+                tracing::warn!(
+                    attempt,
+                    ?delay,
+                    error = %e,
+                    "retrying to connect to the database",
+                );
+                time::sleep(delay).await;
+            }
+            res => return res,
+        }
+    }
+}
+
+async fn connect_with<T>(
+    config: &config::Db,
+    ssl_mode: Option<SslMode>,
+    tls: T,
+) -> Result<Client, Error>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let primary =
+        create_pool(config.url.clone(), config, ssl_mode, tls.clone()).await?;
+    let replica = match &config.read_url {
+        Some(url) => {
+            Some(create_pool(url.clone(), config, ssl_mode, tls).await?)
+        }
+        None => None,
+    };
+    Ok(Client {
+        primary,
+        replica,
+        max_retries: config.max_retries,
+        slow_query_threshold: config.slow_query_threshold,
+        slow_acquire_threshold: config.slow_acquire_threshold,
+        acquire_timeouts: Arc::default(),
+        write_batch_size: config.write_batch_size,
+    })
+}
+
+/// Creates a TLS connector verifying the database against the root
+/// certificates of the platform.
+fn tls_connector() -> Result<MakeRustlsConnect, Error> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add_parsable_certificates(
+        rustls_native_certs::load_native_certs().certs,
+    );
+    if roots.is_empty() {
+        return Err(Error::NoTrustedCertificates);
+    }
+    Ok(MakeRustlsConnect::new(
+        rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    ))
+}
+
+/// Creates a pool of connections to the database at the `url`, sized and
+/// configured as the `config` specifies.
+async fn create_pool<T>(
+    url: String,
+    config: &config::Db,
+    ssl_mode: Option<SslMode>,
+    tls: T,
+) -> Result<Pool, Error>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let mut pool_config = deadpool_postgres::Config::new();
+    pool_config.url = Some(url);
+    // Overrides the `sslmode` of the URL, so the connection can't fall back
+    // to plain text.
+    pool_config.ssl_mode = ssl_mode;
+    pool_config
+        .application_name
+        .clone_from(&config.application_name);
+    let mut pool = PoolConfig::default();
+    if let Some(size) = config.pool_size {
+        pool.max_size = size;
+    }
+    pool.timeouts.wait = config.acquire_timeout;
+    pool_config.pool = Some(pool);
+    let manager = Manager::from_connect(
+        pool_config
+            .get_pg_config()
+            .map_err(CreatePoolError::Config)?,
+        NoticeLoggingConnect(tls),
+        pool_config.get_manager_config(),
+    );
+    let mut builder = Pool::builder(manager)
+        .config(pool_config.get_pool_config())
+        .runtime(Runtime::Tokio1);
+    let mut settings = config.options.clone();
+    if let Some(timeout) = config.statement_timeout {
+        settings.insert(
+            "statement_timeout".to_owned(),
+            format!("{}ms", timeout.as_millis()),
+        );
+    }
+    if !settings.is_empty() {
+        // Applied to every connection once it's established, so a pooled
+        // connection never runs without them.
+        let settings = Arc::new(settings);
+        builder = builder.post_create(Hook::async_fn(
+            move |client: &mut ClientWrapper, _: &Metrics| {
+                let settings = Arc::clone(&settings);
+                Box::pin(async move {
+                    for (name, value) in &*settings {
+                        client
+                            .execute(
+                                "SELECT set_config($1, $2, false)",
+                                &[name, value],
+                            )
+                            .await
+                            .map_err(HookError::Backend)?;
+                    }
+                    Ok(())
+                })
+            },
+        ));
+    }
+    let pool = builder.build().map_err(CreatePoolError::Build)?;
+
+    // Pool establishes connections lazily, so check the database is
+    // reachable at all before returning it.
+    drop(pool.get().await?);
+
+    Ok(pool)
+}
+
+/// [`Connect`]s with the provided TLS connector, logging the notices the
+/// database sends over the connection, like the ones raised by migrations,
+/// which the default [`Connect`] drops silently.
+struct NoticeLoggingConnect<T>(T);
+
+impl<T> Connect for NoticeLoggingConnect<T>
+where
+    T: MakeTlsConnect<Socket> + Clone + Sync + Send + 'static,
+    T::Stream: Sync + Send,
+    T::TlsConnect: Sync + Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    fn connect(
+        &self,
+        pg_config: &tokio_postgres::Config,
+    ) -> BoxFuture<
+        '_,
+        Result<(tokio_postgres::Client, JoinHandle<()>), tokio_postgres::Error>,
+    > {
+        let tls = self.0.clone();
+        let pg_config = pg_config.clone();
+        Box::pin(async move {
+            let (client, mut connection) = pg_config.connect(tls).await?;
+            let task = tokio::spawn(async move {
+                let mut messages =
+                    stream::poll_fn(|cx| connection.poll_message(cx));
+                while let Some(message) = messages.next().await {
+                    match message {
+                        Ok(AsyncMessage::Notice(notice)) => log_notice(&notice),
+                        Ok(_) => {}
+                        Err(e) => {
+                            tracing::warn!("database connection failed: {e}");
+                            break;
+                        }
+                    }
+                }
+            });
+            Ok((client, task))
+        })
+    }
+}
+
+/// Logs the `notice` sent by the database.
+fn log_notice(notice: &DbError) {
+    tracing::debug!(
+        severity = notice.severity(),
+        notice = notice.message(),
+        "postgres notice",
+    );
+}
+
+#[derive(Clone)]
+pub struct Client {
+    primary: Pool,
+    replica: Option<Pool>,
+
+    /// Number of times a read-only query failed due to a transient error is
+    /// retried.
+    max_retries: u32,
+
+    /// Duration of a query above which it's logged as slow.
+    slow_query_threshold: Duration,
+
+    /// Time to wait for a connection above which it's logged as slow.
+    slow_acquire_threshold: Duration,
+
+    /// Shared with the [`Client::primary()`] views of this [`Client`], as
+    /// they check connections out of the same pools.
+    acquire_timeouts: Arc<AcquireTimeouts>,
+
+    /// Number of rows inserted by a single statement of a batch write.
+    write_batch_size: NonZeroUsize,
+}
+
+/// Numbers of times checking out a connection of a [`Client`] timed out, per
+/// pool.
+#[derive(Debug, Default)]
+struct AcquireTimeouts {
+    primary: AtomicU64,
+    replica: AtomicU64,
+}
+
+/// State of a connection pool of a [`Client`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PoolMetrics {
+    /// Database the pool connects to, either `primary` or `replica`.
+    pub pool: &'static str,
+
+    /// Number of connections open.
+    pub size: usize,
+
+    /// Number of connections open, but not checked out.
+    pub idle: usize,
+
+    /// Number of tasks waiting for a connection to be checked out.
+    pub waiting: usize,
+
+    /// Number of times waiting for a connection timed out.
+    ///
+    /// Never happens without [`config::Db::acquire_timeout`] set.
+    pub acquire_timeouts: u64,
+}
+
+/// Connection checked out of the pool, used to run [`Transaction`]s.
+pub struct Connection(Object);
+
+impl Connection {
+    pub async fn transaction(&mut self) -> Result<Transaction<'_>, Error> {
+        Ok(Transaction(self.0.transaction().await?))
+    }
+}
+
+/// Database transaction, rolled back unless [committed].
+///
+/// [committed]: Transaction::commit
+pub struct Transaction<'a>(deadpool_postgres::Transaction<'a>);
+
+impl Transaction<'_> {
+    pub async fn commit(self) -> Result<(), Error> {
+        Ok(self.0.commit().await?)
+    }
+
+    /// Runs the `op` within a [`Savepoint`] of this [`Transaction`] with the
+    /// provided `name`.
+    ///
+    /// The [`Savepoint`] is released if the `op` succeeds, and rolled back to
+    /// otherwise, so a failed `op` leaves no trace, neither its changes nor
+    /// the [`audit::Event`]s recorded along with them, while the rest of this
+    /// [`Transaction`] may still be committed.
+    pub async fn savepoint<T, E, F>(
+        &mut self,
+        name: &str,
+        op: F,
+    ) -> Result<T, E>
+    where
+        F: for<'s> FnOnce(&'s Savepoint<'_>) -> BoxFuture<'s, Result<T, E>>,
+        E: From<Error>,
+    {
+        let sp = self.0.savepoint(name).await.map_err(Error::from)?;
+        let sp = Savepoint(Transaction(sp));
+        let result = op(&sp).await;
+        match result {
+            Ok(v) => {
+                sp.release().await?;
+                Ok(v)
+            }
+            Err(e) => {
+                sp.rollback_to().await?;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Savepoint within a [`Transaction`], running statements just like the
+/// [`Transaction`] itself does.
+pub struct Savepoint<'a>(Transaction<'a>);
+
+impl<'a> Deref for Savepoint<'a> {
+    type Target = Transaction<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Savepoint<'_> {
+    /// Keeps the changes made since this [`Savepoint`] in its
+    /// [`Transaction`].
+    pub async fn release(self) -> Result<(), Error> {
+        Ok(self.0 .0.commit().await?)
+    }
+
+    /// Discards the changes made since this [`Savepoint`], keeping the ones
+    /// made before it in its [`Transaction`].
+    pub async fn rollback_to(self) -> Result<(), Error> {
+        Ok(self.0 .0.rollback().await?)
+    }
+}
+
+/// Database a query is executed on.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Target {
+    /// Primary database, accepting writes.
+    Primary,
+
+    /// Read replica, if configured, which may lag behind the primary.
+    Replica,
+}
+
+impl Target {
+    /// Resolves this [`Target`] to the database actually available.
+    fn resolve(self, replica_configured: bool) -> Self {
+        match self {
+            Self::Replica if replica_configured => Self::Replica,
+            Self::Primary | Self::Replica => Self::Primary,
+        }
+    }
+}
+
+impl Client {
+    /// Returns a [`Client`] executing all the queries on the primary, so they
+    /// observe the preceding writes.
+    pub fn primary(&self) -> Self {
+        Self {
+            primary: self.primary.clone(),
+            replica: None,
+            max_retries: self.max_retries,
+            slow_query_threshold: self.slow_query_threshold,
+            slow_acquire_threshold: self.slow_acquire_threshold,
+            acquire_timeouts: Arc::clone(&self.acquire_timeouts),
+            write_batch_size: self.write_batch_size,
+        }
+    }
+
+    /// Reports the state of the connection pools of this [`Client`], the
+    /// primary first.
+    pub fn pool_metrics(&self) -> Vec<PoolMetrics> {
+        let timeouts = &self.acquire_timeouts;
+        let mut metrics =
+            vec![pool_metrics("primary", &self.primary, &timeouts.primary)];
+        if let Some(replica) = &self.replica {
+            metrics.push(pool_metrics("replica", replica, &timeouts.replica));
+        }
+        metrics
+    }
+
+    /// Runs the `query` of the `operation` within a `db.query` span.
+    ///
+    /// See [`traced()`] for details.
+    async fn traced<Fut: Future>(
+        &self,
+        operation: &'static str,
+        query: Fut,
+    ) -> Fut::Output {
+        traced(operation, self.slow_query_threshold, query).await
+    }
+
+    /// Checks out a [`Connection`] to the primary.
+    pub async fn connection(&self) -> Result<Connection, Error> {
+        self.conn(Target::Primary).await.map(Connection)
+    }
+
+    async fn conn(&self, target: Target) -> Result<Object, Error> {
+        let (pool, timeouts) = match target.resolve(self.replica.is_some()) {
+            Target::Primary => (&self.primary, &self.acquire_timeouts.primary),
+            Target::Replica => (
+                self.replica.as_ref().unwrap_or(&self.primary),
+                &self.acquire_timeouts.replica,
+            ),
+        };
+
+        let started = Instant::now();
+        let conn = pool.get().await;
+        let elapsed = started.elapsed();
+        if elapsed > self.slow_acquire_threshold {
+            let waiting = pool.status().waiting;
+            tracing::warn!(?elapsed, waiting, "database connection is slow");
+        }
+        if let Err(PoolError::Timeout(_)) = conn {
+            timeouts.fetch_add(1, Ordering::Relaxed);
+        }
+        Ok(conn?)
+    }
+
+    /// Executes the read-only `sql` on the `target`, retrying it on
+    /// [transient] errors.
+    ///
+    /// [transient]: Error::is_transient
+    async fn read(
+        &self,
+        target: Target,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Vec<Row>, Error> {
+        let rows = retry(self.max_retries, || async move {
+            Ok(self.conn(target).await?.query(sql, params).await?)
+        })
+        .await?;
+        record_rows(rows.len());
+        Ok(rows)
+    }
 
-pub use tokio_postgres::Error;
+    /// Same as [`Client::read()`], but expects at most one row.
+    async fn read_opt(
+        &self,
+        target: Target,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Option<Row>, Error> {
+        let row = retry(self.max_retries, || async move {
+            Ok(self.conn(target).await?.query_opt(sql, params).await?)
+        })
+        .await?;
+        record_rows(usize::from(row.is_some()));
+        Ok(row)
+    }
 
-pub use self::{ticket::Ticket, user::User};
+    /// Same as [`Client::read()`], but expects exactly one row.
+    async fn read_one(
+        &self,
+        target: Target,
+        sql: &str,
+        params: &[&(dyn ToSql + Sync)],
+    ) -> Result<Row, Error> {
+        let row = retry(self.max_retries, || async move {
+            Ok(self.conn(target).await?.query_one(sql, params).await?)
+        })
+        .await?;
+        record_rows(1);
+        Ok(row)
+    }
 
-pub type Connection = tokio_postgres::Connection<Socket, NoTlsStream>;
+    /// Checks whether the database is reachable and responds within the
+    /// given `timeout`.
+    pub async fn ping(&self, timeout: Duration) -> Result<(), PingError> {
+        const SQL: &str = "SELECT 1";
+        time::timeout(timeout, async {
+            self.conn(Target::Primary).await?.execute(SQL, &[]).await?;
+            Ok::<_, Error>(())
+        })
+        .await
+        .map_err(|_| PingError::Timeout)??;
+        Ok(())
+    }
 
-pub async fn connect(
-    config: config::Db,
-) -> Result<(Client, Connection), Error> {
-    tokio_postgres::connect(&config.url, NoTls)
+    /// Returns the current time by the database clock, which the times
+    /// recorded by the database itself are comparable to.
+    pub async fn get_current_time(
+        &self,
+    ) -> Result<::time::OffsetDateTime, Error> {
+        const SQL: &str = "SELECT now() AS now";
+        self.traced("get_current_time", async move {
+            let row = self.read_one(Target::Replica, SQL, &[]).await?;
+            Ok(row.get("now"))
+        })
         .await
-        .map(|(client, connection)| (Client(client), connection))
+    }
+}
+
+/// Reports the state of the `pool` named so, which timed out checking out a
+/// connection the `timeouts` times.
+fn pool_metrics(
+    name: &'static str,
+    pool: &Pool,
+    timeouts: &AtomicU64,
+) -> PoolMetrics {
+    let status = pool.status();
+    PoolMetrics {
+        pool: name,
+        size: status.size,
+        idle: status.available,
+        waiting: status.waiting,
+        acquire_timeouts: timeouts.load(Ordering::Relaxed),
+    }
+}
+
+/// Runs the `query` of the `operation` within a `db.query` span, recording
+/// how long it took, and warns if it took longer than the `slow_threshold`.
+///
+/// The number of rows the `query` returned is recorded by the [`Client`]
+/// reading them, via [`record_rows()`].
+async fn traced<Fut>(
+    operation: &'static str,
+    slow_threshold: Duration,
+    query: Fut,
+) -> Fut::Output
+where
+    Fut: Future,
+{
+    let span = tracing::info_span!(
+        "db.query",
+        operation,
+        rows = field::Empty,
+        elapsed_ms = field::Empty,
+    );
+    let started = Instant::now();
+    let output = query.instrument(span.clone()).await;
+    let elapsed = started.elapsed();
+    span.record("elapsed_ms", elapsed.as_millis());
+    if elapsed > slow_threshold {
+        span.in_scope(|| {
+            tracing::warn!(operation, ?elapsed, "database query is slow");
+        });
+    }
+    output
+}
+
+/// Records the number of `rows` returned into the current `db.query` span.
+fn record_rows(rows: usize) {
+    Span::current().record("rows", rows);
+}
+
+/// Delay before the first retry of a failed query.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(50);
+
+/// Upper bound of the delay between retries of a failed query.
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// Runs the `query` until it succeeds, fails with a non-[transient] error,
+/// or has been retried `max_retries` times.
+///
+/// Must only be used for queries that are safe to repeat.
+///
+/// [transient]: Error::is_transient
+async fn retry<T, F, Fut>(max_retries: u32, mut query: F) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        match query().await {
+            Err(e) if e.is_transient() && attempt < max_retries => {
+                let delay = retry_delay(attempt);
+                attempt += 1;
+                tracing::warn!(
+                    attempt,
+                    ?delay,
+                    error = %e,
+                    "retrying a query failed due to a transient error",
+                );
+                time::sleep(delay).await;
+            }
+            res => return res,
+        }
+    }
+}
+
+/// Returns the delay before retrying a query failed `attempt` times already.
+///
+/// The delay grows exponentially and is randomly shortened by up to a half,
+/// so the queries failed at once don't hit the database at once again.
+fn retry_delay(attempt: u32) -> Duration {
+    let max = RETRY_BASE_DELAY
+        .saturating_mul(2_u32.saturating_pow(attempt))
+        .min(RETRY_MAX_DELAY);
+    max.mul_f64(rand::thread_rng().gen_range(0.5..=1.0))
+}
+
+#[derive(Debug, Display, From)]
+pub enum Error {
+    #[display("postgres error: {_0}")]
+    Postgres(tokio_postgres::Error),
+    #[display("constraint `{constraint}` is violated")]
+    ConstraintViolation { constraint: String },
+    #[display("transaction is aborted due to a concurrent one: {_0}")]
+    Serialization(tokio_postgres::Error),
+    #[display("connection pool error: {_0}")]
+    #[from]
+    Pool(PoolError),
+    #[display("failed to create connection pool: {_0}")]
+    #[from]
+    CreatePool(CreatePoolError),
+    #[display(
+        "SSL is required, but no trusted root certificates are found to \
+         verify the database with"
+    )]
+    NoTrustedCertificates,
+    #[display("`{param}` is out of range")]
+    OutOfRange { param: &'static str },
 }
 
-pub struct Client(tokio_postgres::Client);
+impl Error {
+    /// Returns the field whose unique constraint is violated, if known.
+    pub fn violated_field(&self) -> Option<&'static str> {
+        let Self::ConstraintViolation { constraint } = self else {
+            return None;
+        };
+        match constraint.as_str() {
+            "users_login_key" => Some("login"),
+            "comments_pkey" | "tickets_pkey" | "users_pkey" => Some("id"),
+            _ => None,
+        }
+    }
+
+    /// Returns the field whose check constraint is violated, if known.
+    ///
+    /// Such violations mean an invalid value made it past the application
+    /// validation, unlike the conflicts reported by
+    /// [`Error::violated_field()`].
+    pub fn violated_check(&self) -> Option<&'static str> {
+        let Self::ConstraintViolation { constraint } = self else {
+            return None;
+        };
+        match constraint.as_str() {
+            "tickets_count_check" => Some("count"),
+            "tickets_received_count_check" => Some("received_count"),
+            _ => None,
+        }
+    }
+
+    /// Indicates whether this [`Error`] is likely to go away on its own (like
+    /// a dropped connection or a serialization failure), so the failed query
+    /// is worth retrying.
+    ///
+    /// Constraint violations, syntax errors and the like are never
+    /// transient.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::Postgres(e) | Self::Pool(PoolError::Backend(e)) => {
+                is_transient_postgres_error(e)
+            }
+            Self::Pool(PoolError::Timeout(_)) | Self::Serialization(_) => true,
+            Self::Pool(_)
+            | Self::ConstraintViolation { .. }
+            | Self::CreatePool(_)
+            | Self::NoTrustedCertificates
+            | Self::OutOfRange { .. } => false,
+        }
+    }
+}
+
+/// Converts the `value` of the query parameter named `param` into a
+/// `BIGINT`, failing rather than panicking if it doesn't fit.
+fn bigint(value: usize, param: &'static str) -> Result<i64, Error> {
+    i64::try_from(value).map_err(|_| Error::OutOfRange { param })
+}
+
+/// Converts a `COUNT(*)`, which is never negative, into a [`usize`],
+/// saturating on the targets it's narrower than a `BIGINT` on.
+fn count(count: i64) -> usize {
+    usize::try_from(count).unwrap_or(usize::MAX)
+}
+
+fn is_transient_postgres_error(e: &tokio_postgres::Error) -> bool {
+    match e.code() {
+        Some(code) => is_transient_code(code),
+        None => {
+            e.is_closed() || e.source().is_some_and(|e| e.is::<io::Error>())
+        }
+    }
+}
+
+/// Indicates whether an error with the provided [`SqlState`] `code` is
+/// transient.
+fn is_transient_code(code: &SqlState) -> bool {
+    /// Class of the connection exception codes.
+    const CONNECTION_EXCEPTION_CLASS: &str = "08";
+
+    code.code().starts_with(CONNECTION_EXCEPTION_CLASS)
+        || [
+            SqlState::T_R_SERIALIZATION_FAILURE,
+            SqlState::T_R_DEADLOCK_DETECTED,
+            SqlState::ADMIN_SHUTDOWN,
+            SqlState::CRASH_SHUTDOWN,
+            SqlState::CANNOT_CONNECT_NOW,
+            SqlState::TOO_MANY_CONNECTIONS,
+        ]
+        .contains(code)
+}
+
+/// Returns the constraint violated by an error with the provided [`SqlState`]
+/// `code`, reported along with the `constraint` name, if any.
+fn violated_constraint<'a>(
+    code: &SqlState,
+    constraint: Option<&'a str>,
+) -> Option<&'a str> {
+    /// Class of the integrity constraint violation codes.
+    const INTEGRITY_CONSTRAINT_VIOLATION_CLASS: &str = "23";
+
+    constraint.filter(|_| {
+        code.code()
+            .starts_with(INTEGRITY_CONSTRAINT_VIOLATION_CLASS)
+    })
+}
+
+/// Indicates whether an error with the provided [`SqlState`] `code` aborts a
+/// transaction in favor of a concurrent one.
+fn is_serialization_code(code: &SqlState) -> bool {
+    [
+        SqlState::T_R_SERIALIZATION_FAILURE,
+        SqlState::T_R_DEADLOCK_DETECTED,
+    ]
+    .contains(code)
+}
+
+impl From<tokio_postgres::Error> for Error {
+    fn from(e: tokio_postgres::Error) -> Self {
+        let Some(db_error) = e.as_db_error() else {
+            return Self::Postgres(e);
+        };
+        if is_serialization_code(db_error.code()) {
+            return Self::Serialization(e);
+        }
+        let constraint =
+            violated_constraint(db_error.code(), db_error.constraint())
+                .map(str::to_owned);
+        match constraint {
+            Some(constraint) => Self::ConstraintViolation { constraint },
+            None => Self::Postgres(e),
+        }
+    }
+}
+
+impl StdError for Error {}
+
+/// [`Error`] as reported in the responses, when [exposed].
+///
+/// [exposed]: crate::config::Http::expose_db_errors
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SerializableDbError {
+    /// SQLSTATE code of the error, if reported by the database, or the kind
+    /// of the error otherwise.
+    pub code: String,
+
+    /// Primary message reported by the database, or the description of the
+    /// error otherwise.
+    pub message: String,
+
+    /// Details reported by the database along with the [`message`].
+    ///
+    /// [`message`]: Self::message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl From<&Error> for SerializableDbError {
+    fn from(e: &Error) -> Self {
+        let code = match e {
+            Error::Postgres(e)
+            | Error::Serialization(e)
+            | Error::Pool(PoolError::Backend(e)) => {
+                if let Some(db) = e.as_db_error() {
+                    return Self {
+                        code: db.code().code().to_owned(),
+                        message: db.message().to_owned(),
+                        detail: db.detail().map(ToOwned::to_owned),
+                    };
+                }
+                e.code().map_or("postgres", SqlState::code)
+            }
+            Error::ConstraintViolation { .. } => "constraint_violation",
+            Error::Pool(_) => "pool",
+            Error::CreatePool(_) => "create_pool",
+            Error::NoTrustedCertificates => "no_trusted_certificates",
+            Error::OutOfRange { .. } => "out_of_range",
+        };
+        Self {
+            code: code.to_owned(),
+            message: e.to_string(),
+            detail: None,
+        }
+    }
+}
+
+#[derive(Debug, Display, From)]
+pub enum PingError {
+    #[display("database error: {_0}")]
+    #[from]
+    DbError(Error),
+    #[display("database didn't respond in time")]
+    Timeout,
+}
+
+#[derive(Debug, Display, From)]
+pub enum SchemaVersionError {
+    #[display("database error: {_0}")]
+    #[from]
+    DbError(Error),
+    #[display(
+        "database schema has no version and lacks the columns: {}",
+        columns.join(", ")
+    )]
+    MissingColumns { columns: Vec<String> },
+    #[display(
+        "database schema is of version `{actual}`, expected `{expected}`"
+    )]
+    Mismatch { expected: String, actual: String },
+}
+
+impl StdError for SchemaVersionError {}
+
+#[cfg(test)]
+mod target_spec {
+    use super::Target;
+
+    #[test]
+    fn uses_replica_when_configured() {
+        assert_eq!(Target::Replica.resolve(true), Target::Replica);
+        assert_eq!(Target::Primary.resolve(true), Target::Primary);
+    }
+
+    #[test]
+    fn falls_back_to_primary_without_replica() {
+        assert_eq!(Target::Replica.resolve(false), Target::Primary);
+        assert_eq!(Target::Primary.resolve(false), Target::Primary);
+    }
+}
+
+#[cfg(test)]
+mod error_spec {
+    use tokio_postgres::error::SqlState;
+
+    use super::{
+        is_serialization_code, violated_constraint, Error, SerializableDbError,
+    };
+
+    #[test]
+    fn reports_violated_constraints() {
+        for code in [
+            SqlState::UNIQUE_VIOLATION,
+            SqlState::FOREIGN_KEY_VIOLATION,
+            SqlState::CHECK_VIOLATION,
+            SqlState::EXCLUSION_VIOLATION,
+        ] {
+            assert_eq!(
+                violated_constraint(&code, Some("users_login_key")),
+                Some("users_login_key"),
+                "{code:?}",
+            );
+        }
+    }
+
+    #[test]
+    fn reports_no_constraint_unless_violated() {
+        assert_eq!(
+            violated_constraint(&SqlState::SYNTAX_ERROR, Some("users_pkey")),
+            None,
+        );
+        assert_eq!(
+            violated_constraint(&SqlState::NOT_NULL_VIOLATION, None),
+            None,
+        );
+    }
+
+    #[test]
+    fn tells_check_violations_from_conflicts() {
+        let check = Error::ConstraintViolation {
+            constraint: "tickets_count_check".to_owned(),
+        };
+        assert_eq!(check.violated_check(), Some("count"));
+        assert_eq!(check.violated_field(), None);
+
+        let conflict = Error::ConstraintViolation {
+            constraint: "users_login_key".to_owned(),
+        };
+        assert_eq!(conflict.violated_check(), None);
+        assert_eq!(conflict.violated_field(), Some("login"));
+    }
+
+    #[test]
+    fn classifies_serialization_codes() {
+        assert!(is_serialization_code(&SqlState::T_R_SERIALIZATION_FAILURE));
+        assert!(is_serialization_code(&SqlState::T_R_DEADLOCK_DETECTED));
+        assert!(!is_serialization_code(&SqlState::UNIQUE_VIOLATION));
+        assert!(!is_serialization_code(&SqlState::CONNECTION_FAILURE));
+    }
+
+    #[test]
+    fn serializes_kind_of_error_without_sqlstate() {
+        let e = Error::ConstraintViolation {
+            constraint: "users_login_key".to_owned(),
+        };
+        assert_eq!(
+            SerializableDbError::from(&e),
+            SerializableDbError {
+                code: "constraint_violation".to_owned(),
+                message: "constraint `users_login_key` is violated".to_owned(),
+                detail: None,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod retry_spec {
+    use std::cell::Cell;
+
+    use deadpool_postgres::{PoolError, TimeoutType};
+    use tokio_postgres::error::SqlState;
+
+    use super::{
+        is_transient_code, retry, retry_delay, Error, RETRY_BASE_DELAY,
+        RETRY_MAX_DELAY,
+    };
+
+    #[test]
+    fn classifies_transient_codes() {
+        for code in [
+            SqlState::CONNECTION_FAILURE,
+            SqlState::CONNECTION_EXCEPTION,
+            SqlState::T_R_SERIALIZATION_FAILURE,
+            SqlState::T_R_DEADLOCK_DETECTED,
+            SqlState::ADMIN_SHUTDOWN,
+            SqlState::CANNOT_CONNECT_NOW,
+        ] {
+            assert!(is_transient_code(&code), "{code:?} must be transient");
+        }
+    }
+
+    #[test]
+    fn classifies_permanent_codes() {
+        for code in [
+            SqlState::UNIQUE_VIOLATION,
+            SqlState::FOREIGN_KEY_VIOLATION,
+            SqlState::CHECK_VIOLATION,
+            SqlState::SYNTAX_ERROR,
+            SqlState::UNDEFINED_TABLE,
+            SqlState::INSUFFICIENT_PRIVILEGE,
+        ] {
+            assert!(!is_transient_code(&code), "{code:?} must be permanent");
+        }
+    }
+
+    #[test]
+    fn classifies_pool_errors() {
+        assert!(
+            Error::Pool(PoolError::Timeout(TimeoutType::Wait)).is_transient()
+        );
+        assert!(!Error::Pool(PoolError::Closed).is_transient());
+        assert!(!Error::ConstraintViolation {
+            constraint: "users_login_key".to_owned(),
+        }
+        .is_transient());
+    }
+
+    #[test]
+    fn grows_delay_exponentially_up_to_max() {
+        for attempt in 0..3 {
+            let max = RETRY_BASE_DELAY * 2_u32.pow(attempt);
+            let delay = retry_delay(attempt);
+            assert!(max / 2 <= delay && delay <= max, "{delay:?}");
+        }
+        assert!(retry_delay(100) <= RETRY_MAX_DELAY);
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors() {
+        let calls = Cell::new(0);
+        let res = retry(3, || async {
+            calls.set(calls.get() + 1);
+            if calls.get() < 3 {
+                return Err(Error::Pool(PoolError::Timeout(TimeoutType::Wait)));
+            }
+            Ok(calls.get())
+        })
+        .await;
+        assert_eq!(res.ok(), Some(3));
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_retries() {
+        let calls = Cell::new(0);
+        let res = retry(2, || async {
+            calls.set(calls.get() + 1);
+            Err::<(), _>(Error::Pool(PoolError::Timeout(TimeoutType::Wait)))
+        })
+        .await;
+        assert!(res.is_err());
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn doesnt_retry_permanent_errors() {
+        let calls = Cell::new(0);
+        let res = retry(3, || async {
+            calls.set(calls.get() + 1);
+            Err::<(), _>(Error::ConstraintViolation {
+                constraint: "users_login_key".to_owned(),
+            })
+        })
+        .await;
+        assert!(res.is_err());
+        assert_eq!(calls.get(), 1);
+    }
+}
+
+#[cfg(test)]
+mod retry_connect_spec {
+    use std::{cell::Cell, time::Duration};
+
+    use deadpool_postgres::PoolError;
+
+    use super::{retry_connect, Error};
+
+    #[tokio::test]
+    async fn retries_until_connected() {
+        let attempts = Cell::new(0);
+        let res = retry_connect(3, Duration::ZERO, || async {
+            attempts.set(attempts.get() + 1);
+            if attempts.get() <= 2 {
+                return Err(Error::Pool(PoolError::Closed));
+            }
+            Ok(())
+        })
+        .await;
+        assert!(res.is_ok(), "{res:?}");
+        assert_eq!(attempts.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn returns_last_error_once_retries_exhausted() {
+        let attempts = Cell::new(0);
+        let res = retry_connect(2, Duration::ZERO, || async {
+            attempts.set(attempts.get() + 1);
+            Err::<(), _>(Error::Pool(PoolError::Closed))
+        })
+        .await;
+        assert!(
+            matches!(res, Err(Error::Pool(PoolError::Closed))),
+            "{res:?}"
+        );
+        assert_eq!(attempts.get(), 3);
+    }
+}
+
+#[cfg(test)]
+mod traced_spec {
+    use std::{
+        collections::HashMap,
+        fmt,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use tokio::time;
+    use tracing::{
+        field::{Field, Visit},
+        span, Event, Level, Subscriber,
+    };
+    use tracing_subscriber::{
+        layer::{Context, SubscriberExt as _},
+        Layer, Registry,
+    };
+
+    use super::{record_rows, traced};
+
+    type Fields = HashMap<String, String>;
+
+    /// [`Layer`] capturing the fields of all the spans and warnings.
+    #[derive(Clone, Default)]
+    struct Captured {
+        spans: Arc<Mutex<HashMap<span::Id, (&'static str, Fields)>>>,
+        warnings: Arc<Mutex<Vec<Fields>>>,
+    }
+
+    impl Captured {
+        /// Returns the fields of the only `db.query` span.
+        fn query_fields(&self) -> Fields {
+            let spans = self.spans.lock().unwrap();
+            let mut queries =
+                spans.values().filter(|(name, _)| *name == "db.query");
+            let (_, fields) = queries.next().expect("no `db.query` span");
+            assert!(queries.next().is_none(), "many `db.query` spans");
+            fields.clone()
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for Captured {
+        fn on_new_span(
+            &self,
+            attrs: &span::Attributes<'_>,
+            id: &span::Id,
+            _: Context<'_, S>,
+        ) {
+            let mut fields = Fields::new();
+            attrs.record(&mut Visitor(&mut fields));
+            self.spans
+                .lock()
+                .unwrap()
+                .insert(id.clone(), (attrs.metadata().name(), fields));
+        }
+
+        fn on_record(
+            &self,
+            id: &span::Id,
+            values: &span::Record<'_>,
+            _: Context<'_, S>,
+        ) {
+            if let Some((_, fields)) = self.spans.lock().unwrap().get_mut(id) {
+                values.record(&mut Visitor(fields));
+            }
+        }
+
+        fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+            if *event.metadata().level() == Level::WARN {
+                let mut fields = Fields::new();
+                event.record(&mut Visitor(&mut fields));
+                self.warnings.lock().unwrap().push(fields);
+            }
+        }
+    }
+
+    struct Visitor<'a>(&'a mut Fields);
+
+    impl Visit for Visitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.insert(field.name().to_owned(), value.to_owned());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.insert(field.name().to_owned(), format!("{value:?}"));
+        }
+    }
+
+    #[tokio::test]
+    async fn records_query_fields() {
+        let captured = Captured::default();
+        let _guard = tracing::subscriber::set_default(
+            Registry::default().with(captured.clone()),
+        );
+
+        let output = traced("get_user_by_id", Duration::from_secs(60), async {
+            record_rows(2);
+            42
+        })
+        .await;
+        assert_eq!(output, 42);
+
+        let fields = captured.query_fields();
+        assert_eq!(fields["operation"], "get_user_by_id");
+        assert_eq!(fields["rows"], "2");
+        assert!(fields.contains_key("elapsed_ms"), "{fields:?}");
+        assert!(captured.warnings.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn warns_about_slow_query() {
+        let captured = Captured::default();
+        let _guard = tracing::subscriber::set_default(
+            Registry::default().with(captured.clone()),
+        );
+
+        traced(
+            "get_tickets_count",
+            Duration::ZERO,
+            time::sleep(Duration::from_millis(1)),
+        )
+        .await;
+
+        let warnings = captured.warnings.lock().unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0]["operation"], "get_tickets_count");
+        assert!(warnings[0].contains_key("elapsed"), "{:?}", warnings[0]);
+    }
+}
+
+#[cfg(test)]
+mod tls_spec {
+    use tokio_postgres_rustls::MakeRustlsConnect;
+
+    use super::connect_with;
+
+    #[test]
+    fn accepts_rustls_connector() {
+        // Instantiating the SSL branch is enough for the compiler to check
+        // the connector satisfies the pool bounds.
+        let _ = connect_with::<MakeRustlsConnect>;
+    }
+}
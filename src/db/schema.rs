@@ -0,0 +1,156 @@
+use std::collections::HashSet;
+
+use tokio_postgres::error::SqlState;
+
+use super::{Client, Error, Target};
+
+/// Version of the database schema this build expects: the name of the
+/// latest migration.
+///
+/// Names of the applied migrations are recorded into the `schema_version`
+/// table by the [migrations runner], so this must be bumped along with adding
+/// a migration.
+///
+/// [migrations runner]: super::migrations::run_pending
+pub const SCHEMA_VERSION: &str = "00000000000021_updated_at";
+
+/// Columns this build queries, by their tables.
+///
+/// Checked to be present in a database without the [`SCHEMA_VERSION`]
+/// recorded, so must be updated along with the queries.
+pub const REQUIRED_COLUMNS: &[(&str, &[&str])] = &[
+    (
+        "users",
+        &[
+            "id",
+            "name",
+            "login",
+            "password_hash",
+            "role",
+            "password_changed_at",
+            "email",
+            "updated_at",
+        ],
+    ),
+    (
+        "tickets",
+        &[
+            "id",
+            "title",
+            "description",
+            "status",
+            "category",
+            "count",
+            "received_count",
+            "price",
+            "payment_reference",
+            "initiator_id",
+            "purchasing_manager_id",
+            "accounting_manager_id",
+            "created_at",
+            "deleted_at",
+            "updated_at",
+            "search_simple",
+            "search_russian",
+        ],
+    ),
+    (
+        "comments",
+        &["id", "ticket_id", "author_id", "text", "created_at"],
+    ),
+    (
+        "audit_events",
+        &[
+            "id",
+            "actor_id",
+            "entity",
+            "entity_id",
+            "action",
+            "payload",
+            "snapshot",
+            "created_at",
+        ],
+    ),
+    (
+        "attachments",
+        &[
+            "id",
+            "ticket_id",
+            "uploader_id",
+            "file_name",
+            "content_type",
+            "size",
+            "created_at",
+        ],
+    ),
+    ("auth_settings", &["tokens_valid_after"]),
+];
+
+impl Client {
+    /// Returns the version of the latest migration applied to the database,
+    /// if any.
+    pub async fn get_schema_version(&self) -> Result<Option<String>, Error> {
+        const SQL: &str = "\
+            SELECT version \
+            FROM schema_version \
+            ORDER BY applied_at DESC, version DESC \
+            LIMIT 1";
+        self.traced("get_schema_version", async move {
+            match self.read_opt(Target::Primary, SQL, &[]).await {
+                Ok(row) => Ok(row.map(|row| row.get("version"))),
+                // Schema managed without migrations has no versions at all.
+                Err(Error::Postgres(e))
+                    if e.code() == Some(&SqlState::UNDEFINED_TABLE) =>
+                {
+                    Ok(None)
+                }
+                Err(e) => Err(e),
+            }
+        })
+        .await
+    }
+
+    /// Returns the [required columns] missing from the tables the queries
+    /// resolve to, as `table.column` names.
+    ///
+    /// [required columns]: REQUIRED_COLUMNS
+    pub async fn get_missing_columns(&self) -> Result<Vec<String>, Error> {
+        // Tables are resolved the same way as in the queries, so a table
+        // shadowing another one in the `search_path` is checked instead.
+        const SQL: &str = "\
+            SELECT c.table_name::TEXT AS table_name, \
+                   c.column_name::TEXT AS column_name \
+            FROM information_schema.columns c \
+            JOIN unnest($1::TEXT[]) t (name) \
+              ON to_regclass(t.name) \
+               = to_regclass(format('%I.%I', c.table_schema, c.table_name))";
+        self.traced("get_missing_columns", async move {
+            let tables = REQUIRED_COLUMNS
+                .iter()
+                .map(|(table, _)| *table)
+                .collect::<Vec<_>>();
+            let present = self
+                .read(Target::Primary, SQL, &[&tables])
+                .await?
+                .into_iter()
+                .map(|row| {
+                    format!(
+                        "{}.{}",
+                        row.get::<_, &str>("table_name"),
+                        row.get::<_, &str>("column_name"),
+                    )
+                })
+                .collect::<HashSet<_>>();
+            Ok(REQUIRED_COLUMNS
+                .iter()
+                .flat_map(|(table, columns)| {
+                    columns
+                        .iter()
+                        .map(move |column| format!("{table}.{column}"))
+                })
+                .filter(|column| !present.contains(column))
+                .collect())
+        })
+        .await
+    }
+}
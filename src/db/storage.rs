@@ -0,0 +1,646 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt as _};
+use serde_json::Value as Json;
+use time::OffsetDateTime;
+
+use super::{
+    attachment::{self, Attachment},
+    audit::{Event, StatusTransition, TicketEdit},
+    ticket::{
+        self, Category, PaymentSummary, PurchasingSummary, Status,
+        StatusUpdateFields, TextSearchConfig, Ticket, TicketFilter,
+        TicketOrder, TicketWithTimes, TicketWithUsers, Visibility,
+        WriteTicketsError,
+    },
+    user::{
+        self, DeleteUserError, PasswordHash, Role, UpdateUserRoleError, User,
+        UserWithTimes,
+    },
+    Client, Error, PingError, PoolMetrics, SchemaVersionError,
+};
+
+/// Storage of the application data, as used by the HTTP handlers.
+///
+/// Implemented by the database [`Client`], and abstracted so the handlers
+/// can be exercised without a live database.
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Returns a [`Storage`] whose reads observe all the preceding writes.
+    fn primary(&self) -> Arc<dyn Storage>;
+
+    /// Checks whether the [`Storage`] is reachable and responds within the
+    /// given `timeout`.
+    async fn ping(&self, timeout: Duration) -> Result<(), PingError>;
+
+    /// Reports the state of the connection pools of the [`Storage`], if it
+    /// has any.
+    fn pool_metrics(&self) -> Vec<PoolMetrics>;
+
+    /// Returns the current time by the clock of the [`Storage`], which the
+    /// times it records are comparable to.
+    async fn get_current_time(&self) -> Result<OffsetDateTime, Error>;
+
+    /// Returns the version of the latest migration applied to the
+    /// [`Storage`], if any.
+    async fn get_schema_version(&self) -> Result<Option<String>, Error>;
+
+    /// Returns the [required columns] missing from the [`Storage`], as
+    /// `table.column` names.
+    ///
+    /// [required columns]: super::REQUIRED_COLUMNS
+    async fn get_missing_columns(&self) -> Result<Vec<String>, Error>;
+
+    /// Checks whether the latest applied migration is the `expected` one, so
+    /// a stale schema is reported upfront rather than by failing queries.
+    ///
+    /// A schema without any migrations recorded, as if managed apart from
+    /// them, is checked to have all the [required columns] instead.
+    ///
+    /// [required columns]: super::REQUIRED_COLUMNS
+    async fn check_schema_version(
+        &self,
+        expected: &str,
+    ) -> Result<(), SchemaVersionError> {
+        match self.get_schema_version().await? {
+            Some(actual) if actual == expected => Ok(()),
+            Some(actual) => Err(SchemaVersionError::Mismatch {
+                expected: expected.to_owned(),
+                actual,
+            }),
+            None => {
+                let columns = self.get_missing_columns().await?;
+                if !columns.is_empty() {
+                    return Err(SchemaVersionError::MissingColumns { columns });
+                }
+                Ok(())
+            }
+        }
+    }
+
+    async fn get_tokens_valid_after(&self) -> Result<OffsetDateTime, Error>;
+
+    async fn set_tokens_valid_after(
+        &self,
+        at: OffsetDateTime,
+    ) -> Result<(), Error>;
+
+    async fn get_user_by_login(
+        &self,
+        login: &str,
+    ) -> Result<Option<User>, Error>;
+
+    async fn get_user_by_id(&self, id: user::Id)
+        -> Result<Option<User>, Error>;
+
+    async fn get_users_by_ids(
+        &self,
+        ids: &[user::Id],
+    ) -> Result<HashMap<user::Id, User>, Error>;
+
+    /// Streams all the users changed at or after `since`, in the order they
+    /// were changed in.
+    ///
+    /// The returned stream doesn't borrow this [`Storage`], so it can be
+    /// moved into a response body.
+    async fn stream_users_updated_since(
+        &self,
+        since: Option<OffsetDateTime>,
+    ) -> Result<BoxStream<'static, Result<UserWithTimes, Error>>, Error>;
+
+    async fn get_user_password_changed_at(
+        &self,
+        id: user::Id,
+    ) -> Result<Option<OffsetDateTime>, Error>;
+
+    async fn update_user_password(
+        &self,
+        id: user::Id,
+        password_hash: &PasswordHash,
+        changed_at: OffsetDateTime,
+    ) -> Result<(), Error>;
+
+    /// Sets the profile fields of the user, returning whether it exists.
+    async fn update_user_profile(
+        &self,
+        id: user::Id,
+        name: &str,
+    ) -> Result<bool, Error>;
+
+    /// Sets the email of the user, returning whether it exists.
+    async fn update_user_email(
+        &self,
+        id: user::Id,
+        email: &str,
+    ) -> Result<bool, Error>;
+
+    async fn delete_user(&self, id: user::Id) -> Result<(), DeleteUserError>;
+
+    /// Changes the role of the user along with recording the audit event of
+    /// this change by the `actor`, unless the user already has the `role`,
+    /// returning the changed user, if there's such one.
+    ///
+    /// Fails if that would leave no admins.
+    async fn update_user_role_with_event(
+        &self,
+        id: user::Id,
+        role: Role,
+        actor: user::Id,
+    ) -> Result<Option<User>, UpdateUserRoleError>;
+
+    async fn get_ticket_by_id(
+        &self,
+        id: ticket::Id,
+        visibility: Visibility,
+    ) -> Result<Option<Ticket>, Error>;
+
+    async fn get_tickets_page_with_count(
+        &self,
+        offset: usize,
+        limit: usize,
+        filter: &TicketFilter,
+    ) -> Result<(Vec<Ticket>, usize), Error>;
+
+    async fn get_tickets_before(
+        &self,
+        created_at: OffsetDateTime,
+        id: ticket::Id,
+        limit: usize,
+        filter: &TicketFilter,
+    ) -> Result<Vec<Ticket>, Error>;
+
+    /// Same as [`Storage::get_tickets_page_with_count()`], but returns the
+    /// tickets along with the users they refer to, in the `order`.
+    async fn get_tickets_page_with_users(
+        &self,
+        offset: usize,
+        limit: usize,
+        filter: &TicketFilter,
+        order: TicketOrder,
+    ) -> Result<(Vec<TicketWithUsers>, usize), Error>;
+
+    /// Same as [`Storage::get_tickets_before()`], but returns the tickets
+    /// along with the users they refer to.
+    async fn get_tickets_before_with_users(
+        &self,
+        created_at: OffsetDateTime,
+        id: ticket::Id,
+        limit: usize,
+        filter: &TicketFilter,
+    ) -> Result<Vec<TicketWithUsers>, Error>;
+
+    async fn get_tickets_count(
+        &self,
+        filter: &TicketFilter,
+    ) -> Result<usize, Error>;
+
+    /// Counts the tickets referring to the user in any role, including the
+    /// soft-deleted ones.
+    async fn count_tickets_for_user(
+        &self,
+        user: user::Id,
+    ) -> Result<usize, Error>;
+
+    async fn get_ticket_ids_for_user(
+        &self,
+        user: user::Id,
+        limit: usize,
+    ) -> Result<Vec<ticket::Id>, Error>;
+
+    /// Counts the tickets matching the `filter` per [`Status`], with zero for
+    /// those no ticket has.
+    async fn get_ticket_counts_by_status(
+        &self,
+        filter: &TicketFilter,
+    ) -> Result<HashMap<Status, usize>, Error>;
+
+    async fn get_purchasing_summary(
+        &self,
+        manager: user::Id,
+    ) -> Result<PurchasingSummary, Error>;
+
+    async fn get_payment_summary(&self) -> Result<PaymentSummary, Error>;
+
+    /// Same as [`Storage::get_tickets_page_with_users()`], but returns only
+    /// the tickets matching the full-text `query`, the most relevant first.
+    async fn search_tickets(
+        &self,
+        query: &str,
+        config: TextSearchConfig,
+        offset: usize,
+        limit: usize,
+        filter: &TicketFilter,
+        order: TicketOrder,
+    ) -> Result<(Vec<TicketWithUsers>, usize), Error>;
+
+    /// Streams all the tickets of the `visibility`, newest first.
+    ///
+    /// The returned stream doesn't borrow this [`Storage`], so it can be
+    /// moved into a response body.
+    async fn stream_tickets(
+        &self,
+        category: Option<Category>,
+        visibility: Visibility,
+    ) -> Result<BoxStream<'static, Result<Ticket, Error>>, Error>;
+
+    /// Streams all the tickets changed at or after `since`, deleted ones
+    /// included, in the order they were changed in.
+    ///
+    /// The returned stream doesn't borrow this [`Storage`], so it can be
+    /// moved into a response body.
+    async fn stream_tickets_updated_since(
+        &self,
+        since: Option<OffsetDateTime>,
+    ) -> Result<BoxStream<'static, Result<TicketWithTimes, Error>>, Error>;
+
+    /// Inserts the new [`Ticket`]s, so either all or none of them are
+    /// stored.
+    async fn write_tickets(
+        &self,
+        tickets: &[Ticket],
+    ) -> Result<usize, WriteTicketsError>;
+
+    /// Writes the [`Ticket`] along with the audit event of its change, so
+    /// either both or none of them are stored.
+    async fn write_ticket_with_event(
+        &self,
+        ticket: &Ticket,
+        actor: user::Id,
+        action: &str,
+        payload: &Json,
+    ) -> Result<(), Error>;
+
+    /// Writes the [`TicketEdit`] along with the audit event of it.
+    ///
+    /// Returns `false` without changing anything if the active ticket isn't
+    /// in the state the edit is decided upon anymore, as another request has
+    /// changed or deleted it meanwhile.
+    async fn edit_ticket_with_event(
+        &self,
+        edit: &TicketEdit,
+    ) -> Result<bool, Error>;
+
+    /// Changes the status of the ticket from the `expected` one to the one
+    /// of the provided [`Ticket`], setting the `fields` along with it and
+    /// recording the audit event of this change.
+    ///
+    /// Returns `false` without changing anything if the ticket isn't in the
+    /// `expected` status, as another request has changed it meanwhile.
+    async fn update_ticket_status_with_event(
+        &self,
+        ticket: &Ticket,
+        expected: ticket::Status,
+        fields: &StatusUpdateFields,
+        actor: user::Id,
+        action: &str,
+        payload: &Json,
+    ) -> Result<bool, Error>;
+
+    /// Applies the [`StatusTransition`]s of several tickets, so either all
+    /// the applicable ones or none of them are stored, returning whether
+    /// each of them is applied.
+    ///
+    /// A ticket not in the expected status is skipped without failing the
+    /// others.
+    async fn update_ticket_statuses_with_events(
+        &self,
+        transitions: &[StatusTransition],
+    ) -> Result<Vec<bool>, Error>;
+
+    /// Returns the [`Event`]s of the ticket, in the order they happened.
+    async fn get_events_for_ticket(
+        &self,
+        ticket_id: ticket::Id,
+    ) -> Result<Vec<Event>, Error>;
+
+    async fn insert_attachment(
+        &self,
+        attachment: &Attachment,
+    ) -> Result<(), Error>;
+
+    async fn get_attachment_by_id(
+        &self,
+        id: attachment::Id,
+    ) -> Result<Option<Attachment>, Error>;
+
+    /// Returns all the [`Attachment`]s of the ticket, from the oldest to the
+    /// newest.
+    async fn get_attachments_for_ticket(
+        &self,
+        ticket_id: ticket::Id,
+    ) -> Result<Vec<Attachment>, Error>;
+}
+
+#[async_trait]
+impl Storage for Client {
+    fn primary(&self) -> Arc<dyn Storage> {
+        Arc::new(Client::primary(self))
+    }
+
+    async fn ping(&self, timeout: Duration) -> Result<(), PingError> {
+        Client::ping(self, timeout).await
+    }
+
+    fn pool_metrics(&self) -> Vec<PoolMetrics> {
+        Client::pool_metrics(self)
+    }
+
+    async fn get_current_time(&self) -> Result<OffsetDateTime, Error> {
+        Client::get_current_time(self).await
+    }
+
+    async fn get_schema_version(&self) -> Result<Option<String>, Error> {
+        Client::get_schema_version(self).await
+    }
+
+    async fn get_missing_columns(&self) -> Result<Vec<String>, Error> {
+        Client::get_missing_columns(self).await
+    }
+
+    async fn get_tokens_valid_after(&self) -> Result<OffsetDateTime, Error> {
+        Client::get_tokens_valid_after(self).await
+    }
+
+    async fn set_tokens_valid_after(
+        &self,
+        at: OffsetDateTime,
+    ) -> Result<(), Error> {
+        Client::set_tokens_valid_after(self, at).await
+    }
+
+    async fn get_user_by_login(
+        &self,
+        login: &str,
+    ) -> Result<Option<User>, Error> {
+        Client::get_user_by_login(self, login).await
+    }
+
+    async fn get_user_by_id(
+        &self,
+        id: user::Id,
+    ) -> Result<Option<User>, Error> {
+        Client::get_user_by_id(self, id).await
+    }
+
+    async fn get_users_by_ids(
+        &self,
+        ids: &[user::Id],
+    ) -> Result<HashMap<user::Id, User>, Error> {
+        Client::get_users_by_ids(self, ids).await
+    }
+
+    async fn stream_users_updated_since(
+        &self,
+        since: Option<OffsetDateTime>,
+    ) -> Result<BoxStream<'static, Result<UserWithTimes, Error>>, Error> {
+        Ok(Client::stream_users_updated_since(self, since)
+            .await?
+            .boxed())
+    }
+
+    async fn get_user_password_changed_at(
+        &self,
+        id: user::Id,
+    ) -> Result<Option<OffsetDateTime>, Error> {
+        Client::get_user_password_changed_at(self, id).await
+    }
+
+    async fn update_user_password(
+        &self,
+        id: user::Id,
+        password_hash: &PasswordHash,
+        changed_at: OffsetDateTime,
+    ) -> Result<(), Error> {
+        Client::update_user_password(self, id, password_hash, changed_at).await
+    }
+
+    async fn update_user_profile(
+        &self,
+        id: user::Id,
+        name: &str,
+    ) -> Result<bool, Error> {
+        Client::update_user_profile(self, id, name).await
+    }
+
+    async fn update_user_email(
+        &self,
+        id: user::Id,
+        email: &str,
+    ) -> Result<bool, Error> {
+        Client::update_user_email(self, id, email).await
+    }
+
+    async fn delete_user(&self, id: user::Id) -> Result<(), DeleteUserError> {
+        Client::delete_user(self, id).await
+    }
+
+    async fn update_user_role_with_event(
+        &self,
+        id: user::Id,
+        role: Role,
+        actor: user::Id,
+    ) -> Result<Option<User>, UpdateUserRoleError> {
+        Client::update_user_role_with_event(self, id, role, actor).await
+    }
+
+    async fn get_ticket_by_id(
+        &self,
+        id: ticket::Id,
+        visibility: Visibility,
+    ) -> Result<Option<Ticket>, Error> {
+        Client::get_ticket_by_id(self, id, visibility).await
+    }
+
+    async fn get_tickets_page_with_count(
+        &self,
+        offset: usize,
+        limit: usize,
+        filter: &TicketFilter,
+    ) -> Result<(Vec<Ticket>, usize), Error> {
+        Client::get_tickets_page_with_count(self, offset, limit, filter).await
+    }
+
+    async fn get_tickets_before(
+        &self,
+        created_at: OffsetDateTime,
+        id: ticket::Id,
+        limit: usize,
+        filter: &TicketFilter,
+    ) -> Result<Vec<Ticket>, Error> {
+        Client::get_tickets_before(self, created_at, id, limit, filter).await
+    }
+
+    async fn get_tickets_page_with_users(
+        &self,
+        offset: usize,
+        limit: usize,
+        filter: &TicketFilter,
+        order: TicketOrder,
+    ) -> Result<(Vec<TicketWithUsers>, usize), Error> {
+        Client::get_tickets_page_with_users(self, offset, limit, filter, order)
+            .await
+    }
+
+    async fn get_tickets_before_with_users(
+        &self,
+        created_at: OffsetDateTime,
+        id: ticket::Id,
+        limit: usize,
+        filter: &TicketFilter,
+    ) -> Result<Vec<TicketWithUsers>, Error> {
+        Client::get_tickets_before_with_users(
+            self, created_at, id, limit, filter,
+        )
+        .await
+    }
+
+    async fn get_tickets_count(
+        &self,
+        filter: &TicketFilter,
+    ) -> Result<usize, Error> {
+        Client::get_tickets_count(self, filter).await
+    }
+
+    async fn count_tickets_for_user(
+        &self,
+        user: user::Id,
+    ) -> Result<usize, Error> {
+        Client::count_tickets_for_user(self, user).await
+    }
+
+    async fn get_ticket_ids_for_user(
+        &self,
+        user: user::Id,
+        limit: usize,
+    ) -> Result<Vec<ticket::Id>, Error> {
+        Client::get_ticket_ids_for_user(self, user, limit).await
+    }
+
+    async fn get_ticket_counts_by_status(
+        &self,
+        filter: &TicketFilter,
+    ) -> Result<HashMap<Status, usize>, Error> {
+        Client::get_ticket_counts_by_status(self, filter).await
+    }
+
+    async fn get_purchasing_summary(
+        &self,
+        manager: user::Id,
+    ) -> Result<PurchasingSummary, Error> {
+        Client::get_purchasing_summary(self, manager).await
+    }
+
+    async fn get_payment_summary(&self) -> Result<PaymentSummary, Error> {
+        Client::get_payment_summary(self).await
+    }
+
+    async fn search_tickets(
+        &self,
+        query: &str,
+        config: TextSearchConfig,
+        offset: usize,
+        limit: usize,
+        filter: &TicketFilter,
+        order: TicketOrder,
+    ) -> Result<(Vec<TicketWithUsers>, usize), Error> {
+        Client::search_tickets(
+            self, query, config, offset, limit, filter, order,
+        )
+        .await
+    }
+
+    async fn stream_tickets(
+        &self,
+        category: Option<Category>,
+        visibility: Visibility,
+    ) -> Result<BoxStream<'static, Result<Ticket, Error>>, Error> {
+        Ok(Client::stream_tickets(self, category, visibility)
+            .await?
+            .boxed())
+    }
+
+    async fn stream_tickets_updated_since(
+        &self,
+        since: Option<OffsetDateTime>,
+    ) -> Result<BoxStream<'static, Result<TicketWithTimes, Error>>, Error> {
+        Ok(Client::stream_tickets_updated_since(self, since)
+            .await?
+            .boxed())
+    }
+
+    async fn write_tickets(
+        &self,
+        tickets: &[Ticket],
+    ) -> Result<usize, WriteTicketsError> {
+        Client::write_tickets(self, tickets).await
+    }
+
+    async fn write_ticket_with_event(
+        &self,
+        ticket: &Ticket,
+        actor: user::Id,
+        action: &str,
+        payload: &Json,
+    ) -> Result<(), Error> {
+        Client::write_ticket_with_event(self, ticket, actor, action, payload)
+            .await
+    }
+
+    async fn edit_ticket_with_event(
+        &self,
+        edit: &TicketEdit,
+    ) -> Result<bool, Error> {
+        Client::edit_ticket_with_event(self, edit).await
+    }
+
+    async fn update_ticket_status_with_event(
+        &self,
+        ticket: &Ticket,
+        expected: ticket::Status,
+        fields: &StatusUpdateFields,
+        actor: user::Id,
+        action: &str,
+        payload: &Json,
+    ) -> Result<bool, Error> {
+        Client::update_ticket_status_with_event(
+            self, ticket, expected, fields, actor, action, payload,
+        )
+        .await
+    }
+
+    async fn update_ticket_statuses_with_events(
+        &self,
+        transitions: &[StatusTransition],
+    ) -> Result<Vec<bool>, Error> {
+        Client::update_ticket_statuses_with_events(self, transitions).await
+    }
+
+    async fn get_events_for_ticket(
+        &self,
+        ticket_id: ticket::Id,
+    ) -> Result<Vec<Event>, Error> {
+        Client::get_events_for_ticket(self, ticket_id).await
+    }
+
+    async fn insert_attachment(
+        &self,
+        attachment: &Attachment,
+    ) -> Result<(), Error> {
+        Client::insert_attachment(self, attachment).await
+    }
+
+    async fn get_attachment_by_id(
+        &self,
+        id: attachment::Id,
+    ) -> Result<Option<Attachment>, Error> {
+        Client::get_attachment_by_id(self, id).await
+    }
+
+    async fn get_attachments_for_ticket(
+        &self,
+        ticket_id: ticket::Id,
+    ) -> Result<Vec<Attachment>, Error> {
+        Client::get_attachments_for_ticket(self, ticket_id).await
+    }
+}
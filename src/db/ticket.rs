@@ -1,7 +1,15 @@
-use std::error::Error as StdError;
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error as StdError,
+    future::Future,
+    str::FromStr,
+};
 
-use derive_more::Display;
+use deadpool_postgres::GenericClient;
+use derive_more::{Display, From};
 use enum_utils::TryFromRepr;
+use futures::{Stream, StreamExt as _};
+use rust_decimal::{prelude::ToPrimitive as _, Decimal};
 use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 use tokio_postgres::{
@@ -9,27 +17,231 @@ use tokio_postgres::{
         accepts, private::BytesMut, to_sql_checked, FromSql, IsNull, ToSql,
         Type,
     },
-    Error,
+    Row,
 };
 use uuid::Uuid;
 
-use super::{user, Client};
+use super::{bigint, count, user, Client, Error, Target, Transaction};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Ticket {
     pub id: Id,
     pub title: String,
     pub description: String,
     pub status: Status,
+    pub category: Category,
     pub count: usize,
+    pub received_count: usize,
     pub price: Option<f64>,
+    pub payment_reference: Option<String>,
     pub initiator: user::Id,
     pub purchasing_manager: Option<user::Id>,
     pub accounting_manager: Option<user::Id>,
     pub created_at: OffsetDateTime,
 }
 
-#[derive(Clone, Copy, Debug, Default, Deserialize, Display, Serialize)]
+/// Columns of a [`Ticket`] set along with its status by
+/// [`Client::update_ticket_status()`].
+///
+/// Columns left [`None`] keep their current values.
+#[derive(Clone, Debug, Default)]
+pub struct StatusUpdateFields {
+    pub price: Option<f64>,
+    pub payment_reference: Option<String>,
+    pub purchasing_manager: Option<user::Id>,
+    pub accounting_manager: Option<user::Id>,
+}
+
+/// Aggregates of the tickets matching a [`TicketFilter`], as computed by
+/// [`Client::get_ticket_stats()`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TicketStats {
+    /// Number of the tickets in every [`Status`], including those no ticket
+    /// has.
+    pub counts_by_status: HashMap<Status, usize>,
+
+    /// Sum of the prices of the tickets confirmed, but not paid yet.
+    pub confirmed_total_price: f64,
+
+    /// Sum of the prices of the paid tickets.
+    pub paid_total_price: f64,
+}
+
+/// Tickets relevant to a purchasing manager, as counted by
+/// [`Client::get_purchasing_summary()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PurchasingSummary {
+    /// Number of the tickets awaiting to be confirmed or denied.
+    pub pending_confirmation: usize,
+
+    /// Number of the tickets confirmed by the manager, paid or not.
+    pub confirmed: usize,
+
+    /// Sum of the prices of the tickets confirmed by the manager.
+    pub confirmed_total_price: f64,
+}
+
+/// Tickets relevant to accounting managers, as counted by
+/// [`Client::get_payment_summary()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PaymentSummary {
+    /// Number of the confirmed tickets awaiting to be paid.
+    pub awaiting_payment: usize,
+
+    /// Sum of the prices of the tickets awaiting to be paid.
+    pub awaiting_payment_total_price: f64,
+}
+
+/// [`Ticket`] along with the users it refers to.
+#[derive(Clone, Debug)]
+pub struct TicketWithUsers {
+    pub ticket: Ticket,
+    pub initiator: user::UserSummary,
+    pub purchasing_manager: Option<user::UserSummary>,
+    pub accounting_manager: Option<user::UserSummary>,
+}
+
+/// Builder of a [`Ticket`], for tests and seeding.
+///
+/// Starts with a new requested ticket of one item, created now, so only the
+/// fields mattering to a test need to be set.
+#[derive(Clone, Debug)]
+pub struct TicketBuilder {
+    /// ID of the built ticket, if set explicitly.
+    ///
+    /// Otherwise, a new one is generated by every [`build()`], so the clones
+    /// of a builder build distinct tickets.
+    ///
+    /// [`build()`]: Self::build
+    id: Option<Id>,
+
+    /// Rest of the built ticket, with a placeholder ID.
+    ticket: Ticket,
+}
+
+impl TicketBuilder {
+    /// Starts building a ticket of the `initiator`.
+    pub fn new(initiator: user::Id) -> Self {
+        Self {
+            id: None,
+            ticket: Ticket {
+                id: Id::from(0),
+                title: "Ticket".to_owned(),
+                description: "Description".to_owned(),
+                status: Status::Requested,
+                category: Category::Other,
+                count: 1,
+                received_count: 0,
+                price: None,
+                payment_reference: None,
+                initiator,
+                purchasing_manager: None,
+                accounting_manager: None,
+                created_at: OffsetDateTime::now_utc(),
+            },
+        }
+    }
+
+    pub fn id(mut self, id: Id) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.ticket.title = title.into();
+        self
+    }
+
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.ticket.description = description.into();
+        self
+    }
+
+    pub fn status(mut self, status: Status) -> Self {
+        self.ticket.status = status;
+        self
+    }
+
+    pub fn category(mut self, category: Category) -> Self {
+        self.ticket.category = category;
+        self
+    }
+
+    pub fn count(mut self, count: usize) -> Self {
+        self.ticket.count = count;
+        self
+    }
+
+    pub fn received_count(mut self, received_count: usize) -> Self {
+        self.ticket.received_count = received_count;
+        self
+    }
+
+    pub fn price(mut self, price: impl Into<Option<f64>>) -> Self {
+        self.ticket.price = price.into();
+        self
+    }
+
+    pub fn payment_reference(
+        mut self,
+        payment_reference: impl Into<Option<String>>,
+    ) -> Self {
+        self.ticket.payment_reference = payment_reference.into();
+        self
+    }
+
+    pub fn purchasing_manager(
+        mut self,
+        purchasing_manager: impl Into<Option<user::Id>>,
+    ) -> Self {
+        self.ticket.purchasing_manager = purchasing_manager.into();
+        self
+    }
+
+    pub fn accounting_manager(
+        mut self,
+        accounting_manager: impl Into<Option<user::Id>>,
+    ) -> Self {
+        self.ticket.accounting_manager = accounting_manager.into();
+        self
+    }
+
+    pub fn created_at(mut self, created_at: OffsetDateTime) -> Self {
+        self.ticket.created_at = created_at;
+        self
+    }
+
+    pub fn build(self) -> Ticket {
+        // Not `unwrap_or_default()`, as the default ID is a nil one.
+        let id = match self.id {
+            Some(id) => id,
+            None => Id::new(),
+        };
+        Ticket { id, ..self.ticket }
+    }
+}
+
+/// [`Ticket`] along with the times it was last changed and deleted at, for
+/// exporting.
+#[derive(Clone, Debug)]
+pub struct TicketWithTimes {
+    pub ticket: Ticket,
+    pub updated_at: OffsetDateTime,
+    pub deleted_at: Option<OffsetDateTime>,
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Display,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
+)]
 pub struct Id(Uuid);
 
 impl Id {
@@ -44,6 +256,20 @@ impl From<u128> for Id {
     }
 }
 
+impl From<Id> for u128 {
+    fn from(value: Id) -> Self {
+        value.0.as_u128()
+    }
+}
+
+impl FromStr for Id {
+    type Err = uuid::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(s).map(Self)
+    }
+}
+
 impl FromSql<'_> for Id {
     accepts!(UUID);
 
@@ -69,11 +295,29 @@ impl ToSql for Id {
     }
 }
 
+/// Stage of the lifecycle of a ticket.
+///
+/// More statuses may be added as the lifecycle evolves, so matching on a
+/// [`Status`] outside of this crate requires a wildcard arm:
+///
+/// ```compile_fail,E0004
+/// use dubna_internship::db::ticket::Status;
+///
+/// fn is_final(status: Status) -> bool {
+///     match status {
+///         Status::Requested | Status::Confirmed => false,
+///         Status::Cancelled
+///         | Status::Denied
+///         | Status::PaymentCompleted => true,
+///     }
+/// }
+/// ```
 #[derive(
-    Clone, Copy, Debug, Deserialize, TryFromRepr, PartialEq, Serialize,
+    Clone, Copy, Debug, Deserialize, Eq, Hash, TryFromRepr, PartialEq, Serialize,
 )]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 #[repr(u8)]
+#[non_exhaustive]
 pub enum Status {
     /// Some materials are requested.
     Requested = 1,
@@ -97,6 +341,17 @@ pub enum Status {
     PaymentCompleted = 5,
 }
 
+impl Status {
+    /// All the [`Status`]es, in the order of the ticket lifecycle.
+    pub const ALL: [Self; 5] = [
+        Self::Requested,
+        Self::Cancelled,
+        Self::Confirmed,
+        Self::Denied,
+        Self::PaymentCompleted,
+    ];
+}
+
 impl FromSql<'_> for Status {
     accepts!(INT2);
 
@@ -126,115 +381,1625 @@ impl ToSql for Status {
     }
 }
 
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, TryFromRepr, PartialEq, Serialize,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[repr(u8)]
+pub enum Category {
+    /// Computers, peripherals, software licenses.
+    It = 1,
+
+    /// Reagents, consumables and other laboratory materials.
+    LabSupplies = 2,
+
+    /// Furniture for offices and laboratories.
+    Furniture = 3,
+
+    /// Anything not fitting the other categories.
+    #[default]
+    Other = 4,
+}
+
+impl FromSql<'_> for Category {
+    accepts!(INT2);
+
+    fn from_sql(
+        ty: &Type,
+        raw: &[u8],
+    ) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+        let repr = i16::from_sql(ty, raw)?;
+        let repr = u8::try_from(repr)?;
+        let category = Self::try_from(repr).map_err(|_| "invalid category")?;
+        Ok(category)
+    }
+}
+
+impl ToSql for Category {
+    accepts!(INT2);
+
+    to_sql_checked!();
+
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn StdError + Sync + Send>> {
+        let repr = i16::from((*self) as u8);
+        repr.to_sql(ty, out)
+    }
+}
+
+/// [`Ticket`] price as stored in the `price` column.
+///
+/// The column may be either `FLOAT8` or `NUMERIC`, so the application keeps
+/// working before, during and after migrating it from one type to another.
+/// The type is taken from the statement itself, so no configuration is
+/// needed.
+#[derive(Clone, Copy, Debug)]
+struct Price(f64);
+
+impl FromSql<'_> for Price {
+    accepts!(FLOAT8, NUMERIC);
+
+    fn from_sql(
+        ty: &Type,
+        raw: &[u8],
+    ) -> Result<Self, Box<dyn StdError + Sync + Send>> {
+        if *ty == Type::NUMERIC {
+            let price = Decimal::from_sql(ty, raw)?;
+            let price = price.to_f64().ok_or("price is out of range")?;
+            Ok(Self(price))
+        } else {
+            f64::from_sql(ty, raw).map(Self)
+        }
+    }
+}
+
+impl ToSql for Price {
+    accepts!(FLOAT8, NUMERIC);
+
+    to_sql_checked!();
+
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn StdError + Sync + Send>> {
+        if *ty == Type::NUMERIC {
+            Decimal::try_from(self.0)?.to_sql(ty, out)
+        } else {
+            self.0.to_sql(ty, out)
+        }
+    }
+}
+
+/// Conditions the listed or counted [`Ticket`]s have to satisfy.
+///
+/// Conditions which aren't specified don't restrict the tickets.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TicketFilter {
+    pub category: Option<Category>,
+
+    /// Statuses the tickets have to have any of, or any status if empty.
+    pub statuses: Vec<Status>,
+
+    pub initiator: Option<user::Id>,
+
+    /// Manager assigned to the tickets, either as the purchasing or the
+    /// accounting one.
+    pub assignee: Option<user::Id>,
+
+    /// Lowest [`Ticket::price`], inclusive.
+    pub min_price: Option<f64>,
+
+    /// Highest [`Ticket::price`], inclusive.
+    pub max_price: Option<f64>,
+
+    /// Moment the tickets have to be created after, exclusive.
+    pub created_after: Option<OffsetDateTime>,
+
+    /// Moment the tickets have to be created before, exclusive.
+    pub created_before: Option<OffsetDateTime>,
+
+    /// Text the title or the description of the tickets has to contain,
+    /// case-insensitively.
+    ///
+    /// Matched with `ILIKE` by scanning the tickets, so
+    /// [`Client::search_tickets()`] should be preferred for large tables.
+    pub text: Option<String>,
+
+    pub visibility: Visibility,
+}
+
+/// Whether soft-deleted [`Ticket`]s are queried along with the active ones.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Visibility {
+    /// Only the tickets which aren't deleted.
+    #[default]
+    ActiveOnly,
+
+    /// Deleted tickets as well as the active ones.
+    IncludeDeleted,
+}
+
+impl Visibility {
+    /// Renders the condition of a `WHERE` clause matching the tickets of
+    /// this [`Visibility`].
+    fn condition(self) -> &'static str {
+        match self {
+            Self::ActiveOnly => "deleted_at IS NULL",
+            Self::IncludeDeleted => "TRUE",
+        }
+    }
+}
+
+impl TicketFilter {
+    /// Renders the condition of a `WHERE` clause matching this
+    /// [`TicketFilter`], along with its parameters, numbered after the
+    /// `preceding` ones of the statement.
+    ///
+    /// Filtered values are only ever passed as parameters, so the SQL text
+    /// depends solely on which conditions are specified.
+    fn render(&self, preceding: usize) -> (String, Vec<&(dyn ToSql + Sync)>) {
+        fn param<T: ToSql + Sync>(
+            value: &Option<T>,
+        ) -> Option<&(dyn ToSql + Sync)> {
+            value.as_ref().map(|v| v as _)
+        }
+
+        /// Value of a condition, if specified, along with the renderer of
+        /// the condition given the number of its parameter.
+        type Condition<'a> =
+            (Option<&'a (dyn ToSql + Sync)>, fn(usize) -> String);
+
+        // Price is compared as `FLOAT8`, whichever type its column has.
+        let conditions: [Condition<'_>; 9] = [
+            (param(&self.category), |n| format!("category = ${n}")),
+            (
+                (!self.statuses.is_empty())
+                    .then_some(&self.statuses as &(dyn ToSql + Sync)),
+                |n| format!("status = ANY(${n}::INT2[])"),
+            ),
+            (param(&self.initiator), |n| format!("initiator_id = ${n}")),
+            (param(&self.assignee), |n| {
+                format!(
+                    "(purchasing_manager_id = ${n} \
+                      OR accounting_manager_id = ${n})"
+                )
+            }),
+            (param(&self.min_price), |n| format!("price >= ${n}::FLOAT8")),
+            (param(&self.max_price), |n| format!("price <= ${n}::FLOAT8")),
+            (param(&self.created_after), |n| format!("created_at > ${n}")),
+            (param(&self.created_before), |n| {
+                format!("created_at < ${n}")
+            }),
+            (param(&self.text), |n| {
+                // Wildcards of the text are escaped, so it's matched as is.
+                let text = format!(r"replace(${n}, '\', '\\')");
+                let text = format!(r"replace({text}, '%', '\%')");
+                let text = format!(r"replace({text}, '_', '\_')");
+                format!(
+                    "(title ILIKE ('%' || {text} || '%') \
+                      OR description ILIKE ('%' || {text} || '%'))"
+                )
+            }),
+        ];
+
+        let mut sql = Vec::new();
+        let mut params = Vec::new();
+        for (value, condition) in conditions {
+            if let Some(value) = value {
+                params.push(value);
+                sql.push(condition(preceding + params.len()));
+            }
+        }
+        if self.visibility == Visibility::ActiveOnly {
+            sql.push(self.visibility.condition().to_owned());
+        }
+
+        if sql.is_empty() {
+            ("TRUE".to_owned(), params)
+        } else {
+            (sql.join(" AND "), params)
+        }
+    }
+}
+
+/// Order the listed [`Ticket`]s are sorted in.
+///
+/// Tickets sorted equally are ordered by their [`Id`], in the same
+/// direction, so pages never overlap.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TicketOrder {
+    pub by: SortBy,
+    pub direction: SortDirection,
+}
+
+impl TicketOrder {
+    /// Renders the `ORDER BY` clause sorting the tickets of a query joining
+    /// their initiators as `initiators`, with the columns of the tickets
+    /// qualified by the `tickets` name, if any.
+    fn render(&self, tickets: Option<&str>, initiators: &str) -> String {
+        format!("ORDER BY {}", self.keys(tickets, initiators))
+    }
+
+    /// Renders the keys of the `ORDER BY` clause rendered by
+    /// [`TicketOrder::render()`] alone, so they may follow other keys.
+    fn keys(&self, tickets: Option<&str>, initiators: &str) -> String {
+        let tickets = tickets.map(|t| format!("{t}.")).unwrap_or_default();
+        let direction = match self.direction {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+        };
+        let key = match self.by {
+            SortBy::CreatedAt => format!("{tickets}created_at"),
+            SortBy::Initiator => format!("{initiators}.name"),
+        };
+        format!("{key} {direction}, {tickets}id {direction}")
+    }
+}
+
+/// Key the listed [`Ticket`]s are sorted by.
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    /// Moment the tickets were created at.
+    #[default]
+    CreatedAt,
+
+    /// Name of the user who initiated the tickets.
+    Initiator,
+}
+
+impl SortBy {
+    /// Returns the direction the tickets are sorted in by this key unless
+    /// requested otherwise: newest or alphabetically first.
+    pub fn default_direction(self) -> SortDirection {
+        match self {
+            Self::CreatedAt => SortDirection::Desc,
+            Self::Initiator => SortDirection::Asc,
+        }
+    }
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// PostgreSQL text search configuration the [`Ticket`]s are searched with
+/// by [`Client::search_tickets()`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TextSearchConfig {
+    /// Words are matched as they are, ignoring their case.
+    #[default]
+    Simple,
+
+    /// Words are matched by their stems, both Russian and English ones.
+    Russian,
+}
+
+impl TextSearchConfig {
+    /// Returns the name of this [`TextSearchConfig`] in the database.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Simple => "simple",
+            Self::Russian => "russian",
+        }
+    }
+
+    /// Returns the generated column holding the words of the tickets as
+    /// parsed by this [`TextSearchConfig`].
+    fn column(self) -> &'static str {
+        match self {
+            Self::Simple => "search_simple",
+            Self::Russian => "search_russian",
+        }
+    }
+}
+
 impl Client {
     pub async fn get_ticket_by_id(
         &self,
         id: Id,
+        visibility: Visibility,
     ) -> Result<Option<Ticket>, Error> {
+        self.traced("get_ticket_by_id", async move {
+            let sql = format!(
+                "\
+                SELECT id, title, description, status, category, \
+                       count, received_count, price, initiator_id, \
+                       purchasing_manager_id, accounting_manager_id, \
+                       created_at, payment_reference \
+                FROM tickets \
+                WHERE id = $1 \
+                  AND {visible}",
+                visible = visibility.condition(),
+            );
+            Ok(self
+                .read_opt(Target::Replica, &sql, &[&id])
+                .await?
+                .as_ref()
+                .map(ticket))
+        })
+        .await
+    }
+
+    pub async fn write_ticket(&self, ticket: &Ticket) -> Result<(), Error> {
+        self.traced("write_ticket", async move {
+            write_ticket(&self.conn(Target::Primary).await?, ticket).await
+        })
+        .await
+    }
+
+    /// Changes the status of the ticket from the `expected` one to the `new`
+    /// one, setting the `fields` along with it.
+    ///
+    /// Returns `false` without changing anything if the ticket isn't in the
+    /// `expected` status (or doesn't exist), so concurrent transitions from
+    /// the same status can't both succeed.
+    pub async fn update_ticket_status(
+        &self,
+        id: Id,
+        expected: Status,
+        new: Status,
+        fields: &StatusUpdateFields,
+    ) -> Result<bool, Error> {
+        self.traced("update_ticket_status", async move {
+            update_ticket_status(
+                &self.conn(Target::Primary).await?,
+                id,
+                expected,
+                new,
+                fields,
+            )
+            .await
+        })
+        .await
+    }
+
+    /// Soft-deletes the ticket, hiding it from the queries of
+    /// [`Visibility::ActiveOnly`], while keeping it along with everything
+    /// referring to it.
+    ///
+    /// Returns `false` if the ticket doesn't exist or is deleted already.
+    pub async fn delete_ticket(&self, id: Id) -> Result<bool, Error> {
+        const SQL: &str = "\
+            UPDATE tickets \
+            SET deleted_at = now(), \
+                updated_at = now() \
+            WHERE id = $1 \
+              AND deleted_at IS NULL";
+
+        self.traced("delete_ticket", async move {
+            let deleted = self
+                .conn(Target::Primary)
+                .await?
+                .execute(SQL, &[&id])
+                .await?;
+            Ok(deleted == 1)
+        })
+        .await
+    }
+
+    /// Inserts the provided [`Ticket`]s within a single transaction, in
+    /// chunks of [`config::Db::write_batch_size`] rows, returning the number
+    /// of rows written.
+    ///
+    /// Unlike [`Client::write_ticket()`], never updates the existing tickets:
+    /// if any of the `tickets` already exists, or is provided twice, none of
+    /// them are written.
+    ///
+    /// [`config::Db::write_batch_size`]: crate::config::Db::write_batch_size
+    pub async fn write_tickets(
+        &self,
+        tickets: &[Ticket],
+    ) -> Result<usize, WriteTicketsError> {
+        self.traced("write_tickets", async move {
+            let mut conn = self.connection().await?;
+            let tx = conn.transaction().await?;
+            for chunk in tickets.chunks(self.write_batch_size.get()) {
+                insert_tickets(&tx.0, chunk).await?;
+            }
+            tx.commit().await?;
+            Ok(tickets.len())
+        })
+        .await
+    }
+
+    pub async fn get_tickets_page(
+        &self,
+        offset: usize,
+        limit: usize,
+        visibility: Visibility,
+    ) -> Result<Vec<Ticket>, Error> {
+        self.traced("get_tickets_page", async move {
+            let offset = bigint(offset, "offset")?;
+            let limit = bigint(limit, "limit")?;
+
+            let sql = format!(
+                "\
+                SELECT id, title, description, status, category, \
+                       count, received_count, price, initiator_id, \
+                       purchasing_manager_id, accounting_manager_id, \
+                       created_at, payment_reference \
+                FROM tickets \
+                WHERE {visible} \
+                ORDER BY created_at DESC, \
+                         id DESC \
+                OFFSET $1 LIMIT $2",
+                visible = visibility.condition(),
+            );
+            Ok(self
+                .read(Target::Replica, &sql, &[&offset, &limit])
+                .await?
+                .iter()
+                .map(ticket)
+                .collect())
+        })
+        .await
+    }
+
+    /// Returns the requested page of tickets matching the `filter` along with
+    /// their total count, both observed by the same statement.
+    pub async fn get_tickets_page_with_count(
+        &self,
+        offset: usize,
+        limit: usize,
+        filter: &TicketFilter,
+    ) -> Result<(Vec<Ticket>, usize), Error> {
+        self.traced("get_tickets_page_with_count", async move {
+            let offset = bigint(offset, "offset")?;
+            let limit = bigint(limit, "limit")?;
+
+            let (condition, filter_params) = filter.render(2);
+            let sql = format!(
+                "\
+                SELECT id, title, description, status, category, \
+                       count, received_count, price, initiator_id, \
+                       purchasing_manager_id, accounting_manager_id, \
+                       created_at, payment_reference, \
+                       COUNT(*) OVER () AS total_count \
+                FROM tickets \
+                WHERE {condition} \
+                ORDER BY created_at DESC, \
+                         id DESC \
+                OFFSET $1 LIMIT $2",
+            );
+            let params = [&offset as &(dyn ToSql + Sync), &limit]
+                .into_iter()
+                .chain(filter_params)
+                .collect::<Vec<_>>();
+            let rows = self.read(Target::Replica, &sql, &params).await?;
+
+            // Window function produces no rows when the offset is beyond the
+            // end or the limit is zero, so the total has to be counted
+            // separately in these cases.
+            let total_count = match rows.first() {
+                Some(row) => count(row.get("total_count")),
+                None if limit > 0 && offset == 0 => 0,
+                None => self.get_tickets_count(filter).await?,
+            };
+
+            let tickets = rows.iter().map(ticket).collect();
+
+            Ok((tickets, total_count))
+        })
+        .await
+    }
+
+    /// Returns up to `limit` tickets matching the `filter` and following the
+    /// one identified by `created_at` and `id` in the listing order.
+    ///
+    /// Unlike [`Client::get_tickets_page()`] doesn't skip over preceding rows,
+    /// so fetching a page takes the same time regardless of its depth.
+    pub async fn get_tickets_before(
+        &self,
+        created_at: OffsetDateTime,
+        id: Id,
+        limit: usize,
+        filter: &TicketFilter,
+    ) -> Result<Vec<Ticket>, Error> {
+        self.traced("get_tickets_before", async move {
+            let limit = bigint(limit, "limit")?;
+
+            // Tickets sharing the same `created_at` are told apart by the row
+            // comparison on `id`, matching the tie-break of the `ORDER BY`.
+            let (condition, filter_params) = filter.render(3);
+            let sql = format!(
+                "\
+                SELECT id, title, description, status, category, \
+                       count, received_count, price, initiator_id, \
+                       purchasing_manager_id, accounting_manager_id, \
+                       created_at, payment_reference \
+                FROM tickets \
+                WHERE (created_at, id) < ($1, $2) \
+                  AND {condition} \
+                ORDER BY created_at DESC, \
+                         id DESC \
+                LIMIT $3",
+            );
+            let params = [&created_at as &(dyn ToSql + Sync), &id, &limit]
+                .into_iter()
+                .chain(filter_params)
+                .collect::<Vec<_>>();
+            Ok(self
+                .read(Target::Replica, &sql, &params)
+                .await?
+                .iter()
+                .map(ticket)
+                .collect())
+        })
+        .await
+    }
+
+    /// Returns the requested page of tickets matching the `filter` along with
+    /// the users they refer to and their total count, all observed by the
+    /// same statement.
+    ///
+    /// Unlike [`Client::get_tickets_page_with_count()`] followed by
+    /// [`Client::get_users_by_ids()`], takes a single round trip.
+    pub async fn get_tickets_page_with_users(
+        &self,
+        offset: usize,
+        limit: usize,
+        filter: &TicketFilter,
+        order: TicketOrder,
+    ) -> Result<(Vec<TicketWithUsers>, usize), Error> {
+        self.traced("get_tickets_page_with_users", async move {
+            let offset = bigint(offset, "offset")?;
+            let limit = bigint(limit, "limit")?;
+
+            // Initiators are only joined to sort by their names, and aren't
+            // selected, so the joined columns can't clash with the ones of
+            // the tickets. Every ticket has an initiator, so joining them
+            // doesn't change the total count.
+            let initiators = match order.by {
+                SortBy::CreatedAt => "",
+                SortBy::Initiator => {
+                    "JOIN (SELECT id AS initiator_id, name FROM users) \
+                          AS initiators USING (initiator_id)"
+                }
+            };
+            let (condition, filter_params) = filter.render(2);
+            let sql = join_users(
+                &format!(
+                    "\
+                    SELECT id, title, description, status, category, \
+                           count, received_count, price, initiator_id, \
+                           purchasing_manager_id, accounting_manager_id, \
+                           created_at, payment_reference, \
+                           COUNT(*) OVER () AS total_count \
+                    FROM tickets {initiators} \
+                    WHERE {condition} \
+                    {order} \
+                    OFFSET $1 LIMIT $2",
+                    order = order.render(None, "initiators"),
+                ),
+                &order.render(Some("t"), "i"),
+            );
+            let params = [&offset as &(dyn ToSql + Sync), &limit]
+                .into_iter()
+                .chain(filter_params)
+                .collect::<Vec<_>>();
+            let rows = self.read(Target::Replica, &sql, &params).await?;
+
+            // Window function produces no rows when the offset is beyond the
+            // end or the limit is zero, so the total has to be counted
+            // separately in these cases.
+            let total_count = match rows.first() {
+                Some(row) => count(row.get("total_count")),
+                None if limit > 0 && offset == 0 => 0,
+                None => self.get_tickets_count(filter).await?,
+            };
+
+            let tickets = rows.iter().map(ticket_with_users).collect();
+
+            Ok((tickets, total_count))
+        })
+        .await
+    }
+
+    /// Returns up to `limit` tickets matching the `filter` and following the
+    /// one identified by `created_at` and `id` in the listing order, along
+    /// with the users they refer to.
+    ///
+    /// Unlike [`Client::get_tickets_before()`] followed by
+    /// [`Client::get_users_by_ids()`], takes a single round trip.
+    pub async fn get_tickets_before_with_users(
+        &self,
+        created_at: OffsetDateTime,
+        id: Id,
+        limit: usize,
+        filter: &TicketFilter,
+    ) -> Result<Vec<TicketWithUsers>, Error> {
+        self.traced("get_tickets_before_with_users", async move {
+            let limit = bigint(limit, "limit")?;
+
+            let (condition, filter_params) = filter.render(3);
+            let sql = join_users(
+                &format!(
+                    "\
+                    SELECT id, title, description, status, category, \
+                           count, received_count, price, initiator_id, \
+                           purchasing_manager_id, accounting_manager_id, \
+                           created_at, payment_reference \
+                    FROM tickets \
+                    WHERE (created_at, id) < ($1, $2) \
+                      AND {condition} \
+                    ORDER BY created_at DESC, \
+                             id DESC \
+                    LIMIT $3",
+                ),
+                &TicketOrder::default().render(Some("t"), "i"),
+            );
+            let params = [&created_at as &(dyn ToSql + Sync), &id, &limit]
+                .into_iter()
+                .chain(filter_params)
+                .collect::<Vec<_>>();
+            Ok(self
+                .read(Target::Replica, &sql, &params)
+                .await?
+                .iter()
+                .map(ticket_with_users)
+                .collect())
+        })
+        .await
+    }
+
+    /// Counts the tickets matching the `filter`.
+    pub async fn get_tickets_count(
+        &self,
+        filter: &TicketFilter,
+    ) -> Result<usize, Error> {
+        self.traced("get_tickets_count", async move {
+            let (condition, params) = filter.render(0);
+            let sql = format!(
+                "\
+                SELECT COUNT(*) \
+                FROM tickets \
+                WHERE {condition}",
+            );
+            let n = self.read_one(Target::Replica, &sql, &params).await?.get(0);
+            Ok(count(n))
+        })
+        .await
+    }
+
+    /// Counts the tickets referring to the user in any role, including the
+    /// soft-deleted ones, which still keep the user from being deleted.
+    ///
+    /// Read from the primary, as it's checked right before deleting the user.
+    pub async fn count_tickets_for_user(
+        &self,
+        user: user::Id,
+    ) -> Result<usize, Error> {
+        const SQL: &str = "\
+            SELECT COUNT(*) \
+            FROM tickets \
+            WHERE initiator_id = $1 \
+               OR purchasing_manager_id = $1 \
+               OR accounting_manager_id = $1";
+        self.traced("count_tickets_for_user", async move {
+            let n = self.read_one(Target::Primary, SQL, &[&user]).await?.get(0);
+            Ok(count(n))
+        })
+        .await
+    }
+
+    /// Returns IDs of up to `limit` tickets referring to the user in any
+    /// role, the latest first.
+    pub async fn get_ticket_ids_for_user(
+        &self,
+        user: user::Id,
+        limit: usize,
+    ) -> Result<Vec<Id>, Error> {
         const SQL: &str = "\
-            SELECT id, title, description, status, \
-                   count, price, initiator_id, \
+            SELECT id \
+            FROM tickets \
+            WHERE initiator_id = $1 \
+               OR purchasing_manager_id = $1 \
+               OR accounting_manager_id = $1 \
+            ORDER BY created_at DESC, \
+                     id DESC \
+            LIMIT $2";
+        self.traced("get_ticket_ids_for_user", async move {
+            let limit = bigint(limit, "limit")?;
+            Ok(self
+                .read(Target::Primary, SQL, &[&user, &limit])
+                .await?
+                .iter()
+                .map(|row| row.get("id"))
+                .collect())
+        })
+        .await
+    }
+
+    /// Returns the requested page of tickets matching both the `query` and
+    /// the `filter` along with the users they refer to and their total
+    /// count, the most relevant first.
+    ///
+    /// The `query` is parsed by `websearch_to_tsquery()`, so may contain
+    /// quoted phrases, `or` and `-` for excluded words. Its words are matched
+    /// against the title and the description of the tickets as parsed by the
+    /// `config`, using its index. Equally relevant tickets are sorted in the
+    /// `order`.
+    pub async fn search_tickets(
+        &self,
+        query: &str,
+        config: TextSearchConfig,
+        offset: usize,
+        limit: usize,
+        filter: &TicketFilter,
+        order: TicketOrder,
+    ) -> Result<(Vec<TicketWithUsers>, usize), Error> {
+        self.traced("search_tickets", async move {
+            let offset = bigint(offset, "offset")?;
+            let limit = bigint(limit, "limit")?;
+
+            let initiators = match order.by {
+                SortBy::CreatedAt => "",
+                SortBy::Initiator => {
+                    "JOIN (SELECT id AS initiator_id, name FROM users) \
+                          AS initiators USING (initiator_id)"
+                }
+            };
+            let (condition, filter_params) = filter.render(3);
+            let sql = join_users(
+                &format!(
+                    "\
+                    SELECT id, title, description, status, category, \
+                           count, received_count, price, initiator_id, \
+                           purchasing_manager_id, accounting_manager_id, \
+                           created_at, payment_reference, \
+                           ts_rank({column}, query) AS rank, \
+                           COUNT(*) OVER () AS total_count \
+                    FROM tickets {initiators}, \
+                         websearch_to_tsquery('{name}', $3) AS query \
+                    WHERE {column} @@ query \
+                      AND {condition} \
+                    ORDER BY rank DESC, {keys} \
+                    OFFSET $1 LIMIT $2",
+                    column = config.column(),
+                    name = config.name(),
+                    keys = order.keys(None, "initiators"),
+                ),
+                &format!(
+                    "ORDER BY t.rank DESC, {}",
+                    order.keys(Some("t"), "i")
+                ),
+            );
+            let params = [&offset as &(dyn ToSql + Sync), &limit, &query]
+                .into_iter()
+                .chain(filter_params)
+                .collect::<Vec<_>>();
+            let rows = self.read(Target::Replica, &sql, &params).await?;
+
+            // Window function produces no rows when the offset is beyond the
+            // end or the limit is zero, so the total has to be counted
+            // separately in these cases.
+            let total_count = match rows.first() {
+                Some(row) => count(row.get("total_count")),
+                None if limit > 0 && offset == 0 => 0,
+                None => {
+                    self.search_tickets_count(query, config, filter).await?
+                }
+            };
+
+            let tickets = rows.iter().map(ticket_with_users).collect();
+
+            Ok((tickets, total_count))
+        })
+        .await
+    }
+
+    /// Counts the tickets matching both the `query` and the `filter`, the
+    /// same way [`Client::search_tickets()`] does.
+    pub async fn search_tickets_count(
+        &self,
+        query: &str,
+        config: TextSearchConfig,
+        filter: &TicketFilter,
+    ) -> Result<usize, Error> {
+        self.traced("search_tickets_count", async move {
+            let (condition, filter_params) = filter.render(1);
+            let sql = format!(
+                "\
+                SELECT COUNT(*) \
+                FROM tickets \
+                WHERE {column} @@ websearch_to_tsquery('{name}', $1) \
+                  AND {condition}",
+                column = config.column(),
+                name = config.name(),
+            );
+            let params = [&query as &(dyn ToSql + Sync)]
+                .into_iter()
+                .chain(filter_params)
+                .collect::<Vec<_>>();
+            let n = self.read_one(Target::Replica, &sql, &params).await?.get(0);
+            Ok(count(n))
+        })
+        .await
+    }
+
+    /// Counts the tickets matching the `filter` per [`Status`], within a
+    /// single query.
+    ///
+    /// Every [`Status`] is present in the returned map, with zero for those no
+    /// ticket has.
+    pub async fn get_ticket_counts_by_status(
+        &self,
+        filter: &TicketFilter,
+    ) -> Result<HashMap<Status, usize>, Error> {
+        self.traced("get_ticket_counts_by_status", async move {
+            let (condition, params) = filter.render(0);
+            let sql = format!(
+                "\
+                SELECT status, COUNT(*) AS count \
+                FROM tickets \
+                WHERE {condition} \
+                GROUP BY status",
+            );
+            let mut counts = Status::ALL
+                .into_iter()
+                .map(|status| (status, 0))
+                .collect::<HashMap<_, _>>();
+            for row in self.read(Target::Replica, &sql, &params).await? {
+                let count = count(row.get("count"));
+                counts.insert(row.get("status"), count);
+            }
+            Ok(counts)
+        })
+        .await
+    }
+
+    /// Computes the [`TicketStats`] of the tickets matching the `filter`
+    /// within a single statement, rather than a query per [`Status`].
+    pub async fn get_ticket_stats(
+        &self,
+        filter: &TicketFilter,
+    ) -> Result<TicketStats, Error> {
+        self.traced("get_ticket_stats", async move {
+            // Statuses are the first parameters, numbered in their order.
+            let param = |status| {
+                Status::ALL.iter().position(|&s| s == status).unwrap() + 1
+            };
+            let counts = (1..=Status::ALL.len())
+                .map(|n| format!("COUNT(*) FILTER (WHERE status = ${n})"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            let (condition, filter_params) = filter.render(Status::ALL.len());
+            let sql = format!(
+                "\
+                SELECT {counts}, \
+                       COALESCE(SUM(price::FLOAT8) \
+                                    FILTER (WHERE status = ${confirmed}), \
+                                0), \
+                       COALESCE(SUM(price::FLOAT8) \
+                                    FILTER (WHERE status = ${paid}), \
+                                0) \
+                FROM tickets \
+                WHERE {condition}",
+                confirmed = param(Status::Confirmed),
+                paid = param(Status::PaymentCompleted),
+            );
+            let params = Status::ALL
+                .iter()
+                .map(|s| s as &(dyn ToSql + Sync))
+                .chain(filter_params)
+                .collect::<Vec<_>>();
+            let row = self.read_one(Target::Replica, &sql, &params).await?;
+
+            let counts_by_status = Status::ALL
+                .into_iter()
+                .enumerate()
+                .map(|(i, status)| (status, count(row.get(i))))
+                .collect();
+            Ok(TicketStats {
+                counts_by_status,
+                confirmed_total_price: row.get(Status::ALL.len()),
+                paid_total_price: row.get(Status::ALL.len() + 1),
+            })
+        })
+        .await
+    }
+
+    /// Counts the active tickets awaiting a decision of any purchasing
+    /// manager along with the ones confirmed by the `manager`, within a
+    /// single query.
+    pub async fn get_purchasing_summary(
+        &self,
+        manager: user::Id,
+    ) -> Result<PurchasingSummary, Error> {
+        self.traced("get_purchasing_summary", async move {
+            let sql = format!(
+                "\
+                SELECT COUNT(*) FILTER (WHERE status = $1) \
+                           AS pending_confirmation, \
+                       COUNT(*) FILTER (WHERE confirmed) AS confirmed, \
+                       COALESCE(SUM(price::FLOAT8) FILTER (WHERE confirmed), \
+                                0) AS confirmed_total_price \
+                FROM tickets, \
+                     LATERAL (SELECT purchasing_manager_id = $2 \
+                                     AND status IN ($3, $4) \
+                                 AS confirmed) AS c \
+                WHERE {active}",
+                active = Visibility::ActiveOnly.condition(),
+            );
+            let row = self
+                .read_one(
+                    Target::Replica,
+                    &sql,
+                    &[
+                        &Status::Requested,
+                        &manager,
+                        &Status::Confirmed,
+                        &Status::PaymentCompleted,
+                    ],
+                )
+                .await?;
+            Ok(PurchasingSummary {
+                pending_confirmation: count(row.get("pending_confirmation")),
+                confirmed: count(row.get("confirmed")),
+                confirmed_total_price: row.get("confirmed_total_price"),
+            })
+        })
+        .await
+    }
+
+    /// Counts the active tickets awaiting to be paid by any accounting
+    /// manager, within a single query.
+    pub async fn get_payment_summary(&self) -> Result<PaymentSummary, Error> {
+        self.traced("get_payment_summary", async move {
+            let sql = format!(
+                "\
+                SELECT COUNT(*) AS awaiting_payment, \
+                       COALESCE(SUM(price::FLOAT8), 0) \
+                           AS awaiting_payment_total_price \
+                FROM tickets \
+                WHERE status = $1 \
+                  AND {active}",
+                active = Visibility::ActiveOnly.condition(),
+            );
+            let row = self
+                .read_one(Target::Replica, &sql, &[&Status::Confirmed])
+                .await?;
+            Ok(PaymentSummary {
+                awaiting_payment: count(row.get("awaiting_payment")),
+                awaiting_payment_total_price: row
+                    .get("awaiting_payment_total_price"),
+            })
+        })
+        .await
+    }
+
+    /// Streams all the tickets of the `visibility`, newest first, without
+    /// buffering them.
+    ///
+    /// Meant for bulk reads, where collecting every row upfront would hold
+    /// the whole table in memory. If `category` is specified, only tickets
+    /// of this category are returned.
+    ///
+    /// Neither the returned [`Future`] nor the [`Stream`] borrow this
+    /// [`Client`], so they can be moved into a response body.
+    pub fn stream_tickets(
+        &self,
+        category: Option<Category>,
+        visibility: Visibility,
+    ) -> impl Future<
+        Output = Result<impl Stream<Item = Result<Ticket, Error>>, Error>,
+    > + 'static {
+        let sql = format!(
+            "\
+            SELECT id, title, description, status, category, \
+                   count, received_count, price, initiator_id, \
                    purchasing_manager_id, accounting_manager_id, \
-                   created_at \
+                   created_at, payment_reference \
             FROM tickets \
-            WHERE id = $1";
-        Ok(self.0.query_opt(SQL, &[&id]).await?.map(|row| Ticket {
-            id: row.get("id"),
-            title: row.get("title"),
-            description: row.get("description"),
-            status: row.get("status"),
-            count: usize::try_from(row.get::<_, i32>("count")).unwrap(),
-            price: row.get("price"),
-            initiator: row.get("initiator_id"),
-            purchasing_manager: row.get("purchasing_manager_id"),
-            accounting_manager: row.get("accounting_manager_id"),
-            created_at: row.get("created_at"),
-        }))
+            WHERE ($1::INT2 IS NULL OR category = $1) \
+              AND {visible} \
+            ORDER BY created_at DESC, \
+                     id DESC",
+            visible = visibility.condition(),
+        );
+
+        let client = self.clone();
+        async move {
+            let conn = client.conn(Target::Replica).await?;
+            let rows = conn
+                .query_raw(&sql, [&category as &(dyn ToSql + Sync)])
+                .await?;
+            Ok(rows.map(move |row| {
+                // Connection is held until the stream is dropped, so the pool
+                // doesn't hand it out while the rows are still being received.
+                let _conn = &conn;
+
+                let row = row?;
+                Ok(ticket(&row))
+            }))
+        }
     }
 
-    pub async fn write_ticket(&self, ticket: &Ticket) -> Result<(), Error> {
+    /// Streams all the tickets changed at or after `since`, deleted ones
+    /// included, in the order they were changed in, without buffering them.
+    ///
+    /// If `since` isn't specified, every ticket is returned.
+    ///
+    /// Neither the returned [`Future`] nor the [`Stream`] borrow this
+    /// [`Client`], so they can be moved into a response body.
+    pub fn stream_tickets_updated_since(
+        &self,
+        since: Option<OffsetDateTime>,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<TicketWithTimes, Error>>,
+            Error,
+        >,
+    > + 'static {
         const SQL: &str = "\
-            INSERT INTO tickets (id, title, description, status, \
-                                 count, price, initiator_id, \
+            SELECT id, title, description, status, category, \
+                   count, received_count, price, initiator_id, \
+                   purchasing_manager_id, accounting_manager_id, \
+                   created_at, payment_reference, updated_at, deleted_at \
+            FROM tickets \
+            WHERE ($1::TIMESTAMPTZ IS NULL OR updated_at >= $1) \
+            ORDER BY updated_at, \
+                     id";
+
+        let client = self.clone();
+        async move {
+            let conn = client.conn(Target::Replica).await?;
+            let rows =
+                conn.query_raw(SQL, [&since as &(dyn ToSql + Sync)]).await?;
+            Ok(rows.map(move |row| {
+                // Connection is held until the stream is dropped, so the pool
+                // doesn't hand it out while the rows are still being received.
+                let _conn = &conn;
+
+                let row = row?;
+                Ok(TicketWithTimes {
+                    ticket: ticket(&row),
+                    updated_at: row.get("updated_at"),
+                    deleted_at: row.get("deleted_at"),
+                })
+            }))
+        }
+    }
+}
+
+impl Transaction<'_> {
+    /// Returns the active ticket with the provided `id`, locking it until
+    /// this [`Transaction`] ends, so nothing else can change or delete it
+    /// meanwhile.
+    pub async fn get_ticket_by_id_for_update(
+        &self,
+        id: Id,
+    ) -> Result<Option<Ticket>, Error> {
+        let sql = format!(
+            "\
+            SELECT id, title, description, status, category, \
+                   count, received_count, price, initiator_id, \
+                   purchasing_manager_id, accounting_manager_id, \
+                   created_at, payment_reference \
+            FROM tickets \
+            WHERE id = $1 \
+              AND {visible} \
+            FOR UPDATE",
+            visible = Visibility::ActiveOnly.condition(),
+        );
+        Ok(self.0.query_opt(&sql, &[&id]).await?.as_ref().map(ticket))
+    }
+
+    pub async fn write_ticket(&self, ticket: &Ticket) -> Result<(), Error> {
+        write_ticket(&self.0, ticket).await
+    }
+
+    pub async fn update_ticket_status(
+        &self,
+        id: Id,
+        expected: Status,
+        new: Status,
+        fields: &StatusUpdateFields,
+    ) -> Result<bool, Error> {
+        update_ticket_status(&self.0, id, expected, new, fields).await
+    }
+}
+
+/// Inserts the provided [`Ticket`] or updates the existing one.
+async fn write_ticket(
+    client: &impl GenericClient,
+    ticket: &Ticket,
+) -> Result<(), Error> {
+    const SQL: &str = "\
+            INSERT INTO tickets (id, title, description, status, category, \
+                                 count, received_count, price, initiator_id, \
                                  purchasing_manager_id, accounting_manager_id, \
-                                 created_at) \
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) \
+                                 created_at, payment_reference) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, \
+                    $13) \
             ON CONFLICT (id) DO UPDATE \
             SET title = EXCLUDED.title, \
                 description = EXCLUDED.description, \
                 status = EXCLUDED.status, \
+                category = EXCLUDED.category, \
                 count = EXCLUDED.count, \
+                received_count = EXCLUDED.received_count, \
                 price = EXCLUDED.price, \
                 initiator_id = EXCLUDED.initiator_id, \
                 purchasing_manager_id = EXCLUDED.purchasing_manager_id, \
                 accounting_manager_id = EXCLUDED.accounting_manager_id, \
-                created_at = EXCLUDED.created_at";
-
-        self.0
-            .execute(
-                SQL,
-                &[
-                    &ticket.id,
-                    &ticket.title,
-                    &ticket.description,
-                    &ticket.status,
-                    &(ticket.count as i32),
-                    &ticket.price,
-                    &ticket.initiator,
-                    &ticket.purchasing_manager,
-                    &ticket.accounting_manager,
-                    &ticket.created_at,
-                ],
-            )
-            .await
-            .map(drop)
+                created_at = EXCLUDED.created_at, \
+                payment_reference = EXCLUDED.payment_reference, \
+                updated_at = now()";
+
+    client
+        .execute(
+            SQL,
+            &[
+                &ticket.id,
+                &ticket.title,
+                &ticket.description,
+                &ticket.status,
+                &ticket.category,
+                &(ticket.count as i32),
+                &(ticket.received_count as i32),
+                &ticket.price.map(Price),
+                &ticket.initiator,
+                &ticket.purchasing_manager,
+                &ticket.accounting_manager,
+                &ticket.created_at,
+                &ticket.payment_reference,
+            ],
+        )
+        .await?;
+    Ok(())
+}
+
+/// Changes the status of the ticket, unless it isn't the `expected` one
+/// anymore or the ticket is deleted, returning whether it was changed.
+async fn update_ticket_status(
+    client: &impl GenericClient,
+    id: Id,
+    expected: Status,
+    new: Status,
+    fields: &StatusUpdateFields,
+) -> Result<bool, Error> {
+    const SQL: &str = "\
+        UPDATE tickets \
+        SET status = $3, \
+            price = COALESCE($4, price), \
+            payment_reference = COALESCE($5, payment_reference), \
+            purchasing_manager_id = COALESCE($6, purchasing_manager_id), \
+            accounting_manager_id = COALESCE($7, accounting_manager_id), \
+            updated_at = now() \
+        WHERE id = $1 AND status = $2 \
+          AND deleted_at IS NULL";
+
+    let updated = client
+        .execute(
+            SQL,
+            &[
+                &id,
+                &expected,
+                &new,
+                &fields.price.map(Price),
+                &fields.payment_reference,
+                &fields.purchasing_manager,
+                &fields.accounting_manager,
+            ],
+        )
+        .await?;
+    Ok(updated == 1)
+}
+
+/// Wraps the query selecting `tickets` into the one joining the users they
+/// refer to, sorting them by the `order` clause, which refers to the
+/// tickets as `t` and their initiators as `i`.
+///
+/// Tickets are selected apart from the users, so their conditions needn't
+/// qualify the columns, and only the selected tickets are joined.
+fn join_users(tickets: &str, order: &str) -> String {
+    format!(
+        "\
+        SELECT t.*, \
+               i.name AS initiator_name, \
+               i.login AS initiator_login, \
+               i.role AS initiator_role, \
+               p.name AS purchasing_manager_name, \
+               p.login AS purchasing_manager_login, \
+               p.role AS purchasing_manager_role, \
+               a.name AS accounting_manager_name, \
+               a.login AS accounting_manager_login, \
+               a.role AS accounting_manager_role \
+        FROM ({tickets}) AS t \
+        JOIN users AS i ON i.id = t.initiator_id \
+        LEFT JOIN users AS p ON p.id = t.purchasing_manager_id \
+        LEFT JOIN users AS a ON a.id = t.accounting_manager_id \
+        {order}",
+    )
+}
+
+/// Reads a [`Ticket`] from the `row` selecting all of its columns.
+fn ticket(row: &Row) -> Ticket {
+    Ticket {
+        id: row.get("id"),
+        title: row.get("title"),
+        description: row.get("description"),
+        status: row.get("status"),
+        category: row.get("category"),
+        count: usize::try_from(row.get::<_, i32>("count")).unwrap(),
+        received_count: usize::try_from(row.get::<_, i32>("received_count"))
+            .unwrap(),
+        price: row.get::<_, Option<Price>>("price").map(|p| p.0),
+        payment_reference: row.get("payment_reference"),
+        initiator: row.get("initiator_id"),
+        purchasing_manager: row.get("purchasing_manager_id"),
+        accounting_manager: row.get("accounting_manager_id"),
+        created_at: row.get("created_at"),
     }
+}
 
-    pub async fn get_tickets_page(
-        &self,
-        offset: usize,
-        limit: usize,
-    ) -> Result<Vec<Ticket>, Error> {
-        let offset = i64::try_from(offset).unwrap();
-        let limit = i64::try_from(limit).unwrap();
+/// Reads a [`TicketWithUsers`] from the `row` returned by a query wrapped
+/// with [`join_users()`].
+fn ticket_with_users(row: &Row) -> TicketWithUsers {
+    TicketWithUsers {
+        ticket: ticket(row),
+        initiator: user::UserSummary {
+            id: row.get("initiator_id"),
+            name: row.get("initiator_name"),
+            login: row.get("initiator_login"),
+            role: row.get("initiator_role"),
+        },
+        purchasing_manager: row
+            .get::<_, Option<user::Id>>("purchasing_manager_id")
+            .map(|id| user::UserSummary {
+                id,
+                name: row.get("purchasing_manager_name"),
+                login: row.get("purchasing_manager_login"),
+                role: row.get("purchasing_manager_role"),
+            }),
+        accounting_manager: row
+            .get::<_, Option<user::Id>>("accounting_manager_id")
+            .map(|id| user::UserSummary {
+                id,
+                name: row.get("accounting_manager_name"),
+                login: row.get("accounting_manager_login"),
+                role: row.get("accounting_manager_role"),
+            }),
+    }
+}
 
-        const SQL: &str = "\
-            SELECT id, title, description, status, \
-                   count, price, initiator_id, \
-                   purchasing_manager_id, accounting_manager_id, \
-                   created_at \
-            FROM tickets \
-            ORDER BY created_at DESC, \
-                     id DESC \
-            OFFSET $1 LIMIT $2";
-        Ok(self
-            .0
-            .query(SQL, &[&offset, &limit])
-            .await?
-            .into_iter()
-            .map(|row| Ticket {
-                id: row.get("id"),
-                title: row.get("title"),
-                description: row.get("description"),
-                status: row.get("status"),
-                count: usize::try_from(row.get::<_, i32>("count")).unwrap(),
-                price: row.get("price"),
-                initiator: row.get("initiator_id"),
-                purchasing_manager: row.get("purchasing_manager_id"),
-                accounting_manager: row.get("accounting_manager_id"),
-                created_at: row.get("created_at"),
-            })
-            .collect())
-    }
-
-    pub async fn get_tickets_count(&self) -> Result<usize, Error> {
-        const SQL: &str = "SELECT COUNT(*) FROM tickets";
-        Ok(self
-            .0
-            .query_one(SQL, &[])
-            .await?
-            .get::<_, i64>(0)
-            .try_into()
-            .unwrap())
+/// Inserts the provided [`Ticket`]s with a single statement.
+///
+/// Columns are passed as arrays, so the statement is the same for any number
+/// of tickets and isn't limited by the number of its parameters.
+async fn insert_tickets(
+    client: &impl GenericClient,
+    tickets: &[Ticket],
+) -> Result<(), WriteTicketsError> {
+    const SQL: &str = "\
+        INSERT INTO tickets (id, title, description, status, category, \
+                             count, received_count, price, initiator_id, \
+                             purchasing_manager_id, accounting_manager_id, \
+                             created_at, payment_reference) \
+        SELECT * \
+        FROM unnest($1::UUID[], $2::TEXT[], $3::TEXT[], $4::INT2[], \
+                    $5::INT2[], $6::INT4[], $7::INT4[], $8::FLOAT8[], \
+                    $9::UUID[], $10::UUID[], $11::UUID[], \
+                    $12::TIMESTAMPTZ[], $13::TEXT[]) \
+        ON CONFLICT (id) DO NOTHING \
+        RETURNING id";
+
+    fn column<'a, T>(
+        tickets: &'a [Ticket],
+        f: impl Fn(&'a Ticket) -> T,
+    ) -> Vec<T> {
+        tickets.iter().map(f).collect()
+    }
+
+    let mut inserted = client
+        .query(
+            SQL,
+            &[
+                &column(tickets, |t| t.id),
+                &column(tickets, |t| t.title.as_str()),
+                &column(tickets, |t| t.description.as_str()),
+                &column(tickets, |t| t.status),
+                &column(tickets, |t| t.category),
+                &column(tickets, |t| t.count as i32),
+                &column(tickets, |t| t.received_count as i32),
+                &column(tickets, |t| t.price),
+                &column(tickets, |t| t.initiator),
+                &column(tickets, |t| t.purchasing_manager),
+                &column(tickets, |t| t.accounting_manager),
+                &column(tickets, |t| t.created_at),
+                &column(tickets, |t| t.payment_reference.as_deref()),
+            ],
+        )
+        .await
+        .map_err(Error::from)?
+        .into_iter()
+        .map(|row| row.get::<_, Id>("id"))
+        .collect::<HashSet<_>>();
+
+    // Conflicting tickets are skipped rather than failing the statement, so
+    // the first of them can be reported.
+    match tickets.iter().find(|t| !inserted.remove(&t.id)) {
+        Some(taken) => Err(WriteTicketsError::IdTaken(taken.id)),
+        None => Ok(()),
+    }
+}
+
+#[derive(Debug, Display, From)]
+pub enum WriteTicketsError {
+    #[display("{_0}")]
+    #[from]
+    DbError(Error),
+    #[display("ticket `{_0}` already exists")]
+    IdTaken(Id),
+}
+
+impl StdError for WriteTicketsError {}
+
+#[cfg(test)]
+mod status_spec {
+    use std::collections::{HashMap, HashSet};
+
+    use serde_json::json;
+
+    use super::Status;
+
+    #[test]
+    fn lists_all_statuses() {
+        let statuses = (0..=u8::MAX)
+            .filter_map(|repr| Status::try_from(repr).ok())
+            .collect::<Vec<_>>();
+        assert_eq!(statuses, Status::ALL);
+    }
+
+    #[test]
+    fn hashes_statuses_apart() {
+        let statuses = Status::ALL.into_iter().collect::<HashSet<_>>();
+        assert_eq!(statuses.len(), Status::ALL.len());
+        assert!(statuses.contains(&Status::Confirmed));
+    }
+
+    #[test]
+    fn serializes_counts_keyed_by_name() {
+        let counts = HashMap::from([
+            (Status::Requested, 2),
+            (Status::PaymentCompleted, 1),
+        ]);
+
+        let json = serde_json::to_value(&counts).unwrap();
+        assert_eq!(json, json!({"REQUESTED": 2, "PAYMENT_COMPLETED": 1}));
+
+        let parsed =
+            serde_json::from_value::<HashMap<Status, usize>>(json).unwrap();
+        assert_eq!(parsed, counts);
+    }
+}
+
+#[cfg(test)]
+mod builder_spec {
+    use time::OffsetDateTime;
+
+    use super::{user, Category, Id, Status, TicketBuilder};
+
+    #[test]
+    fn defaults_to_new_requested_ticket() {
+        let before = OffsetDateTime::now_utc();
+        let ticket = TicketBuilder::new(user::Id::from(1)).build();
+
+        assert_eq!(ticket.status, Status::Requested);
+        assert_eq!(ticket.category, Category::Other);
+        assert!(!ticket.title.is_empty());
+        assert!(!ticket.description.is_empty());
+        assert_eq!(ticket.count, 1);
+        assert_eq!(ticket.received_count, 0);
+        assert_eq!(ticket.price, None);
+        assert_eq!(ticket.payment_reference, None);
+        assert_eq!(ticket.initiator, user::Id::from(1));
+        assert_eq!(ticket.purchasing_manager, None);
+        assert_eq!(ticket.accounting_manager, None);
+        assert!(ticket.created_at >= before);
+        assert!(ticket.created_at <= OffsetDateTime::now_utc());
+    }
+
+    #[test]
+    fn builds_tickets_apart() {
+        let builder = TicketBuilder::new(user::Id::from(1));
+
+        assert_ne!(builder.clone().build().id, builder.build().id);
+        assert_ne!(
+            TicketBuilder::new(user::Id::from(1)).build().id,
+            TicketBuilder::new(user::Id::from(1)).build().id,
+        );
+    }
+
+    #[test]
+    fn overrides_defaults() {
+        let id = Id::new();
+        let created_at = OffsetDateTime::UNIX_EPOCH;
+        let ticket = TicketBuilder::new(user::Id::from(1))
+            .id(id)
+            .title("Title")
+            .description("Text")
+            .status(Status::Confirmed)
+            .category(Category::Furniture)
+            .count(5)
+            .received_count(2)
+            .price(100.0)
+            .payment_reference("Reference".to_owned())
+            .purchasing_manager(user::Id::from(2))
+            .accounting_manager(user::Id::from(3))
+            .created_at(created_at)
+            .build();
+
+        assert_eq!(ticket.id, id);
+        assert_eq!(ticket.title, "Title");
+        assert_eq!(ticket.description, "Text");
+        assert_eq!(ticket.status, Status::Confirmed);
+        assert_eq!(ticket.category, Category::Furniture);
+        assert_eq!(ticket.count, 5);
+        assert_eq!(ticket.received_count, 2);
+        assert_eq!(ticket.price, Some(100.0));
+        assert_eq!(ticket.payment_reference.as_deref(), Some("Reference"));
+        assert_eq!(ticket.purchasing_manager, Some(user::Id::from(2)));
+        assert_eq!(ticket.accounting_manager, Some(user::Id::from(3)));
+        assert_eq!(ticket.created_at, created_at);
+    }
+
+    #[test]
+    fn clears_optional_fields() {
+        let ticket = TicketBuilder::new(user::Id::from(1))
+            .price(100.0)
+            .purchasing_manager(user::Id::from(2))
+            .price(None)
+            .purchasing_manager(None)
+            .build();
+
+        assert_eq!(ticket.price, None);
+        assert_eq!(ticket.purchasing_manager, None);
+    }
+}
+
+#[cfg(test)]
+mod filter_spec {
+    use time::OffsetDateTime;
+
+    use super::{Category, Status, TicketFilter, Visibility};
+    use crate::db::user;
+
+    /// Returns a [`TicketFilter`] with the conditions selected by the bits of
+    /// the `mask` specified, in the order of its fields.
+    fn filter(mask: u16, at: OffsetDateTime) -> TicketFilter {
+        let set = |bit: u16| mask & (1 << bit) != 0;
+        TicketFilter {
+            category: set(0).then_some(Category::It),
+            statuses: if set(1) {
+                vec![Status::Confirmed]
+            } else {
+                Vec::new()
+            },
+            initiator: set(2).then_some(user::Id::from(1)),
+            assignee: set(3).then_some(user::Id::from(2)),
+            min_price: set(4).then_some(10.0),
+            max_price: set(5).then_some(20.0),
+            created_after: set(6).then_some(at),
+            created_before: set(7).then_some(at),
+            text: set(8).then(|| "monitor".to_owned()),
+            visibility: Visibility::IncludeDeleted,
+        }
+    }
+
+    #[test]
+    fn renders_no_conditions_as_true() {
+        let filter = filter(0, OffsetDateTime::UNIX_EPOCH);
+        let (sql, params) = filter.render(2);
+        assert_eq!(sql, "TRUE");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn excludes_deleted_tickets_by_default() {
+        let filter = TicketFilter::default();
+        let (sql, params) = filter.render(2);
+        assert_eq!(sql, "deleted_at IS NULL");
+        assert!(params.is_empty());
+
+        let filter = TicketFilter {
+            category: Some(Category::It),
+            ..TicketFilter::default()
+        };
+        let (sql, params) = filter.render(2);
+        assert_eq!(sql, "category = $3 AND deleted_at IS NULL");
+        assert_eq!(params.len(), 1);
+    }
+
+    #[test]
+    fn renders_every_combination() {
+        const CONDITIONS: [&str; 9] = [
+            "category = $",
+            "status = ANY($",
+            "initiator_id = $",
+            "(purchasing_manager_id = $",
+            "price >= $",
+            "price <= $",
+            "created_at > $",
+            "created_at < $",
+            "(title ILIKE ('%' || replace(replace(replace($",
+        ];
+        let at = OffsetDateTime::UNIX_EPOCH;
+        let values = [
+            format!("{:?}", Category::It),
+            format!("{:?}", [Status::Confirmed]),
+            format!("{:?}", user::Id::from(1)),
+            format!("{:?}", user::Id::from(2)),
+            format!("{:?}", 10.0),
+            format!("{:?}", 20.0),
+            format!("{at:?}"),
+            format!("{at:?}"),
+            format!("{:?}", "monitor"),
+        ];
+
+        for mask in 0..(1 << CONDITIONS.len()) {
+            let filter = filter(mask, at);
+            let (sql, params) = filter.render(3);
+
+            let selected = (0..CONDITIONS.len())
+                .filter(|bit| mask & (1 << bit) != 0)
+                .collect::<Vec<_>>();
+            assert_eq!(params.len(), selected.len(), "{filter:?}");
+
+            let conditions = sql.split(" AND ").collect::<Vec<_>>();
+            for (i, &bit) in selected.iter().enumerate() {
+                let n = 3 + i + 1;
+                assert!(
+                    conditions[i]
+                        .starts_with(&format!("{}{n}", CONDITIONS[bit])),
+                    "{filter:?} rendered as `{sql}`",
+                );
+                assert_eq!(
+                    format!("{:?}", params[i]),
+                    values[bit],
+                    "{filter:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_values_out_of_sql() {
+        let at = OffsetDateTime::UNIX_EPOCH;
+        let hostile = TicketFilter {
+            category: Some(Category::Other),
+            statuses: vec![Status::PaymentCompleted, Status::Requested],
+            initiator: Some(user::Id::from(u128::MAX)),
+            assignee: Some(user::Id::from(u128::MAX - 1)),
+            min_price: Some(f64::NAN),
+            max_price: Some(f64::INFINITY),
+            created_after: Some(OffsetDateTime::now_utc()),
+            created_before: Some(at),
+            text: Some("%' OR TRUE --".to_owned()),
+            visibility: Visibility::IncludeDeleted,
+        };
+
+        let (sql, params) = hostile.render(0);
+        let (expected, _) = filter(u16::MAX, at).render(0);
+        assert_eq!(sql, expected);
+        assert_eq!(params.len(), 9);
+        for param in params {
+            assert!(!sql.contains(&format!("{param:?}")), "{sql}");
+        }
     }
 }
@@ -1,17 +1,17 @@
-use std::{collections::HashMap, error::Error as StdError};
+use std::{collections::HashMap, error::Error as StdError, future::Future};
 
+use deadpool_postgres::GenericClient;
+use derive_more::{Display, From};
 use enum_utils::TryFromRepr;
+use futures::{Stream, StreamExt as _};
 use serde::{Deserialize, Serialize};
-use tokio_postgres::{
-    types::{
-        accepts, private::BytesMut, to_sql_checked, FromSql, IsNull, ToSql,
-        Type,
-    },
-    Error,
+use time::OffsetDateTime;
+use tokio_postgres::types::{
+    accepts, private::BytesMut, to_sql_checked, FromSql, IsNull, ToSql, Type,
 };
 use uuid::Uuid;
 
-use super::Client;
+use super::{bigint, count, Client, Error, Target, Transaction};
 
 #[derive(Clone, Debug)]
 pub struct User {
@@ -20,10 +20,39 @@ pub struct User {
     pub role: Role,
     pub login: String,
     pub password_hash: PasswordHash,
+    pub password_changed_at: OffsetDateTime,
+
+    /// Address to send password resets and notifications to, if known.
+    pub email: Option<String>,
+}
+
+/// [`User`] along with the time it was last changed at, for exporting.
+#[derive(Clone, Debug)]
+pub struct UserWithTimes {
+    pub user: User,
+    pub updated_at: OffsetDateTime,
+}
+
+/// [`User`] without its credentials, for listing users.
+#[derive(Clone, Debug)]
+pub struct UserSummary {
+    pub id: Id,
+    pub name: String,
+    pub role: Role,
+    pub login: String,
 }
 
 #[derive(
-    Clone, Copy, Debug, Default, Deserialize, Eq, Hash, PartialEq, Serialize,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Deserialize,
+    Display,
+    Eq,
+    Hash,
+    PartialEq,
+    Serialize,
 )]
 pub struct Id(Uuid);
 
@@ -64,15 +93,32 @@ impl ToSql for Id {
     }
 }
 
+/// Role of a [`User`], determining what the user may do with the tickets.
+///
+/// More roles may be added, so matching on a [`Role`] outside of this crate
+/// requires a wildcard arm:
+///
+/// ```compile_fail,E0004
+/// use dubna_internship::db::user::Role;
+///
+/// fn may_confirm(role: Role) -> bool {
+///     match role {
+///         Role::PurchasingManager => true,
+///         Role::Initiator | Role::AccountingManager | Role::Admin => false,
+///     }
+/// }
+/// ```
 #[derive(
     Clone, Copy, Debug, Deserialize, Eq, TryFromRepr, PartialEq, Serialize,
 )]
 #[repr(u8)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[non_exhaustive]
 pub enum Role {
     Initiator = 1,
     PurchasingManager = 2,
     AccountingManager = 3,
+    Admin = 4,
 }
 
 impl FromSql<'_> for Role {
@@ -140,64 +186,455 @@ impl ToSql for PasswordHash {
 }
 
 impl Client {
+    /// Served by the primary, so a changed password takes effect immediately.
     pub async fn get_user_by_login(
         &self,
         login: &str,
     ) -> Result<Option<User>, Error> {
-        const SQL: &str = "SELECT id, name, login, password_hash, role \
+        const SQL: &str = "SELECT id, name, login, password_hash, role, \
+                                  password_changed_at, email \
                            FROM users \
                            WHERE login = $1 \
                            LIMIT 1";
-        Ok(self.0.query_opt(SQL, &[&login]).await?.map(|row| User {
-            id: row.get("id"),
-            name: row.get("name"),
-            login: row.get("login"),
-            password_hash: row.get("password_hash"),
-            role: row.get("role"),
-        }))
+        self.traced("get_user_by_login", async move {
+            Ok(self.read_opt(Target::Primary, SQL, &[&login]).await?.map(
+                |row| User {
+                    id: row.get("id"),
+                    name: row.get("name"),
+                    login: row.get("login"),
+                    password_hash: row.get("password_hash"),
+                    password_changed_at: row.get("password_changed_at"),
+                    role: row.get("role"),
+                    email: row.get("email"),
+                },
+            ))
+        })
+        .await
     }
 
     pub async fn get_user_by_id(&self, id: Id) -> Result<Option<User>, Error> {
-        const SQL: &str = "SELECT id, name, login, password_hash, role \
+        const SQL: &str = "SELECT id, name, login, password_hash, role, \
+                                  password_changed_at, email \
                            FROM users \
                            WHERE id = $1 \
                            LIMIT 1";
-        Ok(self.0.query_opt(SQL, &[&id]).await?.map(|row| User {
-            id: row.get("id"),
-            name: row.get("name"),
-            login: row.get("login"),
-            password_hash: row.get("password_hash"),
-            role: row.get("role"),
-        }))
+        self.traced("get_user_by_id", async move {
+            Ok(self
+                .read_opt(Target::Replica, SQL, &[&id])
+                .await?
+                .map(|row| User {
+                    id: row.get("id"),
+                    name: row.get("name"),
+                    login: row.get("login"),
+                    password_hash: row.get("password_hash"),
+                    password_changed_at: row.get("password_changed_at"),
+                    role: row.get("role"),
+                    email: row.get("email"),
+                }))
+        })
+        .await
     }
 
     pub async fn get_users_by_ids(
         &self,
         ids: &[Id],
     ) -> Result<HashMap<Id, User>, Error> {
-        const SQL: &str = "SELECT id, name, login, password_hash, role \
+        const SQL: &str = "SELECT id, name, login, password_hash, role, \
+                                  password_changed_at, email \
                            FROM users \
                            WHERE id IN (SELECT unnest($1::UUID[])) \
                            LIMIT $2";
 
-        let limit = i64::try_from(ids.len()).unwrap();
+        self.traced("get_users_by_ids", async move {
+            let limit = i64::try_from(ids.len()).unwrap();
 
-        Ok(self
-            .0
-            .query(SQL, &[&ids, &limit])
-            .await?
-            .into_iter()
-            .map(|row| {
-                let id = row.get("id");
-                let user = User {
-                    id,
+            Ok(self
+                .read(Target::Replica, SQL, &[&ids, &limit])
+                .await?
+                .into_iter()
+                .map(|row| {
+                    let id = row.get("id");
+                    let user = User {
+                        id,
+                        name: row.get("name"),
+                        login: row.get("login"),
+                        password_hash: row.get("password_hash"),
+                        password_changed_at: row.get("password_changed_at"),
+                        role: row.get("role"),
+                        email: row.get("email"),
+                    };
+                    (id, user)
+                })
+                .collect())
+        })
+        .await
+    }
+
+    /// Streams all the users changed at or after `since`, in the order they
+    /// were changed in, without buffering them.
+    ///
+    /// If `since` isn't specified, every user is returned.
+    ///
+    /// Neither the returned [`Future`] nor the [`Stream`] borrow this
+    /// [`Client`], so they can be moved into a response body.
+    pub fn stream_users_updated_since(
+        &self,
+        since: Option<OffsetDateTime>,
+    ) -> impl Future<
+        Output = Result<
+            impl Stream<Item = Result<UserWithTimes, Error>>,
+            Error,
+        >,
+    > + 'static {
+        const SQL: &str = "SELECT id, name, login, password_hash, role, \
+                                  password_changed_at, email, updated_at \
+                           FROM users \
+                           WHERE ($1::TIMESTAMPTZ IS NULL \
+                                  OR updated_at >= $1) \
+                           ORDER BY updated_at, id";
+
+        let client = self.clone();
+        async move {
+            let conn = client.conn(Target::Replica).await?;
+            let rows =
+                conn.query_raw(SQL, [&since as &(dyn ToSql + Sync)]).await?;
+            Ok(rows.map(move |row| {
+                // Connection is held until the stream is dropped, so the pool
+                // doesn't hand it out while the rows are still being received.
+                let _conn = &conn;
+
+                let row = row?;
+                Ok(UserWithTimes {
+                    user: User {
+                        id: row.get("id"),
+                        name: row.get("name"),
+                        login: row.get("login"),
+                        password_hash: row.get("password_hash"),
+                        password_changed_at: row.get("password_changed_at"),
+                        role: row.get("role"),
+                        email: row.get("email"),
+                    },
+                    updated_at: row.get("updated_at"),
+                })
+            }))
+        }
+    }
+
+    /// Returns the requested page of users, ordered by name.
+    ///
+    /// If `role` is specified, only users having this role are returned.
+    pub async fn get_users_page(
+        &self,
+        offset: usize,
+        limit: usize,
+        role: Option<Role>,
+    ) -> Result<Vec<UserSummary>, Error> {
+        self.traced("get_users_page", async move {
+            let offset = bigint(offset, "offset")?;
+            let limit = bigint(limit, "limit")?;
+
+            const SQL: &str = "\
+                SELECT id, name, login, role \
+                FROM users \
+                WHERE $3::INT2 IS NULL OR role = $3 \
+                ORDER BY name, \
+                         id \
+                OFFSET $1 LIMIT $2";
+            Ok(self
+                .read(Target::Replica, SQL, &[&offset, &limit, &role])
+                .await?
+                .into_iter()
+                .map(|row| UserSummary {
+                    id: row.get("id"),
                     name: row.get("name"),
                     login: row.get("login"),
-                    password_hash: row.get("password_hash"),
                     role: row.get("role"),
-                };
-                (id, user)
+                })
+                .collect())
+        })
+        .await
+    }
+
+    /// Returns the total count of users.
+    ///
+    /// If `role` is specified, only users having this role are counted.
+    pub async fn get_users_count(
+        &self,
+        role: Option<Role>,
+    ) -> Result<usize, Error> {
+        const SQL: &str = "\
+            SELECT COUNT(*) \
+            FROM users \
+            WHERE $1::INT2 IS NULL OR role = $1";
+        self.traced("get_users_count", async move {
+            let n = self.read_one(Target::Replica, SQL, &[&role]).await?.get(0);
+            Ok(count(n))
+        })
+        .await
+    }
+
+    /// Returns the moment the password of the user was changed last time.
+    ///
+    /// Served by the primary, so a changed password takes effect immediately.
+    pub async fn get_user_password_changed_at(
+        &self,
+        id: Id,
+    ) -> Result<Option<OffsetDateTime>, Error> {
+        const SQL: &str = "SELECT password_changed_at \
+                           FROM users \
+                           WHERE id = $1 \
+                           LIMIT 1";
+        self.traced("get_user_password_changed_at", async move {
+            Ok(self
+                .read_opt(Target::Primary, SQL, &[&id])
+                .await?
+                .map(|row| row.get("password_changed_at")))
+        })
+        .await
+    }
+
+    pub async fn update_user_password(
+        &self,
+        id: Id,
+        password_hash: &PasswordHash,
+        changed_at: OffsetDateTime,
+    ) -> Result<(), Error> {
+        const SQL: &str = "UPDATE users \
+                           SET password_hash = $2, \
+                               password_changed_at = $3, \
+                               updated_at = now() \
+                           WHERE id = $1";
+        self.traced("update_user_password", async move {
+            self.conn(Target::Primary)
+                .await?
+                .execute(SQL, &[&id, password_hash, &changed_at])
+                .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    /// Sets the profile fields of the user with the provided `id`, returning
+    /// whether such user exists.
+    pub async fn update_user_profile(
+        &self,
+        id: Id,
+        name: &str,
+    ) -> Result<bool, Error> {
+        const SQL: &str = "UPDATE users \
+                           SET name = $2, \
+                               updated_at = now() \
+                           WHERE id = $1";
+        self.traced("update_user_profile", async move {
+            let updated = self
+                .conn(Target::Primary)
+                .await?
+                .execute(SQL, &[&id, &name])
+                .await?;
+            Ok(updated > 0)
+        })
+        .await
+    }
+
+    /// Sets the email of the user with the provided `id`, returning whether
+    /// such user exists.
+    pub async fn update_user_email(
+        &self,
+        id: Id,
+        email: &str,
+    ) -> Result<bool, Error> {
+        const SQL: &str = "UPDATE users \
+                           SET email = $2, \
+                               updated_at = now() \
+                           WHERE id = $1";
+        self.traced("update_user_email", async move {
+            let updated = self
+                .conn(Target::Primary)
+                .await?
+                .execute(SQL, &[&id, &email])
+                .await?;
+            Ok(updated > 0)
+        })
+        .await
+    }
+
+    pub async fn insert_user(
+        &self,
+        user: &User,
+    ) -> Result<(), InsertUserError> {
+        const SQL: &str = "\
+            INSERT INTO users (id, name, login, password_hash, role, \
+                               password_changed_at, email) \
+            VALUES ($1, $2, $3, $4, $5, $6, $7)";
+
+        self.traced("insert_user", async move {
+            self.conn(Target::Primary)
+                .await?
+                .execute(
+                    SQL,
+                    &[
+                        &user.id,
+                        &user.name,
+                        &user.login,
+                        &user.password_hash,
+                        &user.role,
+                        &user.password_changed_at,
+                        &user.email,
+                    ],
+                )
+                .await
+                .map_err(|e| match Error::from(e) {
+                    Error::ConstraintViolation { constraint }
+                        if constraint == "users_login_key" =>
+                    {
+                        InsertUserError::LoginTaken
+                    }
+                    e => e.into(),
+                })?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn update_user_name(
+        &self,
+        id: Id,
+        name: &str,
+    ) -> Result<(), Error> {
+        const SQL: &str = "UPDATE users \
+                           SET name = $2, \
+                               updated_at = now() \
+                           WHERE id = $1";
+        self.traced("update_user_name", async move {
+            self.conn(Target::Primary)
+                .await?
+                .execute(SQL, &[&id, &name])
+                .await?;
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn update_user_role(
+        &self,
+        id: Id,
+        role: Role,
+    ) -> Result<(), Error> {
+        self.traced("update_user_role", async move {
+            update_user_role(&self.conn(Target::Primary).await?, id, role).await
+        })
+        .await
+    }
+
+    /// Deletes the user.
+    ///
+    /// Users referenced by any ticket, comment or audit event are kept, as
+    /// deleting them would lose the history of these.
+    pub async fn delete_user(&self, id: Id) -> Result<(), DeleteUserError> {
+        const SQL: &str = "DELETE FROM users \
+                           WHERE id = $1";
+        self.traced("delete_user", async move {
+            self.conn(Target::Primary)
+                .await?
+                .execute(SQL, &[&id])
+                .await
+                .map_err(|e| match Error::from(e) {
+                    // Deleting may only violate the foreign keys referring to
+                    // the user.
+                    Error::ConstraintViolation { .. } => {
+                        DeleteUserError::UserReferenced
+                    }
+                    e => e.into(),
+                })?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+impl Transaction<'_> {
+    /// Returns the user with the provided `id`, if any, along with all the
+    /// admins, locking them until this [`Transaction`] ends, so none of them
+    /// can be demoted meanwhile.
+    ///
+    /// Users are locked in the order of their IDs, so concurrent calls can't
+    /// deadlock.
+    pub async fn lock_user_and_admins(
+        &self,
+        id: Id,
+    ) -> Result<Vec<User>, Error> {
+        const SQL: &str = "SELECT id, name, login, password_hash, role, \
+                                  password_changed_at, email \
+                           FROM users \
+                           WHERE id = $1 OR role = $2 \
+                           ORDER BY id \
+                           FOR UPDATE";
+        let rows = self.0.query(SQL, &[&id, &Role::Admin]).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| User {
+                id: row.get("id"),
+                name: row.get("name"),
+                login: row.get("login"),
+                password_hash: row.get("password_hash"),
+                password_changed_at: row.get("password_changed_at"),
+                role: row.get("role"),
+                email: row.get("email"),
             })
             .collect())
     }
+
+    pub async fn update_user_role(
+        &self,
+        id: Id,
+        role: Role,
+    ) -> Result<(), Error> {
+        update_user_role(&self.0, id, role).await
+    }
+}
+
+async fn update_user_role(
+    client: &impl GenericClient,
+    id: Id,
+    role: Role,
+) -> Result<(), Error> {
+    const SQL: &str = "UPDATE users \
+                       SET role = $2, \
+                           updated_at = now() \
+                       WHERE id = $1";
+    client.execute(SQL, &[&id, &role]).await?;
+    Ok(())
+}
+
+#[derive(Debug, Display, From)]
+pub enum InsertUserError {
+    #[display("{_0}")]
+    #[from]
+    DbError(Error),
+    #[display("login is already taken by another user")]
+    LoginTaken,
+}
+
+impl StdError for InsertUserError {}
+
+#[derive(Debug, Display, From)]
+pub enum DeleteUserError {
+    #[display("{_0}")]
+    #[from]
+    DbError(Error),
+    #[display("user is referenced by tickets, comments or audit events")]
+    UserReferenced,
 }
+
+impl StdError for DeleteUserError {}
+
+#[derive(Debug, Display, From)]
+pub enum UpdateUserRoleError {
+    #[display("{_0}")]
+    #[from]
+    DbError(Error),
+    #[display("user is the last admin")]
+    LastAdmin,
+}
+
+impl StdError for UpdateUserRoleError {}
@@ -1,4 +1,5 @@
 pub mod api;
+pub mod blob;
 pub mod config;
 pub mod db;
 
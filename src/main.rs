@@ -1,35 +1,81 @@
-use std::{error::Error, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+    error::Error,
+    fmt::Write as _,
+    future::{Future, IntoFuture as _},
+    io, mem,
+    net::{IpAddr, SocketAddr},
+    path,
+    sync::{
+        atomic::{AtomicBool, AtomicI64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use async_trait::async_trait;
 use axum::{
-    extract::{FromRequestParts, Path, Query, State},
+    body::Body,
+    extract::{
+        multipart::MultipartError, ConnectInfo, DefaultBodyLimit, FromRequest,
+        FromRequestParts, Host, Multipart, Path, Query, Request, State,
+    },
     http::{
-        header::{AUTHORIZATION, CONTENT_TYPE},
-        request, HeaderValue, Method, StatusCode,
+        header::{
+            AUTHORIZATION, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_TYPE,
+            RETRY_AFTER,
+        },
+        request,
+        uri::Authority,
+        HeaderName, HeaderValue, Method, StatusCode, Uri,
     },
-    response::{IntoResponse, Response},
-    routing::{get, post},
-    Json, RequestPartsExt as _, Router,
+    middleware::{self, Next},
+    response::{IntoResponse, Redirect, Response},
+    routing::{delete, get, patch, post},
+    RequestPartsExt as _, Router,
 };
 use axum_extra::{
     headers::{authorization::Bearer, Authorization},
     TypedHeader,
 };
-use derive_more::From;
-use futures::{future::OptionFuture, FutureExt as _};
+use axum_server::tls_rustls::RustlsConfig;
+use derive_more::{Display, From};
+use futures::{future, stream, Stream, StreamExt as _, TryStreamExt as _};
 use itertools::Itertools as _;
 use jsonwebtoken::{
     decode, encode, DecodingKey, EncodingKey, Header, Validation,
 };
-use serde::{Deserialize, Serialize};
-use time::OffsetDateTime;
-use tokio::{fs, net, task};
-use tower_http::cors::CorsLayer;
+use serde::{
+    de::{DeserializeOwned, IntoDeserializer as _},
+    Deserialize, Deserializer, Serialize,
+};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+#[cfg(unix)]
+use tokio::signal::unix::SignalKind;
+use tokio::{fs, net, signal, sync::oneshot};
+use tower_http::{
+    compression::{
+        predicate::{NotForContentType, Predicate as _, SizeAbove},
+        CompressionLayer,
+    },
+    cors::{AllowOrigin, CorsLayer},
+    trace::TraceLayer,
+};
 use tracing_subscriber::{
     layer::SubscriberExt as _, util::SubscriberInitExt as _,
 };
 
-use dubna_internship::{api, db, Config};
+use dubna_internship::{
+    api::{
+        self,
+        validation::{Code, Validator},
+    },
+    blob, config, db, Config,
+};
+
+// Handlers respond with `PrettyJson`, honoring `config::Http::pretty_json`.
+use PrettyJson as Json;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -37,59 +83,410 @@ async fn main() -> Result<(), Box<dyn Error>> {
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let config = fs::read_to_string("config.toml").await?;
-    let config = toml::from_str::<Config>(&config)?;
+    // With `--migrate`, pending migrations are applied instead of serving.
+    let migrate = match env::args().nth(1).as_deref() {
+        None => false,
+        Some("--migrate") => true,
+        Some(arg) => return Err(format!("unknown argument `{arg}`").into()),
+    };
 
-    let (db_client, db_connection) = db::connect(config.db).await?;
+    let config = read_config().await?;
 
-    task::spawn(async move {
-        if let Err(e) = db_connection.await {
-            panic!("database connection failed: {e}");
-        }
-    });
+    let skip_schema_check = config.db.skip_schema_check;
+    let db_client = db::connect(config.db).await?;
+
+    if migrate {
+        let applied = db::migrations::run_pending(
+            &db_client,
+            path::Path::new("migrations"),
+        )
+        .await?;
+        tracing::info!(applied, "database is migrated");
+        return Ok(());
+    }
 
-    let mut cors = CorsLayer::new()
-        .allow_methods([Method::GET, Method::PATCH])
-        .allow_headers([AUTHORIZATION, CONTENT_TYPE]);
-    for origin in &config.http.cors.allowed_origins {
-        cors = cors.allow_origin(origin.parse::<HeaderValue>()?);
+    if skip_schema_check {
+        tracing::warn!("database schema check is skipped");
+    } else {
+        db::Storage::check_schema_version(&db_client, db::SCHEMA_VERSION)
+            .await?;
     }
 
-    let app = Router::new()
-        .route("/auth", post(auth))
+    let tokens_valid_after = db_client.get_tokens_valid_after().await?;
+
+    let blobs = blob::connect(&config.attachments.storage).await;
+    // Multipart encoding adds the boundaries and headers of the parts on top
+    // of the file itself.
+    let attachment_body_limit =
+        config.attachments.max_size + MULTIPART_OVERHEAD;
+
+    let allowed_origins = if config.http.cors.allows_any_origin() {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(
+            config
+                .http
+                .cors
+                .allowed_origins
+                .iter()
+                .map(|origin| origin.parse::<HeaderValue>())
+                .collect::<Result<Vec<_>, _>>()?,
+        )
+    };
+    let exposed_headers = config
+        .http
+        .cors
+        .expose_headers
+        .iter()
+        .map(|name| name.parse::<HeaderName>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let cors = CorsLayer::new()
+        .allow_methods([Method::GET, Method::PATCH, Method::DELETE])
+        .allow_headers([AUTHORIZATION, CONTENT_TYPE])
+        .allow_origin(allowed_origins)
+        .allow_credentials(config.http.cors.allow_credentials)
+        .expose_headers(exposed_headers)
+        .max_age(Duration::from_secs(
+            config.http.cors.preflight_max_age_seconds,
+        ));
+
+    let probes = Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/version", get(version))
+        .route("/metrics", get(metrics));
+    let read_only = ReadOnly {
+        enabled: Arc::new(AtomicBool::new(config.maintenance.read_only)),
+        retry_after: config.maintenance.retry_after,
+    };
+    #[cfg(unix)]
+    reload_read_only_on_hangup(Arc::clone(&read_only.enabled))?;
+    let api = Router::new()
+        .route("/auth/invalidate", post(invalidate_tokens))
         .route("/user", get(get_user))
+        .route("/user/:id", delete(delete_user))
+        .route("/user/:id/role", patch(change_user_role))
+        .route("/user/password", post(change_password))
+        .route("/user/me/assigned", get(list_assigned_tickets))
+        .route("/me", patch(update_profile))
+        .route("/me/email", post(set_email))
+        .route("/me/permissions", get(get_permissions))
+        .route("/dashboard", get(get_dashboard))
+        .route("/export.json", get(export_data))
         .route("/ticket", get(list_tickets).post(add_ticket))
+        .route("/ticket/count", get(count_tickets))
+        .route("/ticket/bulk-transition", post(bulk_transition_tickets))
+        .route("/ticket/export", get(export_tickets))
+        .route("/ticket/import", post(import_tickets))
         .route("/ticket/:id", get(get_ticket).patch(edit_ticket))
+        .route(
+            "/ticket/:id/attachment",
+            post(upload_attachment)
+                .layer(DefaultBodyLimit::max(attachment_body_limit)),
+        )
+        .route("/ticket/:id/history", get(get_ticket_history))
+        .route("/ticket/:id/attachments", get(list_attachments))
+        .route(
+            "/ticket/:id/attachment/:attachment_id",
+            get(download_attachment),
+        )
+        // Signing in is let through, as reading requires a token.
+        .layer(middleware::from_fn_with_state(read_only, reject_writes))
+        .route("/auth", post(auth));
+    let api = match &config.http.login_availability {
+        Some(availability) => api.route(
+            "/auth/available",
+            get(check_login_availability).layer(
+                middleware::from_fn_with_state(
+                    RateLimit::new(
+                        availability.max_requests,
+                        availability.period,
+                        availability.trust_forwarded_for,
+                    ),
+                    rate_limit,
+                ),
+            ),
+        ),
+        None => api,
+    };
+    // Probes stay at the root, so orchestrators needn't know the prefix.
+    let routes = match &config.http.base_path {
+        Some(base_path) => probes.nest(base_path, api),
+        None => probes.merge(api),
+    };
+    let routes = if config.http.expose_db_errors {
+        tracing::warn!("database errors are exposed in responses");
+        routes.layer(middleware::from_fn(expose_db_errors))
+    } else {
+        routes
+    };
+    let routes = if config.http.pretty_json {
+        routes.layer(middleware::from_fn(pretty_json))
+    } else {
+        routes
+    };
+    // Event streams are excluded, as compressing buffers them.
+    let routes = match &config.http.compression {
+        Some(compression) => routes.layer(
+            CompressionLayer::new().compress_when(
+                SizeAbove::new(compression.min_size)
+                    .and(NotForContentType::GRPC)
+                    .and(NotForContentType::IMAGES)
+                    .and(NotForContentType::SSE),
+            ),
+        ),
+        None => routes,
+    };
+    // Request spans enclose the spans of the database queries made while
+    // handling them.
+    let app = routes
         .layer(cors)
-        .with_state(Arc::new(AppState {
-            db_client,
+        .layer(middleware::from_fn(json_charset))
+        .layer(TraceLayer::new_for_http())
+        .with_state(AppState {
+            db_client: Arc::new(db_client),
             jwt_expiration_time: config.jwt.expiration_time,
+            jwt_leeway: config.jwt.leeway,
             jwt_decoding_key: DecodingKey::from_secret(
                 config.jwt.secret.as_bytes(),
             ),
             jwt_encoding_key: EncodingKey::from_secret(
                 config.jwt.secret.as_bytes(),
             ),
-        }));
+            jwt_clients: Arc::new(config.jwt.clients),
+            tokens_valid_after: Arc::new(AtomicI64::new(
+                tokens_valid_after.unix_timestamp(),
+            )),
+            password_changed_at: Arc::default(),
+            user_cache_ttl: config.http.user_cache.map(|c| c.ttl),
+            users: Arc::default(),
+            started_at: OffsetDateTime::now_utc(),
+            started: Instant::now(),
+            blobs,
+            attachments: Arc::new(config.attachments),
+            tickets: Arc::new(config.tickets),
+        });
+
+    let shutdown_signal = shutdown_signal()?;
+    let shutdown_timeout = config.http.server.shutdown_timeout;
+
+    if let Some(tls) = config.http.tls {
+        let mut redirect_addr = config.http.server.addr;
+        redirect_addr.set_port(tls.redirect_from_port);
+        let redirect_listener = net::TcpListener::bind(redirect_addr).await?;
+        let redirect_app = Router::new()
+            .fallback(redirect_to_https)
+            .with_state(config.http.server.addr.port());
+
+        let tls_config =
+            RustlsConfig::from_pem_file(tls.cert_path, tls.key_path).await?;
+
+        let handle = axum_server::Handle::new();
+        tokio::spawn({
+            let handle = handle.clone();
+            async move {
+                shutdown_signal.await;
+                tracing::info!("shutting down, draining in-flight requests");
+                handle.graceful_shutdown(Some(shutdown_timeout));
+            }
+        });
+
+        let redirect = axum_server::from_tcp(redirect_listener.into_std()?)
+            .handle(handle.clone())
+            .serve(redirect_app.into_make_service());
+        let server =
+            axum_server::bind_rustls(config.http.server.addr, tls_config)
+                .handle(handle)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>());
+        tokio::try_join!(server, redirect)?;
+        return Ok(());
+    }
+
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
 
     let listener = net::TcpListener::bind(config.http.server.addr).await?;
-    axum::serve(listener, app).await?;
+    let app = app.into_make_service_with_connect_info::<SocketAddr>();
+    let server = axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            shutdown_signal.await;
+            _ = shutdown_tx.send(());
+        })
+        .into_future();
+    tokio::pin!(server);
+
+    tokio::select! {
+        res = &mut server => res?,
+        Ok(()) = shutdown_rx => {
+            tracing::info!("shutting down, draining in-flight requests");
+            tokio::time::timeout(shutdown_timeout, server)
+                .await
+                .map_err(|_| "in-flight requests didn't complete in time")??;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads and validates the `config.toml` in the working directory.
+async fn read_config() -> Result<Config, ReadConfigError> {
+    let config = fs::read_to_string("config.toml").await?;
+    let config = toml::from_str::<Config>(&config)?;
+    config.validate()?;
+    Ok(config)
+}
 
+#[derive(Debug, Display, From)]
+enum ReadConfigError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    Invalid(config::ValidationError),
+}
+
+impl Error for ReadConfigError {}
+
+/// Re-reads [`config::Maintenance::read_only`] from the `config.toml`
+/// whenever `SIGHUP` is received, updating the `read_only` flag.
+///
+/// Other settings are left as they were at startup.
+#[cfg(unix)]
+fn reload_read_only_on_hangup(read_only: Arc<AtomicBool>) -> io::Result<()> {
+    let mut hangup = signal::unix::signal(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            match read_config().await {
+                Ok(config) => {
+                    let enabled = config.maintenance.read_only;
+                    read_only.store(enabled, Ordering::Relaxed);
+                    tracing::info!(read_only = enabled, "config is reloaded");
+                }
+                Err(e) => {
+                    tracing::warn!("failed to reload the config: {e}");
+                }
+            }
+        }
+    });
     Ok(())
 }
 
+/// Installs the handlers of the signals requesting the server to shut down,
+/// returning a [`Future`] resolving once any of them is received.
+///
+/// On Unix this is `SIGTERM` or `SIGINT`, elsewhere only Ctrl+C.
+fn shutdown_signal() -> io::Result<impl Future<Output = ()>> {
+    #[cfg(unix)]
+    let mut terminate = signal::unix::signal(SignalKind::terminate())?;
+
+    Ok(async move {
+        #[cfg(unix)]
+        tokio::select! {
+            _ = terminate.recv() => {}
+            _ = signal::ctrl_c() => {}
+        }
+
+        #[cfg(not(unix))]
+        {
+            let _ = signal::ctrl_c().await;
+        }
+    })
+}
+
+/// Permanently redirects a plain HTTP request to the same URI served over
+/// HTTPS on the `https_port`.
+async fn redirect_to_https(
+    State(https_port): State<u16>,
+    Host(host): Host,
+    uri: Uri,
+) -> Result<Redirect, StatusCode> {
+    let authority: Authority =
+        host.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+    let host = authority.host();
+    let path = uri.path_and_query().map_or("/", |p| p.as_str());
+
+    let location = if https_port == 443 {
+        format!("https://{host}{path}")
+    } else {
+        format!("https://{host}:{https_port}{path}")
+    };
+    Ok(Redirect::permanent(&location))
+}
+
+/// Liveness probe, which doesn't touch the database.
+async fn healthz() -> StatusCode {
+    StatusCode::OK
+}
+
+/// Readiness probe, succeeding only when the database is reachable.
+async fn readyz(
+    State(state): State<AppState>,
+) -> Result<StatusCode, ReadyzError> {
+    const PING_TIMEOUT: Duration = Duration::from_secs(1);
+
+    state.db_client.ping(PING_TIMEOUT).await?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Debug, From)]
+pub enum ReadyzError {
+    #[from]
+    PingError(db::PingError),
+}
+
+impl IntoResponse for ReadyzError {
+    fn into_response(self) -> Response {
+        #[derive(Serialize)]
+        struct Body {
+            reason: String,
+        }
+
+        match self {
+            Self::PingError(e) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(Body {
+                    reason: e.to_string(),
+                }),
+            ),
+        }
+        .into_response()
+    }
+}
+
+/// Reports the build the server runs and how long it has been running.
+async fn version(State(state): State<AppState>) -> Json<api::Version> {
+    Json(api::Version {
+        version: env!("CARGO_PKG_VERSION").to_owned(),
+        git_sha: option_env!("GIT_SHA").map(ToOwned::to_owned),
+        started_at: state.started_at,
+        uptime_secs: state.started.elapsed().as_secs(),
+    })
+}
+
 #[derive(Deserialize)]
 struct AuthInput {
     login: String,
     password: String,
+
+    /// Name of the [`config::JwtClient`] profile to issue the token by.
+    client: Option<String>,
 }
 
 async fn auth(
-    State(state): State<SharedAppState>,
-    Json(AuthInput { login, password }): Json<AuthInput>,
-) -> Result<String, AuthError> {
+    State(state): State<AppState>,
+    Json(AuthInput {
+        login,
+        password,
+        client,
+    }): Json<AuthInput>,
+) -> Result<Json<api::user::AuthResponse>, AuthError> {
     use AuthError as E;
 
+    let profile = client
+        .map(|name| state.jwt_clients.get(&name).ok_or(E::UnknownClient))
+        .transpose()?;
+    let expiration_time = profile
+        .and_then(|p| p.expiration_time)
+        .unwrap_or(state.jwt_expiration_time);
+
     let password_hash = api::user::PasswordHash::new(&password);
 
     let user = state
@@ -99,16 +496,66 @@ async fn auth(
         .filter(|u| u.password_hash == password_hash)
         .ok_or(E::WrongLoginOrPassword)?;
 
-    let expires_at = OffsetDateTime::now_utc() + state.jwt_expiration_time;
-    encode(
+    // Claims have a one-second precision, so the reported times are
+    // truncated the same way. Tokens issued within the second of the last
+    // password change are rejected, so the ones issued after it are dated to
+    // the next second, up to a second ahead of time.
+    let changed_at = user.password_changed_at.unix_timestamp() + 1;
+    let issued_at = OffsetDateTime::now_utc()
+        .replace_nanosecond(0)
+        .unwrap()
+        .max(OffsetDateTime::from_unix_timestamp(changed_at).unwrap());
+    let expires_at = issued_at + Duration::from_secs(expiration_time.as_secs());
+    let token = encode(
         &Header::default(),
-        &AuthClaims {
-            user_id: user.id,
-            exp: expires_at.unix_timestamp(),
+        &IssuedClaims {
+            claims: AuthClaims {
+                user_id: user.id,
+                exp: expires_at.unix_timestamp(),
+                iat: issued_at.unix_timestamp(),
+            },
+            aud: profile.map(|p| p.audience.as_str()),
         },
         &state.jwt_encoding_key,
     )
-    .map_err(|_| E::InvalidToken)
+    .map_err(|_| E::InvalidToken)?;
+    Ok(Json(api::user::AuthResponse {
+        token,
+        issued_at,
+        expires_at,
+    }))
+}
+
+#[derive(Deserialize)]
+struct CheckLoginAvailabilityInput {
+    login: String,
+}
+
+/// Checks whether the `login` isn't taken by any user yet.
+async fn check_login_availability(
+    State(state): State<AppState>,
+    Query(CheckLoginAvailabilityInput { login }): Query<
+        CheckLoginAvailabilityInput,
+    >,
+) -> Result<Json<api::user::LoginAvailability>, CheckLoginAvailabilityError> {
+    let user = state.db_client.get_user_by_login(&login).await?;
+    Ok(Json(api::user::LoginAvailability {
+        available: user.is_none(),
+    }))
+}
+
+#[derive(Debug, From)]
+pub enum CheckLoginAvailabilityError {
+    #[from]
+    DbError(db::Error),
+}
+
+impl IntoResponse for CheckLoginAvailabilityError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::DbError(e) => internal_db_error(&e),
+        }
+    }
 }
 
 #[derive(Debug, From)]
@@ -116,37 +563,129 @@ pub enum AuthError {
     #[from]
     DbError(db::Error),
     InvalidToken,
+    UnknownClient,
     WrongLoginOrPassword,
 }
 
 impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let (status, code, message) = match &self {
+            // Details are only reported if `expose_db_errors` is set, as for
+            // any other handler.
+            Self::DbError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "INTERNAL_ERROR",
+                "Failed to authenticate, try again later.",
+            ),
+            Self::InvalidToken => (
+                StatusCode::UNAUTHORIZED,
+                "INVALID_TOKEN",
+                "Access token is missing, invalid or expired, sign in again.",
+            ),
+            Self::UnknownClient => (
+                StatusCode::BAD_REQUEST,
+                "UNKNOWN_CLIENT",
+                "Client the token is requested for is unknown.",
+            ),
+            Self::WrongLoginOrPassword => (
+                StatusCode::FORBIDDEN,
+                "WRONG_CREDENTIALS",
+                "Login or password is wrong.",
+            ),
+        };
+        let failure = api::user::AuthFailure {
+            code: code.to_owned(),
+            message: message.to_owned(),
+        };
+        let mut response = (status, Json(failure)).into_response();
+        if let Self::DbError(e) = &self {
+            response
+                .extensions_mut()
+                .insert(db::SerializableDbError::from(e));
+        }
+        response
+    }
+}
+
+/// Invalidates all the access tokens issued so far.
+///
+/// Intended to be used when the JWT secret is suspected to be compromised:
+/// 1. An admin calls `POST /auth/invalidate`, so every outstanding token is
+///    rejected from now on (including the admin's own one).
+/// 2. The secret in `config.toml` is rotated and the server is restarted.
+/// 3. Users authenticate again via `POST /auth`.
+///
+/// The cutoff is persisted in the database and loaded on startup, so it
+/// survives restarts. Tokens issued within the same second as the cutoff
+/// remain valid, as `iat` has a one-second precision.
+async fn invalidate_tokens(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+) -> Result<StatusCode, InvalidateTokensError> {
+    use InvalidateTokensError as E;
+
+    let my = state
+        .db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
+    if my.role != db::user::Role::Admin {
+        return Err(E::NotAdmin);
+    }
+
+    let now = OffsetDateTime::now_utc();
+    state.db_client.set_tokens_valid_after(now).await?;
+    state
+        .tokens_valid_after
+        .store(now.unix_timestamp(), Ordering::Relaxed);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, From)]
+pub enum InvalidateTokensError {
+    #[from]
+    DbError(db::Error),
+    NotAdmin,
+    UserNotFound,
+}
+
+impl IntoResponse for InvalidateTokensError {
     fn into_response(self) -> Response {
         match self {
-            Self::DbError(_) => StatusCode::INTERNAL_SERVER_ERROR,
-            Self::InvalidToken => StatusCode::UNAUTHORIZED,
-            Self::WrongLoginOrPassword => StatusCode::FORBIDDEN,
+            Self::NotAdmin => StatusCode::FORBIDDEN,
+            Self::DbError(e) => return internal_db_error(&e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
         }
         .into_response()
     }
 }
 
 async fn get_user(
-    State(state): State<SharedAppState>,
+    State(state): State<AppState>,
     auth_claims: AuthClaims,
 ) -> Result<Json<api::User>, GetUserError> {
     use GetUserError as E;
 
+    if let Some(my) = state.cached_user(auth_claims.user_id) {
+        return Ok(Json(my));
+    }
+
+    // Read from the primary, so the replicas lagging behind the changes
+    // evicting the user aren't cached.
     let my = state
         .db_client
+        .primary()
         .get_user_by_id(auth_claims.user_id)
         .await?
         .ok_or(E::UserNotFound)?;
 
-    Ok(Json(api::User {
-        id: my.id,
-        name: my.name,
-        role: my.role,
-    }))
+    let my = api::User {
+        email: my.email.clone(),
+        ..(&my).into()
+    };
+    state.cache_user(&my);
+    Ok(Json(my))
 }
 
 #[derive(Debug, From)]
@@ -159,477 +698,4234 @@ pub enum GetUserError {
 impl IntoResponse for GetUserError {
     fn into_response(self) -> Response {
         match self {
-            Self::DbError(_) | Self::UserNotFound => {
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
+            Self::DbError(e) => return internal_db_error(&e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
         }
         .into_response()
     }
 }
 
 #[derive(Deserialize)]
-struct ListTicketsInput {
-    offset: usize,
-    limit: usize,
+struct UpdateProfileInput {
+    name: String,
 }
 
-async fn list_tickets(
-    State(state): State<SharedAppState>,
-    _: AuthClaims,
-    Query(ListTicketsInput { offset, limit }): Query<ListTicketsInput>,
-) -> Result<Json<api::ticket::List>, ListTicketsError> {
-    use ListTicketsError as E;
-
-    let page_fut = state.db_client.get_tickets_page(offset, limit);
-    let total_count_fut = state.db_client.get_tickets_count();
-    let (page, total_count) = tokio::try_join!(page_fut, total_count_fut)?;
+/// Updates the profile of the current user, returning the updated user.
+async fn update_profile(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Json(UpdateProfileInput { name }): Json<UpdateProfileInput>,
+) -> Result<Json<api::User>, UpdateProfileError> {
+    use UpdateProfileError as E;
 
-    let user_ids = page
-        .iter()
-        .map(|ticket| ticket.initiator)
-        .chain(page.iter().filter_map(|ticket| ticket.purchasing_manager))
-        .chain(page.iter().filter_map(|ticket| ticket.accounting_manager))
-        .unique()
-        .collect::<Vec<_>>();
-    let users = state.db_client.get_users_by_ids(&user_ids).await?;
+    let mut validator = Validator::new();
+    validator
+        .check("name", !name.trim().is_empty(), Code::MustNotBeEmpty)
+        .check(
+            "name",
+            name.chars().count() <= api::user::NAME_MAX_LEN,
+            Code::TooLong,
+        );
+    validator.finish().map_err(E::Invalid)?;
 
-    let tickets = page
-        .into_iter()
-        .map(|ticket| {
-            let initiator = users
-                .get(&ticket.initiator)
-                .ok_or(E::UserNotFound(ticket.initiator))?;
-            let purchasing_manager = ticket
-                .purchasing_manager
-                .map(|id| users.get(&id).ok_or(E::UserNotFound(id)))
-                .transpose()?;
-            let accounting_manager = ticket
-                .accounting_manager
-                .map(|id| users.get(&id).ok_or(E::UserNotFound(id)))
-                .transpose()?;
-            Ok::<_, E>(api::Ticket {
-                id: ticket.id,
-                title: ticket.title,
-                description: ticket.description,
-                status: ticket.status,
-                count: ticket.count,
-                price: ticket.price,
-                initiator: api::User {
-                    id: initiator.id,
-                    name: initiator.name.clone(),
-                    role: initiator.role,
-                },
-                purchasing_manager: purchasing_manager.map(|u| api::User {
-                    id: u.id,
-                    name: u.name.clone(),
-                    role: u.role,
-                }),
-                accounting_manager: accounting_manager.map(|u| api::User {
-                    id: u.id,
-                    name: u.name.clone(),
-                    role: u.role,
-                }),
-            })
-        })
-        .collect::<Result<Vec<_>, _>>()?;
+    let db_client = state.db_client.primary();
+    if !db_client
+        .update_user_profile(auth_claims.user_id, &name)
+        .await?
+    {
+        return Err(E::UserNotFound);
+    }
+    let my = db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
 
-    Ok(Json(api::ticket::List {
-        tickets,
-        total_count,
-    }))
+    // Read from the primary, so the replicas lagging behind aren't cached.
+    let my = api::User {
+        email: my.email.clone(),
+        ..(&my).into()
+    };
+    state.cache_user(&my);
+    Ok(Json(my))
 }
 
 #[derive(Debug, From)]
-pub enum ListTicketsError {
+pub enum UpdateProfileError {
     #[from]
     DbError(db::Error),
-    UserNotFound(api::user::Id),
+    Invalid(api::validation::Errors),
+    UserNotFound,
 }
 
-impl IntoResponse for ListTicketsError {
+impl IntoResponse for UpdateProfileError {
     fn into_response(self) -> Response {
         match self {
-            Self::DbError(_) | Self::UserNotFound(_) => {
-                StatusCode::INTERNAL_SERVER_ERROR
+            Self::Invalid(errors) => {
+                return (StatusCode::UNPROCESSABLE_ENTITY, Json(errors))
+                    .into_response();
             }
+            Self::DbError(e) => return db_error_into_response(e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
         }
         .into_response()
     }
 }
 
 #[derive(Deserialize)]
-struct AddTicketInput {
-    title: String,
-    description: String,
-    count: usize,
+#[serde(rename_all = "camelCase")]
+struct SetEmailInput {
+    email: String,
+    current_password: String,
 }
 
-async fn add_ticket(
-    State(state): State<SharedAppState>,
+/// Sets the email of the current user, returning the updated user.
+///
+/// Requires the current password, so a leaked token alone isn't enough to
+/// redirect the password resets of the user.
+async fn set_email(
+    State(state): State<AppState>,
     auth_claims: AuthClaims,
-    Json(AddTicketInput {
-        title,
-        description,
-        count,
-    }): Json<AddTicketInput>,
-) -> Result<Json<api::Ticket>, AddTicketError> {
-    use AddTicketError as E;
+    Json(SetEmailInput {
+        email,
+        current_password,
+    }): Json<SetEmailInput>,
+) -> Result<Json<api::User>, SetEmailError> {
+    use SetEmailError as E;
 
-    let my = state
-        .db_client
+    let email = email.trim();
+    let mut validator = Validator::new();
+    validator
+        .check(
+            "email",
+            api::user::is_valid_email(email),
+            Code::InvalidFormat,
+        )
+        .check(
+            "email",
+            email.chars().count() <= api::user::EMAIL_MAX_LEN,
+            Code::TooLong,
+        );
+    validator.finish().map_err(E::Invalid)?;
+
+    let db_client = state.db_client.primary();
+    let my = db_client
         .get_user_by_id(auth_claims.user_id)
         .await?
         .ok_or(E::UserNotFound)?;
-    if my.role != db::user::Role::Initiator {
-        return Err(E::TicketCannotBeCreated);
+    if my.password_hash != api::user::PasswordHash::new(&current_password) {
+        return Err(E::WrongPassword);
     }
 
-    let ticket = db::Ticket {
-        id: db::ticket::Id::new(),
-        title,
-        description,
-        status: db::ticket::Status::Requested,
-        count,
-        price: None,
-        initiator: my.id,
-        purchasing_manager: None,
-        accounting_manager: None,
-        created_at: OffsetDateTime::now_utc(),
-    };
-
-    state.db_client.write_ticket(&ticket).await?;
+    if !db_client.update_user_email(my.id, email).await? {
+        return Err(E::UserNotFound);
+    }
 
-    Ok(Json(api::Ticket {
-        id: ticket.id,
-        title: ticket.title,
-        description: ticket.description,
-        count: ticket.count,
-        price: ticket.price,
-        initiator: api::User {
-            id: my.id,
-            name: my.name.clone(),
-            role: my.role,
-        },
-        purchasing_manager: None,
-        accounting_manager: None,
-        status: ticket.status,
-    }))
+    let my = api::User {
+        email: Some(email.to_owned()),
+        ..(&my).into()
+    };
+    state.cache_user(&my);
+    Ok(Json(my))
 }
 
 #[derive(Debug, From)]
-pub enum AddTicketError {
+pub enum SetEmailError {
     #[from]
     DbError(db::Error),
-    TicketCannotBeCreated,
+    Invalid(api::validation::Errors),
     UserNotFound,
+    WrongPassword,
 }
 
-impl IntoResponse for AddTicketError {
+impl IntoResponse for SetEmailError {
     fn into_response(self) -> Response {
         match self {
-            Self::TicketCannotBeCreated => StatusCode::BAD_REQUEST,
-            Self::DbError(_) | Self::UserNotFound => {
-                StatusCode::INTERNAL_SERVER_ERROR
+            Self::Invalid(errors) => {
+                return (StatusCode::UNPROCESSABLE_ENTITY, Json(errors))
+                    .into_response();
             }
+            Self::DbError(e) => return db_error_into_response(e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::WrongPassword => StatusCode::FORBIDDEN,
         }
         .into_response()
     }
 }
 
-#[derive(Deserialize)]
-#[serde(content = "data", rename_all = "camelCase", tag = "op")]
-enum EditTicketInput {
-    EditTitle { title: String },
-    EditDescription { description: String },
-    Cancel,
-    Confirm { price: f64 },
-    Deny,
-    MarkAsPaid,
-}
-
-async fn edit_ticket(
-    State(state): State<SharedAppState>,
+/// Deletes the user, if no tickets refer to it.
+///
+/// Deleting a user referred to by tickets is refused with the tickets listed
+/// in [`api::user::UserHasTickets`], rather than deleting or orphaning them.
+/// The same goes for the users who have commented, attached files or acted on
+/// tickets, keeping the history of the tickets intact.
+async fn delete_user(
+    State(state): State<AppState>,
     auth_claims: AuthClaims,
-    Path(id): Path<api::ticket::Id>,
-    Json(op): Json<EditTicketInput>,
-) -> Result<Json<api::Ticket>, EditTicketError> {
-    use EditTicketError as E;
-    use EditTicketInput as Op;
+    Path(id): Path<api::user::Id>,
+) -> Result<StatusCode, DeleteUserError> {
+    use DeleteUserError as E;
 
-    let state = &state;
-
-    let my = state
-        .db_client
+    let db_client = state.db_client.primary();
+    let my = db_client
         .get_user_by_id(auth_claims.user_id)
         .await?
-        .ok_or(E::UserNotFound)?;
-    let mut ticket = state
-        .db_client
-        .get_ticket_by_id(id)
-        .await?
-        .ok_or(E::TicketNotFound)?;
+        .ok_or(E::MyUserNotFound)?;
+    if my.role != db::user::Role::Admin {
+        return Err(E::NotAdmin);
+    }
+    if db_client.get_user_by_id(id).await?.is_none() {
+        return Err(E::UserNotFound);
+    }
 
-    match op {
-        Op::EditTitle { title } => {
-            if ticket.status != db::ticket::Status::Requested
-                || ticket.initiator != my.id
-            {
-                return Err(E::TicketCannotBeModified);
-            }
+    let ticket_count = db_client.count_tickets_for_user(id).await?;
+    if ticket_count > 0 {
+        let ticket_ids = db_client
+            .get_ticket_ids_for_user(id, api::user::USER_TICKETS_MAX_LISTED)
+            .await?;
+        return Err(E::HasTickets(api::user::UserHasTickets {
+            code: "USER_HAS_TICKETS".to_owned(),
+            ticket_count,
+            ticket_ids,
+        }));
+    }
 
-            ticket.title = title;
-        }
-        Op::EditDescription { description } => {
-            // Description can be used for comments, so should be editable
-            // throughout the ticket lifecycle.
-            ticket.description = description;
-        }
-        Op::Cancel => {
-            if ticket.status != db::ticket::Status::Requested
-                || ticket.initiator != my.id
-            {
-                return Err(E::TicketCannotBeCancelled);
-            }
+    db_client.delete_user(id).await.map_err(|e| match e {
+        db::user::DeleteUserError::DbError(e) => E::DbError(e),
+        db::user::DeleteUserError::UserReferenced => E::UserReferenced,
+    })?;
+    state.evict_user(id);
+    Ok(StatusCode::NO_CONTENT)
+}
 
-            ticket.status = db::ticket::Status::Cancelled;
-        }
-        Op::Confirm { price } => {
-            if ticket.status != db::ticket::Status::Requested
-                || my.role != db::user::Role::PurchasingManager
-            {
-                return Err(E::TicketCannotBeConfirmed);
-            }
+#[derive(Debug, From)]
+pub enum DeleteUserError {
+    #[from]
+    DbError(db::Error),
+    HasTickets(api::user::UserHasTickets),
+    MyUserNotFound,
+    NotAdmin,
+    UserNotFound,
+    UserReferenced,
+}
 
-            ticket.status = db::ticket::Status::Confirmed;
-            ticket.price = Some(price);
-            ticket.purchasing_manager = Some(my.id);
-        }
-        Op::Deny => {
-            if ticket.status != db::ticket::Status::Requested
-                || my.role != db::user::Role::PurchasingManager
-            {
-                return Err(E::TicketCannotBeConfirmed);
+impl IntoResponse for DeleteUserError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::HasTickets(tickets) => {
+                return (StatusCode::CONFLICT, Json(tickets)).into_response();
             }
+            Self::DbError(e) => return db_error_into_response(e),
+            Self::MyUserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotAdmin => StatusCode::FORBIDDEN,
+            Self::UserNotFound => StatusCode::NOT_FOUND,
+            Self::UserReferenced => StatusCode::CONFLICT,
+        }
+        .into_response()
+    }
+}
 
-            ticket.status = db::ticket::Status::Denied;
-            ticket.purchasing_manager = Some(my.id);
+#[derive(Deserialize)]
+struct ChangeUserRoleInput {
+    role: api::user::Role,
+}
+
+/// Changes the role of the user, responding with the changed user.
+///
+/// Changing the role to the one the user already has changes nothing, so
+/// repeating the request is safe.
+async fn change_user_role(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Path(id): Path<api::user::Id>,
+    Json(input): Json<ChangeUserRoleInput>,
+) -> Result<Json<api::User>, ChangeUserRoleError> {
+    use ChangeUserRoleError as E;
+
+    let db_client = state.db_client.primary();
+    let my = db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::MyUserNotFound)?;
+    if my.role != db::user::Role::Admin {
+        return Err(E::NotAdmin);
+    }
+
+    let user = db_client
+        .update_user_role_with_event(id, input.role, my.id)
+        .await
+        .map_err(|e| match e {
+            db::user::UpdateUserRoleError::DbError(e) => E::DbError(e),
+            db::user::UpdateUserRoleError::LastAdmin => E::LastAdmin,
+        })?
+        .ok_or(E::UserNotFound)?;
+    state.evict_user(id);
+    Ok(Json((&user).into()))
+}
+
+#[derive(Debug, From)]
+pub enum ChangeUserRoleError {
+    #[from]
+    DbError(db::Error),
+    LastAdmin,
+    MyUserNotFound,
+    NotAdmin,
+    UserNotFound,
+}
+
+impl IntoResponse for ChangeUserRoleError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::DbError(e) => return db_error_into_response(e),
+            Self::LastAdmin => StatusCode::CONFLICT,
+            Self::MyUserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+            Self::NotAdmin => StatusCode::FORBIDDEN,
+            Self::UserNotFound => StatusCode::NOT_FOUND,
         }
-        Op::MarkAsPaid => {
-            if ticket.status != db::ticket::Status::Confirmed
-                || my.role != db::user::Role::AccountingManager
-            {
-                return Err(E::TicketCannotBePaid);
-            }
+        .into_response()
+    }
+}
 
-            ticket.status = db::ticket::Status::PaymentCompleted;
-            ticket.accounting_manager = Some(my.id);
+/// Time clients may reuse the [`api::Permissions`] for, as they only change
+/// along with the role of the user.
+const PERMISSIONS_MAX_AGE: Duration = Duration::from_secs(5 * 60);
+
+/// Returns the actions the current user may perform.
+async fn get_permissions(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+) -> Result<impl IntoResponse, GetPermissionsError> {
+    use GetPermissionsError as E;
+
+    // Tokens don't carry the role, so it has to be looked up.
+    let my = state
+        .db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
+
+    // Responses are specific to the token, so shared caches mustn't keep
+    // them.
+    let cache_control =
+        format!("private, max-age={}", PERMISSIONS_MAX_AGE.as_secs());
+    Ok((
+        [(CACHE_CONTROL, cache_control)],
+        Json(api::Permissions::of(my.role)),
+    ))
+}
+
+#[derive(Debug, From)]
+pub enum GetPermissionsError {
+    #[from]
+    DbError(db::Error),
+    UserNotFound,
+}
+
+impl IntoResponse for GetPermissionsError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::DbError(e) => return db_error_into_response(e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
         }
+        .into_response()
     }
+}
 
-    state.db_client.write_ticket(&ticket).await?;
+/// Returns the summary of the tickets relevant to the role of the current
+/// user.
+///
+/// Only the counts are queried, never the tickets themselves.
+async fn get_dashboard(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+) -> Result<Json<api::Dashboard>, GetDashboardError> {
+    use db::user::Role;
+    use GetDashboardError as E;
 
-    let initiator = state
+    let my = state
         .db_client
-        .get_user_by_id(ticket.initiator)
+        .get_user_by_id(auth_claims.user_id)
         .await?
         .ok_or(E::UserNotFound)?;
-    let purchasing_manager =
-        OptionFuture::from(ticket.purchasing_manager.map(|id| async move {
-            state
-                .db_client
-                .get_user_by_id(id)
-                .await?
-                .ok_or(E::UserNotFound)
-        }))
-        .map(Option::transpose)
-        .await?;
-    let accounting_manager =
-        OptionFuture::from(ticket.accounting_manager.map(|id| async move {
-            state
-                .db_client
-                .get_user_by_id(id)
-                .await?
-                .ok_or(E::UserNotFound)
-        }))
-        .map(Option::transpose)
-        .await?;
 
-    Ok(Json(api::Ticket {
-        id: ticket.id,
-        title: ticket.title,
-        description: ticket.description,
-        status: ticket.status,
-        count: ticket.count,
-        price: ticket.price,
-        initiator: api::User {
-            id: initiator.id,
-            name: initiator.name.clone(),
-            role: initiator.role,
+    let dashboard = match my.role {
+        Role::Initiator => {
+            let filter = db::ticket::TicketFilter {
+                initiator: Some(my.id),
+                ..Default::default()
+            };
+            api::Dashboard::Initiator {
+                tickets_by_status: state
+                    .db_client
+                    .get_ticket_counts_by_status(&filter)
+                    .await?,
+            }
+        }
+        Role::PurchasingManager => {
+            let summary = state.db_client.get_purchasing_summary(my.id).await?;
+            api::Dashboard::PurchasingManager {
+                pending_confirmation: summary.pending_confirmation,
+                confirmed: summary.confirmed,
+                confirmed_total_price: summary.confirmed_total_price,
+            }
+        }
+        Role::AccountingManager => {
+            let summary = state.db_client.get_payment_summary().await?;
+            api::Dashboard::AccountingManager {
+                awaiting_payment: summary.awaiting_payment,
+                awaiting_payment_total_price: summary
+                    .awaiting_payment_total_price,
+            }
+        }
+        Role::Admin => api::Dashboard::Admin {
+            tickets_by_status: state
+                .db_client
+                .get_ticket_counts_by_status(&Default::default())
+                .await?,
         },
-        purchasing_manager: purchasing_manager.map(|u| api::User {
-            id: u.id,
-            name: u.name.clone(),
-            role: u.role,
-        }),
-        accounting_manager: accounting_manager.map(|u| api::User {
-            id: u.id,
-            name: u.name.clone(),
-            role: u.role,
-        }),
-    }))
+        _ => return Err(E::UnsupportedRole),
+    };
+    Ok(Json(dashboard))
 }
 
 #[derive(Debug, From)]
-pub enum EditTicketError {
+pub enum GetDashboardError {
     #[from]
     DbError(db::Error),
-    TicketCannotBeCancelled,
-    TicketCannotBeConfirmed,
-    TicketCannotBeModified,
-    TicketCannotBePaid,
-    TicketNotFound,
+
+    /// No dashboard is designed for the role of the user yet.
+    UnsupportedRole,
+
     UserNotFound,
 }
 
-impl IntoResponse for EditTicketError {
+impl IntoResponse for GetDashboardError {
     fn into_response(self) -> Response {
         match self {
-            Self::TicketCannotBeCancelled
-            | Self::TicketCannotBeConfirmed
-            | Self::TicketCannotBeModified
-            | Self::TicketCannotBePaid => StatusCode::BAD_REQUEST,
-            Self::TicketNotFound => StatusCode::NOT_FOUND,
-            Self::DbError(_) | Self::UserNotFound => {
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
+            Self::DbError(e) => return db_error_into_response(e),
+            Self::UnsupportedRole => StatusCode::NOT_IMPLEMENTED,
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
         }
         .into_response()
     }
 }
 
-async fn get_ticket(
-    State(state): State<SharedAppState>,
-    _: AuthClaims,
-    Path(id): Path<api::ticket::Id>,
-) -> Result<Json<api::Ticket>, GetTicketError> {
-    use GetTicketError as E;
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangePasswordInput {
+    current_password: String,
+    new_password: String,
+}
 
-    let state = &state;
+/// Changes the password of the current user.
+///
+/// All the access tokens of the user issued before the change are rejected
+/// afterwards.
+async fn change_password(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Json(ChangePasswordInput {
+        current_password,
+        new_password,
+    }): Json<ChangePasswordInput>,
+) -> Result<StatusCode, ChangePasswordError> {
+    use ChangePasswordError as E;
 
-    let ticket = state
+    let my = state
         .db_client
-        .get_ticket_by_id(id)
+        .primary()
+        .get_user_by_id(auth_claims.user_id)
         .await?
-        .ok_or(E::TicketNotFound)?;
+        .ok_or(E::UserNotFound)?;
+    if my.password_hash != api::user::PasswordHash::new(&current_password) {
+        return Err(E::WrongPassword);
+    }
 
-    let initiator = state
+    let changed_at = OffsetDateTime::now_utc();
+    state
         .db_client
-        .get_user_by_id(ticket.initiator)
-        .await?
-        .ok_or(E::UserNotFound)?;
-    let purchasing_manager =
-        OptionFuture::from(ticket.purchasing_manager.map(|id| async move {
-            state
-                .db_client
-                .get_user_by_id(id)
-                .await?
-                .ok_or(E::UserNotFound)
-        }))
-        .map(Option::transpose)
-        .await?;
-    let accounting_manager =
-        OptionFuture::from(ticket.accounting_manager.map(|id| async move {
-            state
-                .db_client
-                .get_user_by_id(id)
-                .await?
-                .ok_or(E::UserNotFound)
-        }))
-        .map(Option::transpose)
+        .update_user_password(
+            my.id,
+            &api::user::PasswordHash::new(&new_password),
+            changed_at,
+        )
         .await?;
+    state.cache_password_changed_at(my.id, changed_at.unix_timestamp());
+    state.evict_user(my.id);
 
-    Ok(Json(api::Ticket {
-        id: ticket.id,
-        title: ticket.title,
-        description: ticket.description,
-        status: ticket.status,
-        count: ticket.count,
-        price: ticket.price,
-        initiator: api::User {
-            id: initiator.id,
-            name: initiator.name.clone(),
-            role: initiator.role,
-        },
-        purchasing_manager: purchasing_manager.map(|u| api::User {
-            id: u.id,
-            name: u.name.clone(),
-            role: u.role,
-        }),
-        accounting_manager: accounting_manager.map(|u| api::User {
-            id: u.id,
-            name: u.name.clone(),
-            role: u.role,
-        }),
-    }))
+    Ok(StatusCode::NO_CONTENT)
 }
 
 #[derive(Debug, From)]
-pub enum GetTicketError {
+pub enum ChangePasswordError {
     #[from]
     DbError(db::Error),
-    TicketNotFound,
     UserNotFound,
+    WrongPassword,
 }
 
-impl IntoResponse for GetTicketError {
+impl IntoResponse for ChangePasswordError {
     fn into_response(self) -> Response {
         match self {
-            Self::TicketNotFound => StatusCode::NOT_FOUND,
-            Self::DbError(_) | Self::UserNotFound => {
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
+            Self::WrongPassword => StatusCode::FORBIDDEN,
+            Self::DbError(e) => return internal_db_error(&e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
         }
         .into_response()
     }
 }
 
-type SharedAppState = Arc<AppState>;
+#[derive(Deserialize)]
+struct ListTicketsInput {
+    #[serde(default)]
+    offset: usize,
+    limit: usize,
+    category: Option<api::ticket::Category>,
 
-struct AppState {
-    db_client: db::Client,
+    /// Statuses to list the tickets of any of, separated by commas.
+    #[serde(default, deserialize_with = "comma_separated")]
+    status: Vec<api::ticket::Status>,
 
-    jwt_expiration_time: Duration,
+    before: Option<String>,
+    #[serde(default)]
+    sort_by: api::ticket::SortBy,
+    order: Option<api::ticket::SortDirection>,
+    q: Option<String>,
+}
 
-    jwt_decoding_key: DecodingKey,
+/// Deserializes a comma-separated list of values, like `REQUESTED,CONFIRMED`
+/// of a query parameter, rejecting it whole if any of them is invalid.
+fn comma_separated<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned,
+{
+    let list = String::deserialize(deserializer)?;
+    list.split(',')
+        .map(|value| T::deserialize(value.trim().into_deserializer()))
+        .collect()
+}
 
-    jwt_encoding_key: EncodingKey,
+async fn list_tickets(
+    State(state): State<AppState>,
+    _: AuthClaims,
+    Query(ListTicketsInput {
+        offset,
+        limit,
+        category,
+        status,
+        before,
+        sort_by,
+        order,
+        q,
+    }): Query<ListTicketsInput>,
+) -> Result<Json<api::ticket::List>, ListTicketsError> {
+    let q = q.filter(|q| !q.trim().is_empty());
+    let full_text_search = state
+        .tickets
+        .full_text_search
+        .as_ref()
+        .map(|s| s.config)
+        .zip(q.as_deref());
+    let filter = db::ticket::TicketFilter {
+        category,
+        statuses: status,
+        text: q.clone().filter(|_| full_text_search.is_none()),
+        ..Default::default()
+    };
+    let order = db::ticket::TicketOrder {
+        by: sort_by,
+        direction: order.unwrap_or(sort_by.default_direction()),
+    };
+    // Relevance takes precedence over the requested order when searching.
+    let is_default_order = order == db::ticket::TicketOrder::default()
+        && full_text_search.is_none();
+    let (page, total_count) = if let Some(cursor) = before {
+        // Cursors identify tickets by their creation time, so can't page
+        // through the tickets sorted any other way.
+        if !is_default_order {
+            return Err(ListTicketsError::CursorWithOrder);
+        }
+        let (created_at, id) =
+            api::cursor::decode_signed(&cursor, &state.jwt_decoding_key)?;
+        let page_fut = state
+            .db_client
+            .get_tickets_before_with_users(created_at, id, limit, &filter);
+        let total_count_fut = state.db_client.get_tickets_count(&filter);
+        tokio::try_join!(page_fut, total_count_fut)?
+    } else if let Some((config, query)) = full_text_search {
+        state
+            .db_client
+            .search_tickets(query, config, offset, limit, &filter, order)
+            .await?
+    } else {
+        state
+            .db_client
+            .get_tickets_page_with_users(offset, limit, &filter, order)
+            .await?
+    };
+
+    // Cursors only continue the tickets sorted the default way.
+    let next_cursor = page
+        .last()
+        .filter(|_| page.len() == limit && is_default_order)
+        .map(|t| {
+            api::cursor::encode_signed(
+                t.ticket.created_at,
+                t.ticket.id,
+                &state.jwt_encoding_key,
+            )
+        });
+
+    let tickets = page.into_iter().map(listed_ticket).collect();
+
+    Ok(Json(api::ticket::List {
+        tickets,
+        total_count,
+        next_cursor,
+    }))
 }
 
-#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
-pub struct AuthClaims {
-    user_id: api::user::Id,
-    exp: i64,
+#[derive(Debug, From)]
+pub enum ListTicketsError {
+    CursorWithOrder,
+    #[from]
+    InvalidCursor(api::cursor::CursorError),
+    #[from]
+    DbError(db::Error),
 }
 
-#[async_trait]
-impl FromRequestParts<SharedAppState> for AuthClaims {
-    type Rejection = AuthError;
+impl IntoResponse for ListTicketsError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::CursorWithOrder => StatusCode::BAD_REQUEST.into_response(),
+            Self::InvalidCursor(_) => {
+                let invalid = api::cursor::InvalidCursor {
+                    code: "INVALID_CURSOR".to_owned(),
+                };
+                (StatusCode::BAD_REQUEST, Json(invalid)).into_response()
+            }
+            Self::DbError(e) => db_error_into_response(e),
+        }
+    }
+}
 
-    async fn from_request_parts(
-        parts: &mut request::Parts,
-        state: &SharedAppState,
-    ) -> Result<Self, Self::Rejection> {
-        let TypedHeader(Authorization(bearer)) = parts
-            .extract::<TypedHeader<Authorization<Bearer>>>()
-            .await
-            .map_err(|_| AuthError::InvalidToken)?;
-        let token_data = decode::<Self>(
-            bearer.token(),
-            &state.jwt_decoding_key,
-            &Validation::default(),
-        )
-        .map_err(|_| AuthError::InvalidToken)?;
+/// Converts the listed `ticket` into its API representation.
+fn listed_ticket(
+    db::ticket::TicketWithUsers {
+        ticket,
+        initiator,
+        purchasing_manager,
+        accounting_manager,
+    }: db::ticket::TicketWithUsers,
+) -> api::Ticket {
+    api::Ticket {
+        id: ticket.id,
+        title: ticket.title,
+        description: ticket.description,
+        status: ticket.status,
+        category: ticket.category,
+        count: ticket.count,
+        received_count: ticket.received_count,
+        fully_received: ticket.received_count == ticket.count,
+        price: ticket.price,
+        payment_reference: ticket.payment_reference,
+        initiator: initiator.into(),
+        purchasing_manager: purchasing_manager.map(Into::into),
+        accounting_manager: accounting_manager.map(Into::into),
+        attachments: None,
+    }
+}
 
-        Ok(token_data.claims)
+#[derive(Deserialize)]
+struct ListAssignedTicketsInput {
+    #[serde(default)]
+    offset: usize,
+    limit: usize,
+}
+
+/// Lists the tickets the caller is assigned to as a manager, newest first,
+/// along with the role they have on each of them.
+async fn list_assigned_tickets(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Query(ListAssignedTicketsInput { offset, limit }): Query<
+        ListAssignedTicketsInput,
+    >,
+) -> Result<Json<api::ticket::AssignedList>, ListTicketsError> {
+    use api::ticket::RoleOnTicket;
+
+    let filter = db::ticket::TicketFilter {
+        assignee: Some(auth_claims.user_id),
+        ..Default::default()
+    };
+    let (page, total_count) = state
+        .db_client
+        .get_tickets_page_with_users(offset, limit, &filter, Default::default())
+        .await?;
+
+    let tickets = page
+        .into_iter()
+        .map(|t| {
+            // Tickets are handled by different roles, so a manager is
+            // assigned to a ticket in a single one of them.
+            let role_on_ticket =
+                if t.ticket.purchasing_manager == Some(auth_claims.user_id) {
+                    RoleOnTicket::PurchasingManager
+                } else {
+                    RoleOnTicket::AccountingManager
+                };
+            api::ticket::AssignedTicket {
+                ticket: listed_ticket(t),
+                role_on_ticket,
+            }
+        })
+        .collect();
+
+    Ok(Json(api::ticket::AssignedList {
+        tickets,
+        total_count,
+    }))
+}
+
+#[derive(Deserialize)]
+struct CountTicketsInput {
+    category: Option<api::ticket::Category>,
+
+    /// Statuses to count the tickets of any of, separated by commas.
+    #[serde(default, deserialize_with = "comma_separated")]
+    status: Vec<api::ticket::Status>,
+}
+
+/// Counts the tickets matching the same filters as [`list_tickets()`],
+/// without transferring the tickets themselves.
+async fn count_tickets(
+    State(state): State<AppState>,
+    _: AuthClaims,
+    Query(CountTicketsInput { category, status }): Query<CountTicketsInput>,
+) -> Result<Json<api::ticket::Count>, CountTicketsError> {
+    let filter = db::ticket::TicketFilter {
+        category,
+        statuses: status,
+        ..Default::default()
+    };
+    let count = state.db_client.get_tickets_count(&filter).await?;
+    Ok(Json(api::ticket::Count { count }))
+}
+
+#[derive(Debug, From)]
+pub enum CountTicketsError {
+    #[from]
+    DbError(db::Error),
+}
+
+impl IntoResponse for CountTicketsError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::DbError(e) => internal_db_error(&e),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ExportTicketsInput {
+    category: Option<api::ticket::Category>,
+}
+
+/// Exports all the tickets as CSV.
+///
+/// Tickets are streamed row-by-row as the client reads the response, so
+/// neither the whole table is held in memory, nor a slow client makes rows
+/// pile up in buffers.
+async fn export_tickets(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Query(ExportTicketsInput { category }): Query<ExportTicketsInput>,
+) -> Result<Response, ExportTicketsError> {
+    use ExportTicketsError as E;
+
+    let my = state
+        .db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
+    if my.role != db::user::Role::Admin {
+        return Err(E::NotAdmin);
+    }
+
+    let tickets = state
+        .db_client
+        .stream_tickets(category, db::ticket::Visibility::ActiveOnly)
+        .await?;
+    let rows = stream::once(future::ready(Ok(csv_row(CSV_HEADER))))
+        .chain(tickets.map_ok(|ticket| csv_row(&ticket_csv_fields(&ticket))));
+
+    Ok((
+        [
+            (CONTENT_TYPE, "text/csv"),
+            (CONTENT_DISPOSITION, "attachment; filename=\"tickets.csv\""),
+        ],
+        Body::from_stream(rows),
+    )
+        .into_response())
+}
+
+const CSV_HEADER: &[&str] = &[
+    "id",
+    "title",
+    "description",
+    "status",
+    "category",
+    "count",
+    "received_count",
+    "price",
+    "payment_reference",
+    "initiator_id",
+    "purchasing_manager_id",
+    "accounting_manager_id",
+    "created_at",
+];
+
+/// Returns fields of the [`db::Ticket`] in the order of [`CSV_HEADER`].
+fn ticket_csv_fields(ticket: &db::Ticket) -> Vec<String> {
+    vec![
+        ticket.id.to_string(),
+        ticket.title.clone(),
+        ticket.description.clone(),
+        csv_enum(&ticket.status),
+        csv_enum(&ticket.category),
+        ticket.count.to_string(),
+        ticket.received_count.to_string(),
+        ticket.price.map(|p| p.to_string()).unwrap_or_default(),
+        ticket.payment_reference.clone().unwrap_or_default(),
+        ticket.initiator.to_string(),
+        ticket
+            .purchasing_manager
+            .map(|id| id.to_string())
+            .unwrap_or_default(),
+        ticket
+            .accounting_manager
+            .map(|id| id.to_string())
+            .unwrap_or_default(),
+        ticket
+            .created_at
+            .format(&Rfc3339)
+            .expect("`created_at` is representable in RFC 3339"),
+    ]
+}
+
+/// Formats an enum the same way the API represents it.
+fn csv_enum(value: &impl Serialize) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => unreachable!("enums are serialized as strings"),
+    }
+}
+
+/// Formats a CSV row, quoting the fields as described in RFC 4180.
+fn csv_row(fields: &[impl AsRef<str>]) -> String {
+    let mut row = fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains([',', '"', '\r', '\n']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_owned()
+            }
+        })
+        .join(",");
+    row.push_str("\r\n");
+    row
+}
+
+#[derive(Debug, From)]
+pub enum ExportTicketsError {
+    #[from]
+    DbError(db::Error),
+    NotAdmin,
+    UserNotFound,
+}
+
+impl IntoResponse for ExportTicketsError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::NotAdmin => StatusCode::FORBIDDEN,
+            Self::DbError(e) => return internal_db_error(&e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+        .into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct ExportDataInput {
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    since: Option<OffsetDateTime>,
+}
+
+/// Exports the users and the tickets changed at or after the `since` time,
+/// or all of them, as an [`api::export::Export`] for backups.
+///
+/// Rows are streamed the same way [`export_tickets()`] streams them, while
+/// the document is composed around them by hand.
+async fn export_data(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Query(ExportDataInput { since }): Query<ExportDataInput>,
+) -> Result<Response, ExportDataError> {
+    use ExportDataError as E;
+
+    let my = state
+        .db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
+    if my.role != db::user::Role::Admin {
+        return Err(E::NotAdmin);
+    }
+
+    // Read from the primary by its clock, and taken before querying, so
+    // nothing changed meanwhile is skipped by the next export continuing
+    // from it.
+    let db_client = state.db_client.primary();
+    let exported_at = db_client
+        .get_current_time()
+        .await?
+        .format(&Rfc3339)
+        .expect("database time is representable in RFC 3339");
+    let users = db_client.stream_users_updated_since(since).await?;
+    let tickets = db_client.stream_tickets_updated_since(since).await?;
+
+    let chunk = |s: String| stream::once(future::ready(Ok::<_, db::Error>(s)));
+    let body = chunk(format!(r#"{{"exportedAt":"{exported_at}","users":["#))
+        .chain(json_elements(users.map_ok(api::export::User::from)))
+        .chain(chunk(r#"],"tickets":["#.to_owned()))
+        .chain(json_elements(tickets.map_ok(api::export::Ticket::from)))
+        .chain(chunk("]}".to_owned()));
+
+    Ok((
+        [
+            (CONTENT_TYPE, "application/json"),
+            (CONTENT_DISPOSITION, "attachment; filename=\"export.json\""),
+        ],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// Serializes the `items` as the comma-separated elements of a JSON array.
+fn json_elements<T: Serialize>(
+    items: impl Stream<Item = Result<T, db::Error>>,
+) -> impl Stream<Item = Result<String, db::Error>> {
+    items.enumerate().map(|(i, item)| {
+        let json = serde_json::to_string(&item?)
+            .expect("exported rows are serializable");
+        Ok(if i == 0 { json } else { format!(",{json}") })
+    })
+}
+
+#[derive(Debug, From)]
+pub enum ExportDataError {
+    #[from]
+    DbError(db::Error),
+    NotAdmin,
+    UserNotFound,
+}
+
+impl IntoResponse for ExportDataError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::NotAdmin => StatusCode::FORBIDDEN,
+            Self::DbError(e) => return internal_db_error(&e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+        .into_response()
+    }
+}
+
+/// Columns of an imported CSV, the [`IMPORT_CREATED_AT`] one being optional.
+const IMPORT_COLUMNS: [&str; 4] =
+    ["title", "description", "count", "initiator_login"];
+
+/// Optional column of an imported CSV, defaulting to the time of the import.
+const IMPORT_CREATED_AT: &str = "created_at";
+
+/// Imports the tickets from a CSV, for migrating from the spreadsheets they
+/// used to be tracked in.
+///
+/// The CSV starts with a header naming the [`IMPORT_COLUMNS`] in any order.
+/// Every row is validated as if the ticket were added by its initiator, and
+/// if any row is invalid, nothing is imported and all the offending lines
+/// are reported. Otherwise, all the tickets are inserted within a single
+/// transaction.
+async fn import_tickets(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    csv: String,
+) -> Result<(StatusCode, Json<api::ticket::Imported>), ImportTicketsError> {
+    use ImportTicketsError as E;
+
+    let db_client = state.db_client.primary();
+    let my = db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
+    if my.role != db::user::Role::Admin {
+        return Err(E::NotAdmin);
+    }
+
+    let reject = |line, field, code| {
+        E::Invalid(api::ticket::ImportErrors {
+            lines: vec![import_line_error(line, field, code)],
+        })
+    };
+    let mut records = parse_csv(&csv)
+        .map_err(|line| reject(line, "row", Code::InvalidFormat))?
+        .into_iter();
+    let Some((_, header)) = records.next() else {
+        return Err(reject(1, "row", Code::MustNotBeEmpty));
+    };
+    let column = |name| header.iter().position(|h| h.trim() == name);
+    let mut columns = [0; IMPORT_COLUMNS.len()];
+    for (i, name) in IMPORT_COLUMNS.into_iter().enumerate() {
+        columns[i] =
+            column(name).ok_or_else(|| reject(1, name, Code::NotFound))?;
+    }
+    let created_at_column = column(IMPORT_CREATED_AT);
+
+    let now = OffsetDateTime::now_utc();
+    let mut initiators = HashMap::<String, Option<db::User>>::new();
+    let mut tickets = Vec::new();
+    let mut errors = api::ticket::ImportErrors::default();
+    for (line, row) in records {
+        if row.len() != header.len() {
+            errors.lines.push(import_line_error(
+                line,
+                "row",
+                Code::InvalidFormat,
+            ));
+            continue;
+        }
+        let [title, description, count, login] = columns.map(|i| &row[i]);
+
+        let login = login.trim();
+        if !initiators.contains_key(login) {
+            let user = db_client.get_user_by_login(login).await?;
+            initiators.insert(login.to_owned(), user);
+        }
+        let initiator = initiators[login].as_ref();
+        let parsed_count = count.trim().parse::<usize>();
+        let created_at = match created_at_column.map(|i| row[i].trim()) {
+            None | Some("") => Ok(now),
+            Some(at) => OffsetDateTime::parse(at, &Rfc3339),
+        };
+
+        let mut validator = Validator::new();
+        validator
+            .check("title", !title.trim().is_empty(), Code::MustNotBeEmpty)
+            .check(
+                "title",
+                title.chars().count() <= api::ticket::TITLE_MAX_LEN,
+                Code::TooLong,
+            )
+            .check(
+                "description",
+                description.chars().count() <= api::ticket::DESCRIPTION_MAX_LEN,
+                Code::TooLong,
+            )
+            .check("count", parsed_count.is_ok(), Code::InvalidFormat)
+            .check("initiator_login", initiator.is_some(), Code::NotFound)
+            .check(
+                "initiator_login",
+                initiator.is_none_or(|u| u.role == db::user::Role::Initiator),
+                Code::WrongRole,
+            )
+            .check(IMPORT_CREATED_AT, created_at.is_ok(), Code::InvalidFormat);
+        if let Ok(&count) = parsed_count.as_ref() {
+            validator
+                .check("count", count > 0, Code::MustBePositive)
+                .check(
+                    "count",
+                    count == 0 || count >= state.tickets.min_count,
+                    Code::TooSmall,
+                )
+                .check(
+                    "count",
+                    count <= state.tickets.max_count,
+                    Code::TooLarge,
+                );
+        }
+        if let Err(e) = validator.finish() {
+            errors
+                .lines
+                .push(api::ticket::ImportLineErrors { line, errors: e });
+            continue;
+        }
+
+        let (Ok(count), Some(initiator), Ok(created_at)) =
+            (parsed_count, initiator, created_at)
+        else {
+            unreachable!("checked by the validator");
+        };
+        tickets.push(db::Ticket {
+            id: db::ticket::Id::new(),
+            title: title.clone(),
+            description: description.clone(),
+            status: db::ticket::Status::Requested,
+            category: api::ticket::Category::default(),
+            count,
+            received_count: 0,
+            price: None,
+            payment_reference: None,
+            initiator: initiator.id,
+            purchasing_manager: None,
+            accounting_manager: None,
+            created_at,
+        });
+    }
+    if !errors.lines.is_empty() {
+        return Err(E::Invalid(errors));
+    }
+
+    db_client
+        .write_tickets(&tickets)
+        .await
+        .map_err(|e| match e {
+            db::ticket::WriteTicketsError::DbError(e) => E::DbError(e),
+            db::ticket::WriteTicketsError::IdTaken(id) => E::IdTaken(id),
+        })?;
+
+    let ids = tickets.into_iter().map(|t| t.id).collect();
+    Ok((StatusCode::CREATED, Json(api::ticket::Imported { ids })))
+}
+
+/// Returns the [`api::ticket::ImportLineErrors`] of a single violation.
+fn import_line_error(
+    line: usize,
+    field: &str,
+    code: Code,
+) -> api::ticket::ImportLineErrors {
+    api::ticket::ImportLineErrors {
+        line,
+        errors: api::validation::Errors {
+            errors: vec![api::validation::FieldError {
+                field: field.to_owned(),
+                code,
+            }],
+        },
+    }
+}
+
+/// Parses the CSV as described in RFC 4180, returning its records along
+/// with the numbers of the lines they start on, and skipping the empty lines.
+///
+/// Fails with the number of the line a quoted field is left open on.
+fn parse_csv(csv: &str) -> Result<Vec<(usize, Vec<String>)>, usize> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let (mut line, mut record_line) = (1, 1);
+    let mut is_quoted = false;
+
+    let mut chars = csv.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if is_quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if is_quoted => is_quoted = false,
+            '"' if field.is_empty() => is_quoted = true,
+            '\n' if is_quoted => {
+                line += 1;
+                field.push(c);
+            }
+            ',' if !is_quoted => record.push(mem::take(&mut field)),
+            '\r' if !is_quoted && chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                record.push(mem::take(&mut field));
+                records.push((record_line, mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            c => field.push(c),
+        }
+    }
+    if is_quoted {
+        return Err(record_line);
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+
+    records.retain(|(_, record)| record != &[""]);
+    Ok(records)
+}
+
+#[derive(Debug, From)]
+pub enum ImportTicketsError {
+    #[from]
+    DbError(db::Error),
+    IdTaken(db::ticket::Id),
+    Invalid(api::ticket::ImportErrors),
+    NotAdmin,
+    UserNotFound,
+}
+
+impl IntoResponse for ImportTicketsError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Invalid(errors) => {
+                return (StatusCode::UNPROCESSABLE_ENTITY, Json(errors))
+                    .into_response();
+            }
+            Self::DbError(e) => return db_error_into_response(e),
+            Self::IdTaken(id) => {
+                tracing::error!(%id, "generated ticket ID is already taken");
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            Self::NotAdmin => StatusCode::FORBIDDEN,
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+        .into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct AddTicketInput {
+    title: String,
+    description: String,
+    #[serde(default)]
+    category: api::ticket::Category,
+    count: usize,
+}
+
+async fn add_ticket(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Json(AddTicketInput {
+        title,
+        description,
+        category,
+        count,
+    }): Json<AddTicketInput>,
+) -> Result<Json<api::Ticket>, AddTicketError> {
+    use AddTicketError as E;
+
+    let my = state
+        .db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
+    if my.role != db::user::Role::Initiator {
+        return Err(E::TicketCannotBeCreated);
+    }
+
+    let mut validator = Validator::new();
+    validator
+        .check("title", !title.trim().is_empty(), Code::MustNotBeEmpty)
+        .check(
+            "title",
+            title.chars().count() <= api::ticket::TITLE_MAX_LEN,
+            Code::TooLong,
+        )
+        .check(
+            "description",
+            description.chars().count() <= api::ticket::DESCRIPTION_MAX_LEN,
+            Code::TooLong,
+        )
+        .check("count", count > 0, Code::MustBePositive)
+        .check(
+            "count",
+            count == 0 || count >= state.tickets.min_count,
+            Code::TooSmall,
+        )
+        .check("count", count <= state.tickets.max_count, Code::TooLarge);
+    validator.finish().map_err(E::Invalid)?;
+
+    let ticket = db::Ticket {
+        id: db::ticket::Id::new(),
+        title,
+        description,
+        status: db::ticket::Status::Requested,
+        category,
+        count,
+        received_count: 0,
+        price: None,
+        payment_reference: None,
+        initiator: my.id,
+        purchasing_manager: None,
+        accounting_manager: None,
+        created_at: OffsetDateTime::now_utc(),
+    };
+
+    let payload = serde_json::json!({
+        "title": ticket.title,
+        "description": ticket.description,
+        "category": ticket.category,
+        "count": ticket.count,
+    });
+    state
+        .db_client
+        .write_ticket_with_event(&ticket, my.id, "create", &payload)
+        .await?;
+
+    Ok(Json((&ticket, &my, None, None).into()))
+}
+
+#[derive(Debug, From)]
+pub enum AddTicketError {
+    #[from]
+    DbError(db::Error),
+    Invalid(api::validation::Errors),
+    TicketCannotBeCreated,
+    UserNotFound,
+}
+
+impl IntoResponse for AddTicketError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Invalid(errors) => {
+                return (StatusCode::UNPROCESSABLE_ENTITY, Json(errors))
+                    .into_response();
+            }
+            Self::TicketCannotBeCreated => StatusCode::BAD_REQUEST,
+            Self::DbError(e) => return db_error_into_response(e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+        .into_response()
+    }
+}
+
+/// Converts a [`db::Error`] into a [`Response`], reporting a violated
+/// constraint as a conflict on the field it guards, if known, and a
+/// serialization failure as a temporary unavailability, so the request may
+/// be retried. Parameters out of the database range are reported as a bad
+/// request.
+fn db_error_into_response(e: db::Error) -> Response {
+    #[derive(Serialize)]
+    struct Violation {
+        field: Option<&'static str>,
+    }
+
+    match e {
+        db::Error::ConstraintViolation { .. } => {
+            if let Some(field) = e.violated_check() {
+                let field = Some(field);
+                return (StatusCode::BAD_REQUEST, Json(Violation { field }))
+                    .into_response();
+            }
+            let field = e.violated_field();
+            (StatusCode::CONFLICT, Json(Violation { field })).into_response()
+        }
+        db::Error::Serialization(_) => {
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+        db::Error::OutOfRange { .. } => {
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+        db::Error::Postgres(_)
+        | db::Error::Pool(_)
+        | db::Error::CreatePool(_)
+        | db::Error::NoTrustedCertificates => internal_db_error(&e),
+    }
+}
+
+/// Responds with `500 Internal Server Error` caused by the database error
+/// `e`, attaching the error to the [`Response`], so [`expose_db_errors()`]
+/// may report it.
+fn internal_db_error(e: &db::Error) -> Response {
+    let mut response = StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    response
+        .extensions_mut()
+        .insert(db::SerializableDbError::from(e));
+    response
+}
+
+/// Reports the database error attached to the [`Response`] by
+/// [`internal_db_error()`] in its body.
+///
+/// Only applied if [`config::Http::expose_db_errors`] is set.
+async fn expose_db_errors(request: Request, next: Next) -> Response {
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ErrorBody {
+        db_error: db::SerializableDbError,
+    }
+
+    let mut response = next.run(request).await;
+    match response
+        .extensions_mut()
+        .remove::<db::SerializableDbError>()
+    {
+        Some(db_error) => {
+            (response.status(), Json(ErrorBody { db_error })).into_response()
+        }
+        None => response,
+    }
+}
+
+/// State of [`reject_writes()`].
+#[derive(Clone)]
+struct ReadOnly {
+    /// Mirrors [`config::Maintenance::read_only`], reloaded on `SIGHUP`.
+    enabled: Arc<AtomicBool>,
+    retry_after: Duration,
+}
+
+/// Rejects the requests changing any data with `503 Service Unavailable`
+/// while the server is [read-only](config::Maintenance::read_only).
+async fn reject_writes(
+    State(read_only): State<ReadOnly>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_write = !request.method().is_safe();
+    if is_write && read_only.enabled.load(Ordering::Relaxed) {
+        let retry_after = read_only.retry_after.as_secs().to_string();
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            [(RETRY_AFTER, retry_after)],
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// State of [`rate_limit()`].
+#[derive(Clone)]
+struct RateLimit {
+    max_requests: u32,
+    period: Duration,
+
+    /// Whether a client is told by the `X-Forwarded-For` header.
+    trust_forwarded_for: bool,
+
+    /// Current [`RateWindow`] of every client.
+    ///
+    /// Requests not telling their client, like the ones of the tests not
+    /// connecting over TCP, share a single one.
+    windows: Arc<Mutex<HashMap<Option<IpAddr>, RateWindow>>>,
+}
+
+/// Current period of a client within a [`RateLimit`].
+struct RateWindow {
+    started: Instant,
+
+    /// Requests let through within this period.
+    requests: u32,
+}
+
+impl RateLimit {
+    fn new(
+        max_requests: u32,
+        period: Duration,
+        trust_forwarded_for: bool,
+    ) -> Self {
+        Self {
+            max_requests,
+            period,
+            trust_forwarded_for,
+            windows: Arc::default(),
+        }
+    }
+
+    /// Returns the address of the client sending the `request`, if known.
+    fn client(&self, request: &Request) -> Option<IpAddr> {
+        // Proxy appends the address it's connected from to the ones sent by
+        // the client, so only the last one can be trusted.
+        let forwarded = self
+            .trust_forwarded_for
+            .then(|| request.headers().get("x-forwarded-for"))
+            .flatten()
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.rsplit(',').next())
+            .and_then(|addr| addr.trim().parse().ok());
+        forwarded.or_else(|| {
+            let ConnectInfo(addr) =
+                request.extensions().get::<ConnectInfo<SocketAddr>>()?;
+            Some(addr.ip())
+        })
+    }
+}
+
+/// Rejects the requests of a client beyond [`RateLimit::max_requests`] per
+/// [`RateLimit::period`] with `429 Too Many Requests`.
+async fn rate_limit(
+    State(limit): State<RateLimit>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let client = limit.client(&request);
+    let retry_after = {
+        let mut windows = limit.windows.lock().unwrap();
+        // Periods past are dropped, so the clients seen once don't pile up.
+        windows.retain(|_, w| w.started.elapsed() < limit.period);
+        let window = windows.entry(client).or_insert_with(|| RateWindow {
+            started: Instant::now(),
+            requests: 0,
+        });
+        if window.requests < limit.max_requests {
+            window.requests += 1;
+            None
+        } else {
+            Some(limit.period.saturating_sub(window.started.elapsed()))
+        }
+    };
+    if let Some(retry_after) = retry_after {
+        // Rounded up, so the retry doesn't land in the same period.
+        let retry_after = retry_after.as_secs() + 1;
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(RETRY_AFTER, retry_after.to_string())],
+        )
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Reports the state of the database connection pools in the Prometheus
+/// text format.
+async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    type Metric = (&'static str, &'static str, fn(&db::PoolMetrics) -> u64);
+    let metrics: [Metric; 4] = [
+        ("db_pool_connections", "gauge", |p| p.size as u64),
+        ("db_pool_idle_connections", "gauge", |p| p.idle as u64),
+        ("db_pool_waiting_tasks", "gauge", |p| p.waiting as u64),
+        ("db_pool_acquire_timeouts_total", "counter", |p| {
+            p.acquire_timeouts
+        }),
+    ];
+
+    let pools = state.db_client.pool_metrics();
+    let mut body = String::new();
+    for (name, kind, value) in metrics {
+        _ = writeln!(body, "# TYPE {name} {kind}");
+        for pool in &pools {
+            _ = writeln!(
+                body,
+                "{name}{{pool=\"{}\"}} {}",
+                pool.pool,
+                value(pool)
+            );
+        }
+    }
+    ([(CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+/// Makes the charset of JSON responses explicit, as some clients don't
+/// assume UTF-8 and garble non-ASCII names otherwise.
+async fn json_charset(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/json"));
+    if is_json {
+        response.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/json; charset=utf-8"),
+        );
+    }
+    response
+}
+
+tokio::task_local! {
+    /// Whether [`PrettyJson`] responses are pretty-printed.
+    static PRETTY_JSON: bool;
+}
+
+/// Pretty-prints [`PrettyJson`] responses to the request.
+///
+/// Only applied if [`config::Http::pretty_json`] is set.
+async fn pretty_json(request: Request, next: Next) -> Response {
+    PRETTY_JSON.scope(true, next.run(request)).await
+}
+
+/// [`axum::Json`] pretty-printing responses under [`pretty_json()`].
+struct PrettyJson<T>(T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for PrettyJson<T>
+where
+    axum::Json<T>: FromRequest<S>,
+    S: Send + Sync,
+{
+    type Rejection = <axum::Json<T> as FromRequest<S>>::Rejection;
+
+    async fn from_request(
+        req: Request,
+        state: &S,
+    ) -> Result<Self, Self::Rejection> {
+        let axum::Json(value) = axum::Json::from_request(req, state).await?;
+        Ok(Self(value))
+    }
+}
+
+impl<T: Serialize> IntoResponse for PrettyJson<T> {
+    fn into_response(self) -> Response {
+        if !PRETTY_JSON.try_with(|pretty| *pretty).unwrap_or(false) {
+            return axum::Json(self.0).into_response();
+        }
+        match serde_json::to_vec_pretty(&self.0) {
+            Ok(body) => {
+                ([(CONTENT_TYPE, "application/json")], body).into_response()
+            }
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+                .into_response(),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(content = "data", rename_all = "camelCase", tag = "op")]
+enum EditTicketInput {
+    EditTitle {
+        title: String,
+    },
+    EditDescription {
+        description: String,
+    },
+    EditCategory {
+        category: api::ticket::Category,
+    },
+    Cancel,
+    Confirm {
+        price: f64,
+    },
+    AdjustPrice {
+        price: f64,
+    },
+    Deny,
+    MarkAsPaid(Option<MarkAsPaidInput>),
+    RecordReceipt {
+        count: usize,
+    },
+    ReassignInitiator {
+        #[serde(rename = "userId")]
+        user_id: api::user::Id,
+    },
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MarkAsPaidInput {
+    payment_reference: Option<String>,
+}
+
+impl EditTicketInput {
+    /// Names of all the operations, as accepted in the `op` field.
+    const OPS: [&'static str; 10] = [
+        "editTitle",
+        "editDescription",
+        "editCategory",
+        "cancel",
+        "confirm",
+        "adjustPrice",
+        "deny",
+        "markAsPaid",
+        "recordReceipt",
+        "reassignInitiator",
+    ];
+
+    /// Name of this operation, as recorded in the audit log.
+    fn action(&self) -> &'static str {
+        match self {
+            Self::EditTitle { .. } => "editTitle",
+            Self::EditDescription { .. } => "editDescription",
+            Self::EditCategory { .. } => "editCategory",
+            Self::Cancel => "cancel",
+            Self::Confirm { .. } => "confirm",
+            Self::AdjustPrice { .. } => "adjustPrice",
+            Self::Deny => "deny",
+            Self::MarkAsPaid(_) => "markAsPaid",
+            Self::RecordReceipt { .. } => "recordReceipt",
+            Self::ReassignInitiator { .. } => "reassignInitiator",
+        }
+    }
+}
+
+/// [`Json`] extractor of an [`EditTicketInput`], reporting an unknown `op`
+/// along with the valid ones, rather than as an opaque deserialization error.
+struct EditTicketBody(EditTicketInput);
+
+#[async_trait]
+impl FromRequest<AppState> for EditTicketBody {
+    type Rejection = Response;
+
+    async fn from_request(
+        req: Request,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let Json(body) = Json::<serde_json::Value>::from_request(req, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+
+        let op = body.get("op").and_then(serde_json::Value::as_str);
+        if !op.is_some_and(|op| EditTicketInput::OPS.contains(&op)) {
+            let unknown = api::ticket::UnknownOp {
+                code: "UNKNOWN_OP".to_owned(),
+                op: op.map(ToOwned::to_owned),
+                valid_ops: EditTicketInput::OPS.map(ToOwned::to_owned).into(),
+            };
+            return Err(
+                (StatusCode::BAD_REQUEST, Json(unknown)).into_response()
+            );
+        }
+
+        serde_json::from_value(body).map(Self).map_err(|e| {
+            let message = format!(
+                "Failed to deserialize the JSON body into the target type: {e}",
+            );
+            (StatusCode::UNPROCESSABLE_ENTITY, message).into_response()
+        })
+    }
+}
+
+async fn edit_ticket(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Path(id): Path<api::ticket::Id>,
+    EditTicketBody(op): EditTicketBody,
+) -> Result<Json<api::Ticket>, EditTicketError> {
+    use EditTicketError as E;
+
+    // Ticket is read back and written within the same request, so reading
+    // from a lagging replica would overwrite the recent changes.
+    let db_client = state.db_client.primary();
+
+    let ticket = db_client
+        .get_ticket_by_id(id, db::ticket::Visibility::ActiveOnly)
+        .await?
+        .ok_or(E::TicketNotFound)?;
+    let (edit, users) =
+        apply_ticket_edit(&*db_client, auth_claims.user_id, ticket, op).await?;
+
+    // Edit is decided without the ticket locked, so nothing waits on it
+    // meanwhile, and is refused if the ticket has changed since it's read,
+    // rather than overwriting that change.
+    if !db_client.edit_ticket_with_event(&edit).await? {
+        return Err(E::TicketChanged);
+    }
+    let ticket = edit.ticket;
+
+    let initiator = users.get(&ticket.initiator).ok_or(E::UserNotFound)?;
+    let purchasing_manager = ticket
+        .purchasing_manager
+        .map(|id| users.get(&id).ok_or(E::UserNotFound))
+        .transpose()?;
+    let accounting_manager = ticket
+        .accounting_manager
+        .map(|id| users.get(&id).ok_or(E::UserNotFound))
+        .transpose()?;
+
+    Ok(Json(
+        (&ticket, initiator, purchasing_manager, accounting_manager).into(),
+    ))
+}
+
+/// Applies the `op` of the user with the `my_id` to the `ticket`, returning
+/// the resulting [`db::audit::TicketEdit`] along with the users it may refer
+/// to, without writing anything.
+async fn apply_ticket_edit(
+    db_client: &dyn db::Storage,
+    my_id: api::user::Id,
+    mut ticket: db::Ticket,
+    op: EditTicketInput,
+) -> Result<
+    (db::audit::TicketEdit, HashMap<api::user::Id, db::User>),
+    EditTicketError,
+> {
+    use db::ticket::StatusUpdateFields;
+    use EditTicketError as E;
+    use EditTicketInput as Op;
+
+    // Users are cached for the duration of this request only. Any user the
+    // response may refer to is either already assigned to the ticket, is the
+    // caller (who may become one of its managers) or is the new initiator,
+    // so fetching them all upfront avoids reading users back after the
+    // ticket is written.
+    let new_initiator = match &op {
+        Op::ReassignInitiator { user_id } => Some(*user_id),
+        _ => None,
+    };
+    let user_ids = [my_id, ticket.initiator]
+        .into_iter()
+        .chain(ticket.purchasing_manager)
+        .chain(ticket.accounting_manager)
+        .chain(new_initiator)
+        .unique()
+        .collect::<Vec<_>>();
+    let users = db_client.get_users_by_ids(&user_ids).await?;
+
+    let my = users.get(&my_id).ok_or(E::UserNotFound)?;
+
+    let original = ticket.clone();
+    let mut status_fields = None;
+    let action = op.action();
+    let mut payload =
+        serde_json::to_value(&op).expect("`EditTicketInput` serializes");
+
+    match op {
+        Op::EditTitle { title } => {
+            if ticket.status != db::ticket::Status::Requested
+                || ticket.initiator != my.id
+            {
+                return Err(E::TicketCannotBeModified);
+            }
+
+            ticket.title = title;
+        }
+        Op::EditDescription { description } => {
+            // Description can be used for comments, so should be editable
+            // throughout the ticket lifecycle.
+            ticket.description = description;
+        }
+        Op::EditCategory { category } => {
+            if ticket.status != db::ticket::Status::Requested
+                || ticket.initiator != my.id
+            {
+                return Err(E::TicketCannotBeModified);
+            }
+
+            ticket.category = category;
+        }
+        Op::Cancel => {
+            if ticket.status != db::ticket::Status::Requested
+                || ticket.initiator != my.id
+            {
+                return Err(E::TicketCannotBeCancelled);
+            }
+
+            ticket.status = db::ticket::Status::Cancelled;
+            status_fields = Some(StatusUpdateFields::default());
+        }
+        Op::Confirm { price } => {
+            if ticket.status != db::ticket::Status::Requested
+                || my.role != db::user::Role::PurchasingManager
+            {
+                return Err(E::TicketCannotBeConfirmed);
+            }
+
+            ticket.status = db::ticket::Status::Confirmed;
+            ticket.price = Some(price);
+            ticket.purchasing_manager = Some(my.id);
+            status_fields = Some(StatusUpdateFields {
+                price: Some(price),
+                purchasing_manager: Some(my.id),
+                ..StatusUpdateFields::default()
+            });
+        }
+        Op::AdjustPrice { price } => {
+            // Price may only be corrected until the ticket is paid.
+            let may_adjust = my.role == db::user::Role::Admin
+                || ticket.purchasing_manager == Some(my.id);
+            if ticket.status != db::ticket::Status::Confirmed || !may_adjust {
+                return Err(E::TicketPriceCannotBeAdjusted);
+            }
+
+            let mut validator = Validator::new();
+            validator.check("price", price > 0.0, Code::MustBePositive);
+            validator.finish().map_err(E::Invalid)?;
+
+            payload["data"]["previousPrice"] = serde_json::json!(ticket.price);
+            ticket.price = Some(price);
+        }
+        Op::Deny => {
+            if ticket.status != db::ticket::Status::Requested
+                || my.role != db::user::Role::PurchasingManager
+            {
+                return Err(E::TicketCannotBeConfirmed);
+            }
+
+            ticket.status = db::ticket::Status::Denied;
+            ticket.purchasing_manager = Some(my.id);
+            status_fields = Some(StatusUpdateFields {
+                purchasing_manager: Some(my.id),
+                ..StatusUpdateFields::default()
+            });
+        }
+        Op::MarkAsPaid(input) => {
+            if ticket.status != db::ticket::Status::Confirmed
+                || my.role != db::user::Role::AccountingManager
+            {
+                return Err(E::TicketCannotBePaid);
+            }
+
+            let payment_reference = input.and_then(|i| i.payment_reference);
+            if let Some(reference) = &payment_reference {
+                let mut validator = Validator::new();
+                validator
+                    .check(
+                        "paymentReference",
+                        !reference.trim().is_empty(),
+                        Code::MustNotBeEmpty,
+                    )
+                    .check(
+                        "paymentReference",
+                        reference.chars().count()
+                            <= api::ticket::PAYMENT_REFERENCE_MAX_LEN,
+                        Code::TooLong,
+                    );
+                validator.finish().map_err(E::Invalid)?;
+            }
+
+            ticket.status = db::ticket::Status::PaymentCompleted;
+            ticket.accounting_manager = Some(my.id);
+            ticket.payment_reference.clone_from(&payment_reference);
+            status_fields = Some(StatusUpdateFields {
+                payment_reference,
+                accounting_manager: Some(my.id),
+                ..StatusUpdateFields::default()
+            });
+        }
+        Op::RecordReceipt { count } => {
+            if !matches!(
+                ticket.status,
+                db::ticket::Status::Confirmed
+                    | db::ticket::Status::PaymentCompleted
+            ) || my.role != db::user::Role::AccountingManager
+            {
+                return Err(E::TicketCannotBeReceived);
+            }
+            let mut validator = Validator::new();
+            validator.check("count", count > 0, Code::MustBePositive);
+            validator.finish().map_err(E::Invalid)?;
+
+            // Count is sent by the client, so may overflow the sum.
+            ticket.received_count = ticket
+                .received_count
+                .checked_add(count)
+                .filter(|&received| received <= ticket.count)
+                .ok_or(E::TicketReceiptExceedsCount)?;
+        }
+        Op::ReassignInitiator { user_id } => {
+            if my.role != db::user::Role::Admin {
+                return Err(E::TicketCannotBeReassigned);
+            }
+            match users.get(&user_id) {
+                Some(u) if u.role == db::user::Role::Initiator => {}
+                Some(_) | None => return Err(E::InvalidInitiator),
+            }
+
+            payload["data"]["previousUserId"] =
+                serde_json::json!(ticket.initiator);
+            ticket.initiator = user_id;
+        }
+    }
+
+    let edit = db::audit::TicketEdit {
+        original,
+        ticket,
+        status_fields,
+        actor: my.id,
+        action: action.to_owned(),
+        payload,
+    };
+    Ok((edit, users))
+}
+
+#[derive(Debug, From)]
+pub enum EditTicketError {
+    #[from]
+    DbError(db::Error),
+    Invalid(api::validation::Errors),
+    TicketCannotBeCancelled,
+    TicketCannotBeConfirmed,
+    TicketCannotBeModified,
+    TicketCannotBePaid,
+    TicketPriceCannotBeAdjusted,
+    TicketCannotBeReceived,
+    TicketNotFound,
+    TicketReceiptExceedsCount,
+    TicketCannotBeReassigned,
+    TicketChanged,
+    InvalidInitiator,
+    UserNotFound,
+}
+
+impl IntoResponse for EditTicketError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Invalid(errors) => {
+                return (StatusCode::UNPROCESSABLE_ENTITY, Json(errors))
+                    .into_response();
+            }
+            Self::TicketCannotBeCancelled
+            | Self::TicketCannotBeConfirmed
+            | Self::TicketCannotBeModified
+            | Self::TicketCannotBePaid
+            | Self::TicketPriceCannotBeAdjusted
+            | Self::TicketCannotBeReceived
+            | Self::TicketReceiptExceedsCount
+            | Self::TicketCannotBeReassigned
+            | Self::InvalidInitiator => StatusCode::BAD_REQUEST,
+            Self::TicketNotFound => StatusCode::NOT_FOUND,
+            Self::TicketChanged => StatusCode::CONFLICT,
+            Self::DbError(e) => return db_error_into_response(e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+        .into_response()
+    }
+}
+
+#[derive(Deserialize)]
+struct BulkTransitionInput {
+    ids: Vec<api::ticket::Id>,
+    op: api::ticket::BulkOp,
+
+    /// Price the tickets are confirmed at, required to confirm them.
+    price: Option<f64>,
+}
+
+/// Applies the same transition to several tickets at once, in a single
+/// transaction, reporting whether it's applied to each of them.
+///
+/// Each ticket is guarded as by [`edit_ticket()`], and the ones failing the
+/// guards are skipped without failing the others, in which case the
+/// response is `207 Multi-Status`.
+async fn bulk_transition_tickets(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Json(input): Json<BulkTransitionInput>,
+) -> Result<
+    (StatusCode, Json<Vec<api::ticket::BulkTransitionResult>>),
+    BulkTransitionTicketsError,
+> {
+    use api::ticket::{BulkOp, BulkTransitionError as Failure};
+    use db::ticket::StatusUpdateFields;
+    use BulkTransitionTicketsError as E;
+
+    let mut validator = Validator::new();
+    validator.check(
+        "ids",
+        input.ids.len() <= api::ticket::BULK_TRANSITION_MAX_IDS,
+        Code::TooLong,
+    );
+    let (op, status, price) = match input.op {
+        BulkOp::Confirm => {
+            validator.check(
+                "price",
+                input.price.is_some_and(|p| p > 0.0),
+                Code::MustBePositive,
+            );
+            let price = input.price.unwrap_or_default();
+            let op = EditTicketInput::Confirm { price };
+            (op, db::ticket::Status::Confirmed, Some(price))
+        }
+        BulkOp::Deny => {
+            (EditTicketInput::Deny, db::ticket::Status::Denied, None)
+        }
+    };
+    validator.finish().map_err(E::Invalid)?;
+
+    // Tickets are read back and written within the same request, as by
+    // `edit_ticket()`.
+    let db_client = state.db_client.primary();
+
+    let my = db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
+
+    let action = op.action();
+    let payload =
+        serde_json::to_value(&op).expect("`EditTicketInput` serializes");
+
+    // Either the failure of a ticket, or the index of its transition.
+    let mut outcomes = Vec::new();
+    let mut transitions = Vec::new();
+    for id in input.ids.into_iter().unique() {
+        let Some(mut ticket) = db_client
+            .get_ticket_by_id(id, db::ticket::Visibility::ActiveOnly)
+            .await?
+        else {
+            outcomes.push((id, Err(Failure::NotFound)));
+            continue;
+        };
+        if ticket.status != db::ticket::Status::Requested
+            || my.role != db::user::Role::PurchasingManager
+        {
+            outcomes.push((id, Err(Failure::CannotBeTransitioned)));
+            continue;
+        }
+
+        let expected = ticket.status;
+        ticket.status = status;
+        ticket.price = price.or(ticket.price);
+        ticket.purchasing_manager = Some(my.id);
+        let fields = StatusUpdateFields {
+            price,
+            purchasing_manager: Some(my.id),
+            ..StatusUpdateFields::default()
+        };
+
+        outcomes.push((id, Ok(transitions.len())));
+        transitions.push(db::audit::StatusTransition {
+            ticket,
+            expected,
+            fields,
+            actor: my.id,
+            action: action.to_owned(),
+            payload: payload.clone(),
+        });
+    }
+
+    let applied = if transitions.is_empty() {
+        Vec::new()
+    } else {
+        db_client
+            .update_ticket_statuses_with_events(&transitions)
+            .await?
+    };
+
+    let results = outcomes
+        .into_iter()
+        .map(|(id, outcome)| {
+            let error = match outcome {
+                Ok(i) if applied[i] => None,
+                Ok(_) => Some(Failure::StatusChanged),
+                Err(failure) => Some(failure),
+            };
+            api::ticket::BulkTransitionResult {
+                id,
+                ok: error.is_none(),
+                error,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let status = if results.iter().all(|r| r.ok) {
+        StatusCode::OK
+    } else {
+        StatusCode::MULTI_STATUS
+    };
+    Ok((status, Json(results)))
+}
+
+#[derive(Debug, From)]
+pub enum BulkTransitionTicketsError {
+    #[from]
+    DbError(db::Error),
+    Invalid(api::validation::Errors),
+    UserNotFound,
+}
+
+impl IntoResponse for BulkTransitionTicketsError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Invalid(errors) => {
+                return (StatusCode::UNPROCESSABLE_ENTITY, Json(errors))
+                    .into_response();
+            }
+            Self::DbError(e) => return db_error_into_response(e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+        .into_response()
+    }
+}
+
+async fn get_ticket(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Path(id): Path<api::ticket::Id>,
+) -> Result<Json<api::Ticket>, GetTicketError> {
+    use GetTicketError as E;
+
+    let ticket = state
+        .db_client
+        .get_ticket_by_id(id, db::ticket::Visibility::ActiveOnly)
+        .await?
+        .ok_or(E::TicketNotFound)?;
+
+    let user_ids = [auth_claims.user_id, ticket.initiator]
+        .into_iter()
+        .chain(ticket.purchasing_manager)
+        .chain(ticket.accounting_manager)
+        .unique()
+        .collect::<Vec<_>>();
+    let users = state.db_client.get_users_by_ids(&user_ids).await?;
+
+    let my = users.get(&auth_claims.user_id).ok_or(E::UserNotFound)?;
+    let attachments = if is_participant(&ticket, my) {
+        let attachments = state
+            .db_client
+            .get_attachments_for_ticket(ticket.id)
+            .await?;
+        Some(attachments.into_iter().map(Into::into).collect())
+    } else {
+        None
+    };
+
+    let initiator = users.get(&ticket.initiator).ok_or(E::UserNotFound)?;
+    let purchasing_manager = ticket
+        .purchasing_manager
+        .map(|id| users.get(&id).ok_or(E::UserNotFound))
+        .transpose()?;
+    let accounting_manager = ticket
+        .accounting_manager
+        .map(|id| users.get(&id).ok_or(E::UserNotFound))
+        .transpose()?;
+
+    Ok(Json(api::Ticket {
+        attachments,
+        ..(&ticket, initiator, purchasing_manager, accounting_manager).into()
+    }))
+}
+
+#[derive(Debug, From)]
+pub enum GetTicketError {
+    #[from]
+    DbError(db::Error),
+    TicketNotFound,
+    UserNotFound,
+}
+
+impl IntoResponse for GetTicketError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::TicketNotFound => StatusCode::NOT_FOUND,
+            Self::DbError(e) => return internal_db_error(&e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+        .into_response()
+    }
+}
+
+/// Size of the multipart encoding of an uploaded attachment, on top of the
+/// file itself.
+const MULTIPART_OVERHEAD: usize = 64 * 1024;
+
+/// Query of `GET /ticket/:id/history`.
+#[derive(Deserialize)]
+struct TicketHistoryInput {
+    /// Whether to return the fields altered by every change.
+    #[serde(default)]
+    diff: bool,
+}
+
+async fn get_ticket_history(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Path(id): Path<api::ticket::Id>,
+    Query(TicketHistoryInput { diff }): Query<TicketHistoryInput>,
+) -> Result<Json<Vec<api::ticket::Event>>, GetTicketHistoryError> {
+    use GetTicketHistoryError as E;
+
+    let ticket = state
+        .db_client
+        .get_ticket_by_id(id, db::ticket::Visibility::ActiveOnly)
+        .await?
+        .ok_or(E::TicketNotFound)?;
+    let my = state
+        .db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
+    if !is_participant(&ticket, &my) && my.role != db::user::Role::Admin {
+        return Err(E::NotParticipant);
+    }
+
+    let events = state.db_client.get_events_for_ticket(ticket.id).await?;
+
+    // Changes are the differences between the snapshots of the ticket taken
+    // along with the successive events, starting from no ticket at all.
+    let mut before = Some(serde_json::Map::new());
+    let mut history = Vec::with_capacity(events.len());
+    for event in events {
+        let after = match event.snapshot {
+            Some(serde_json::Value::Object(snapshot)) => Some(snapshot),
+            _ => None,
+        };
+        let changes = match (&before, &after) {
+            (Some(before), Some(after)) if diff => {
+                Some(field_changes(before, after))
+            }
+            _ => None,
+        };
+        history.push(api::ticket::Event {
+            id: event.id,
+            action: event.action,
+            actor: event.actor,
+            created_at: event.created_at,
+            changes,
+        });
+        before = after;
+    }
+    Ok(Json(history))
+}
+
+/// Returns the fields whose values differ between the `before` and `after`
+/// snapshots of a ticket.
+fn field_changes(
+    before: &serde_json::Map<String, serde_json::Value>,
+    after: &serde_json::Map<String, serde_json::Value>,
+) -> Vec<api::ticket::FieldChange> {
+    let fields = before.keys().chain(after.keys()).unique();
+    fields
+        .filter_map(|field| {
+            let before = before.get(field).unwrap_or(&serde_json::Value::Null);
+            let after = after.get(field).unwrap_or(&serde_json::Value::Null);
+            (before != after).then(|| api::ticket::FieldChange {
+                field: field.clone(),
+                before: before.clone(),
+                after: after.clone(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, From)]
+pub enum GetTicketHistoryError {
+    #[from]
+    DbError(db::Error),
+    NotParticipant,
+    TicketNotFound,
+    UserNotFound,
+}
+
+impl IntoResponse for GetTicketHistoryError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::NotParticipant => StatusCode::FORBIDDEN,
+            Self::TicketNotFound => StatusCode::NOT_FOUND,
+            Self::DbError(e) => return internal_db_error(&e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+        .into_response()
+    }
+}
+
+/// Indicates whether the `user` takes part in the `ticket`, and so may access
+/// its attachments.
+///
+/// Besides the initiator and the assigned managers, managers of a role not
+/// assigned to the ticket yet take part too, as it awaits one of them.
+fn is_participant(ticket: &db::Ticket, user: &db::User) -> bool {
+    use db::user::Role;
+
+    ticket.initiator == user.id
+        || ticket.purchasing_manager == Some(user.id)
+        || ticket.accounting_manager == Some(user.id)
+        || (user.role == Role::PurchasingManager
+            && ticket.purchasing_manager.is_none())
+        || (user.role == Role::AccountingManager
+            && ticket.accounting_manager.is_none())
+}
+
+async fn upload_attachment(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Path(id): Path<api::ticket::Id>,
+    mut multipart: Multipart,
+) -> Result<Json<api::Attachment>, UploadAttachmentError> {
+    use UploadAttachmentError as E;
+
+    let ticket = state
+        .db_client
+        .get_ticket_by_id(id, db::ticket::Visibility::ActiveOnly)
+        .await?
+        .ok_or(E::TicketNotFound)?;
+    let my = state
+        .db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
+    if !is_participant(&ticket, &my) {
+        return Err(E::NotParticipant);
+    }
+
+    // File must be the first part, any following ones are ignored.
+    let mut field = multipart
+        .next_field()
+        .await?
+        .filter(|f| f.name() == Some("file"))
+        .ok_or(E::FileMissing)?;
+
+    let content_type = field.content_type().unwrap_or_default().to_owned();
+    if !state
+        .attachments
+        .allowed_content_types
+        .contains(&content_type)
+    {
+        return Err(E::UnsupportedContentType);
+    }
+
+    let file_name = field.file_name().unwrap_or_default().to_owned();
+    let mut validator = Validator::new();
+    validator
+        .check(
+            "fileName",
+            !file_name.trim().is_empty(),
+            Code::MustNotBeEmpty,
+        )
+        .check(
+            "fileName",
+            file_name.chars().count() <= api::attachment::FILE_NAME_MAX_LEN,
+            Code::TooLong,
+        );
+    validator.finish().map_err(E::Invalid)?;
+
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await? {
+        if data.len() + chunk.len() > state.attachments.max_size {
+            return Err(E::TooLarge);
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    // Truncated to the precision of the database, so the response shows the
+    // same time as the later listings do.
+    let now = OffsetDateTime::now_utc();
+    let created_at = now.replace_microsecond(now.microsecond()).unwrap();
+
+    let attachment = db::Attachment {
+        id: db::attachment::Id::new(),
+        ticket_id: ticket.id,
+        uploader: my.id,
+        file_name,
+        content_type,
+        size: data.len(),
+        created_at,
+    };
+
+    // Contents are stored first, so the metadata never refers to missing
+    // ones.
+    state
+        .blobs
+        .put(
+            &attachment.blob_key(),
+            &attachment.content_type,
+            data.into(),
+        )
+        .await?;
+    state.db_client.insert_attachment(&attachment).await?;
+
+    Ok(Json(attachment.into()))
+}
+
+#[derive(Debug, From)]
+pub enum UploadAttachmentError {
+    #[from]
+    DbError(db::Error),
+    #[from]
+    BlobError(blob::Error),
+    #[from]
+    Multipart(MultipartError),
+    Invalid(api::validation::Errors),
+    FileMissing,
+    NotParticipant,
+    TicketNotFound,
+    TooLarge,
+    UnsupportedContentType,
+    UserNotFound,
+}
+
+impl IntoResponse for UploadAttachmentError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::Invalid(errors) => {
+                return (StatusCode::UNPROCESSABLE_ENTITY, Json(errors))
+                    .into_response();
+            }
+            Self::Multipart(e) => return e.into_response(),
+            Self::FileMissing => StatusCode::BAD_REQUEST,
+            Self::NotParticipant => StatusCode::FORBIDDEN,
+            Self::TicketNotFound => StatusCode::NOT_FOUND,
+            Self::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::UnsupportedContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::DbError(e) => return db_error_into_response(e),
+            Self::BlobError(_) | Self::UserNotFound => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+        .into_response()
+    }
+}
+
+async fn list_attachments(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Path(id): Path<api::ticket::Id>,
+) -> Result<Json<Vec<api::Attachment>>, ListAttachmentsError> {
+    use ListAttachmentsError as E;
+
+    let ticket = state
+        .db_client
+        .get_ticket_by_id(id, db::ticket::Visibility::ActiveOnly)
+        .await?
+        .ok_or(E::TicketNotFound)?;
+    let my = state
+        .db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
+    if !is_participant(&ticket, &my) {
+        return Err(E::NotParticipant);
+    }
+
+    let attachments = state
+        .db_client
+        .get_attachments_for_ticket(ticket.id)
+        .await?;
+    Ok(Json(attachments.into_iter().map(Into::into).collect()))
+}
+
+#[derive(Debug, From)]
+pub enum ListAttachmentsError {
+    #[from]
+    DbError(db::Error),
+    NotParticipant,
+    TicketNotFound,
+    UserNotFound,
+}
+
+impl IntoResponse for ListAttachmentsError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::NotParticipant => StatusCode::FORBIDDEN,
+            Self::TicketNotFound => StatusCode::NOT_FOUND,
+            Self::DbError(e) => return internal_db_error(&e),
+            Self::UserNotFound => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+        .into_response()
+    }
+}
+
+async fn download_attachment(
+    State(state): State<AppState>,
+    auth_claims: AuthClaims,
+    Path((id, attachment_id)): Path<(api::ticket::Id, api::attachment::Id)>,
+) -> Result<Response, DownloadAttachmentError> {
+    use DownloadAttachmentError as E;
+
+    let ticket = state
+        .db_client
+        .get_ticket_by_id(id, db::ticket::Visibility::ActiveOnly)
+        .await?
+        .ok_or(E::TicketNotFound)?;
+    let my = state
+        .db_client
+        .get_user_by_id(auth_claims.user_id)
+        .await?
+        .ok_or(E::UserNotFound)?;
+    if !is_participant(&ticket, &my) {
+        return Err(E::NotParticipant);
+    }
+
+    let attachment = state
+        .db_client
+        .get_attachment_by_id(attachment_id)
+        .await?
+        .filter(|a| a.ticket_id == ticket.id)
+        .ok_or(E::AttachmentNotFound)?;
+    let data = state
+        .blobs
+        .get(&attachment.blob_key())
+        .await?
+        .ok_or(E::BlobNotFound)?;
+
+    let content_type = HeaderValue::from_str(&attachment.content_type)
+        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+    Ok((
+        [
+            (CONTENT_TYPE, content_type),
+            (
+                CONTENT_DISPOSITION,
+                attachment_disposition(&attachment.file_name),
+            ),
+        ],
+        data,
+    )
+        .into_response())
+}
+
+/// Returns the `Content-Disposition` of a downloaded file named `file_name`.
+///
+/// The name is only a hint for saving the file, so the characters not fitting
+/// a quoted header value are replaced rather than escaped.
+fn attachment_disposition(file_name: &str) -> HeaderValue {
+    let file_name = file_name
+        .chars()
+        .map(|c| match c {
+            ' ' => c,
+            '"' | '\\' => '_',
+            c if c.is_ascii_graphic() => c,
+            _ => '_',
+        })
+        .collect::<String>();
+    HeaderValue::from_str(&format!("attachment; filename=\"{file_name}\""))
+        .expect("visible ASCII is a valid header value")
+}
+
+#[derive(Debug, From)]
+pub enum DownloadAttachmentError {
+    #[from]
+    DbError(db::Error),
+    #[from]
+    BlobError(blob::Error),
+    AttachmentNotFound,
+    BlobNotFound,
+    NotParticipant,
+    TicketNotFound,
+    UserNotFound,
+}
+
+impl IntoResponse for DownloadAttachmentError {
+    fn into_response(self) -> Response {
+        match self {
+            Self::NotParticipant => StatusCode::FORBIDDEN,
+            Self::AttachmentNotFound | Self::TicketNotFound => {
+                StatusCode::NOT_FOUND
+            }
+            Self::DbError(e) => return internal_db_error(&e),
+            Self::BlobError(_) | Self::BlobNotFound | Self::UserNotFound => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+        .into_response()
+    }
+}
+
+#[derive(Clone)]
+struct AppState {
+    db_client: Arc<dyn db::Storage>,
+
+    jwt_expiration_time: Duration,
+
+    /// Time past its expiration a token is still accepted for.
+    jwt_leeway: Duration,
+
+    jwt_decoding_key: DecodingKey,
+
+    jwt_encoding_key: EncodingKey,
+
+    /// Profiles of the tokens issued to different clients, by their names.
+    jwt_clients: Arc<BTreeMap<String, config::JwtClient>>,
+
+    /// Unix timestamp before which all issued access tokens are rejected.
+    tokens_valid_after: Arc<AtomicI64>,
+
+    /// Cached Unix timestamps of the last password change of each user,
+    /// along with the time they were cached at.
+    ///
+    /// Filled lazily on token validation and kept up to date by the password
+    /// change endpoint of this instance.
+    password_changed_at: Arc<Mutex<HashMap<api::user::Id, (Instant, i64)>>>,
+
+    /// Time [`AppState::users`] and [`AppState::password_changed_at`] are
+    /// served from the cache for, if caching is enabled.
+    user_cache_ttl: Option<Duration>,
+
+    /// Cached users responded by `GET /user`, along with the time they were
+    /// cached at.
+    ///
+    /// Kept up to date by the endpoints of this instance changing the users.
+    users: Arc<Mutex<HashMap<api::user::Id, (Instant, api::User)>>>,
+
+    /// Wall-clock time the server was started at.
+    started_at: OffsetDateTime,
+
+    /// Monotonic time the server was started at, measuring its uptime.
+    started: Instant,
+
+    /// Storage of the files attached to tickets.
+    blobs: Arc<dyn blob::Store>,
+
+    attachments: Arc<config::Attachments>,
+
+    tickets: Arc<config::Tickets>,
+}
+
+impl AppState {
+    /// Returns the Unix timestamp of the last password change of the user
+    /// with the provided `id`, if such user exists.
+    ///
+    /// Served from the cache until it expires, so a password changed through
+    /// another instance is only seen by this one after that.
+    async fn password_changed_at(
+        &self,
+        id: api::user::Id,
+    ) -> Result<Option<i64>, db::Error> {
+        if let Some(ttl) = self.user_cache_ttl {
+            let mut cache = self.password_changed_at.lock().unwrap();
+            match cache.get(&id) {
+                Some(&(at, changed_at)) if at.elapsed() < ttl => {
+                    return Ok(Some(changed_at));
+                }
+                Some(_) => {
+                    cache.remove(&id);
+                }
+                None => {}
+            }
+        }
+
+        let Some(at) = self.db_client.get_user_password_changed_at(id).await?
+        else {
+            return Ok(None);
+        };
+        let at = at.unix_timestamp();
+        self.cache_password_changed_at(id, at);
+        Ok(Some(at))
+    }
+
+    /// Caches the Unix timestamp of the last password change of the user
+    /// with the provided `id`, if caching is enabled.
+    fn cache_password_changed_at(&self, id: api::user::Id, changed_at: i64) {
+        if self.user_cache_ttl.is_some() {
+            let mut cache = self.password_changed_at.lock().unwrap();
+            cache.insert(id, (Instant::now(), changed_at));
+        }
+    }
+
+    /// Returns the cached user with the provided `id`, unless it has expired.
+    fn cached_user(&self, id: api::user::Id) -> Option<api::User> {
+        let ttl = self.user_cache_ttl?;
+        let mut users = self.users.lock().unwrap();
+        match users.get(&id) {
+            Some((at, user)) if at.elapsed() < ttl => Some(user.clone()),
+            Some(_) => {
+                users.remove(&id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Caches the `user`, if caching is enabled.
+    fn cache_user(&self, user: &api::User) {
+        if self.user_cache_ttl.is_some() {
+            let mut users = self.users.lock().unwrap();
+            users.insert(user.id, (Instant::now(), user.clone()));
+        }
+    }
+
+    /// Evicts the user with the provided `id` from the cache, once it has
+    /// been changed.
+    fn evict_user(&self, id: api::user::Id) {
+        self.users.lock().unwrap().remove(&id);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct AuthClaims {
+    user_id: api::user::Id,
+    exp: i64,
+    iat: i64,
+}
+
+/// [`AuthClaims`] of an issued token, along with its audience.
+///
+/// The audience is only checked on validation, so isn't a part of the
+/// [`AuthClaims`] themselves.
+#[derive(Serialize)]
+struct IssuedClaims<'a> {
+    #[serde(flatten)]
+    claims: AuthClaims,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aud: Option<&'a str>,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthClaims {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(
+        parts: &mut request::Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) = parts
+            .extract::<TypedHeader<Authorization<Bearer>>>()
+            .await
+            .map_err(|_| AuthError::InvalidToken)?;
+
+        let mut validation = Validation::default();
+        validation.leeway = state.jwt_leeway.as_secs();
+        // Tokens without an audience are accepted as well, as it isn't
+        // required.
+        if !state.jwt_clients.is_empty() {
+            let audiences = state
+                .jwt_clients
+                .values()
+                .map(|c| c.audience.as_str())
+                .collect::<Vec<_>>();
+            validation.set_audience(&audiences);
+        }
+
+        let token_data = decode::<Self>(
+            bearer.token(),
+            &state.jwt_decoding_key,
+            &validation,
+        )
+        .map_err(|_| AuthError::InvalidToken)?;
+
+        let valid_after = state.tokens_valid_after.load(Ordering::Relaxed);
+        if token_data.claims.iat < valid_after {
+            return Err(AuthError::InvalidToken);
+        }
+
+        let password_changed_at = state
+            .password_changed_at(token_data.claims.user_id)
+            .await?
+            .ok_or(AuthError::InvalidToken)?;
+        // Tokens issued after the change are dated past its second.
+        if token_data.claims.iat <= password_changed_at {
+            return Err(AuthError::InvalidToken);
+        }
+
+        Ok(token_data.claims)
+    }
+}
+
+/// In-memory [`db::Storage`], for exercising the handlers without a
+/// database.
+#[cfg(test)]
+mod memory_storage {
+    use std::{
+        collections::{HashMap, HashSet},
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use async_trait::async_trait;
+    use futures::{stream, stream::BoxStream, StreamExt as _};
+    use serde_json::Value as Json;
+    use time::OffsetDateTime;
+
+    use dubna_internship::db::{
+        self,
+        attachment::{self, Attachment},
+        audit::{Entity, Event, StatusTransition, TicketEdit},
+        ticket::{
+            self, Category, PaymentSummary, PurchasingSummary, SortBy,
+            SortDirection, Status, StatusUpdateFields, TextSearchConfig,
+            TicketFilter, TicketOrder, TicketWithTimes, TicketWithUsers,
+            Visibility, WriteTicketsError,
+        },
+        user::{
+            self, DeleteUserError, PasswordHash, Role, UpdateUserRoleError,
+            UserSummary, UserWithTimes,
+        },
+        Storage, Ticket, User,
+    };
+
+    #[derive(Clone, Default)]
+    pub struct MemoryStorage(Arc<Mutex<Data>>);
+
+    struct Data {
+        users: HashMap<user::Id, User>,
+        tickets: HashMap<ticket::Id, Ticket>,
+        events: Vec<Event>,
+        attachments: Vec<Attachment>,
+        tokens_valid_after: OffsetDateTime,
+        schema_version: Option<String>,
+        missing_columns: Vec<String>,
+    }
+
+    impl Default for Data {
+        fn default() -> Self {
+            Self {
+                users: HashMap::new(),
+                tickets: HashMap::new(),
+                events: Vec::new(),
+                attachments: Vec::new(),
+                tokens_valid_after: OffsetDateTime::UNIX_EPOCH,
+                schema_version: Some(db::SCHEMA_VERSION.to_owned()),
+                missing_columns: Vec::new(),
+            }
+        }
+    }
+
+    impl MemoryStorage {
+        pub fn insert_user(&self, user: User) {
+            self.0.lock().unwrap().users.insert(user.id, user);
+        }
+
+        pub fn insert_ticket(&self, ticket: Ticket) {
+            self.0.lock().unwrap().tickets.insert(ticket.id, ticket);
+        }
+
+        pub fn ticket(&self, id: ticket::Id) -> Option<Ticket> {
+            self.0.lock().unwrap().tickets.get(&id).cloned()
+        }
+
+        pub fn events(&self) -> Vec<Event> {
+            self.0.lock().unwrap().events.clone()
+        }
+
+        pub fn set_schema_version(&self, version: Option<&str>) {
+            self.0.lock().unwrap().schema_version =
+                version.map(ToOwned::to_owned);
+        }
+
+        pub fn set_missing_columns(&self, columns: &[&str]) {
+            self.0.lock().unwrap().missing_columns =
+                columns.iter().map(|&c| c.to_owned()).collect();
+        }
+
+        /// Returns the tickets matching the `filter` in the listing order:
+        /// newest first.
+        fn tickets(&self, filter: &TicketFilter) -> Vec<Ticket> {
+            let mut tickets = self
+                .0
+                .lock()
+                .unwrap()
+                .tickets
+                .values()
+                .filter(|t| matches(filter, t))
+                .cloned()
+                .collect::<Vec<_>>();
+            // Hyphenated UUIDs order the same way as their bytes, which is
+            // how the database orders them.
+            tickets.sort_by_key(|t| (t.created_at, t.id.to_string()));
+            tickets.reverse();
+            tickets
+        }
+
+        /// Joins the `tickets` with the users they refer to, the same way the
+        /// database does, dropping the tickets of missing initiators.
+        fn with_users(&self, tickets: Vec<Ticket>) -> Vec<TicketWithUsers> {
+            let data = self.0.lock().unwrap();
+            let summary = |id: &user::Id| {
+                data.users.get(id).map(|u| UserSummary {
+                    id: u.id,
+                    name: u.name.clone(),
+                    login: u.login.clone(),
+                    role: u.role,
+                })
+            };
+            tickets
+                .into_iter()
+                .filter_map(|ticket| {
+                    Some(TicketWithUsers {
+                        initiator: summary(&ticket.initiator)?,
+                        purchasing_manager: ticket
+                            .purchasing_manager
+                            .as_ref()
+                            .and_then(summary),
+                        accounting_manager: ticket
+                            .accounting_manager
+                            .as_ref()
+                            .and_then(summary),
+                        ticket,
+                    })
+                })
+                .collect()
+        }
+    }
+
+    /// Indicates whether the `ticket` refers to the `user` in any role.
+    fn refers_to(ticket: &Ticket, user: user::Id) -> bool {
+        ticket.initiator == user
+            || ticket.purchasing_manager == Some(user)
+            || ticket.accounting_manager == Some(user)
+    }
+
+    /// Indicates whether the `ticket` matches the `filter`, the same way the
+    /// database evaluates it.
+    fn matches(filter: &TicketFilter, ticket: &Ticket) -> bool {
+        filter.category.iter().all(|&c| ticket.category == c)
+            && (filter.statuses.is_empty()
+                || filter.statuses.contains(&ticket.status))
+            && filter.initiator.iter().all(|&id| ticket.initiator == id)
+            && filter.assignee.iter().all(|&id| {
+                ticket.purchasing_manager == Some(id)
+                    || ticket.accounting_manager == Some(id)
+            })
+            && filter
+                .min_price
+                .iter()
+                .all(|&min| ticket.price.is_some_and(|p| p >= min))
+            && filter
+                .max_price
+                .iter()
+                .all(|&max| ticket.price.is_some_and(|p| p <= max))
+            && filter
+                .created_after
+                .iter()
+                .all(|&at| ticket.created_at > at)
+            && filter
+                .created_before
+                .iter()
+                .all(|&at| ticket.created_at < at)
+            && filter.text.iter().all(|text| {
+                let text = text.to_lowercase();
+                ticket.title.to_lowercase().contains(&text)
+                    || ticket.description.to_lowercase().contains(&text)
+            })
+    }
+
+    #[async_trait]
+    impl Storage for MemoryStorage {
+        fn primary(&self) -> Arc<dyn Storage> {
+            Arc::new(self.clone())
+        }
+
+        async fn ping(&self, _: Duration) -> Result<(), db::PingError> {
+            Ok(())
+        }
+
+        fn pool_metrics(&self) -> Vec<db::PoolMetrics> {
+            Vec::new()
+        }
+
+        async fn get_current_time(&self) -> Result<OffsetDateTime, db::Error> {
+            Ok(OffsetDateTime::now_utc())
+        }
+
+        async fn get_schema_version(
+            &self,
+        ) -> Result<Option<String>, db::Error> {
+            Ok(self.0.lock().unwrap().schema_version.clone())
+        }
+
+        async fn get_missing_columns(&self) -> Result<Vec<String>, db::Error> {
+            Ok(self.0.lock().unwrap().missing_columns.clone())
+        }
+
+        async fn get_tokens_valid_after(
+            &self,
+        ) -> Result<OffsetDateTime, db::Error> {
+            Ok(self.0.lock().unwrap().tokens_valid_after)
+        }
+
+        async fn set_tokens_valid_after(
+            &self,
+            at: OffsetDateTime,
+        ) -> Result<(), db::Error> {
+            self.0.lock().unwrap().tokens_valid_after = at;
+            Ok(())
+        }
+
+        async fn get_user_by_login(
+            &self,
+            login: &str,
+        ) -> Result<Option<User>, db::Error> {
+            let data = self.0.lock().unwrap();
+            Ok(data.users.values().find(|u| u.login == login).cloned())
+        }
+
+        async fn get_user_by_id(
+            &self,
+            id: user::Id,
+        ) -> Result<Option<User>, db::Error> {
+            Ok(self.0.lock().unwrap().users.get(&id).cloned())
+        }
+
+        async fn get_users_by_ids(
+            &self,
+            ids: &[user::Id],
+        ) -> Result<HashMap<user::Id, User>, db::Error> {
+            let data = self.0.lock().unwrap();
+            Ok(ids
+                .iter()
+                .filter_map(|id| data.users.get(id))
+                .map(|u| (u.id, u.clone()))
+                .collect())
+        }
+
+        // Tracks no update times, so takes users as changed when their
+        // passwords were.
+        async fn stream_users_updated_since(
+            &self,
+            since: Option<OffsetDateTime>,
+        ) -> Result<
+            BoxStream<'static, Result<UserWithTimes, db::Error>>,
+            db::Error,
+        > {
+            let mut users = self
+                .0
+                .lock()
+                .unwrap()
+                .users
+                .values()
+                .filter(|u| since.is_none_or(|s| u.password_changed_at >= s))
+                .map(|u| UserWithTimes {
+                    user: u.clone(),
+                    updated_at: u.password_changed_at,
+                })
+                .collect::<Vec<_>>();
+            users.sort_by_key(|u| (u.updated_at, u.user.id.to_string()));
+            Ok(stream::iter(users.into_iter().map(Ok)).boxed())
+        }
+
+        async fn get_user_password_changed_at(
+            &self,
+            id: user::Id,
+        ) -> Result<Option<OffsetDateTime>, db::Error> {
+            let data = self.0.lock().unwrap();
+            Ok(data.users.get(&id).map(|u| u.password_changed_at))
+        }
+
+        async fn update_user_password(
+            &self,
+            id: user::Id,
+            password_hash: &PasswordHash,
+            changed_at: OffsetDateTime,
+        ) -> Result<(), db::Error> {
+            if let Some(u) = self.0.lock().unwrap().users.get_mut(&id) {
+                u.password_hash = password_hash.clone();
+                u.password_changed_at = changed_at;
+            }
+            Ok(())
+        }
+
+        async fn update_user_profile(
+            &self,
+            id: user::Id,
+            name: &str,
+        ) -> Result<bool, db::Error> {
+            let mut data = self.0.lock().unwrap();
+            let Some(u) = data.users.get_mut(&id) else {
+                return Ok(false);
+            };
+            name.clone_into(&mut u.name);
+            Ok(true)
+        }
+
+        async fn update_user_email(
+            &self,
+            id: user::Id,
+            email: &str,
+        ) -> Result<bool, db::Error> {
+            let mut data = self.0.lock().unwrap();
+            let Some(u) = data.users.get_mut(&id) else {
+                return Ok(false);
+            };
+            u.email = Some(email.to_owned());
+            Ok(true)
+        }
+
+        // Refuses the same deletions the foreign keys do.
+        async fn delete_user(
+            &self,
+            id: user::Id,
+        ) -> Result<(), DeleteUserError> {
+            let mut data = self.0.lock().unwrap();
+            let is_referenced = data.tickets.values().any(|t| refers_to(t, id))
+                || data.events.iter().any(|e| e.actor == id)
+                || data.attachments.iter().any(|a| a.uploader == id);
+            if is_referenced {
+                return Err(DeleteUserError::UserReferenced);
+            }
+            data.users.remove(&id);
+            Ok(())
+        }
+
+        async fn update_user_role_with_event(
+            &self,
+            id: user::Id,
+            role: Role,
+            actor: user::Id,
+        ) -> Result<Option<User>, UpdateUserRoleError> {
+            let mut data = self.0.lock().unwrap();
+            let admins = data
+                .users
+                .values()
+                .filter(|u| u.role == Role::Admin)
+                .count();
+            let Some(user) = data.users.get_mut(&id) else {
+                return Ok(None);
+            };
+            if user.role == role {
+                return Ok(Some(user.clone()));
+            }
+            if user.role == Role::Admin && admins == 1 {
+                return Err(UpdateUserRoleError::LastAdmin);
+            }
+            let previous = user.role;
+            user.role = role;
+            let user = user.clone();
+            let event_id = i64::try_from(data.events.len()).unwrap() + 1;
+            data.events.push(Event {
+                id: event_id,
+                actor,
+                entity: Entity::User(id),
+                action: "changeRole".to_owned(),
+                payload: serde_json::json!({
+                    "op": "changeRole",
+                    "data": { "role": role, "previousRole": previous },
+                }),
+                snapshot: Some(user.snapshot()),
+                created_at: OffsetDateTime::now_utc(),
+            });
+            Ok(Some(user))
+        }
+
+        // Tickets are never deleted here, so every visibility is the same.
+        async fn get_ticket_by_id(
+            &self,
+            id: ticket::Id,
+            _: Visibility,
+        ) -> Result<Option<Ticket>, db::Error> {
+            Ok(self.ticket(id))
+        }
+
+        async fn get_tickets_page_with_count(
+            &self,
+            offset: usize,
+            limit: usize,
+            filter: &TicketFilter,
+        ) -> Result<(Vec<Ticket>, usize), db::Error> {
+            let tickets = self.tickets(filter);
+            let total_count = tickets.len();
+            let page = tickets.into_iter().skip(offset).take(limit).collect();
+            Ok((page, total_count))
+        }
+
+        async fn get_tickets_before(
+            &self,
+            created_at: OffsetDateTime,
+            id: ticket::Id,
+            limit: usize,
+            filter: &TicketFilter,
+        ) -> Result<Vec<Ticket>, db::Error> {
+            let cursor = (created_at, id.to_string());
+            Ok(self
+                .tickets(filter)
+                .into_iter()
+                .filter(|t| (t.created_at, t.id.to_string()) < cursor)
+                .take(limit)
+                .collect())
+        }
+
+        async fn get_tickets_page_with_users(
+            &self,
+            offset: usize,
+            limit: usize,
+            filter: &TicketFilter,
+            order: TicketOrder,
+        ) -> Result<(Vec<TicketWithUsers>, usize), db::Error> {
+            let total_count = self.tickets(filter).len();
+            let mut tickets = self.with_users(self.tickets(filter));
+            tickets.sort_by(|a, b| {
+                let by_key = match order.by {
+                    SortBy::CreatedAt => {
+                        a.ticket.created_at.cmp(&b.ticket.created_at)
+                    }
+                    SortBy::Initiator => {
+                        a.initiator.name.cmp(&b.initiator.name)
+                    }
+                };
+                let ordering = by_key.then_with(|| {
+                    a.ticket.id.to_string().cmp(&b.ticket.id.to_string())
+                });
+                match order.direction {
+                    SortDirection::Asc => ordering,
+                    SortDirection::Desc => ordering.reverse(),
+                }
+            });
+            let page = tickets.into_iter().skip(offset).take(limit).collect();
+            Ok((page, total_count))
+        }
+
+        async fn get_tickets_before_with_users(
+            &self,
+            created_at: OffsetDateTime,
+            id: ticket::Id,
+            limit: usize,
+            filter: &TicketFilter,
+        ) -> Result<Vec<TicketWithUsers>, db::Error> {
+            let page = self
+                .get_tickets_before(created_at, id, limit, filter)
+                .await?;
+            Ok(self.with_users(page))
+        }
+
+        async fn get_tickets_count(
+            &self,
+            filter: &TicketFilter,
+        ) -> Result<usize, db::Error> {
+            Ok(self.tickets(filter).len())
+        }
+
+        async fn count_tickets_for_user(
+            &self,
+            user: user::Id,
+        ) -> Result<usize, db::Error> {
+            Ok(self
+                .tickets(&TicketFilter::default())
+                .iter()
+                .filter(|t| refers_to(t, user))
+                .count())
+        }
+
+        async fn get_ticket_ids_for_user(
+            &self,
+            user: user::Id,
+            limit: usize,
+        ) -> Result<Vec<ticket::Id>, db::Error> {
+            Ok(self
+                .tickets(&TicketFilter::default())
+                .iter()
+                .filter(|t| refers_to(t, user))
+                .take(limit)
+                .map(|t| t.id)
+                .collect())
+        }
+
+        async fn get_ticket_counts_by_status(
+            &self,
+            filter: &TicketFilter,
+        ) -> Result<HashMap<Status, usize>, db::Error> {
+            let mut counts = Status::ALL
+                .into_iter()
+                .map(|status| (status, 0))
+                .collect::<HashMap<_, _>>();
+            for ticket in self.tickets(filter) {
+                *counts.entry(ticket.status).or_default() += 1;
+            }
+            Ok(counts)
+        }
+
+        async fn get_purchasing_summary(
+            &self,
+            manager: user::Id,
+        ) -> Result<PurchasingSummary, db::Error> {
+            let tickets = self.tickets(&TicketFilter::default());
+            let confirmed = tickets.iter().filter(|t| {
+                t.purchasing_manager == Some(manager)
+                    && matches!(
+                        t.status,
+                        Status::Confirmed | Status::PaymentCompleted
+                    )
+            });
+            Ok(PurchasingSummary {
+                pending_confirmation: tickets
+                    .iter()
+                    .filter(|t| t.status == Status::Requested)
+                    .count(),
+                confirmed: confirmed.clone().count(),
+                confirmed_total_price: confirmed.filter_map(|t| t.price).sum(),
+            })
+        }
+
+        async fn get_payment_summary(
+            &self,
+        ) -> Result<PaymentSummary, db::Error> {
+            let filter = TicketFilter {
+                statuses: vec![Status::Confirmed],
+                ..TicketFilter::default()
+            };
+            let tickets = self.tickets(&filter);
+            Ok(PaymentSummary {
+                awaiting_payment: tickets.len(),
+                awaiting_payment_total_price: tickets
+                    .iter()
+                    .filter_map(|t| t.price)
+                    .sum(),
+            })
+        }
+
+        /// Approximates the full-text search by looking for the whole `query`
+        /// in the tickets, as if it was [`TicketFilter::text`].
+        async fn search_tickets(
+            &self,
+            query: &str,
+            _: TextSearchConfig,
+            offset: usize,
+            limit: usize,
+            filter: &TicketFilter,
+            order: TicketOrder,
+        ) -> Result<(Vec<TicketWithUsers>, usize), db::Error> {
+            let filter = TicketFilter {
+                text: Some(query.to_owned()),
+                ..filter.clone()
+            };
+            self.get_tickets_page_with_users(offset, limit, &filter, order)
+                .await
+        }
+
+        async fn stream_tickets(
+            &self,
+            category: Option<Category>,
+            _: Visibility,
+        ) -> Result<BoxStream<'static, Result<Ticket, db::Error>>, db::Error>
+        {
+            let filter = TicketFilter {
+                category,
+                ..TicketFilter::default()
+            };
+            Ok(stream::iter(self.tickets(&filter).into_iter().map(Ok)).boxed())
+        }
+
+        // Tracks no update times nor deletions, so takes tickets as changed
+        // when created.
+        async fn stream_tickets_updated_since(
+            &self,
+            since: Option<OffsetDateTime>,
+        ) -> Result<
+            BoxStream<'static, Result<TicketWithTimes, db::Error>>,
+            db::Error,
+        > {
+            let mut tickets = self
+                .0
+                .lock()
+                .unwrap()
+                .tickets
+                .values()
+                .filter(|t| since.is_none_or(|s| t.created_at >= s))
+                .map(|t| TicketWithTimes {
+                    ticket: t.clone(),
+                    updated_at: t.created_at,
+                    deleted_at: None,
+                })
+                .collect::<Vec<_>>();
+            tickets.sort_by_key(|t| (t.updated_at, t.ticket.id.to_string()));
+            Ok(stream::iter(tickets.into_iter().map(Ok)).boxed())
+        }
+
+        // Refuses taken IDs the same way the database does.
+        async fn write_tickets(
+            &self,
+            tickets: &[Ticket],
+        ) -> Result<usize, WriteTicketsError> {
+            let mut data = self.0.lock().unwrap();
+            let mut ids = HashSet::new();
+            for t in tickets {
+                if data.tickets.contains_key(&t.id) || !ids.insert(t.id) {
+                    return Err(WriteTicketsError::IdTaken(t.id));
+                }
+            }
+            for t in tickets {
+                data.tickets.insert(t.id, t.clone());
+            }
+            Ok(tickets.len())
+        }
+
+        async fn write_ticket_with_event(
+            &self,
+            ticket: &Ticket,
+            actor: user::Id,
+            action: &str,
+            payload: &Json,
+        ) -> Result<(), db::Error> {
+            let mut data = self.0.lock().unwrap();
+            data.tickets.insert(ticket.id, ticket.clone());
+            let id = i64::try_from(data.events.len()).unwrap() + 1;
+            data.events.push(Event {
+                id,
+                actor,
+                entity: Entity::Ticket(ticket.id),
+                action: action.to_owned(),
+                payload: payload.clone(),
+                snapshot: Some(ticket.snapshot()),
+                created_at: OffsetDateTime::now_utc(),
+            });
+            Ok(())
+        }
+
+        async fn edit_ticket_with_event(
+            &self,
+            edit: &TicketEdit,
+        ) -> Result<bool, db::Error> {
+            if self.ticket(edit.original.id).as_ref() != Some(&edit.original) {
+                return Ok(false);
+            }
+            if let Some(fields) = &edit.status_fields {
+                return self
+                    .update_ticket_status_with_event(
+                        &edit.ticket,
+                        edit.original.status,
+                        fields,
+                        edit.actor,
+                        &edit.action,
+                        &edit.payload,
+                    )
+                    .await;
+            }
+            self.write_ticket_with_event(
+                &edit.ticket,
+                edit.actor,
+                &edit.action,
+                &edit.payload,
+            )
+            .await?;
+            Ok(true)
+        }
+
+        async fn update_ticket_status_with_event(
+            &self,
+            ticket: &Ticket,
+            expected: ticket::Status,
+            fields: &StatusUpdateFields,
+            actor: user::Id,
+            action: &str,
+            payload: &Json,
+        ) -> Result<bool, db::Error> {
+            let mut data = self.0.lock().unwrap();
+            let Some(stored) = data.tickets.get_mut(&ticket.id) else {
+                return Ok(false);
+            };
+            if stored.status != expected {
+                return Ok(false);
+            }
+            stored.status = ticket.status;
+            if let Some(price) = fields.price {
+                stored.price = Some(price);
+            }
+            if let Some(reference) = &fields.payment_reference {
+                stored.payment_reference = Some(reference.clone());
+            }
+            if let Some(id) = fields.purchasing_manager {
+                stored.purchasing_manager = Some(id);
+            }
+            if let Some(id) = fields.accounting_manager {
+                stored.accounting_manager = Some(id);
+            }
+            let snapshot = stored.snapshot();
+            let id = i64::try_from(data.events.len()).unwrap() + 1;
+            data.events.push(Event {
+                id,
+                actor,
+                entity: Entity::Ticket(ticket.id),
+                action: action.to_owned(),
+                payload: payload.clone(),
+                snapshot: Some(snapshot),
+                created_at: OffsetDateTime::now_utc(),
+            });
+            Ok(true)
+        }
+
+        async fn update_ticket_statuses_with_events(
+            &self,
+            transitions: &[StatusTransition],
+        ) -> Result<Vec<bool>, db::Error> {
+            let mut applied = Vec::with_capacity(transitions.len());
+            for t in transitions {
+                applied.push(
+                    self.update_ticket_status_with_event(
+                        &t.ticket, t.expected, &t.fields, t.actor, &t.action,
+                        &t.payload,
+                    )
+                    .await?,
+                );
+            }
+            Ok(applied)
+        }
+
+        async fn get_events_for_ticket(
+            &self,
+            ticket_id: ticket::Id,
+        ) -> Result<Vec<Event>, db::Error> {
+            Ok(self
+                .events()
+                .into_iter()
+                .filter(|e| e.entity == Entity::Ticket(ticket_id))
+                .collect())
+        }
+
+        async fn insert_attachment(
+            &self,
+            attachment: &Attachment,
+        ) -> Result<(), db::Error> {
+            self.0.lock().unwrap().attachments.push(attachment.clone());
+            Ok(())
+        }
+
+        async fn get_attachment_by_id(
+            &self,
+            id: attachment::Id,
+        ) -> Result<Option<Attachment>, db::Error> {
+            let data = self.0.lock().unwrap();
+            Ok(data.attachments.iter().find(|a| a.id == id).cloned())
+        }
+
+        async fn get_attachments_for_ticket(
+            &self,
+            ticket_id: ticket::Id,
+        ) -> Result<Vec<Attachment>, db::Error> {
+            let data = self.0.lock().unwrap();
+            Ok(data
+                .attachments
+                .iter()
+                .filter(|a| a.ticket_id == ticket_id)
+                .cloned()
+                .collect())
+        }
+    }
+}
+
+#[cfg(test)]
+mod schema_version_spec {
+    use dubna_internship::db::{self, SchemaVersionError, Storage as _};
+
+    use super::memory_storage::MemoryStorage;
+
+    #[tokio::test]
+    async fn accepts_expected_version() {
+        let storage = MemoryStorage::default();
+        storage.set_schema_version(Some("00000000000042_latest"));
+
+        let res = storage.check_schema_version("00000000000042_latest").await;
+        assert!(res.is_ok(), "{res:?}");
+    }
+
+    #[tokio::test]
+    async fn reports_mismatch() {
+        let storage = MemoryStorage::default();
+        storage.set_schema_version(Some("00000000000041_stale"));
+
+        let res = storage.check_schema_version("00000000000042_latest").await;
+        match res {
+            Err(SchemaVersionError::Mismatch { expected, actual }) => {
+                assert_eq!(expected, "00000000000042_latest");
+                assert_eq!(actual, "00000000000041_stale");
+            }
+            res => panic!("expected mismatch, found {res:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn accepts_unversioned_schema_with_required_columns() {
+        let storage = MemoryStorage::default();
+        storage.set_schema_version(None);
+
+        let res = storage.check_schema_version(db::SCHEMA_VERSION).await;
+        assert!(res.is_ok(), "{res:?}");
+    }
+
+    #[tokio::test]
+    async fn reports_missing_columns_of_unversioned_schema() {
+        let storage = MemoryStorage::default();
+        storage.set_schema_version(None);
+        storage.set_missing_columns(&["tickets.payment_reference"]);
+
+        let res = storage.check_schema_version(db::SCHEMA_VERSION).await;
+        match res {
+            Err(SchemaVersionError::MissingColumns { columns }) => {
+                assert_eq!(columns, ["tickets.payment_reference"]);
+            }
+            res => panic!("expected missing columns, found {res:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod auth_claims_spec {
+    use std::{
+        env,
+        sync::{atomic::AtomicI64, Arc},
+        time::{Duration, Instant},
+    };
+
+    use axum::{extract::FromRequestParts as _, http::Request};
+    use jsonwebtoken::{DecodingKey, EncodingKey, Header};
+    use time::OffsetDateTime;
+
+    use dubna_internship::{
+        blob,
+        db::{
+            self,
+            user::{PasswordHash, Role},
+        },
+    };
+
+    use super::{memory_storage::MemoryStorage, AppState, AuthClaims};
+
+    const SECRET: &[u8] = b"secret";
+
+    /// Validates a token of an existing user which expired `expired_ago`,
+    /// accepting the ones expired within the `leeway`.
+    async fn validate_expired(
+        expired_ago: time::Duration,
+        leeway: Duration,
+    ) -> bool {
+        let storage = MemoryStorage::default();
+        storage.insert_user(db::User {
+            id: 1.into(),
+            name: "User 1".to_owned(),
+            login: "user1".to_owned(),
+            password_hash: PasswordHash::new("password"),
+            role: Role::Initiator,
+            password_changed_at: OffsetDateTime::UNIX_EPOCH,
+            email: None,
+        });
+        let state = AppState {
+            db_client: Arc::new(storage),
+            jwt_expiration_time: Duration::from_secs(3600),
+            jwt_leeway: leeway,
+            jwt_decoding_key: DecodingKey::from_secret(SECRET),
+            jwt_encoding_key: EncodingKey::from_secret(SECRET),
+            jwt_clients: Arc::default(),
+            tokens_valid_after: Arc::new(AtomicI64::new(0)),
+            password_changed_at: Arc::default(),
+            user_cache_ttl: None,
+            users: Arc::default(),
+            started_at: OffsetDateTime::now_utc(),
+            started: Instant::now(),
+            blobs: Arc::new(blob::LocalDir::new(env::temp_dir())),
+            attachments: Arc::default(),
+            tickets: Arc::default(),
+        };
+
+        let expires_at = OffsetDateTime::now_utc() - expired_ago;
+        let token = jsonwebtoken::encode(
+            &Header::default(),
+            &AuthClaims {
+                user_id: 1.into(),
+                exp: expires_at.unix_timestamp(),
+                iat: (expires_at - time::Duration::HOUR).unix_timestamp(),
+            },
+            &EncodingKey::from_secret(SECRET),
+        )
+        .unwrap();
+        let (mut parts, ()) = Request::builder()
+            .header("Authorization", format!("Bearer {token}"))
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        AuthClaims::from_request_parts(&mut parts, &state)
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn rejects_expired_token_without_leeway() {
+        assert!(
+            !validate_expired(time::Duration::seconds(5), Duration::ZERO).await
+        );
+    }
+
+    #[tokio::test]
+    async fn accepts_expired_token_within_leeway() {
+        assert!(
+            validate_expired(
+                time::Duration::seconds(5),
+                Duration::from_secs(30),
+            )
+            .await
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_expired_token_beyond_leeway() {
+        assert!(
+            !validate_expired(
+                time::Duration::seconds(60),
+                Duration::from_secs(30),
+            )
+            .await
+        );
+    }
+}
+
+#[cfg(test)]
+mod ticket_history_spec {
+    use serde_json::{json, Value};
+
+    use dubna_internship::api::ticket::FieldChange;
+
+    use super::field_changes;
+
+    fn snapshot(value: Value) -> serde_json::Map<String, Value> {
+        let Value::Object(snapshot) = value else {
+            panic!("snapshot must be an object");
+        };
+        snapshot
+    }
+
+    #[test]
+    fn reports_changed_fields_only() {
+        let before = snapshot(json!({"title": "A", "price": null}));
+        let after = snapshot(json!({"title": "A", "price": 100.0}));
+
+        assert_eq!(
+            field_changes(&before, &after),
+            [FieldChange {
+                field: "price".to_owned(),
+                before: json!(null),
+                after: json!(100.0),
+            }],
+        );
+    }
+
+    #[test]
+    fn reports_missing_fields_as_null() {
+        let before = snapshot(json!({}));
+        let after = snapshot(json!({"title": "A", "price": null}));
+
+        assert_eq!(
+            field_changes(&before, &after),
+            [FieldChange {
+                field: "title".to_owned(),
+                before: json!(null),
+                after: json!("A"),
+            }],
+        );
+    }
+}
+
+#[cfg(test)]
+mod edit_ticket_spec {
+    use std::{
+        env,
+        sync::{atomic::AtomicI64, Arc},
+        time::{Duration, Instant},
+    };
+
+    use axum::extract::{Path, State};
+    use jsonwebtoken::{DecodingKey, EncodingKey};
+    use time::OffsetDateTime;
+
+    use dubna_internship::{
+        api, blob,
+        db::{
+            self,
+            ticket::Status,
+            user::{PasswordHash, Role},
+        },
+    };
+
+    use super::{
+        edit_ticket, memory_storage::MemoryStorage, AppState, AuthClaims,
+        EditTicketBody, EditTicketError, EditTicketInput as Op, Json,
+    };
+
+    const INITIATOR: u128 = 1;
+    const PURCHASING_MANAGER: u128 = 2;
+    const ACCOUNTING_MANAGER: u128 = 3;
+    const ADMIN: u128 = 4;
+    const OTHER_INITIATOR: u128 = 5;
+
+    /// Returns a [`MemoryStorage`] holding a user of each role along with a
+    /// ticket of the [`INITIATOR`] in the provided `status`.
+    fn storage_with_ticket(status: Status) -> (MemoryStorage, db::Ticket) {
+        let storage = MemoryStorage::default();
+        for (id, role) in [
+            (INITIATOR, Role::Initiator),
+            (PURCHASING_MANAGER, Role::PurchasingManager),
+            (ACCOUNTING_MANAGER, Role::AccountingManager),
+            (ADMIN, Role::Admin),
+            (OTHER_INITIATOR, Role::Initiator),
+        ] {
+            storage.insert_user(db::User {
+                id: id.into(),
+                name: format!("User {id}"),
+                role,
+                login: format!("user{id}"),
+                password_hash: PasswordHash::new("password"),
+                password_changed_at: OffsetDateTime::UNIX_EPOCH,
+                email: None,
+            });
+        }
+
+        let confirmed = !matches!(
+            status,
+            Status::Requested | Status::Cancelled | Status::Denied,
+        );
+        let ticket = db::Ticket {
+            id: db::ticket::Id::new(),
+            title: "Ticket".to_owned(),
+            description: "Description".to_owned(),
+            status,
+            category: db::ticket::Category::Other,
+            count: 2,
+            received_count: 0,
+            price: confirmed.then_some(100.0),
+            payment_reference: None,
+            initiator: INITIATOR.into(),
+            purchasing_manager: confirmed.then_some(PURCHASING_MANAGER.into()),
+            accounting_manager: (status == Status::PaymentCompleted)
+                .then_some(ACCOUNTING_MANAGER.into()),
+            created_at: OffsetDateTime::now_utc(),
+        };
+        storage.insert_ticket(ticket.clone());
+
+        (storage, ticket)
+    }
+
+    async fn edit(
+        storage: &MemoryStorage,
+        user: u128,
+        ticket_id: db::ticket::Id,
+        op: Op,
+    ) -> Result<api::Ticket, EditTicketError> {
+        let state = AppState {
+            db_client: Arc::new(storage.clone()),
+            jwt_expiration_time: Duration::from_secs(3600),
+            jwt_leeway: Duration::ZERO,
+            jwt_decoding_key: DecodingKey::from_secret(b"secret"),
+            jwt_encoding_key: EncodingKey::from_secret(b"secret"),
+            jwt_clients: Arc::default(),
+            tokens_valid_after: Arc::new(AtomicI64::new(0)),
+            password_changed_at: Arc::default(),
+            user_cache_ttl: None,
+            users: Arc::default(),
+            started_at: OffsetDateTime::now_utc(),
+            started: Instant::now(),
+            blobs: Arc::new(blob::LocalDir::new(env::temp_dir())),
+            attachments: Arc::default(),
+            tickets: Arc::default(),
+        };
+        let claims = AuthClaims {
+            user_id: user.into(),
+            exp: 0,
+            iat: 0,
+        };
+        edit_ticket(State(state), claims, Path(ticket_id), EditTicketBody(op))
+            .await
+            .map(|Json(ticket)| ticket)
+    }
+
+    #[tokio::test]
+    async fn allows_only_permitted_operations() {
+        let cases = [
+            (Status::Requested, INITIATOR, Op::Cancel, true),
+            (Status::Requested, OTHER_INITIATOR, Op::Cancel, false),
+            (Status::Requested, ADMIN, Op::Cancel, false),
+            (Status::Confirmed, INITIATOR, Op::Cancel, false),
+            (
+                Status::Requested,
+                INITIATOR,
+                Op::EditTitle {
+                    title: "New".to_owned(),
+                },
+                true,
+            ),
+            (
+                Status::Requested,
+                OTHER_INITIATOR,
+                Op::EditTitle {
+                    title: "New".to_owned(),
+                },
+                false,
+            ),
+            (
+                Status::Confirmed,
+                INITIATOR,
+                Op::EditTitle {
+                    title: "New".to_owned(),
+                },
+                false,
+            ),
+            (
+                Status::Requested,
+                INITIATOR,
+                Op::EditCategory {
+                    category: db::ticket::Category::It,
+                },
+                true,
+            ),
+            (
+                Status::Denied,
+                INITIATOR,
+                Op::EditCategory {
+                    category: db::ticket::Category::It,
+                },
+                false,
+            ),
+            (
+                Status::PaymentCompleted,
+                ACCOUNTING_MANAGER,
+                Op::EditDescription {
+                    description: "New".to_owned(),
+                },
+                true,
+            ),
+            (
+                Status::Requested,
+                PURCHASING_MANAGER,
+                Op::Confirm { price: 10.0 },
+                true,
+            ),
+            (
+                Status::Requested,
+                ACCOUNTING_MANAGER,
+                Op::Confirm { price: 10.0 },
+                false,
+            ),
+            (
+                Status::Requested,
+                INITIATOR,
+                Op::Confirm { price: 10.0 },
+                false,
+            ),
+            (
+                Status::Cancelled,
+                PURCHASING_MANAGER,
+                Op::Confirm { price: 10.0 },
+                false,
+            ),
+            (
+                Status::Confirmed,
+                PURCHASING_MANAGER,
+                Op::AdjustPrice { price: 20.0 },
+                true,
+            ),
+            (
+                Status::Confirmed,
+                ADMIN,
+                Op::AdjustPrice { price: 20.0 },
+                true,
+            ),
+            (
+                Status::Confirmed,
+                INITIATOR,
+                Op::AdjustPrice { price: 20.0 },
+                false,
+            ),
+            (
+                Status::Requested,
+                PURCHASING_MANAGER,
+                Op::AdjustPrice { price: 20.0 },
+                false,
+            ),
+            (
+                Status::PaymentCompleted,
+                PURCHASING_MANAGER,
+                Op::AdjustPrice { price: 20.0 },
+                false,
+            ),
+            (
+                Status::Confirmed,
+                PURCHASING_MANAGER,
+                Op::AdjustPrice { price: 0.0 },
+                false,
+            ),
+            (Status::Requested, PURCHASING_MANAGER, Op::Deny, true),
+            (Status::Requested, ADMIN, Op::Deny, false),
+            (Status::Confirmed, PURCHASING_MANAGER, Op::Deny, false),
+            (
+                Status::Confirmed,
+                ACCOUNTING_MANAGER,
+                Op::MarkAsPaid(None),
+                true,
+            ),
+            (
+                Status::Confirmed,
+                PURCHASING_MANAGER,
+                Op::MarkAsPaid(None),
+                false,
+            ),
+            (
+                Status::Requested,
+                ACCOUNTING_MANAGER,
+                Op::MarkAsPaid(None),
+                false,
+            ),
+            (
+                Status::PaymentCompleted,
+                ACCOUNTING_MANAGER,
+                Op::MarkAsPaid(None),
+                false,
+            ),
+            (
+                Status::Confirmed,
+                ACCOUNTING_MANAGER,
+                Op::RecordReceipt { count: 1 },
+                true,
+            ),
+            (
+                Status::PaymentCompleted,
+                ACCOUNTING_MANAGER,
+                Op::RecordReceipt { count: 1 },
+                true,
+            ),
+            (
+                Status::Requested,
+                ACCOUNTING_MANAGER,
+                Op::RecordReceipt { count: 1 },
+                false,
+            ),
+            (
+                Status::Confirmed,
+                INITIATOR,
+                Op::RecordReceipt { count: 1 },
+                false,
+            ),
+            (
+                Status::Confirmed,
+                ADMIN,
+                Op::ReassignInitiator {
+                    user_id: OTHER_INITIATOR.into(),
+                },
+                true,
+            ),
+            (
+                Status::Requested,
+                INITIATOR,
+                Op::ReassignInitiator {
+                    user_id: OTHER_INITIATOR.into(),
+                },
+                false,
+            ),
+            (
+                Status::Requested,
+                ADMIN,
+                Op::ReassignInitiator {
+                    user_id: PURCHASING_MANAGER.into(),
+                },
+                false,
+            ),
+        ];
+
+        for (status, user, op, allowed) in cases {
+            let (storage, ticket) = storage_with_ticket(status);
+            let action = op.action();
+
+            let res = edit(&storage, user, ticket.id, op).await;
+            assert_eq!(
+                res.is_ok(),
+                allowed,
+                "`{action}` by user {user} on {status:?} ticket: {res:?}",
+            );
+
+            let events = storage.events();
+            if allowed {
+                assert_eq!(events.len(), 1, "`{action}` must be recorded");
+                assert_eq!(events[0].action, action);
+                assert_eq!(events[0].actor, api::user::Id::from(user));
+            } else {
+                assert!(events.is_empty(), "`{action}` must not be recorded");
+                let stored = storage.ticket(ticket.id).unwrap();
+                assert_eq!(stored.status, ticket.status);
+                assert_eq!(stored.initiator, ticket.initiator);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn assigns_purchasing_manager_on_confirmation() {
+        let (storage, ticket) = storage_with_ticket(Status::Requested);
+
+        let edited = edit(
+            &storage,
+            PURCHASING_MANAGER,
+            ticket.id,
+            Op::Confirm { price: 10.0 },
+        )
+        .await
+        .unwrap();
+        assert_eq!(edited.status, Status::Confirmed);
+        assert_eq!(edited.price, Some(10.0));
+        assert_eq!(
+            edited.purchasing_manager.map(|u| u.id),
+            Some(api::user::Id::from(PURCHASING_MANAGER)),
+        );
+
+        let stored = storage.ticket(ticket.id).unwrap();
+        assert_eq!(stored.status, Status::Confirmed);
+        assert_eq!(
+            stored.purchasing_manager,
+            Some(api::user::Id::from(PURCHASING_MANAGER))
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_receipt_exceeding_count() {
+        let (storage, ticket) = storage_with_ticket(Status::Confirmed);
+
+        let res = edit(
+            &storage,
+            ACCOUNTING_MANAGER,
+            ticket.id,
+            Op::RecordReceipt { count: 3 },
+        )
+        .await;
+        assert!(
+            matches!(res, Err(EditTicketError::TicketReceiptExceedsCount)),
+            "{res:?}",
+        );
+    }
+
+    #[tokio::test]
+    async fn reports_missing_ticket() {
+        let (storage, _) = storage_with_ticket(Status::Requested);
+
+        let res =
+            edit(&storage, INITIATOR, db::ticket::Id::new(), Op::Cancel).await;
+        assert!(
+            matches!(res, Err(EditTicketError::TicketNotFound)),
+            "{res:?}"
+        );
+    }
+
+    #[test]
+    fn lists_every_op() {
+        for op in Op::OPS {
+            let res = serde_json::from_value::<Op>(serde_json::json!({
+                "op": op,
+            }));
+            if let Err(e) = res {
+                assert!(
+                    !e.to_string().contains("unknown variant"),
+                    "`{op}`: {e}",
+                );
+            }
+        }
+
+        let ops = [
+            Op::EditTitle { title: "".into() },
+            Op::EditDescription {
+                description: "".into(),
+            },
+            Op::EditCategory {
+                category: db::ticket::Category::Other,
+            },
+            Op::Cancel,
+            Op::Confirm { price: 1.0 },
+            Op::AdjustPrice { price: 1.0 },
+            Op::Deny,
+            Op::MarkAsPaid(None),
+            Op::RecordReceipt { count: 1 },
+            Op::ReassignInitiator {
+                user_id: INITIATOR.into(),
+            },
+        ];
+        let actions = ops.iter().map(Op::action).collect::<Vec<_>>();
+        assert_eq!(actions, Op::OPS);
+    }
+}
+
+#[cfg(test)]
+mod parse_csv_spec {
+    use super::parse_csv;
+
+    fn record(line: usize, fields: &[&str]) -> (usize, Vec<String>) {
+        (line, fields.iter().map(|&f| f.to_owned()).collect())
+    }
+
+    #[test]
+    fn parses_plain_records() {
+        let csv = "title,count\r\nPaper,2\r\nPens,10";
+        assert_eq!(
+            parse_csv(csv),
+            Ok(vec![
+                record(1, &["title", "count"]),
+                record(2, &["Paper", "2"]),
+                record(3, &["Pens", "10"]),
+            ]),
+        );
+    }
+
+    #[test]
+    fn parses_quoted_fields() {
+        let csv = "\"A, \"\"quoted\"\"\",\"multi\nline\"\nnext,\"\"\n";
+        assert_eq!(
+            parse_csv(csv),
+            Ok(vec![
+                record(1, &["A, \"quoted\"", "multi\nline"]),
+                record(3, &["next", ""]),
+            ]),
+        );
+    }
+
+    #[test]
+    fn skips_empty_lines() {
+        let csv = "title\n\nPaper\n\n";
+        assert_eq!(
+            parse_csv(csv),
+            Ok(vec![record(1, &["title"]), record(3, &["Paper"])]),
+        );
+    }
+
+    #[test]
+    fn fails_on_open_quote() {
+        assert_eq!(parse_csv("title\nPaper\n\"Pens\nmore"), Err(3));
     }
 }
@@ -1,10 +1,14 @@
 pub mod common;
 
-use dubna_internship::api;
+use dubna_internship::api::{
+    self,
+    validation::{Code, FieldError},
+};
 
 #[tokio::test]
 async fn creates_valid_ticket() {
-    let ticket = common::Client::new()
+    let ticket = common::setup()
+        .await
         .auth("alice", "password")
         .await
         .add_ticket("Ticket 1", "Description 1", 1)
@@ -13,6 +17,7 @@ async fn creates_valid_ticket() {
     assert_eq!(ticket.title, "Ticket 1");
     assert_eq!(ticket.description, "Description 1");
     assert_eq!(ticket.status, api::ticket::Status::Requested);
+    assert_eq!(ticket.category, api::ticket::Category::Other);
     assert_eq!(ticket.count, 1);
     assert_eq!(ticket.price, None);
     assert_eq!(ticket.initiator.id, api::user::Id::from(1));
@@ -24,7 +29,8 @@ async fn creates_valid_ticket() {
 
 #[tokio::test]
 async fn cant_created_when_not_initiator() {
-    let status = common::Client::new()
+    let status = common::setup()
+        .await
         .auth("bob", "password")
         .await
         .add_ticket("Ticket 1", "Description 1", 1)
@@ -32,3 +38,69 @@ async fn cant_created_when_not_initiator() {
         .unwrap_err();
     assert_eq!(status, reqwest::StatusCode::BAD_REQUEST);
 }
+
+#[tokio::test]
+async fn creates_ticket_with_category() {
+    let ticket = common::setup()
+        .await
+        .auth("alice", "password")
+        .await
+        .add_ticket_with_category("Ticket 1", "Description 1", "IT", 1)
+        .await
+        .unwrap();
+    assert_eq!(ticket.category, api::ticket::Category::It);
+}
+
+#[tokio::test]
+async fn fails_when_unauthorized() {
+    let status = common::setup()
+        .await
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap_err();
+    assert_eq!(status, reqwest::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn reports_all_invalid_fields() {
+    let errors = common::setup()
+        .await
+        .auth("alice", "password")
+        .await
+        .add_invalid_ticket(" ", &"a".repeat(5001), 0)
+        .await;
+    assert_eq!(
+        errors.errors,
+        [
+            FieldError {
+                field: "title".into(),
+                code: Code::MustNotBeEmpty,
+            },
+            FieldError {
+                field: "description".into(),
+                code: Code::TooLong,
+            },
+            FieldError {
+                field: "count".into(),
+                code: Code::MustBePositive,
+            },
+        ],
+    );
+}
+
+#[tokio::test]
+async fn rejects_too_long_title() {
+    let errors = common::setup()
+        .await
+        .auth("alice", "password")
+        .await
+        .add_invalid_ticket(&"a".repeat(201), "Description 1", 1)
+        .await;
+    assert_eq!(
+        errors.errors,
+        [FieldError {
+            field: "title".into(),
+            code: Code::TooLong,
+        }],
+    );
+}
@@ -0,0 +1,54 @@
+use dubna_internship::api;
+
+fn ticket() -> api::Ticket {
+    api::Ticket {
+        id: api::ticket::Id::from(1),
+        title: "Ticket 1".into(),
+        description: "Description 1".into(),
+        status: api::ticket::Status::Requested,
+        category: api::ticket::Category::Other,
+        count: 1,
+        received_count: 0,
+        fully_received: false,
+        price: None,
+        payment_reference: None,
+        initiator: api::User {
+            id: api::user::Id::from(1),
+            name: "Alice".into(),
+            role: api::user::Role::Initiator,
+            email: None,
+        },
+        purchasing_manager: None,
+        accounting_manager: None,
+        attachments: None,
+    }
+}
+
+#[test]
+fn compares_tickets_by_value() {
+    assert_eq!(ticket(), ticket());
+    assert_ne!(
+        ticket(),
+        api::Ticket {
+            price: Some(100.0),
+            ..ticket()
+        },
+    );
+}
+
+#[test]
+fn compares_lists_by_value() {
+    let list = api::ticket::List {
+        tickets: vec![ticket()],
+        total_count: 1,
+        next_cursor: None,
+    };
+    assert_eq!(list, list.clone());
+    assert_ne!(
+        list,
+        api::ticket::List {
+            total_count: 2,
+            ..list.clone()
+        },
+    );
+}
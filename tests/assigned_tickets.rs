@@ -0,0 +1,95 @@
+pub mod common;
+
+use dubna_internship::api::ticket::RoleOnTicket;
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn lists_tickets_assigned_to_manager() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let bob = common::Client::new().auth("bob", "password").await;
+    let charlie = common::Client::new().auth("charlie", "password").await;
+
+    let mut ids = Vec::new();
+    for i in 0..3 {
+        let ticket = alice
+            .add_ticket(&format!("Ticket {i}"), "Description", 1)
+            .await
+            .unwrap();
+        ids.push(ticket.id);
+    }
+    bob.confirm_ticket(ids[0], 100).await.unwrap();
+    bob.confirm_ticket(ids[1], 100).await.unwrap();
+    charlie.mark_ticket_as_paid(ids[1]).await.unwrap();
+
+    let list = bob.get_assigned_tickets(0, 10).await.unwrap();
+    assert_eq!(list.total_count, 2);
+    let assigned = list
+        .tickets
+        .iter()
+        .map(|t| (t.ticket.id, t.role_on_ticket))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        assigned,
+        [
+            (ids[1], RoleOnTicket::PurchasingManager),
+            (ids[0], RoleOnTicket::PurchasingManager),
+        ],
+    );
+
+    let list = charlie.get_assigned_tickets(0, 10).await.unwrap();
+    assert_eq!(list.total_count, 1);
+    assert_eq!(list.tickets[0].ticket.id, ids[1]);
+    assert_eq!(
+        list.tickets[0].role_on_ticket,
+        RoleOnTicket::AccountingManager
+    );
+    assert_eq!(
+        list.tickets[0]
+            .ticket
+            .accounting_manager
+            .as_ref()
+            .map(|u| u.id),
+        Some(charlie.user().await.unwrap().id),
+    );
+
+    let list = alice.get_assigned_tickets(0, 10).await.unwrap();
+    assert_eq!(list.total_count, 0);
+    assert!(list.tickets.is_empty());
+}
+
+#[tokio::test]
+async fn pages_through_assigned_tickets() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let bob = common::Client::new().auth("bob", "password").await;
+
+    for i in 0..3 {
+        let ticket = alice
+            .add_ticket(&format!("Ticket {i}"), "Description", 1)
+            .await
+            .unwrap();
+        bob.confirm_ticket(ticket.id, 100).await.unwrap();
+    }
+
+    let list = bob.get_assigned_tickets(1, 1).await.unwrap();
+    assert_eq!(list.total_count, 3);
+    assert_eq!(list.tickets.len(), 1);
+    assert_eq!(list.tickets[0].ticket.title, "Ticket 1");
+}
+
+#[tokio::test]
+async fn requires_auth() {
+    let client = common::setup().await;
+
+    assert_eq!(
+        client.get_assigned_tickets(0, 10).await.unwrap_err(),
+        reqwest::StatusCode::UNAUTHORIZED,
+    );
+}
+
+#[tokio::test]
+async fn rejects_offset_out_of_range() {
+    let bob = common::setup().await.auth("bob", "password").await;
+
+    let status = bob.get_assigned_tickets(usize::MAX, 10).await.unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
@@ -0,0 +1,132 @@
+pub mod common;
+
+use std::slice;
+
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn uploads_and_downloads_attachment() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let attachment = alice
+        .upload_attachment(
+            ticket.id,
+            "quote.pdf",
+            "application/pdf",
+            b"%PDF-1.4".to_vec(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(attachment.file_name, "quote.pdf");
+    assert_eq!(attachment.content_type, "application/pdf");
+    assert_eq!(attachment.size, 8);
+
+    let attachments = alice.get_attachments(ticket.id).await.unwrap();
+    assert_eq!(attachments, slice::from_ref(&attachment));
+
+    let (content_type, data) = alice
+        .download_attachment(ticket.id, attachment.id)
+        .await
+        .unwrap();
+    assert_eq!(content_type, "application/pdf");
+    assert_eq!(data, b"%PDF-1.4");
+
+    let ticket = alice.get_ticket(ticket.id).await.unwrap();
+    assert_eq!(ticket.attachments, Some(vec![attachment]));
+}
+
+#[tokio::test]
+async fn lets_awaited_manager_download_attachment() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+    let attachment = alice
+        .upload_attachment(ticket.id, "spec.txt", "text/plain", b"spec".into())
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    let (_, data) = bob
+        .download_attachment(ticket.id, attachment.id)
+        .await
+        .unwrap();
+    assert_eq!(data, b"spec");
+}
+
+#[tokio::test]
+async fn hides_attachments_from_non_participants() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+    let attachment = alice
+        .upload_attachment(ticket.id, "spec.txt", "text/plain", b"spec".into())
+        .await
+        .unwrap();
+
+    let eve = common::Client::new().auth("eve", "password").await;
+    let status = eve
+        .upload_attachment(ticket.id, "spec.txt", "text/plain", b"spec".into())
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let status = eve.get_attachments(ticket.id).await.unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let status = eve
+        .download_attachment(ticket.id, attachment.id)
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let ticket = eve.get_ticket(ticket.id).await.unwrap();
+    assert_eq!(ticket.attachments, None);
+}
+
+#[tokio::test]
+async fn rejects_disallowed_content_type() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let status = alice
+        .upload_attachment(
+            ticket.id,
+            "script.sh",
+            "application/x-sh",
+            b"#!/bin/sh".into(),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert!(alice.get_attachments(ticket.id).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn rejects_too_large_attachment() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let status = alice
+        .upload_attachment(
+            ticket.id,
+            "huge.txt",
+            "text/plain",
+            vec![b'a'; 10 * 1024 * 1024 + 1],
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(alice.get_attachments(ticket.id).await.unwrap().is_empty());
+}
@@ -0,0 +1,139 @@
+pub mod common;
+
+use dubna_internship::{api, db};
+use reqwest::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn records_ticket_mutations() {
+    let client = common::setup().await;
+    let alice = client.auth("alice", "password").await;
+    let bob = common::Client::new().auth("bob", "password").await;
+
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let events = common::db()
+        .await
+        .get_events_for_ticket(ticket.id)
+        .await
+        .unwrap();
+    match events.as_slice() {
+        [created, confirmed] => {
+            assert_eq!(created.actor, api::user::Id::from(1));
+            assert_eq!(created.entity, db::audit::Entity::Ticket(ticket.id));
+            assert_eq!(created.action, "create");
+            assert_eq!(created.payload["title"], "Ticket 1");
+
+            assert_eq!(confirmed.actor, api::user::Id::from(2));
+            assert_eq!(confirmed.action, "confirm");
+            assert_eq!(
+                confirmed.payload,
+                json!({"op": "confirm", "data": {"price": 100.0}}),
+            );
+        }
+        found => panic!("expected two events, found {found:?}"),
+    }
+}
+
+#[tokio::test]
+async fn doesnt_record_rejected_mutations() {
+    let alice = common::setup().await.auth("alice", "password").await;
+
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+    let status = alice.confirm_ticket(ticket.id, 100).await.unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let events = common::db()
+        .await
+        .get_events_for_ticket(ticket.id)
+        .await
+        .unwrap();
+    assert_eq!(events.len(), 1);
+}
+
+#[tokio::test]
+async fn reports_fields_changed_by_every_event() {
+    let client = common::setup().await;
+    let alice = client.auth("alice", "password").await;
+    let bob = common::Client::new().auth("bob", "password").await;
+
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let history = alice.get_ticket_history(ticket.id, true).await.unwrap();
+    match history.as_slice() {
+        [created, confirmed] => {
+            assert_eq!(created.action, "create");
+            let created = created.changes.as_ref().unwrap();
+            assert!(created.contains(&api::ticket::FieldChange {
+                field: "title".to_owned(),
+                before: json!(null),
+                after: json!("Ticket 1"),
+            }));
+            assert!(created.iter().all(|c| c.before.is_null()));
+
+            assert_eq!(confirmed.action, "confirm");
+            assert_eq!(confirmed.actor, api::user::Id::from(2));
+            let mut changed = confirmed
+                .changes
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|c| (c.field.as_str(), &c.before, &c.after))
+                .collect::<Vec<_>>();
+            changed.sort_by_key(|(field, ..)| *field);
+            assert_eq!(
+                changed,
+                [
+                    ("price", &json!(null), &json!(100.0)),
+                    (
+                        "purchasingManager",
+                        &json!(null),
+                        &json!(api::user::Id::from(2)),
+                    ),
+                    ("status", &json!("REQUESTED"), &json!("CONFIRMED")),
+                ],
+            );
+        }
+        found => panic!("expected two events, found {found:?}"),
+    }
+}
+
+#[tokio::test]
+async fn omits_changes_unless_requested() {
+    let alice = common::setup().await.auth("alice", "password").await;
+
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let history = alice.get_ticket_history(ticket.id, false).await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].changes, None);
+}
+
+#[tokio::test]
+async fn forbids_history_to_non_participants() {
+    let client = common::setup().await;
+    let alice = client.auth("alice", "password").await;
+    let eve = common::Client::new().auth("eve", "password").await;
+
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let status = eve.get_ticket_history(ticket.id, true).await.unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
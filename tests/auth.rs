@@ -1,7 +1,102 @@
 pub mod common;
 
+use std::time::Duration;
+
+use dubna_internship::api;
+use reqwest::StatusCode;
+use tokio::time;
+
 #[tokio::test]
 async fn retreieves_access_token() {
-    let client = common::Client::new().auth("alice", "password").await;
+    let client = common::setup().await.auth("alice", "password").await;
     assert!(client.auth_token.is_some());
 }
+
+#[tokio::test]
+async fn reports_token_lifetime() {
+    let mut client = common::setup().await;
+
+    let resp = client.sign_in("alice", "password").await;
+    assert!(!resp.token.is_empty());
+    assert!(resp.expires_at > resp.issued_at, "{resp:?}");
+
+    client.auth_token = Some(resp.token);
+    client.user().await.unwrap();
+}
+
+#[tokio::test]
+async fn rejects_tokens_issued_before_invalidation() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    alice.user().await.unwrap();
+
+    // `iat` has a one-second precision.
+    time::sleep(Duration::from_secs(1)).await;
+
+    let dave = common::Client::new().auth("dave", "password").await;
+    dave.invalidate_tokens().await.unwrap();
+
+    let status = alice.user().await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let alice = common::Client::new().auth("alice", "password").await;
+    alice.user().await.unwrap();
+}
+
+#[tokio::test]
+async fn cant_invalidate_tokens_when_not_admin() {
+    let status = common::setup()
+        .await
+        .auth("alice", "password")
+        .await
+        .invalidate_tokens()
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn rejects_expired_tokens() {
+    let alice = common::setup()
+        .await
+        .auth_with_expiry("alice", "password", Duration::from_millis(1))
+        .await;
+
+    // `exp` has a one-second precision.
+    time::sleep(Duration::from_secs(1)).await;
+
+    let status = alice.user().await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn explains_wrong_credentials() {
+    let client = common::setup().await;
+
+    let (status, wrong_password) =
+        client.sign_in_failure("alice", "wrong").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(wrong_password.code, "WRONG_CREDENTIALS");
+    assert!(!wrong_password.message.is_empty());
+
+    // Unknown logins are indistinguishable from the wrong passwords.
+    let (status, unknown_login) =
+        client.sign_in_failure("mallory", "wrong").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(unknown_login, wrong_password);
+}
+
+#[tokio::test]
+async fn explains_invalid_tokens() {
+    let _client = common::setup().await;
+
+    let res = reqwest::Client::new()
+        .get(common::url("/user"))
+        .bearer_auth("invalid")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let failure = res.json::<api::user::AuthFailure>().await.unwrap();
+    assert_eq!(failure.code, "INVALID_TOKEN");
+    assert!(!failure.message.is_empty());
+}
@@ -0,0 +1,44 @@
+pub mod common;
+
+use reqwest::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn serves_api_under_base_path() {
+    const ADDR: &str = "127.0.0.1:3006";
+
+    let _client = common::setup().await;
+    let _server =
+        common::Server::spawn(ADDR, "[http]\nbase_path = \"/api\"", &[]).await;
+    let client = reqwest::Client::new();
+
+    let resp = client
+        .post(format!("http://{ADDR}/api/auth"))
+        .json(&json!({"login": "alice", "password": "password"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = client
+        .post(format!("http://{ADDR}/auth"))
+        .json(&json!({"login": "alice", "password": "password"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn serves_probes_at_root() {
+    const ADDR: &str = "127.0.0.1:3007";
+
+    let _client = common::setup().await;
+    let _server =
+        common::Server::spawn(ADDR, "[http]\nbase_path = \"/api\"", &[]).await;
+
+    for path in ["/healthz", "/readyz", "/version"] {
+        let resp = reqwest::get(format!("http://{ADDR}{path}")).await.unwrap();
+        assert_eq!(resp.status(), StatusCode::OK, "{path}");
+    }
+}
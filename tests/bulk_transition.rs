@@ -0,0 +1,157 @@
+pub mod common;
+
+use dubna_internship::api::{
+    self,
+    ticket::{BulkOp, BulkTransitionError, BulkTransitionResult},
+};
+use reqwest::StatusCode;
+
+fn ok(id: api::ticket::Id) -> BulkTransitionResult {
+    BulkTransitionResult {
+        id,
+        ok: true,
+        error: None,
+    }
+}
+
+fn failed(
+    id: api::ticket::Id,
+    error: BulkTransitionError,
+) -> BulkTransitionResult {
+    BulkTransitionResult {
+        id,
+        ok: false,
+        error: Some(error),
+    }
+}
+
+/// Adds `count` tickets as the `client`, returning their IDs.
+async fn add_tickets(
+    client: &common::Client,
+    count: usize,
+) -> Vec<api::ticket::Id> {
+    let mut ids = Vec::new();
+    for i in 0..count {
+        let ticket = client
+            .add_ticket(&format!("Ticket {i}"), "Description", 1)
+            .await
+            .unwrap();
+        ids.push(ticket.id);
+    }
+    ids
+}
+
+#[tokio::test]
+async fn confirms_tickets_in_bulk() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let bob = common::Client::new().auth("bob", "password").await;
+    let ids = add_tickets(&alice, 3).await;
+
+    let (status, results) = bob
+        .bulk_transition_tickets(&ids, BulkOp::Confirm, Some(100.0))
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(results, ids.iter().copied().map(ok).collect::<Vec<_>>());
+
+    for id in ids {
+        let ticket = alice.get_ticket(id).await.unwrap();
+        assert_eq!(ticket.status, api::ticket::Status::Confirmed);
+        assert_eq!(ticket.price, Some(100.0));
+        assert_eq!(
+            ticket.purchasing_manager.map(|u| u.id),
+            Some(api::user::Id::from(2)),
+        );
+
+        let history = alice.get_ticket_history(id, false).await.unwrap();
+        let actions = history.iter().map(|e| e.action.as_str());
+        assert_eq!(actions.collect::<Vec<_>>(), ["create", "confirm"]);
+    }
+}
+
+#[tokio::test]
+async fn denies_tickets_in_bulk() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let bob = common::Client::new().auth("bob", "password").await;
+    let ids = add_tickets(&alice, 2).await;
+
+    let (status, results) = bob
+        .bulk_transition_tickets(&ids, BulkOp::Deny, None)
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(results, ids.iter().copied().map(ok).collect::<Vec<_>>());
+
+    for id in ids {
+        let ticket = alice.get_ticket(id).await.unwrap();
+        assert_eq!(ticket.status, api::ticket::Status::Denied);
+        assert_eq!(ticket.price, None);
+    }
+}
+
+#[tokio::test]
+async fn reports_failures_per_ticket() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let bob = common::Client::new().auth("bob", "password").await;
+    let ids = add_tickets(&alice, 2).await;
+    alice.cancel_ticket(ids[1]).await.unwrap();
+    let missing = api::ticket::Id::new();
+
+    let (status, results) = bob
+        .bulk_transition_tickets(
+            &[ids[0], ids[1], missing],
+            BulkOp::Confirm,
+            Some(100.0),
+        )
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::MULTI_STATUS);
+    assert_eq!(
+        results,
+        [
+            ok(ids[0]),
+            failed(ids[1], BulkTransitionError::CannotBeTransitioned),
+            failed(missing, BulkTransitionError::NotFound),
+        ],
+    );
+
+    let ticket = alice.get_ticket(ids[0]).await.unwrap();
+    assert_eq!(ticket.status, api::ticket::Status::Confirmed);
+    let ticket = alice.get_ticket(ids[1]).await.unwrap();
+    assert_eq!(ticket.status, api::ticket::Status::Cancelled);
+}
+
+#[tokio::test]
+async fn cant_transition_tickets_when_not_purchasing_manager() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ids = add_tickets(&alice, 2).await;
+
+    let (status, results) = alice
+        .bulk_transition_tickets(&ids, BulkOp::Deny, None)
+        .await
+        .unwrap();
+    assert_eq!(status, StatusCode::MULTI_STATUS);
+    assert!(results
+        .iter()
+        .all(|r| r.error == Some(BulkTransitionError::CannotBeTransitioned)));
+
+    for id in ids {
+        let ticket = alice.get_ticket(id).await.unwrap();
+        assert_eq!(ticket.status, api::ticket::Status::Requested);
+    }
+}
+
+#[tokio::test]
+async fn requires_positive_price_to_confirm() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let bob = common::Client::new().auth("bob", "password").await;
+    let ids = add_tickets(&alice, 1).await;
+
+    for price in [None, Some(0.0)] {
+        let status = bob
+            .bulk_transition_tickets(&ids, BulkOp::Confirm, price)
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{price:?}");
+    }
+}
@@ -0,0 +1,65 @@
+pub mod common;
+
+use dubna_internship::db::{self, ticket::TicketBuilder};
+use time::{Duration, OffsetDateTime};
+
+fn ticket() -> db::Ticket {
+    TicketBuilder::new(db::user::Id::from(1))
+        .title("Ticket 1")
+        .description("Description 1")
+        .build()
+}
+
+#[tokio::test]
+async fn pages_through_comments() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let ticket = ticket();
+    db.write_ticket(&ticket).await.unwrap();
+
+    let created_at = OffsetDateTime::now_utc();
+    for i in 0..5 {
+        db.insert_comment(&db::Comment {
+            id: db::comment::Id::new(),
+            ticket_id: ticket.id,
+            author: db::user::Id::from(2),
+            text: format!("Comment {i}"),
+            created_at: created_at + Duration::seconds(i),
+        })
+        .await
+        .unwrap();
+    }
+
+    assert_eq!(db.count_comments_for_ticket(ticket.id).await.unwrap(), 5);
+
+    let page = db.get_comments_for_ticket(ticket.id, 1, 2).await.unwrap();
+    let texts = page.iter().map(|c| c.text.as_str()).collect::<Vec<_>>();
+    assert_eq!(texts, ["Comment 1", "Comment 2"]);
+    assert!(page.iter().all(|c| c.ticket_id == ticket.id));
+    assert!(page.iter().all(|c| c.author == db::user::Id::from(2)));
+
+    let page = db.get_comments_for_ticket(ticket.id, 5, 2).await.unwrap();
+    assert!(page.is_empty());
+}
+
+#[tokio::test]
+async fn cant_comment_unknown_ticket() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let err = db
+        .insert_comment(&db::Comment {
+            id: db::comment::Id::new(),
+            ticket_id: db::ticket::Id::new(),
+            author: db::user::Id::from(1),
+            text: "Comment".into(),
+            created_at: OffsetDateTime::now_utc(),
+        })
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(err, db::comment::InsertCommentError::TicketNotFound),
+        "expected `TicketNotFound`, found {err:?}",
+    );
+}
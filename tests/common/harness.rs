@@ -0,0 +1,189 @@
+//! Disposable environment the tests of a single test binary run against: a
+//! Postgres container and a server process in front of it.
+//!
+//! Both are started on the first use and torn down once the test binary
+//! exits, so `cargo test` needs nothing but Docker.
+
+use std::{
+    env, fs,
+    net::{TcpListener, TcpStream},
+    path::Path,
+    process::{self, Child, Command, Stdio},
+    sync::OnceLock,
+    thread,
+    time::Duration,
+};
+
+use dubna_internship::{config, db};
+
+/// Image of the Postgres the tests run against, the same as the one in the
+/// `docker-compose.yml`.
+const POSTGRES_IMAGE: &str = "postgres:12";
+
+static HARNESS: OnceLock<Harness> = OnceLock::new();
+
+/// Running environment of the tests.
+pub struct Harness {
+    /// Connection string of a database role privileged to truncate tables.
+    pub database_url: String,
+
+    /// URL the server serves the API on, without a trailing slash.
+    pub base_url: String,
+
+    /// Process of the server, killed by the reaper once the tests are done.
+    _server: Child,
+}
+
+/// Returns the [`Harness`] of this test binary, starting it if needed.
+///
+/// The database can be provided with the `TEST_DATABASE_URL` environment
+/// variable instead of starting a container.
+pub fn get() -> &'static Harness {
+    HARNESS.get_or_init(|| {
+        let mut reaped = Vec::new();
+
+        let database_url = match env::var("TEST_DATABASE_URL") {
+            Ok(url) => url,
+            Err(_) => {
+                let (id, url) = start_postgres();
+                reaped.push(format!("docker rm -f {id}"));
+                url
+            }
+        };
+        migrate(&database_url);
+
+        let dir = env::temp_dir()
+            .join(format!("dubna-internship-{}-harness", process::id()));
+        let (server, base_url) = start_server(&dir, &database_url);
+        reaped.push(format!("kill {}", server.id()));
+        reaped.push(format!("rm -rf '{}'", dir.display()));
+
+        reap_on_exit(&reaped);
+
+        Harness {
+            database_url,
+            base_url,
+            _server: server,
+        }
+    })
+}
+
+/// Starts a Postgres container, returning its ID and the connection string
+/// of its superuser.
+fn start_postgres() -> (String, String) {
+    let id = docker(&[
+        "run",
+        "--rm",
+        "--detach",
+        "--env",
+        "POSTGRES_PASSWORD=postgres",
+        "--publish",
+        "127.0.0.1::5432",
+        POSTGRES_IMAGE,
+    ]);
+    let addr = docker(&["port", &id, "5432/tcp"]);
+    let url = format!("postgres://postgres:postgres@{addr}/postgres");
+    (id, url)
+}
+
+/// Runs `docker` with the `args`, returning the first line of its output.
+fn docker(args: &[&str]) -> String {
+    let output = Command::new("docker")
+        .args(args)
+        .stderr(Stdio::inherit())
+        .output()
+        .expect("failed to run `docker`");
+    assert!(
+        output.status.success(),
+        "`docker {}` failed",
+        args.join(" ")
+    );
+    String::from_utf8(output.stdout)
+        .expect("`docker` output isn't UTF-8")
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .to_owned()
+}
+
+/// Applies the pending migrations to the database at the `url` and resets
+/// it to its fixture state, waiting for it to accept connections first.
+///
+/// The database is set up on a runtime of its own, as the connections can't
+/// outlive the runtime of the test that happens to start the [`Harness`].
+fn migrate(url: &str) {
+    let config = config::Db {
+        max_connect_retries: 300,
+        connect_retry_delay: Duration::from_millis(100),
+        ..super::db_config(url.to_owned())
+    };
+    thread::scope(|s| {
+        s.spawn(|| {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to build a runtime")
+                .block_on(async {
+                    let client = db::connect(config)
+                        .await
+                        .expect("failed to connect to the database");
+                    db::migrations::run_pending(
+                        &client,
+                        &Path::new(env!("CARGO_MANIFEST_DIR"))
+                            .join("migrations"),
+                    )
+                    .await
+                    .expect("failed to migrate the database");
+                    db::fixtures::reset(&client)
+                        .await
+                        .expect("failed to reset the database");
+                })
+        });
+    });
+}
+
+/// Spawns the server in the `dir` against the database at the
+/// `database_url` on a free port, returning its process and base URL once it
+/// accepts connections.
+fn start_server(dir: &Path, database_url: &str) -> (Child, String) {
+    let addr = TcpListener::bind("127.0.0.1:0")
+        .and_then(|l| l.local_addr())
+        .expect("failed to pick a free port")
+        .to_string();
+
+    fs::create_dir_all(dir).expect("failed to create a directory");
+    super::write_config(dir, database_url, &addr, "");
+
+    let server = Command::new(env!("CARGO_BIN_EXE_dubna-internship"))
+        .current_dir(dir)
+        .stdout(Stdio::null())
+        .spawn()
+        .expect("failed to spawn the server");
+
+    while TcpStream::connect(&addr).is_err() {
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    (server, format!("http://{addr}"))
+}
+
+/// Spawns a detached shell running the `commands` once this process exits,
+/// as statics are never dropped.
+///
+/// The reaper is backgrounded by an intermediate shell, which is waited on,
+/// so it doesn't remain a child of this process.
+fn reap_on_exit(commands: &[String]) {
+    let script = format!(
+        "(while kill -0 {} 2>/dev/null; do sleep 1; done; {}) &",
+        process::id(),
+        commands.join("; "),
+    );
+    let status = Command::new("sh")
+        .args(["-c", &script])
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .expect("failed to spawn a reaper");
+    assert!(status.success(), "failed to spawn a reaper");
+}
@@ -1,50 +1,932 @@
-use constcat::concat;
-use dubna_internship::api;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::Arc,
+    time::Duration,
+};
+
+use dubna_internship::{
+    api::{self, ticket::BulkTransitionResult},
+    config, db, Config,
+};
+use jsonwebtoken::{EncodingKey, Header};
 use reqwest::StatusCode;
 use serde_json::json;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tokio::{
+    net::TcpStream,
+    sync::{Mutex, MutexGuard},
+};
+
+mod harness;
+
+/// Lock serializing the tests of a single test binary, so they don't observe
+/// each other's data.
+static DATABASE_LOCK: Mutex<()> = Mutex::const_new(());
+
+/// Resets the database to its fixture state and returns a fresh [`Client`].
+///
+/// The database stays exclusively owned by the calling test until the
+/// returned [`Client`] is dropped.
+pub async fn setup() -> Client {
+    let guard = DATABASE_LOCK.lock().await;
+
+    db::fixtures::reset(&db().await)
+        .await
+        .expect("failed to reset the database");
+
+    Client {
+        _guard: Some(Arc::new(guard)),
+        ..Client::new()
+    }
+}
+
+/// Connects to the test database directly, bypassing the HTTP API.
+pub async fn db() -> db::Client {
+    db::connect(db_config(database_url()))
+        .await
+        .expect("failed to connect to the database")
+}
+
+/// Connects to the test database directly, resolving unqualified table names
+/// in the given `schema` before the `public` one.
+pub async fn db_in_schema(schema: &str) -> db::Client {
+    let url = format!(
+        "{}?options=-c%20search_path%3D{schema}%2Cpublic",
+        database_url(),
+    );
+    db::connect(db_config(url))
+        .await
+        .expect("failed to connect to the database")
+}
+
+/// Connects to the test database directly, with at most `size` connections,
+/// waiting for one no longer than the `acquire_timeout`, if any.
+pub async fn db_with_pool(
+    size: usize,
+    acquire_timeout: Option<Duration>,
+) -> db::Client {
+    db::connect(config::Db {
+        pool_size: Some(size),
+        acquire_timeout,
+        ..db_config(database_url())
+    })
+    .await
+    .expect("failed to connect to the database")
+}
+
+/// Connects to the test database directly, naming the connections with the
+/// `application_name` and setting the session `options` on them.
+pub async fn db_with_session(
+    application_name: &str,
+    options: BTreeMap<String, String>,
+) -> db::Client {
+    db::connect(config::Db {
+        application_name: Some(application_name.to_owned()),
+        options,
+        ..db_config(database_url())
+    })
+    .await
+    .expect("failed to connect to the database")
+}
+
+/// Configuration of a connection to the database at the `url` failing fast,
+/// rather than retrying anything.
+fn db_config(url: String) -> config::Db {
+    config::Db {
+        url,
+        read_url: None,
+        max_retries: 0,
+        statement_timeout: None,
+        application_name: None,
+        options: BTreeMap::new(),
+        require_ssl: false,
+        max_connect_retries: 0,
+        connect_retry_delay: Duration::ZERO,
+        slow_query_threshold: Duration::from_secs(1),
+        pool_size: None,
+        acquire_timeout: None,
+        slow_acquire_threshold: Duration::from_secs(1),
+        write_batch_size: NonZeroUsize::new(1000).unwrap(),
+        skip_schema_check: false,
+    }
+}
+
+/// Server process spawned from the built binary, apart from the one the tests
+/// run against, so it can be configured and shut down freely.
+///
+/// The process is killed once this [`Server`] is dropped.
+pub struct Server {
+    process: Child,
+    dir: PathBuf,
+    addr: String,
+}
+
+impl Server {
+    /// Spawns a server listening on the `addr`, with the `extra` settings
+    /// appended to its `config.toml`, and waits until it accepts
+    /// connections.
+    ///
+    /// The server is run in its own working directory, where the `files`
+    /// are written to beforehand.
+    pub async fn spawn(
+        addr: &str,
+        extra: &str,
+        files: &[(&str, &[u8])],
+    ) -> Self {
+        let dir = env::temp_dir().join(format!(
+            "dubna-internship-{}-{}",
+            std::process::id(),
+            addr.replace([':', '.'], "-"),
+        ));
+        fs::create_dir_all(&dir).expect("failed to create a directory");
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents)
+                .expect("failed to write a file");
+        }
+        write_config(&dir, &database_url(), addr, extra);
+
+        let process = Command::new(env!("CARGO_BIN_EXE_dubna-internship"))
+            .current_dir(&dir)
+            .stdout(Stdio::null())
+            .spawn()
+            .expect("failed to spawn the server");
+
+        while TcpStream::connect(addr).await.is_err() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+
+        Self {
+            process,
+            dir,
+            addr: addr.to_owned(),
+        }
+    }
+
+    /// Rewrites the `config.toml` of the server with the `extra` settings
+    /// instead of the ones it was spawned with, and sends `SIGHUP` to the
+    /// server, so it reloads them.
+    pub fn reload(&self, extra: &str) {
+        write_config(&self.dir, &database_url(), &self.addr, extra);
+        self.signal("HUP");
+    }
+
+    /// Sends `SIGTERM` to the server.
+    pub fn terminate(&self) {
+        self.signal("TERM");
+    }
+
+    fn signal(&self, signal: &str) {
+        let status = Command::new("kill")
+            .args([&format!("-{signal}"), &self.process.id().to_string()])
+            .status()
+            .expect("failed to run `kill`");
+        assert!(status.success(), "failed to send `SIG{signal}`");
+    }
+
+    /// Waits for the server to exit.
+    pub fn wait(&mut self) -> ExitStatus {
+        self.process.wait().expect("failed to wait for the server")
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        _ = self.process.kill();
+        _ = self.process.wait();
+        _ = fs::remove_dir_all(&self.dir);
+    }
+}
 
-const BASE_URL: &str = "http://localhost:3000";
+/// Writes the `config.toml` of the server listening on the `addr` against
+/// the database at the `database_url` to the `dir`, with the `extra`
+/// settings appended.
+fn write_config(dir: &Path, database_url: &str, addr: &str, extra: &str) {
+    fs::write(
+        dir.join("config.toml"),
+        format!(
+            "\
+            [db]\n\
+            url = \"{database_url}\"\n\
+            [jwt]\n\
+            secret = \"my_secret_key\"\n\
+            expiration_time = \"1h\"\n\
+            [http.server]\n\
+            addr = \"{addr}\"\n\
+            [http.cors]\n\
+            {extra}\n",
+        ),
+    )
+    .expect("failed to write the config");
+}
+
+/// Connection string of a database role privileged to truncate tables.
+pub fn database_url() -> String {
+    harness::get().database_url.clone()
+}
 
+/// url of the `path` on the server the tests run against.
+pub fn url(path: &str) -> String {
+    format!("{}{path}", harness::get().base_url)
+}
+
+#[derive(Default)]
 pub struct Client {
     inner: reqwest::Client,
     pub auth_token: Option<String>,
+    _guard: Option<Arc<MutexGuard<'static, ()>>>,
 }
 
-impl Client {
-    pub fn new() -> Self {
-        Self {
-            inner: reqwest::Client::new(),
-            auth_token: None,
+impl Client {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn healthz(&self) -> StatusCode {
+        let url = url("/healthz");
+
+        self.inner
+            .get(url)
+            .send()
+            .await
+            .expect("failed to send a request")
+            .status()
+    }
+
+    pub async fn readyz(&self) -> StatusCode {
+        let url = url("/readyz");
+
+        self.inner
+            .get(url)
+            .send()
+            .await
+            .expect("failed to send a request")
+            .status()
+    }
+
+    pub async fn version(&self) -> api::Version {
+        let url = url("/version");
+
+        self.inner
+            .get(url)
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .expect("failed to get the version")
+            .json()
+            .await
+            .expect("failed to get a response")
+    }
+
+    pub async fn auth(mut self, login: &str, password: &str) -> Self {
+        self.auth_token = Some(self.sign_in(login, password).await.token);
+        self
+    }
+
+    /// Signs in as the user, returning the issued token along with its
+    /// lifetime, without authenticating this [`Client`] with it.
+    pub async fn sign_in(
+        &self,
+        login: &str,
+        password: &str,
+    ) -> api::user::AuthResponse {
+        let url = url("/auth");
+
+        self.inner
+            .post(url)
+            .json(&json!({
+                "login": login,
+                "password": password,
+            }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .expect("wrong status code")
+            .json()
+            .await
+            .expect("failed to get a response")
+    }
+
+    /// Signs in with the credentials expected to be rejected, returning the
+    /// status and the explanation of the rejection.
+    pub async fn sign_in_failure(
+        &self,
+        login: &str,
+        password: &str,
+    ) -> (StatusCode, api::user::AuthFailure) {
+        let url = url("/auth");
+
+        let res = self
+            .inner
+            .post(url)
+            .json(&json!({
+                "login": login,
+                "password": password,
+            }))
+            .send()
+            .await
+            .expect("failed to send a request");
+        let status = res.status();
+        assert!(!status.is_success(), "signed in with {status}");
+        let failure = res.json().await.expect("failed to get a response");
+        (status, failure)
+    }
+
+    /// Authenticates as the user with a token expiring after `expiry`.
+    ///
+    /// `POST /auth` always issues tokens for the configured expiration time,
+    /// so the token is signed directly with the secret from `config.toml`
+    /// the server is run with.
+    pub async fn auth_with_expiry(
+        mut self,
+        login: &str,
+        password: &str,
+        expiry: Duration,
+    ) -> Self {
+        let config =
+            toml::from_str::<Config>(include_str!("../../config.toml"))
+                .expect("failed to parse config");
+
+        let password_hash = api::user::PasswordHash::new(password);
+        let user = db()
+            .await
+            .get_user_by_login(login)
+            .await
+            .expect("failed to get a user")
+            .filter(|u| u.password_hash == password_hash)
+            .expect("wrong login or password");
+
+        let issued_at = OffsetDateTime::now_utc();
+        self.auth_token = Some(
+            jsonwebtoken::encode(
+                &Header::default(),
+                &json!({
+                    "user_id": user.id,
+                    "exp": (issued_at + expiry).unix_timestamp(),
+                    "iat": issued_at.unix_timestamp(),
+                }),
+                &EncodingKey::from_secret(config.jwt.secret.as_bytes()),
+            )
+            .expect("failed to sign a token"),
+        );
+
+        self
+    }
+
+    pub async fn invalidate_tokens(&self) -> Result<(), StatusCode> {
+        let url = url("/auth/invalidate");
+
+        let mut req = self.inner.post(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        req.send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?;
+        Ok(())
+    }
+
+    pub async fn change_password(
+        &self,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), StatusCode> {
+        let url = url("/user/password");
+
+        let mut req = self.inner.post(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        req.json(&json!({
+            "currentPassword": current_password,
+            "newPassword": new_password,
+        }))
+        .send()
+        .await
+        .expect("failed to send a request")
+        .error_for_status()
+        .map_err(|e| e.status().expect("status error"))?;
+        Ok(())
+    }
+
+    pub async fn user(&self) -> Result<api::User, StatusCode> {
+        let url = url("/user");
+
+        let mut req = self.inner.get(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::User>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn update_profile(
+        &self,
+        name: &str,
+    ) -> Result<api::User, StatusCode> {
+        let url = url("/me");
+
+        let mut req = self.inner.patch(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .json(&json!({ "name": name }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::User>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn update_invalid_profile(
+        &self,
+        name: &str,
+    ) -> api::validation::Errors {
+        let url = url("/me");
+
+        let mut req = self.inner.patch(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        let res = req
+            .json(&json!({ "name": name }))
+            .send()
+            .await
+            .expect("failed to send a request");
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        res.json::<api::validation::Errors>()
+            .await
+            .expect("failed to get a response")
+    }
+
+    pub async fn set_email(
+        &self,
+        email: &str,
+        current_password: &str,
+    ) -> Result<api::User, StatusCode> {
+        let url = url("/me/email");
+
+        let mut req = self.inner.post(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .json(&json!({
+                "email": email,
+                "currentPassword": current_password,
+            }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::User>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn set_invalid_email(
+        &self,
+        email: &str,
+    ) -> api::validation::Errors {
+        let url = url("/me/email");
+
+        let mut req = self.inner.post(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        let res = req
+            .json(&json!({ "email": email, "currentPassword": "password" }))
+            .send()
+            .await
+            .expect("failed to send a request");
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        res.json::<api::validation::Errors>()
+            .await
+            .expect("failed to get a response")
+    }
+
+    pub async fn delete_user(
+        &self,
+        id: api::user::Id,
+    ) -> Result<(), StatusCode> {
+        let url = url("/user");
+
+        let mut req = self.inner.delete(format!("{url}/{id}"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        req.send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?;
+        Ok(())
+    }
+
+    pub async fn delete_user_with_tickets(
+        &self,
+        id: api::user::Id,
+    ) -> api::user::UserHasTickets {
+        let url = url("/user");
+
+        let mut req = self.inner.delete(format!("{url}/{id}"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        let res = req.send().await.expect("failed to send a request");
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        res.json::<api::user::UserHasTickets>()
+            .await
+            .expect("failed to get a response")
+    }
+
+    pub async fn change_user_role(
+        &self,
+        id: api::user::Id,
+        role: api::user::Role,
+    ) -> Result<api::User, StatusCode> {
+        let url = url("/user");
+
+        let mut req = self.inner.patch(format!("{url}/{id}/role"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .json(&json!({ "role": role }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    /// Requests the permissions of the current user, returning the whole
+    /// response, so its caching headers may be checked too.
+    pub async fn permissions(&self) -> Result<reqwest::Response, StatusCode> {
+        let url = url("/me/permissions");
+
+        let mut req = self.inner.get(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        req.send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))
+    }
+
+    pub async fn dashboard(&self) -> Result<api::Dashboard, StatusCode> {
+        let url = url("/dashboard");
+
+        let mut req = self.inner.get(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::Dashboard>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn get_tickets(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<api::ticket::List, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self
+            .inner
+            .get(format!("{url}?offset={offset}&limit={limit}"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::ticket::List>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    /// Lists tickets with the provided query `params`, like `sort_by`.
+    pub async fn get_tickets_with(
+        &self,
+        params: &[(&str, &str)],
+    ) -> Result<api::ticket::List, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self.inner.get(url).query(params);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::ticket::List>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn get_tickets_before_invalid(
+        &self,
+        cursor: &str,
+    ) -> api::cursor::InvalidCursor {
+        let url = url("/ticket");
+
+        let mut req = self
+            .inner
+            .get(url)
+            .query(&[("limit", "10"), ("before", cursor)]);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        let res = req.send().await.expect("failed to send a request");
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        res.json::<api::cursor::InvalidCursor>()
+            .await
+            .expect("failed to get a response")
+    }
+
+    pub async fn get_assigned_tickets(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<api::ticket::AssignedList, StatusCode> {
+        let url = url("/user/me/assigned");
+
+        let mut req = self
+            .inner
+            .get(format!("{url}?offset={offset}&limit={limit}"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::ticket::AssignedList>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn add_ticket(
+        &self,
+        title: &str,
+        description: &str,
+        count: usize,
+    ) -> Result<api::Ticket, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self.inner.post(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .json(&json!({
+                "title": title,
+                "description": description,
+                "count": count,
+            }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::Ticket>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    /// Adds a ticket expected to be rejected by validation, returning the
+    /// reported violations.
+    pub async fn add_invalid_ticket(
+        &self,
+        title: &str,
+        description: &str,
+        count: usize,
+    ) -> api::validation::Errors {
+        let url = url("/ticket");
+
+        let mut req = self.inner.post(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        let res = req
+            .json(&json!({
+                "title": title,
+                "description": description,
+                "count": count,
+            }))
+            .send()
+            .await
+            .expect("failed to send a request");
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        res.json::<api::validation::Errors>()
+            .await
+            .expect("failed to get a response")
+    }
+
+    pub async fn get_tickets_before(
+        &self,
+        cursor: &str,
+        limit: usize,
+    ) -> Result<api::ticket::List, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self
+            .inner
+            .get(url)
+            .query(&[("limit", &*limit.to_string()), ("before", cursor)]);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::ticket::List>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn get_tickets_by_category(
+        &self,
+        offset: usize,
+        limit: usize,
+        category: &str,
+    ) -> Result<api::ticket::List, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self.inner.get(format!(
+            "{url}?offset={offset}&limit={limit}&category={category}"
+        ));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::ticket::List>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn add_ticket_with_category(
+        &self,
+        title: &str,
+        description: &str,
+        category: &str,
+        count: usize,
+    ) -> Result<api::Ticket, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self.inner.post(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .json(&json!({
+                "title": title,
+                "description": description,
+                "category": category,
+                "count": count,
+            }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::Ticket>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn count_tickets(
+        &self,
+        category: Option<&str>,
+    ) -> Result<api::ticket::Count, StatusCode> {
+        let url = url("/ticket/count");
+
+        let mut req = self.inner.get(url);
+        if let Some(category) = category {
+            req = req.query(&[("category", category)]);
+        }
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::ticket::Count>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    /// Counts tickets with the provided query `params`, like `status`.
+    pub async fn count_tickets_with(
+        &self,
+        params: &[(&str, &str)],
+    ) -> Result<api::ticket::Count, StatusCode> {
+        let url = url("/ticket/count");
+
+        let mut req = self.inner.get(url).query(params);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
         }
+        Ok(req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::ticket::Count>()
+            .await
+            .expect("failed to get a response"))
     }
 
-    pub async fn auth(mut self, login: &str, password: &str) -> Self {
-        const URL: &str = concat!(BASE_URL, "/auth");
+    /// Exports tickets as CSV, returning the response body in the chunks it
+    /// was received in.
+    pub async fn export_tickets(&self) -> Result<Vec<Vec<u8>>, StatusCode> {
+        let url = url("/ticket/export");
 
-        self.auth_token = Some(
-            self.inner
-                .post(URL)
-                .json(&json!({
-                    "login": login,
-                    "password": password,
-                }))
-                .send()
-                .await
-                .expect("failed to send a request")
-                .error_for_status()
-                .expect("wrong status code")
-                .text()
-                .await
-                .expect("failed to get a response"),
-        );
+        let mut req = self.inner.get(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        let mut res = req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?;
 
-        self
+        let mut chunks = Vec::new();
+        while let Some(chunk) =
+            res.chunk().await.expect("failed to get a response")
+        {
+            chunks.push(chunk.to_vec());
+        }
+        Ok(chunks)
     }
 
-    pub async fn user(&self) -> Result<api::User, StatusCode> {
-        const URL: &str = concat!(BASE_URL, "/user");
+    /// Exports the users and tickets changed at or after `since`, or all of
+    /// them.
+    pub async fn export(
+        &self,
+        since: Option<OffsetDateTime>,
+    ) -> Result<api::export::Export, StatusCode> {
+        let url = url("/export.json");
 
-        let mut req = self.inner.get(URL);
+        let mut req = self.inner.get(url);
+        if let Some(since) = since {
+            let since = since.format(&Rfc3339).expect("invalid time");
+            req = req.query(&[("since", since)]);
+        }
         if let Some(token) = &self.auth_token {
             req = req.header("Authorization", format!("Bearer {token}"));
         }
@@ -54,70 +936,65 @@ impl Client {
             .expect("failed to send a request")
             .error_for_status()
             .map_err(|e| e.status().expect("status error"))?
-            .json::<api::User>()
+            .json()
             .await
             .expect("failed to get a response"))
     }
 
-    pub async fn get_tickets(
+    pub async fn import_tickets(
         &self,
-        offset: usize,
-        limit: usize,
-    ) -> Result<api::ticket::List, StatusCode> {
-        const URL: &str = concat!(BASE_URL, "/ticket");
+        csv: &str,
+    ) -> Result<api::ticket::Imported, StatusCode> {
+        let url = url("/ticket/import");
 
-        let mut req = self
-            .inner
-            .get(format!("{URL}?offset={offset}&limit={limit}"));
+        let mut req = self.inner.post(url);
         if let Some(token) = &self.auth_token {
             req = req.header("Authorization", format!("Bearer {token}"));
         }
-        Ok(req
+        let res = req
+            .header("Content-Type", "text/csv")
+            .body(csv.to_owned())
             .send()
             .await
             .expect("failed to send a request")
             .error_for_status()
-            .map_err(|e| e.status().expect("status error"))?
-            .json::<api::ticket::List>()
+            .map_err(|e| e.status().expect("status error"))?;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        Ok(res
+            .json::<api::ticket::Imported>()
             .await
             .expect("failed to get a response"))
     }
 
-    pub async fn add_ticket(
+    pub async fn import_invalid_tickets(
         &self,
-        title: &str,
-        description: &str,
-        count: usize,
-    ) -> Result<api::Ticket, StatusCode> {
-        const URL: &str = concat!(BASE_URL, "/ticket");
+        csv: &str,
+    ) -> api::ticket::ImportErrors {
+        let url = url("/ticket/import");
 
-        let mut req = self.inner.post(URL);
+        let mut req = self.inner.post(url);
         if let Some(token) = &self.auth_token {
             req = req.header("Authorization", format!("Bearer {token}"));
         }
-        Ok(req
-            .json(&json!({
-                "title": title,
-                "description": description,
-                "count": count,
-            }))
+        let res = req
+            .header("Content-Type", "text/csv")
+            .body(csv.to_owned())
             .send()
             .await
-            .expect("failed to send a request")
-            .error_for_status()
-            .map_err(|e| e.status().expect("status error"))?
-            .json::<api::Ticket>()
+            .expect("failed to send a request");
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        res.json::<api::ticket::ImportErrors>()
             .await
-            .expect("failed to get a response"))
+            .expect("failed to get a response")
     }
 
     pub async fn get_ticket(
         &self,
         id: api::ticket::Id,
     ) -> Result<api::Ticket, StatusCode> {
-        const URL: &str = concat!(BASE_URL, "/ticket");
+        let url = url("/ticket");
 
-        let mut req = self.inner.get(format!("{URL}/{id}"));
+        let mut req = self.inner.get(format!("{url}/{id}"));
         if let Some(token) = &self.auth_token {
             req = req.header("Authorization", format!("Bearer {token}"));
         }
@@ -137,9 +1014,9 @@ impl Client {
         id: api::ticket::Id,
         title: &str,
     ) -> Result<api::Ticket, StatusCode> {
-        const URL: &str = concat!(BASE_URL, "/ticket");
+        let url = url("/ticket");
 
-        let mut req = self.inner.patch(format!("{URL}/{id}"));
+        let mut req = self.inner.patch(format!("{url}/{id}"));
         if let Some(token) = &self.auth_token {
             req = req.header("Authorization", format!("Bearer {token}"));
         }
@@ -165,9 +1042,9 @@ impl Client {
         id: api::ticket::Id,
         description: &str,
     ) -> Result<api::Ticket, StatusCode> {
-        const URL: &str = concat!(BASE_URL, "/ticket");
+        let url = url("/ticket");
 
-        let mut req = self.inner.patch(format!("{URL}/{id}"));
+        let mut req = self.inner.patch(format!("{url}/{id}"));
         if let Some(token) = &self.auth_token {
             req = req.header("Authorization", format!("Bearer {token}"));
         }
@@ -188,13 +1065,41 @@ impl Client {
             .expect("failed to get a response"))
     }
 
+    pub async fn edit_ticket_category(
+        &self,
+        id: api::ticket::Id,
+        category: &str,
+    ) -> Result<api::Ticket, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self.inner.patch(format!("{url}/{id}"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .json(&json!({
+                "op": "editCategory",
+                "data": {
+                    "category": category,
+                }
+            }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::Ticket>()
+            .await
+            .expect("failed to get a response"))
+    }
+
     pub async fn cancel_ticket(
         &self,
         id: api::ticket::Id,
     ) -> Result<api::Ticket, StatusCode> {
-        const URL: &str = concat!(BASE_URL, "/ticket");
+        let url = url("/ticket");
 
-        let mut req = self.inner.patch(format!("{URL}/{id}"));
+        let mut req = self.inner.patch(format!("{url}/{id}"));
         if let Some(token) = &self.auth_token {
             req = req.header("Authorization", format!("Bearer {token}"));
         }
@@ -217,9 +1122,9 @@ impl Client {
         id: api::ticket::Id,
         price: usize,
     ) -> Result<api::Ticket, StatusCode> {
-        const URL: &str = concat!(BASE_URL, "/ticket");
+        let url = url("/ticket");
 
-        let mut req = self.inner.patch(format!("{URL}/{id}"));
+        let mut req = self.inner.patch(format!("{url}/{id}"));
         if let Some(token) = &self.auth_token {
             req = req.header("Authorization", format!("Bearer {token}"));
         }
@@ -240,13 +1145,41 @@ impl Client {
             .expect("failed to get a response"))
     }
 
+    pub async fn adjust_ticket_price(
+        &self,
+        id: api::ticket::Id,
+        price: f64,
+    ) -> Result<api::Ticket, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self.inner.patch(format!("{url}/{id}"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .json(&json!({
+                "op": "adjustPrice",
+                "data": {
+                    "price": price,
+                }
+            }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::Ticket>()
+            .await
+            .expect("failed to get a response"))
+    }
+
     pub async fn deny_ticket(
         &self,
         id: api::ticket::Id,
     ) -> Result<api::Ticket, StatusCode> {
-        const URL: &str = concat!(BASE_URL, "/ticket");
+        let url = url("/ticket");
 
-        let mut req = self.inner.patch(format!("{URL}/{id}"));
+        let mut req = self.inner.patch(format!("{url}/{id}"));
         if let Some(token) = &self.auth_token {
             req = req.header("Authorization", format!("Bearer {token}"));
         }
@@ -268,15 +1201,99 @@ impl Client {
         &self,
         id: api::ticket::Id,
     ) -> Result<api::Ticket, StatusCode> {
-        const URL: &str = concat!(BASE_URL, "/ticket");
+        let url = url("/ticket");
+
+        let mut req = self.inner.patch(format!("{url}/{id}"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .json(&json!({
+                "op": "markAsPaid",
+            }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::Ticket>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn mark_ticket_as_paid_with_reference(
+        &self,
+        id: api::ticket::Id,
+        payment_reference: &str,
+    ) -> Result<api::Ticket, StatusCode> {
+        let url = url("/ticket");
 
-        let mut req = self.inner.patch(format!("{URL}/{id}"));
+        let mut req = self.inner.patch(format!("{url}/{id}"));
         if let Some(token) = &self.auth_token {
             req = req.header("Authorization", format!("Bearer {token}"));
         }
         Ok(req
             .json(&json!({
                 "op": "markAsPaid",
+                "data": {
+                    "paymentReference": payment_reference,
+                }
+            }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::Ticket>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn record_ticket_receipt(
+        &self,
+        id: api::ticket::Id,
+        count: usize,
+    ) -> Result<api::Ticket, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self.inner.patch(format!("{url}/{id}"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .json(&json!({
+                "op": "recordReceipt",
+                "data": {
+                    "count": count,
+                }
+            }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::Ticket>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn reassign_ticket_initiator(
+        &self,
+        id: api::ticket::Id,
+        user_id: api::user::Id,
+    ) -> Result<api::Ticket, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self.inner.patch(format!("{url}/{id}"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .json(&json!({
+                "op": "reassignInitiator",
+                "data": {
+                    "userId": user_id,
+                }
             }))
             .send()
             .await
@@ -287,4 +1304,165 @@ impl Client {
             .await
             .expect("failed to get a response"))
     }
+
+    /// Edits a ticket with an operation expected to be rejected as unknown,
+    /// returning the reported rejection.
+    pub async fn edit_ticket_with_unknown_op(
+        &self,
+        id: api::ticket::Id,
+        body: serde_json::Value,
+    ) -> api::ticket::UnknownOp {
+        let url = url("/ticket");
+
+        let mut req = self.inner.patch(format!("{url}/{id}"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        let res = req
+            .json(&body)
+            .send()
+            .await
+            .expect("failed to send a request");
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        res.json::<api::ticket::UnknownOp>()
+            .await
+            .expect("failed to get a response")
+    }
+
+    pub async fn upload_attachment(
+        &self,
+        id: api::ticket::Id,
+        file_name: &str,
+        content_type: &str,
+        data: Vec<u8>,
+    ) -> Result<api::Attachment, StatusCode> {
+        let url = url("/ticket");
+
+        let part = reqwest::multipart::Part::bytes(data)
+            .file_name(file_name.to_owned())
+            .mime_str(content_type)
+            .expect("invalid content type");
+        let form = reqwest::multipart::Form::new().part("file", part);
+
+        let mut req = self.inner.post(format!("{url}/{id}/attachment"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .multipart(form)
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<api::Attachment>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    /// Applies the `op` to the tickets with the `ids`, returning the response
+    /// status along with the outcome for each of them.
+    pub async fn bulk_transition_tickets(
+        &self,
+        ids: &[api::ticket::Id],
+        op: api::ticket::BulkOp,
+        price: Option<f64>,
+    ) -> Result<(StatusCode, Vec<BulkTransitionResult>), StatusCode> {
+        let url = url("/ticket/bulk-transition");
+
+        let mut req = self.inner.post(url);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        let resp = req
+            .json(&json!({
+                "ids": ids,
+                "op": op,
+                "price": price,
+            }))
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?;
+        Ok((
+            resp.status(),
+            resp.json().await.expect("failed to get a response"),
+        ))
+    }
+
+    pub async fn get_ticket_history(
+        &self,
+        id: api::ticket::Id,
+        diff: bool,
+    ) -> Result<Vec<api::ticket::Event>, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self
+            .inner
+            .get(format!("{url}/{id}/history"))
+            .query(&[("diff", diff)]);
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<Vec<api::ticket::Event>>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    pub async fn get_attachments(
+        &self,
+        id: api::ticket::Id,
+    ) -> Result<Vec<api::Attachment>, StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self.inner.get(format!("{url}/{id}/attachments"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        Ok(req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?
+            .json::<Vec<api::Attachment>>()
+            .await
+            .expect("failed to get a response"))
+    }
+
+    /// Downloads an attachment, returning its content type along with the
+    /// contents.
+    pub async fn download_attachment(
+        &self,
+        id: api::ticket::Id,
+        attachment_id: api::attachment::Id,
+    ) -> Result<(String, Vec<u8>), StatusCode> {
+        let url = url("/ticket");
+
+        let mut req = self
+            .inner
+            .get(format!("{url}/{id}/attachment/{attachment_id}"));
+        if let Some(token) = &self.auth_token {
+            req = req.header("Authorization", format!("Bearer {token}"));
+        }
+        let res = req
+            .send()
+            .await
+            .expect("failed to send a request")
+            .error_for_status()
+            .map_err(|e| e.status().expect("status error"))?;
+        let content_type = res.headers()["content-type"]
+            .to_str()
+            .expect("invalid content type")
+            .to_owned();
+        let data = res.bytes().await.expect("failed to get a response");
+        Ok((content_type, data.into()))
+    }
 }
@@ -0,0 +1,49 @@
+pub mod common;
+
+use reqwest::header::{ACCEPT_ENCODING, CONTENT_ENCODING};
+
+/// Requests the version of the server listening on the `addr` accepting
+/// gzip, returning the `Content-Encoding` of the response, if any.
+async fn get_version_encoding(addr: &str) -> Option<String> {
+    let resp = reqwest::Client::new()
+        .get(format!("http://{addr}/version"))
+        .header(ACCEPT_ENCODING, "gzip")
+        .send()
+        .await
+        .unwrap();
+    resp.headers()
+        .get(CONTENT_ENCODING)
+        .map(|e| e.to_str().unwrap().to_owned())
+}
+
+#[tokio::test]
+async fn doesnt_compress_by_default() {
+    const ADDR: &str = "127.0.0.1:3012";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(ADDR, "", &[]).await;
+
+    assert_eq!(get_version_encoding(ADDR).await, None);
+}
+
+#[tokio::test]
+async fn compresses_when_enabled() {
+    const ADDR: &str = "127.0.0.1:3013";
+
+    let _client = common::setup().await;
+    let _server =
+        common::Server::spawn(ADDR, "[http.compression]\nmin_size = 16", &[])
+            .await;
+
+    assert_eq!(get_version_encoding(ADDR).await.as_deref(), Some("gzip"));
+}
+
+#[tokio::test]
+async fn doesnt_compress_responses_below_min_size() {
+    const ADDR: &str = "127.0.0.1:3014";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(ADDR, "[http.compression]", &[]).await;
+
+    assert_eq!(get_version_encoding(ADDR).await, None);
+}
@@ -0,0 +1,94 @@
+pub mod common;
+
+use reqwest::{
+    header::{
+        ACCESS_CONTROL_ALLOW_CREDENTIALS, ACCESS_CONTROL_EXPOSE_HEADERS,
+        ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN,
+    },
+    Method,
+};
+
+#[tokio::test]
+async fn caches_preflight_for_configured_time() {
+    const ADDR: &str = "127.0.0.1:3019";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(
+        ADDR,
+        "allowed_origins = [\"https://example.com\"]\n\
+         preflight_max_age_seconds = 300",
+        &[],
+    )
+    .await;
+
+    let resp = reqwest::Client::new()
+        .request(Method::OPTIONS, format!("http://{ADDR}/ticket"))
+        .header(ORIGIN, "https://example.com")
+        .header(ACCESS_CONTROL_REQUEST_METHOD, "PATCH")
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success(), "{}", resp.status());
+    assert_eq!(resp.headers()[ACCESS_CONTROL_MAX_AGE], "300");
+}
+
+#[tokio::test]
+async fn allows_credentials_when_configured() {
+    const ADDR: &str = "127.0.0.1:3021";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(
+        ADDR,
+        "allowed_origins = [\"https://example.com\"]\n\
+         allow_credentials = true",
+        &[],
+    )
+    .await;
+
+    let resp = reqwest::Client::new()
+        .request(Method::OPTIONS, format!("http://{ADDR}/ticket"))
+        .header(ORIGIN, "https://example.com")
+        .header(ACCESS_CONTROL_REQUEST_METHOD, "PATCH")
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success(), "{}", resp.status());
+    assert_eq!(resp.headers()[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+    let resp = reqwest::Client::new()
+        .get(format!("http://{ADDR}/healthz"))
+        .header(ORIGIN, "https://example.com")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.headers()[ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+}
+
+#[tokio::test]
+async fn exposes_configured_headers() {
+    const ADDR: &str = "127.0.0.1:3025";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(
+        ADDR,
+        "allowed_origins = [\"https://example.com\"]\n\
+         expose_headers = [\"ETag\", \"Link\"]",
+        &[],
+    )
+    .await;
+
+    let resp = reqwest::Client::new()
+        .get(format!("http://{ADDR}/healthz"))
+        .header(ORIGIN, "https://example.com")
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.status().is_success(), "{}", resp.status());
+    let exposed = resp.headers()[ACCESS_CONTROL_EXPOSE_HEADERS]
+        .to_str()
+        .unwrap()
+        .split(',')
+        .map(|h| h.trim().to_ascii_lowercase())
+        .collect::<Vec<_>>();
+    assert_eq!(exposed, ["etag", "link"]);
+}
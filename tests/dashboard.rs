@@ -0,0 +1,109 @@
+pub mod common;
+
+use std::collections::HashMap;
+
+use dubna_internship::api::{self, ticket::Status};
+use reqwest::StatusCode;
+
+/// Creates four tickets by Alice and one by Eve, then has Bob confirm three
+/// of Alice's tickets and deny the fourth one, and Charlie pay for one of
+/// the confirmed, returning the client of Alice.
+async fn seed() -> common::Client {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let eve = common::Client::new().auth("eve", "password").await;
+    let bob = common::Client::new().auth("bob", "password").await;
+    let charlie = common::Client::new().auth("charlie", "password").await;
+
+    let mut ids = Vec::new();
+    for i in 0..4 {
+        let ticket = alice
+            .add_ticket(&format!("Ticket {i}"), "Description", 1)
+            .await
+            .unwrap();
+        ids.push(ticket.id);
+    }
+    eve.add_ticket("Ticket 4", "Description", 1).await.unwrap();
+    bob.confirm_ticket(ids[0], 100).await.unwrap();
+    bob.confirm_ticket(ids[1], 200).await.unwrap();
+    bob.confirm_ticket(ids[2], 400).await.unwrap();
+    bob.deny_ticket(ids[3]).await.unwrap();
+    charlie.mark_ticket_as_paid(ids[0]).await.unwrap();
+
+    alice
+}
+
+fn counts(counts: [(Status, usize); 5]) -> HashMap<Status, usize> {
+    counts.into()
+}
+
+#[tokio::test]
+async fn counts_initiator_tickets_by_status() {
+    let alice = seed().await;
+
+    assert_eq!(
+        alice.dashboard().await.unwrap(),
+        api::Dashboard::Initiator {
+            tickets_by_status: counts([
+                (Status::Requested, 0),
+                (Status::Cancelled, 0),
+                (Status::Confirmed, 2),
+                (Status::Denied, 1),
+                (Status::PaymentCompleted, 1),
+            ]),
+        },
+    );
+}
+
+#[tokio::test]
+async fn summarizes_purchasing_manager_work() {
+    let _alice = seed().await;
+    let bob = common::Client::new().auth("bob", "password").await;
+
+    assert_eq!(
+        bob.dashboard().await.unwrap(),
+        api::Dashboard::PurchasingManager {
+            pending_confirmation: 1,
+            confirmed: 3,
+            confirmed_total_price: 700.0,
+        },
+    );
+}
+
+#[tokio::test]
+async fn summarizes_tickets_awaiting_payment() {
+    let _alice = seed().await;
+    let charlie = common::Client::new().auth("charlie", "password").await;
+
+    assert_eq!(
+        charlie.dashboard().await.unwrap(),
+        api::Dashboard::AccountingManager {
+            awaiting_payment: 2,
+            awaiting_payment_total_price: 600.0,
+        },
+    );
+}
+
+#[tokio::test]
+async fn counts_all_tickets_by_status_for_admin() {
+    let _alice = seed().await;
+    let dave = common::Client::new().auth("dave", "password").await;
+
+    assert_eq!(
+        dave.dashboard().await.unwrap(),
+        api::Dashboard::Admin {
+            tickets_by_status: counts([
+                (Status::Requested, 1),
+                (Status::Cancelled, 0),
+                (Status::Confirmed, 2),
+                (Status::Denied, 1),
+                (Status::PaymentCompleted, 1),
+            ]),
+        },
+    );
+}
+
+#[tokio::test]
+async fn fails_when_unauthorized() {
+    let status = common::setup().await.dashboard().await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
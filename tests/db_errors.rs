@@ -0,0 +1,70 @@
+pub mod common;
+
+use dubna_internship::api;
+use reqwest::StatusCode;
+use serde_json::{json, Value as Json};
+
+/// Adds a ticket the database fails to store on the server listening on the
+/// `addr`, returning the response status and body.
+async fn add_unstorable_ticket(addr: &str) -> (StatusCode, String) {
+    let client = reqwest::Client::new();
+
+    let token = client
+        .post(format!("http://{addr}/auth"))
+        .json(&json!({"login": "alice", "password": "password"}))
+        .send()
+        .await
+        .unwrap()
+        .json::<api::user::AuthResponse>()
+        .await
+        .unwrap()
+        .token;
+
+    // Postgres rejects the NUL character in text.
+    let resp = client
+        .post(format!("http://{addr}/ticket"))
+        .header("Authorization", format!("Bearer {token}"))
+        .json(&json!({
+            "title": "Ticket\u{0}",
+            "description": "Description",
+            "count": 1,
+        }))
+        .send()
+        .await
+        .unwrap();
+    (resp.status(), resp.text().await.unwrap())
+}
+
+#[tokio::test]
+async fn omits_db_errors_by_default() {
+    const ADDR: &str = "127.0.0.1:3008";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(ADDR, "", &[]).await;
+
+    let (status, body) = add_unstorable_ticket(ADDR).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    assert_eq!(body, "");
+}
+
+#[tokio::test]
+async fn exposes_db_errors_when_enabled() {
+    const ADDR: &str = "127.0.0.1:3009";
+
+    let _client = common::setup().await;
+    let _server =
+        common::Server::spawn(ADDR, "[http]\nexpose_db_errors = true", &[])
+            .await;
+
+    let (status, body) = add_unstorable_ticket(ADDR).await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+    let body = serde_json::from_str::<Json>(&body).unwrap();
+    // `character_not_in_repertoire`
+    assert_eq!(body["dbError"]["code"], "22021");
+    assert!(
+        body["dbError"]["message"]
+            .as_str()
+            .is_some_and(|m| m.contains("0x00")),
+        "{body}",
+    );
+}
@@ -0,0 +1,21 @@
+pub mod common;
+
+use std::collections::BTreeMap;
+
+use dubna_internship::db::fixtures::session_parameter;
+
+#[tokio::test]
+async fn sets_application_name_and_options() {
+    let _client = common::setup().await;
+    let db = common::db_with_session(
+        "dubna-internship-test",
+        BTreeMap::from([("work_mem".to_owned(), "8MB".to_owned())]),
+    )
+    .await;
+
+    assert_eq!(
+        session_parameter(&db, "application_name").await.unwrap(),
+        "dubna-internship-test",
+    );
+    assert_eq!(session_parameter(&db, "work_mem").await.unwrap(), "8MB");
+}
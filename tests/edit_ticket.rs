@@ -1,11 +1,17 @@
 pub mod common;
 
-use dubna_internship::api;
+use std::time::Duration;
+
+use dubna_internship::{
+    api,
+    db::{self, ticket::TicketBuilder},
+};
 use reqwest::StatusCode;
+use serde_json::json;
 
 #[tokio::test]
 async fn edits_ticket_title() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -25,7 +31,7 @@ async fn edits_ticket_title() {
 
 #[tokio::test]
 async fn cant_edits_ticket_title_when_confirmed() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -43,7 +49,7 @@ async fn cant_edits_ticket_title_when_confirmed() {
 
 #[tokio::test]
 async fn cant_edits_ticket_title_when_not_initiator() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -59,7 +65,7 @@ async fn cant_edits_ticket_title_when_not_initiator() {
 
 #[tokio::test]
 async fn edits_ticket_description() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -85,27 +91,24 @@ async fn edits_ticket_description() {
 
 #[tokio::test]
 async fn cancels_ticket() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
         .unwrap();
-    let ticket = alice.cancel_ticket(ticket.id).await.unwrap();
-    assert_eq!(ticket.title, "Ticket 1");
-    assert_eq!(ticket.description, "Description 1");
-    assert_eq!(ticket.status, api::ticket::Status::Cancelled);
-    assert_eq!(ticket.count, 1);
-    assert_eq!(ticket.price, None);
-    assert_eq!(ticket.initiator.id, api::user::Id::from(1));
-    assert_eq!(ticket.initiator.name, "Alice");
-    assert_eq!(ticket.initiator.role, api::user::Role::Initiator);
-    assert_eq!(ticket.purchasing_manager, None);
-    assert_eq!(ticket.accounting_manager, None);
+    let cancelled = alice.cancel_ticket(ticket.id).await.unwrap();
+    assert_eq!(
+        cancelled,
+        api::Ticket {
+            status: api::ticket::Status::Cancelled,
+            ..ticket
+        },
+    );
 }
 
 #[tokio::test]
 async fn cant_cancel_ticket_when_confirmed() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -120,7 +123,7 @@ async fn cant_cancel_ticket_when_confirmed() {
 
 #[tokio::test]
 async fn cant_cancel_ticket_when_not_initiator() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -132,42 +135,172 @@ async fn cant_cancel_ticket_when_not_initiator() {
 }
 
 #[tokio::test]
-async fn confirms_ticket() {
-    let alice = common::Client::new().auth("alice", "password").await;
+async fn lets_either_cancel_or_confirm_racing_win() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let bob = common::Client::new().auth("bob", "password").await;
+
+    for _ in 0..10 {
+        let ticket = alice
+            .add_ticket("Ticket 1", "Description 1", 1)
+            .await
+            .unwrap();
+
+        let (cancelled, confirmed) = tokio::join!(
+            alice.cancel_ticket(ticket.id),
+            bob.confirm_ticket(ticket.id, 100),
+        );
+        // The loser either sees the ticket already changed, or has it changed
+        // after deciding on its own change.
+        let (winner, loser) = match (cancelled, confirmed) {
+            (Ok(winner), Err(loser)) | (Err(loser), Ok(winner)) => {
+                (winner, loser)
+            }
+            res => panic!("expected exactly one to win, got {res:?}"),
+        };
+        assert!(
+            [StatusCode::BAD_REQUEST, StatusCode::CONFLICT].contains(&loser),
+            "{loser}",
+        );
+
+        let ticket = alice.get_ticket(ticket.id).await.unwrap();
+        assert_eq!(ticket.status, winner.status);
+        assert_eq!(ticket.price, winner.price);
+    }
+}
+
+#[tokio::test]
+async fn refuses_transition_of_ticket_changed_meanwhile() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let bob = common::Client::new().auth("bob", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
         .unwrap();
 
+    // Keeps the ticket locked until both transitions are decided, so the one
+    // written last finds the ticket changed.
+    let db = common::db().await;
+    let mut conn = db.connection().await.unwrap();
+    let tx = conn.transaction().await.unwrap();
+    tx.get_ticket_by_id_for_update(ticket.id).await.unwrap();
+
+    let (cancelled, confirmed, ()) = tokio::join!(
+        alice.cancel_ticket(ticket.id),
+        bob.confirm_ticket(ticket.id, 100),
+        async {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            tx.commit().await.unwrap();
+        },
+    );
+    let winner = match (cancelled, confirmed) {
+        (Ok(winner), Err(StatusCode::CONFLICT))
+        | (Err(StatusCode::CONFLICT), Ok(winner)) => winner,
+        res => panic!("expected one to win and one to conflict, got {res:?}"),
+    };
+
+    let ticket = alice.get_ticket(ticket.id).await.unwrap();
+    assert_eq!(ticket.status, winner.status);
+    let events = db.get_events_for_ticket(ticket.id).await.unwrap();
+    let transitions = events.iter().filter(|e| e.action != "create");
+    assert_eq!(transitions.count(), 1, "{events:?}");
+}
+
+#[tokio::test]
+async fn never_loses_racing_edits() {
+    let alice = common::setup().await.auth("alice", "password").await;
     let bob = common::Client::new().auth("bob", "password").await;
-    let ticket = bob.confirm_ticket(ticket.id, 100).await.unwrap();
+    let charlie = common::Client::new().auth("charlie", "password").await;
 
-    assert_eq!(ticket.title, "Ticket 1");
-    assert_eq!(ticket.description, "Description 1");
-    assert_eq!(ticket.status, api::ticket::Status::Confirmed);
-    assert_eq!(ticket.count, 1);
-    assert_eq!(ticket.price, Some(100.0));
-    assert_eq!(ticket.initiator.id, api::user::Id::from(1));
-    assert_eq!(ticket.initiator.name, "Alice");
-    assert_eq!(ticket.initiator.role, api::user::Role::Initiator);
-    assert_eq!(
-        ticket.purchasing_manager.as_ref().map(|u| u.id),
-        Some(api::user::Id::from(2))
-    );
-    assert_eq!(
-        ticket.purchasing_manager.as_ref().map(|u| u.name.as_str()),
-        Some("Bob")
+    for _ in 0..10 {
+        let ticket = alice
+            .add_ticket("Ticket 1", "Description 1", 2)
+            .await
+            .unwrap();
+        bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+        // Each edit writes the whole ticket back, so the last one would undo
+        // the others, if they weren't refused once the ticket is changed.
+        let (first, second, edited) = tokio::join!(
+            charlie.record_ticket_receipt(ticket.id, 1),
+            charlie.record_ticket_receipt(ticket.id, 1),
+            alice.edit_ticket_description(ticket.id, "Description 2"),
+        );
+        let applied = |res: Result<api::Ticket, StatusCode>| match res {
+            Ok(_) => true,
+            Err(StatusCode::CONFLICT) => false,
+            Err(status) => panic!("expected a conflict, got {status}"),
+        };
+        let received =
+            usize::from(applied(first)) + usize::from(applied(second));
+        let edited = applied(edited);
+
+        let ticket = alice.get_ticket(ticket.id).await.unwrap();
+        assert_eq!(ticket.received_count, received);
+        assert_eq!(ticket.description == "Description 2", edited);
+    }
+}
+
+#[tokio::test]
+async fn writes_racing_edits_with_single_connection() {
+    let _client = common::setup().await;
+    let db = common::db_with_pool(1, Some(Duration::from_secs(5))).await;
+
+    let ticket = TicketBuilder::new(db::user::Id::from(1)).build();
+    db.write_ticket(&ticket).await.unwrap();
+    // Read back, as the database keeps less precise times.
+    let original = db
+        .get_ticket_by_id(ticket.id, Default::default())
+        .await
+        .unwrap()
+        .unwrap();
+    let edit = |description: &str| db::audit::TicketEdit {
+        original: original.clone(),
+        ticket: db::Ticket {
+            description: description.to_owned(),
+            ..original.clone()
+        },
+        status_fields: None,
+        actor: db::user::Id::from(1),
+        action: "editDescription".to_owned(),
+        payload: json!({}),
+    };
+    let edits = [edit("Description 1"), edit("Description 2")];
+
+    // Nothing but the locking connection is acquired, so the edits wait for
+    // each other instead of for the pool.
+    let (first, second) = tokio::join!(
+        db.edit_ticket_with_event(&edits[0]),
+        db.edit_ticket_with_event(&edits[1]),
     );
+    let (first, second) = (first.unwrap(), second.unwrap());
+    assert!(first != second, "exactly one edit must apply");
+}
+
+#[tokio::test]
+async fn confirms_ticket() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    let confirmed = bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
     assert_eq!(
-        ticket.purchasing_manager.as_ref().map(|u| u.role),
-        Some(api::user::Role::PurchasingManager)
+        confirmed,
+        api::Ticket {
+            status: api::ticket::Status::Confirmed,
+            price: Some(100.0),
+            purchasing_manager: Some(bob.user().await.unwrap()),
+            ..ticket
+        },
     );
-    assert_eq!(ticket.accounting_manager, None);
 }
 
 #[tokio::test]
 async fn cant_confirm_ticket_when_not_purchasing_manager() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -178,7 +311,7 @@ async fn cant_confirm_ticket_when_not_purchasing_manager() {
 
 #[tokio::test]
 async fn cant_confirm_ticket_when_not_requested() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -191,42 +324,120 @@ async fn cant_confirm_ticket_when_not_requested() {
 }
 
 #[tokio::test]
-async fn denies_ticket() {
-    let alice = common::Client::new().auth("alice", "password").await;
+async fn adjusts_price_of_confirmed_ticket() {
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
         .unwrap();
 
     let bob = common::Client::new().auth("bob", "password").await;
-    let ticket = bob.deny_ticket(ticket.id).await.unwrap();
-
-    assert_eq!(ticket.title, "Ticket 1");
-    assert_eq!(ticket.description, "Description 1");
-    assert_eq!(ticket.status, api::ticket::Status::Denied);
-    assert_eq!(ticket.count, 1);
-    assert_eq!(ticket.price, None);
-    assert_eq!(ticket.initiator.id, api::user::Id::from(1));
-    assert_eq!(ticket.initiator.name, "Alice");
-    assert_eq!(ticket.initiator.role, api::user::Role::Initiator);
+    let confirmed = bob.confirm_ticket(ticket.id, 100).await.unwrap();
+    let adjusted = bob.adjust_ticket_price(ticket.id, 120.5).await.unwrap();
     assert_eq!(
-        ticket.purchasing_manager.as_ref().map(|u| u.id),
-        Some(api::user::Id::from(2))
-    );
-    assert_eq!(
-        ticket.purchasing_manager.as_ref().map(|u| u.name.as_str()),
-        Some("Bob")
+        adjusted,
+        api::Ticket {
+            price: Some(120.5),
+            ..confirmed
+        },
     );
+
+    let dave = common::Client::new().auth("dave", "password").await;
+    let adjusted = dave.adjust_ticket_price(ticket.id, 110.0).await.unwrap();
+    assert_eq!(adjusted.price, Some(110.0));
+}
+
+#[tokio::test]
+async fn cant_adjust_price_when_not_purchasing_manager() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let status = alice
+        .adjust_ticket_price(ticket.id, 120.0)
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn cant_adjust_price_when_not_confirmed() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    let status = bob.adjust_ticket_price(ticket.id, 120.0).await.unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn cant_adjust_price_when_paid() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let charlie = common::Client::new().auth("charlie", "password").await;
+    charlie.mark_ticket_as_paid(ticket.id).await.unwrap();
+
+    let status = bob.adjust_ticket_price(ticket.id, 120.0).await.unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let ticket = alice.get_ticket(ticket.id).await.unwrap();
+    assert_eq!(ticket.price, Some(100.0));
+}
+
+#[tokio::test]
+async fn rejects_non_positive_adjusted_price() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let status = bob.adjust_ticket_price(ticket.id, 0.0).await.unwrap_err();
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn denies_ticket() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    let denied = bob.deny_ticket(ticket.id).await.unwrap();
+
     assert_eq!(
-        ticket.purchasing_manager.as_ref().map(|u| u.role),
-        Some(api::user::Role::PurchasingManager)
+        denied,
+        api::Ticket {
+            status: api::ticket::Status::Denied,
+            purchasing_manager: Some(bob.user().await.unwrap()),
+            ..ticket
+        },
     );
-    assert_eq!(ticket.accounting_manager, None);
 }
 
 #[tokio::test]
 async fn cant_deny_ticket_when_not_purchasing_manager() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -237,7 +448,7 @@ async fn cant_deny_ticket_when_not_purchasing_manager() {
 
 #[tokio::test]
 async fn cant_deny_ticket_when_not_requested() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -251,7 +462,31 @@ async fn cant_deny_ticket_when_not_requested() {
 
 #[tokio::test]
 async fn mark_ticket_as_paid() {
-    let alice = common::Client::new().auth("alice", "password").await;
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    let confirmed = bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let charlie = common::Client::new().auth("charlie", "password").await;
+    let paid = charlie.mark_ticket_as_paid(ticket.id).await.unwrap();
+
+    assert_eq!(
+        paid,
+        api::Ticket {
+            status: api::ticket::Status::PaymentCompleted,
+            accounting_manager: Some(charlie.user().await.unwrap()),
+            ..confirmed
+        },
+    );
+}
+
+#[tokio::test]
+async fn cant_mark_ticket_as_paid_when_not_accounting_manager() {
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -259,47 +494,304 @@ async fn mark_ticket_as_paid() {
 
     let bob = common::Client::new().auth("bob", "password").await;
     bob.confirm_ticket(ticket.id, 100).await.unwrap();
+    let status = bob.mark_ticket_as_paid(ticket.id).await.unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn cant_mark_ticket_as_paid_when_not_confirmed() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
 
     let charlie = common::Client::new().auth("charlie", "password").await;
-    let ticket = charlie.mark_ticket_as_paid(ticket.id).await.unwrap();
+    let status = charlie.mark_ticket_as_paid(ticket.id).await.unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
 
-    assert_eq!(ticket.title, "Ticket 1");
-    assert_eq!(ticket.description, "Description 1");
+#[tokio::test]
+async fn records_ticket_receipt() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 3)
+        .await
+        .unwrap();
+    assert_eq!(ticket.received_count, 0);
+    assert!(!ticket.fully_received);
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let charlie = common::Client::new().auth("charlie", "password").await;
+    let ticket = charlie.record_ticket_receipt(ticket.id, 2).await.unwrap();
+    assert_eq!(ticket.status, api::ticket::Status::Confirmed);
+    assert_eq!(ticket.received_count, 2);
+    assert!(!ticket.fully_received);
+
+    charlie.mark_ticket_as_paid(ticket.id).await.unwrap();
+    let ticket = charlie.record_ticket_receipt(ticket.id, 1).await.unwrap();
     assert_eq!(ticket.status, api::ticket::Status::PaymentCompleted);
-    assert_eq!(ticket.count, 1);
-    assert_eq!(ticket.price, Some(100.0));
-    assert_eq!(ticket.initiator.id, api::user::Id::from(1));
+    assert_eq!(ticket.received_count, 3);
+    assert!(ticket.fully_received);
+}
+
+#[tokio::test]
+async fn cant_record_ticket_receipt_exceeding_count() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 2)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let charlie = common::Client::new().auth("charlie", "password").await;
+    charlie.record_ticket_receipt(ticket.id, 1).await.unwrap();
+    let status = charlie
+        .record_ticket_receipt(ticket.id, 2)
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn cant_record_ticket_receipt_overflowing_count() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 2)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let charlie = common::Client::new().auth("charlie", "password").await;
+    charlie.record_ticket_receipt(ticket.id, 1).await.unwrap();
+    let status = charlie
+        .record_ticket_receipt(ticket.id, usize::MAX)
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let ticket = alice.get_ticket(ticket.id).await.unwrap();
+    assert_eq!(ticket.received_count, 1);
+}
+
+#[tokio::test]
+async fn rejects_empty_ticket_receipt() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 2)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let charlie = common::Client::new().auth("charlie", "password").await;
+    let status = charlie
+        .record_ticket_receipt(ticket.id, 0)
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+#[tokio::test]
+async fn cant_record_ticket_receipt_when_not_confirmed() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let charlie = common::Client::new().auth("charlie", "password").await;
+    let status = charlie
+        .record_ticket_receipt(ticket.id, 1)
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn cant_record_ticket_receipt_when_not_accounting_manager() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+    let status = bob.record_ticket_receipt(ticket.id, 1).await.unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn edits_ticket_category() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+    let ticket = alice
+        .edit_ticket_category(ticket.id, "LAB_SUPPLIES")
+        .await
+        .unwrap();
+    assert_eq!(ticket.category, api::ticket::Category::LabSupplies);
+}
+
+#[tokio::test]
+async fn cant_edit_ticket_category_when_confirmed() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let status = alice
+        .edit_ticket_category(ticket.id, "FURNITURE")
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn resolves_all_users_after_edit() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let charlie = common::Client::new().auth("charlie", "password").await;
+    charlie.mark_ticket_as_paid(ticket.id).await.unwrap();
+
+    let ticket = alice
+        .edit_ticket_description(ticket.id, "Description 2")
+        .await
+        .unwrap();
+
+    assert_eq!(ticket.description, "Description 2");
     assert_eq!(ticket.initiator.name, "Alice");
-    assert_eq!(ticket.initiator.role, api::user::Role::Initiator);
-    assert_eq!(
-        ticket.purchasing_manager.as_ref().map(|u| u.id),
-        Some(api::user::Id::from(2))
-    );
     assert_eq!(
         ticket.purchasing_manager.as_ref().map(|u| u.name.as_str()),
         Some("Bob")
     );
-    assert_eq!(
-        ticket.purchasing_manager.as_ref().map(|u| u.role),
-        Some(api::user::Role::PurchasingManager)
-    );
-    assert_eq!(
-        ticket.accounting_manager.as_ref().map(|u| u.id),
-        Some(api::user::Id::from(3))
-    );
     assert_eq!(
         ticket.accounting_manager.as_ref().map(|u| u.name.as_str()),
         Some("Charlie")
     );
+}
+
+#[tokio::test]
+async fn admin_reassigns_initiator() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let dave = common::Client::new().auth("dave", "password").await;
+    let ticket = dave
+        .reassign_ticket_initiator(ticket.id, api::user::Id::from(5))
+        .await
+        .unwrap();
+    assert_eq!(ticket.initiator.id, api::user::Id::from(5));
+    assert_eq!(ticket.initiator.name, "Eve");
+
+    let events = common::db()
+        .await
+        .get_events_for_ticket(ticket.id)
+        .await
+        .unwrap();
+    let event = events.last().expect("expected an event");
+    assert_eq!(event.action, "reassignInitiator");
+    assert_eq!(
+        event.payload["data"]["previousUserId"],
+        serde_json::json!(api::user::Id::from(1)),
+    );
     assert_eq!(
-        ticket.accounting_manager.as_ref().map(|u| u.role),
-        Some(api::user::Role::AccountingManager)
+        event.payload["data"]["userId"],
+        serde_json::json!(api::user::Id::from(5)),
     );
 }
 
 #[tokio::test]
-async fn cant_mark_ticket_as_paid_when_not_accounting_manager() {
-    let alice = common::Client::new().auth("alice", "password").await;
+async fn only_admin_can_reassign_initiator() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let status = alice
+        .reassign_ticket_initiator(ticket.id, api::user::Id::from(5))
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn cant_reassign_initiator_to_non_initiator() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let dave = common::Client::new().auth("dave", "password").await;
+    let status = dave
+        .reassign_ticket_initiator(ticket.id, api::user::Id::from(2))
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let status = dave
+        .reassign_ticket_initiator(ticket.id, api::user::Id::from(42))
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn ticket_not_found_in_edit() {
+    let status = common::setup()
+        .await
+        .auth("alice", "password")
+        .await
+        .edit_ticket_title(api::ticket::Id::new(), "Ticket 2")
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn fails_when_unauthorized() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let status = common::Client::new()
+        .edit_ticket_title(ticket.id, "Ticket 2")
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn records_payment_reference() {
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
@@ -307,19 +799,62 @@ async fn cant_mark_ticket_as_paid_when_not_accounting_manager() {
 
     let bob = common::Client::new().auth("bob", "password").await;
     bob.confirm_ticket(ticket.id, 100).await.unwrap();
-    let status = bob.mark_ticket_as_paid(ticket.id).await.unwrap_err();
-    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let charlie = common::Client::new().auth("charlie", "password").await;
+    let ticket = charlie
+        .mark_ticket_as_paid_with_reference(ticket.id, "TX-42")
+        .await
+        .unwrap();
+    assert_eq!(ticket.status, api::ticket::Status::PaymentCompleted);
+    assert_eq!(ticket.payment_reference.as_deref(), Some("TX-42"));
+
+    let ticket = alice.get_ticket(ticket.id).await.unwrap();
+    assert_eq!(ticket.payment_reference.as_deref(), Some("TX-42"));
 }
 
 #[tokio::test]
-async fn cant_mark_ticket_as_paid_when_not_confirmed() {
-    let alice = common::Client::new().auth("alice", "password").await;
+async fn rejects_too_long_payment_reference() {
+    let alice = common::setup().await.auth("alice", "password").await;
     let ticket = alice
         .add_ticket("Ticket 1", "Description 1", 1)
         .await
         .unwrap();
 
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
     let charlie = common::Client::new().auth("charlie", "password").await;
-    let status = charlie.mark_ticket_as_paid(ticket.id).await.unwrap_err();
-    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let status = charlie
+        .mark_ticket_as_paid_with_reference(ticket.id, &"a".repeat(101))
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+
+    let ticket = alice.get_ticket(ticket.id).await.unwrap();
+    assert_eq!(ticket.status, api::ticket::Status::Confirmed);
+}
+
+#[tokio::test]
+async fn rejects_unknown_op() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let unknown = alice
+        .edit_ticket_with_unknown_op(ticket.id, json!({ "op": "frobnicate" }))
+        .await;
+    assert_eq!(unknown.code, "UNKNOWN_OP");
+    assert_eq!(unknown.op.as_deref(), Some("frobnicate"));
+    assert!(unknown.valid_ops.iter().any(|op| op == "editTitle"));
+    assert!(unknown.valid_ops.iter().any(|op| op == "reassignInitiator"));
+
+    let unknown = alice
+        .edit_ticket_with_unknown_op(ticket.id, json!({ "data": {} }))
+        .await;
+    assert_eq!(unknown.op, None);
+
+    let ticket = alice.get_ticket(ticket.id).await.unwrap();
+    assert_eq!(ticket.status, api::ticket::Status::Requested);
 }
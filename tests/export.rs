@@ -0,0 +1,112 @@
+pub mod common;
+
+use dubna_internship::api;
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn exports_users_and_tickets() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let dave = common::Client::new().auth("dave", "password").await;
+
+    let ticket = alice.add_ticket("Ticket", "Description", 3).await.unwrap();
+
+    let export = dave.export(None).await.unwrap();
+
+    let logins = export
+        .users
+        .iter()
+        .map(|u| u.login.as_str())
+        .collect::<Vec<_>>();
+    for login in ["alice", "bob", "charlie", "dave", "eve"] {
+        assert!(logins.contains(&login), "{login} isn't exported");
+    }
+    let exported = export
+        .tickets
+        .iter()
+        .find(|t| t.id == ticket.id)
+        .expect("ticket isn't exported");
+    assert_eq!(exported.title, "Ticket");
+    assert_eq!(exported.count, 3);
+    assert_eq!(exported.initiator, ticket.initiator.id);
+    assert_eq!(exported.deleted_at, None);
+    assert!(exported.updated_at <= export.exported_at);
+}
+
+#[tokio::test]
+async fn omits_password_hashes() {
+    let _client = common::setup().await;
+    let dave = common::Client::new().auth("dave", "password").await;
+
+    let export = reqwest::Client::new()
+        .get(common::url("/export.json"))
+        .bearer_auth(dave.auth_token.unwrap())
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json::<serde_json::Value>()
+        .await
+        .unwrap();
+
+    let users = export["users"].as_array().unwrap();
+    assert!(!users.is_empty());
+    for user in users {
+        assert!(user.get("passwordHash").is_none(), "{user}");
+    }
+}
+
+#[tokio::test]
+async fn exports_changes_since_previous_export() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let eve = common::Client::new().auth("eve", "password").await;
+    let dave = common::Client::new().auth("dave", "password").await;
+    let db = common::db().await;
+
+    let unchanged = alice
+        .add_ticket("Unchanged", "Description", 1)
+        .await
+        .unwrap();
+    let deleted = alice.add_ticket("Deleted", "Description", 1).await.unwrap();
+    let previous = dave.export(None).await.unwrap();
+
+    let added = alice.add_ticket("Added", "Description", 1).await.unwrap();
+    assert!(db.delete_ticket(deleted.id).await.unwrap());
+    eve.update_profile("Evelyn").await.unwrap();
+
+    let export = dave.export(Some(previous.exported_at)).await.unwrap();
+
+    let ticket_ids = export.tickets.iter().map(|t| t.id).collect::<Vec<_>>();
+    assert_eq!(ticket_ids, [added.id, deleted.id]);
+    assert!(!ticket_ids.contains(&unchanged.id));
+    assert!(export.tickets[1].deleted_at.is_some());
+    assert_eq!(
+        export
+            .users
+            .iter()
+            .map(|u| u.name.as_str())
+            .collect::<Vec<_>>(),
+        ["Evelyn"],
+    );
+    assert!(export.exported_at >= previous.exported_at);
+}
+
+#[tokio::test]
+async fn forbids_non_admins() {
+    let alice = common::setup().await.auth("alice", "password").await;
+
+    assert_eq!(alice.export(None).await, Err(StatusCode::FORBIDDEN));
+}
+
+#[tokio::test]
+async fn round_trips_through_api_types() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let dave = common::Client::new().auth("dave", "password").await;
+
+    alice.add_ticket("Ticket", "Description", 1).await.unwrap();
+    let export = dave.export(None).await.unwrap();
+
+    let json = serde_json::to_string(&export).unwrap();
+    let parsed = serde_json::from_str::<api::export::Export>(&json).unwrap();
+    assert_eq!(parsed, export);
+}
@@ -0,0 +1,59 @@
+pub mod common;
+
+use dubna_internship::db::{self, ticket::TicketBuilder};
+use reqwest::StatusCode;
+use time::OffsetDateTime;
+
+#[tokio::test]
+async fn streams_tickets_as_csv() {
+    const TICKETS: usize = 2000;
+
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let created_at = OffsetDateTime::now_utc();
+    for i in 0..TICKETS {
+        let ticket = TicketBuilder::new(db::user::Id::from(1))
+            .title(format!("Ticket {i}"))
+            .description("Needs \"quotes\", and commas")
+            .created_at(created_at)
+            .build();
+        db.write_ticket(&ticket).await.unwrap();
+    }
+
+    let chunks = common::Client::new()
+        .auth("dave", "password")
+        .await
+        .export_tickets()
+        .await
+        .unwrap();
+    // Rows are sent as soon as they are read, rather than all at once.
+    assert!(chunks.len() > 1, "expected the body to be streamed");
+
+    let csv = String::from_utf8(chunks.concat()).unwrap();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some(
+            "id,title,description,status,category,count,received_count,\
+             price,payment_reference,initiator_id,purchasing_manager_id,\
+             accounting_manager_id,created_at"
+        ),
+    );
+    let rows = lines.collect::<Vec<_>>();
+    assert_eq!(rows.len(), TICKETS);
+    let fields = r#","Needs ""quotes"", and commas",REQUESTED,OTHER,1,0,,"#;
+    assert!(rows.iter().all(|row| row.contains(fields)));
+}
+
+#[tokio::test]
+async fn only_admin_can_export_tickets() {
+    let status = common::setup()
+        .await
+        .auth("alice", "password")
+        .await
+        .export_tickets()
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
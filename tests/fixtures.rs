@@ -0,0 +1,34 @@
+pub mod common;
+
+use dubna_internship::db::fixtures;
+
+#[tokio::test]
+async fn resets_to_default_users_only() {
+    let client = common::setup().await.auth("alice", "password").await;
+    client
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let db = common::db().await;
+    fixtures::reset(&db).await.unwrap();
+
+    assert_eq!(db.get_tickets_count(&Default::default()).await.unwrap(), 0);
+    let ids = fixtures::default_users().map(|u| u.id);
+    let users = db.get_users_by_ids(&ids).await.unwrap();
+    assert_eq!(users.len(), ids.len());
+}
+
+#[tokio::test]
+async fn seeds_default_users_idempotently() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    fixtures::seed_default_users(&db).await.unwrap();
+    fixtures::seed_default_users(&db).await.unwrap();
+
+    for user in fixtures::default_users() {
+        let found = db.get_user_by_login(&user.login).await.unwrap();
+        assert_eq!(found.map(|u| u.id), Some(user.id));
+    }
+}
@@ -1,10 +1,11 @@
 pub mod common;
 
 use dubna_internship::api;
+use reqwest::StatusCode;
 
 #[tokio::test]
 async fn retrieves_ticket() {
-    let client = common::Client::new().auth("alice", "password").await;
+    let client = common::setup().await.auth("alice", "password").await;
 
     let ticket = client
         .add_ticket("Ticket 1", "Description 1", 1)
@@ -23,3 +24,77 @@ async fn retrieves_ticket() {
     assert_eq!(ticket.purchasing_manager, None);
     assert_eq!(ticket.accounting_manager, None);
 }
+
+#[tokio::test]
+async fn retrieves_fully_staffed_ticket() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let bob = common::Client::new().auth("bob", "password").await;
+    bob.confirm_ticket(ticket.id, 100).await.unwrap();
+
+    let charlie = common::Client::new().auth("charlie", "password").await;
+    charlie.mark_ticket_as_paid(ticket.id).await.unwrap();
+
+    let ticket = alice.get_ticket(ticket.id).await.unwrap();
+
+    assert_eq!(ticket.status, api::ticket::Status::PaymentCompleted);
+    assert_eq!(ticket.price, Some(100.0));
+    assert_eq!(ticket.initiator.id, api::user::Id::from(1));
+    assert_eq!(ticket.initiator.name, "Alice");
+    assert_eq!(ticket.initiator.role, api::user::Role::Initiator);
+    assert_eq!(
+        ticket.purchasing_manager.as_ref().map(|u| u.id),
+        Some(api::user::Id::from(2))
+    );
+    assert_eq!(
+        ticket.purchasing_manager.as_ref().map(|u| u.name.as_str()),
+        Some("Bob")
+    );
+    assert_eq!(
+        ticket.purchasing_manager.as_ref().map(|u| u.role),
+        Some(api::user::Role::PurchasingManager)
+    );
+    assert_eq!(
+        ticket.accounting_manager.as_ref().map(|u| u.id),
+        Some(api::user::Id::from(3))
+    );
+    assert_eq!(
+        ticket.accounting_manager.as_ref().map(|u| u.name.as_str()),
+        Some("Charlie")
+    );
+    assert_eq!(
+        ticket.accounting_manager.as_ref().map(|u| u.role),
+        Some(api::user::Role::AccountingManager)
+    );
+}
+
+#[tokio::test]
+async fn ticket_not_found() {
+    let status = common::setup()
+        .await
+        .auth("alice", "password")
+        .await
+        .get_ticket(api::ticket::Id::new())
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn fails_when_unauthorized() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let ticket = alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let status = common::Client::new()
+        .get_ticket(ticket.id)
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
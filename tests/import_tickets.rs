@@ -0,0 +1,111 @@
+pub mod common;
+
+use dubna_internship::{
+    api::{
+        self,
+        validation::{Code, FieldError},
+    },
+    db::ticket::Visibility,
+};
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn imports_tickets() {
+    let dave = common::setup().await.auth("dave", "password").await;
+    let db = common::db().await;
+
+    let csv = "\
+        title,description,count,initiator_login,created_at\r\n\
+        Paper,\"A4, 80 g/m²\",10,alice,2020-01-02T03:04:05Z\r\n\
+        Pens,\"Blue, \"\"gel\"\"\",3,eve,\r\n";
+    let imported = dave.import_tickets(csv).await.unwrap();
+    assert_eq!(imported.ids.len(), 2);
+
+    let paper = db
+        .get_ticket_by_id(imported.ids[0], Visibility::ActiveOnly)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(paper.title, "Paper");
+    assert_eq!(paper.description, "A4, 80 g/m²");
+    assert_eq!(paper.count, 10);
+    assert_eq!(paper.initiator, api::user::Id::from(1));
+    assert_eq!(paper.status, api::ticket::Status::Requested);
+    assert_eq!(paper.created_at.unix_timestamp(), 1_577_934_245);
+
+    let pens = db
+        .get_ticket_by_id(imported.ids[1], Visibility::ActiveOnly)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(pens.title, "Pens");
+    assert_eq!(pens.description, "Blue, \"gel\"");
+    assert_eq!(pens.initiator, api::user::Id::from(5));
+}
+
+#[tokio::test]
+async fn imports_nothing_on_invalid_rows() {
+    let dave = common::setup().await.auth("dave", "password").await;
+    let db = common::db().await;
+
+    let csv = "\
+        initiator_login,title,description,count\n\
+        alice,Paper,Description,1\n\
+        nobody,Pens,Description,1\n\
+        eve,,Description,many\n\
+        alice,Too few columns\n\
+        bob,Pencils,Description,1\n";
+    let errors = dave.import_invalid_tickets(csv).await;
+    let lines = errors
+        .lines
+        .iter()
+        .map(|l| (l.line, l.errors.errors.clone()))
+        .collect::<Vec<_>>();
+    let error = |field: &str, code| FieldError {
+        field: field.into(),
+        code,
+    };
+    assert_eq!(
+        lines,
+        [
+            (3, vec![error("initiator_login", Code::NotFound)]),
+            (
+                4,
+                vec![
+                    error("title", Code::MustNotBeEmpty),
+                    error("count", Code::InvalidFormat),
+                ],
+            ),
+            (5, vec![error("row", Code::InvalidFormat)]),
+            (6, vec![error("initiator_login", Code::WrongRole)]),
+        ],
+    );
+    assert_eq!(db.get_tickets_count(&Default::default()).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn rejects_missing_columns() {
+    let dave = common::setup().await.auth("dave", "password").await;
+
+    let errors = dave
+        .import_invalid_tickets("title,description,count\nPaper,,1\n")
+        .await;
+    assert_eq!(errors.lines.len(), 1);
+    assert_eq!(errors.lines[0].line, 1);
+    assert_eq!(
+        errors.lines[0].errors.errors,
+        [FieldError {
+            field: "initiator_login".into(),
+            code: Code::NotFound,
+        }],
+    );
+}
+
+#[tokio::test]
+async fn only_admin_imports_tickets() {
+    let alice = common::setup().await.auth("alice", "password").await;
+
+    let csv = "title,description,count,initiator_login\nPaper,,1,alice\n";
+    let status = alice.import_tickets(csv).await.unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
@@ -0,0 +1,32 @@
+pub mod common;
+
+use reqwest::header::CONTENT_TYPE;
+
+#[tokio::test]
+async fn specifies_charset_of_json_responses() {
+    const ADDR: &str = "127.0.0.1:3015";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(ADDR, "", &[]).await;
+
+    let resp = reqwest::get(format!("http://{ADDR}/version"))
+        .await
+        .unwrap();
+    assert_eq!(
+        resp.headers()[CONTENT_TYPE],
+        "application/json; charset=utf-8",
+    );
+}
+
+#[tokio::test]
+async fn doesnt_add_content_type_to_other_responses() {
+    const ADDR: &str = "127.0.0.1:3016";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(ADDR, "", &[]).await;
+
+    let resp = reqwest::get(format!("http://{ADDR}/healthz"))
+        .await
+        .unwrap();
+    assert_eq!(resp.headers().get(CONTENT_TYPE), None);
+}
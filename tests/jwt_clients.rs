@@ -0,0 +1,82 @@
+pub mod common;
+
+use dubna_internship::api;
+use jsonwebtoken::{EncodingKey, Header};
+use reqwest::StatusCode;
+use serde_json::json;
+use time::OffsetDateTime;
+
+const ADDR: &str = "127.0.0.1:3028";
+
+const PROFILES: &str = "\
+    [jwt.clients.web]\n\
+    audience = \"web\"\n\
+    expiration_time = \"15m\"\n\
+    [jwt.clients.integration]\n\
+    audience = \"integration\"\n";
+
+/// Signs in as Alice on the server listening on the [`ADDR`], issuing the
+/// token by the `client` profile.
+async fn sign_in(client: Option<&str>) -> reqwest::Response {
+    reqwest::Client::new()
+        .post(format!("http://{ADDR}/auth"))
+        .json(&json!({
+            "login": "alice",
+            "password": "password",
+            "client": client,
+        }))
+        .send()
+        .await
+        .unwrap()
+}
+
+/// Requests the current user with the `token` from the server listening on
+/// the [`ADDR`].
+async fn get_user(token: &str) -> StatusCode {
+    reqwest::Client::new()
+        .get(format!("http://{ADDR}/user"))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+#[tokio::test]
+async fn issues_tokens_by_client_profile() {
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(ADDR, PROFILES, &[]).await;
+
+    for (client, lifetime) in [
+        (None, time::Duration::HOUR),
+        (Some("web"), time::Duration::minutes(15)),
+        (Some("integration"), time::Duration::HOUR),
+    ] {
+        let resp = sign_in(client)
+            .await
+            .error_for_status()
+            .unwrap()
+            .json::<api::user::AuthResponse>()
+            .await
+            .unwrap();
+        assert_eq!(resp.expires_at - resp.issued_at, lifetime, "{client:?}");
+        assert_eq!(get_user(&resp.token).await, StatusCode::OK, "{client:?}");
+    }
+
+    let status = sign_in(Some("mobile")).await.status();
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let issued_at = OffsetDateTime::now_utc();
+    let foreign = jsonwebtoken::encode(
+        &Header::default(),
+        &json!({
+            "user_id": api::user::Id::from(1),
+            "exp": (issued_at + time::Duration::HOUR).unix_timestamp(),
+            "iat": issued_at.unix_timestamp(),
+            "aud": "mobile",
+        }),
+        &EncodingKey::from_secret(b"my_secret_key"),
+    )
+    .unwrap();
+    assert_eq!(get_user(&foreign).await, StatusCode::UNAUTHORIZED);
+}
@@ -1,11 +1,21 @@
 pub mod common;
 
-use dubna_internship::api;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use dubna_internship::{
+    api,
+    db::{self, ticket::TicketBuilder},
+};
+use futures::TryStreamExt as _;
+use reqwest::StatusCode;
+use time::OffsetDateTime;
 
-// NOTE: Should be executed as serial test to avoid conflicts with other tests.
 #[tokio::test]
 async fn limit_tickets() {
-    let client = common::Client::new().auth("alice", "password").await;
+    let client = common::setup().await.auth("alice", "password").await;
 
     client
         .add_ticket("Ticket 1", "Description 1", 1)
@@ -53,10 +63,9 @@ async fn limit_tickets() {
     }
 }
 
-// NOTE: Should be executed as serial test to avoid conflicts with other tests.
 #[tokio::test]
 async fn skips_tickets() {
-    let client = common::Client::new().auth("alice", "password").await;
+    let client = common::setup().await.auth("alice", "password").await;
 
     client
         .add_ticket("Ticket 1", "Description 1", 1)
@@ -101,3 +110,627 @@ async fn skips_tickets() {
         found => panic!("expected two tickets, found {found:?}"),
     }
 }
+
+#[tokio::test]
+async fn counts_tickets_on_empty_page() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    client
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let list = client.get_tickets(10, 10).await.unwrap();
+    assert!(list.tickets.is_empty());
+    assert_eq!(list.total_count, 1);
+
+    let list = client.get_tickets(0, 0).await.unwrap();
+    assert!(list.tickets.is_empty());
+    assert_eq!(list.total_count, 1);
+}
+
+#[tokio::test]
+async fn filters_tickets_by_category() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    client
+        .add_ticket_with_category("Ticket 1", "Description 1", "FURNITURE", 1)
+        .await
+        .unwrap();
+    client
+        .add_ticket_with_category("Ticket 2", "Description 2", "IT", 2)
+        .await
+        .unwrap();
+
+    let list = client
+        .get_tickets_by_category(0, 100, "FURNITURE")
+        .await
+        .unwrap();
+    match list.tickets.as_slice() {
+        [ticket] => {
+            assert_eq!(ticket.title, "Ticket 1");
+            assert_eq!(ticket.category, api::ticket::Category::Furniture);
+        }
+        found => panic!("expected one ticket, found {found:?}"),
+    }
+    assert_eq!(list.total_count, 1);
+}
+
+#[tokio::test]
+async fn pages_tickets_with_cursor() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    for i in 1..=4 {
+        client
+            .add_ticket(&format!("Ticket {i}"), &format!("Description {i}"), i)
+            .await
+            .unwrap();
+    }
+
+    let first = client.get_tickets(0, 2).await.unwrap();
+    assert_eq!(first.tickets.len(), 2);
+
+    let cursor = first.next_cursor.expect("expected next cursor");
+    let second = client.get_tickets_before(&cursor, 2).await.unwrap();
+    assert_eq!(second.tickets.len(), 2);
+    assert_eq!(second.total_count, 4);
+    assert!(second
+        .tickets
+        .iter()
+        .all(|t| first.tickets.iter().all(|f| f.id != t.id)));
+}
+
+#[tokio::test]
+async fn fetches_deep_cursor_page_in_constant_time() {
+    const PAGE_SIZE: usize = 20;
+    const PAGES: usize = 10;
+
+    let client = common::setup().await.auth("alice", "password").await;
+
+    for i in 0..PAGE_SIZE * PAGES {
+        client
+            .add_ticket(&format!("Ticket {i}"), "Description", 1)
+            .await
+            .unwrap();
+    }
+
+    let mut cursor = client
+        .get_tickets(0, PAGE_SIZE)
+        .await
+        .unwrap()
+        .next_cursor
+        .expect("expected next cursor");
+    let mut timings = Vec::with_capacity(PAGES);
+    for _ in 1..PAGES {
+        let started_at = Instant::now();
+        let list = client.get_tickets_before(&cursor, PAGE_SIZE).await.unwrap();
+        timings.push(started_at.elapsed());
+
+        assert_eq!(list.tickets.len(), PAGE_SIZE);
+        cursor = list.next_cursor.expect("expected next cursor");
+    }
+
+    let first = timings.first().copied().unwrap();
+    let last = timings.last().copied().unwrap();
+    assert!(
+        last <= first * 5 + Duration::from_millis(100),
+        "deep page took {last:?}, while the first one took {first:?}",
+    );
+}
+
+#[tokio::test]
+async fn empty_list() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    let list = client.get_tickets(0, 10).await.unwrap();
+    assert!(list.tickets.is_empty());
+    assert_eq!(list.total_count, 0);
+    assert_eq!(list.next_cursor, None);
+}
+
+#[tokio::test]
+async fn empty_list_past_total_count() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    let list = client.get_tickets(5, 10).await.unwrap();
+    assert!(list.tickets.is_empty());
+    assert_eq!(list.total_count, 0);
+}
+
+#[tokio::test]
+async fn rejects_offset_out_of_range() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    for offset in [i64::MAX as usize + 1, usize::MAX] {
+        let status = client.get_tickets(offset, 10).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST, "offset {offset}");
+    }
+}
+
+#[tokio::test]
+async fn rejects_limit_out_of_range() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    for limit in [i64::MAX as usize + 1, usize::MAX] {
+        let status = client.get_tickets(0, limit).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST, "limit {limit}");
+    }
+}
+
+#[tokio::test]
+async fn lists_no_tickets_past_largest_offset() {
+    let client = common::setup().await.auth("alice", "password").await;
+    client
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+
+    let list = client.get_tickets(i64::MAX as usize, 10).await.unwrap();
+    assert!(list.tickets.is_empty());
+    assert_eq!(list.total_count, 1);
+}
+
+#[tokio::test]
+async fn streams_all_tickets() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    for i in 1..=3 {
+        client
+            .add_ticket(&format!("Ticket {i}"), "Description", i)
+            .await
+            .unwrap();
+    }
+
+    let tickets = common::db()
+        .await
+        .stream_tickets(None, Default::default())
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    let titles = tickets.iter().map(|t| t.title.as_str()).collect::<Vec<_>>();
+    assert_eq!(titles, ["Ticket 3", "Ticket 2", "Ticket 1"]);
+}
+
+#[tokio::test]
+async fn orders_tickets_with_equal_timestamps_by_id() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let created_at = OffsetDateTime::now_utc();
+    for id in [2, 4, 1, 3] {
+        let ticket = TicketBuilder::new(db::user::Id::from(1))
+            .id(db::ticket::Id::from(id))
+            .title(format!("Ticket {id}"))
+            .created_at(created_at)
+            .build();
+        db.write_ticket(&ticket).await.unwrap();
+    }
+    let expected = [4, 3, 2, 1].map(db::ticket::Id::from);
+
+    let mut ids = Vec::new();
+    for offset in [0, 2] {
+        let (page, total_count) = db
+            .get_tickets_page_with_count(offset, 2, &Default::default())
+            .await
+            .unwrap();
+        assert_eq!(total_count, 4);
+        ids.extend(page.into_iter().map(|t| t.id));
+    }
+    assert_eq!(ids, expected);
+
+    let page = db
+        .get_tickets_before(created_at, expected[0], 2, &Default::default())
+        .await
+        .unwrap();
+    let ids = page.into_iter().map(|t| t.id).collect::<Vec<_>>();
+    assert_eq!(ids, expected[1..3]);
+}
+
+#[tokio::test]
+async fn counts_tickets() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    client
+        .add_ticket_with_category("Ticket 1", "Description 1", "FURNITURE", 1)
+        .await
+        .unwrap();
+    client
+        .add_ticket_with_category("Ticket 2", "Description 2", "IT", 2)
+        .await
+        .unwrap();
+
+    let count = client.count_tickets(None).await.unwrap();
+    assert_eq!(count.count, 2);
+
+    let count = client.count_tickets(Some("FURNITURE")).await.unwrap();
+    assert_eq!(count.count, 1);
+}
+
+#[tokio::test]
+async fn cant_count_tickets_when_unauthorized() {
+    let status = common::setup().await.count_tickets(None).await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn fails_when_unauthorized() {
+    let status = common::setup().await.get_tickets(0, 10).await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn counts_tickets_by_status() {
+    use db::ticket::{Category, Status};
+
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    for (i, (status, category)) in [
+        (Status::Requested, Category::It),
+        (Status::Requested, Category::It),
+        (Status::Requested, Category::Furniture),
+        (Status::Confirmed, Category::It),
+        (Status::Confirmed, Category::Other),
+        (Status::Denied, Category::Furniture),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let ticket = TicketBuilder::new(db::user::Id::from(1))
+            .title(format!("Ticket {i}"))
+            .status(status)
+            .category(category)
+            .build();
+        db.write_ticket(&ticket).await.unwrap();
+    }
+
+    let counts = db
+        .get_ticket_counts_by_status(&Default::default())
+        .await
+        .unwrap();
+    assert_eq!(
+        counts,
+        HashMap::from([
+            (Status::Requested, 3),
+            (Status::Cancelled, 0),
+            (Status::Confirmed, 2),
+            (Status::Denied, 1),
+            (Status::PaymentCompleted, 0),
+        ]),
+    );
+
+    let counts = db
+        .get_ticket_counts_by_status(&db::ticket::TicketFilter {
+            category: Some(Category::It),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(
+        counts,
+        HashMap::from([
+            (Status::Requested, 2),
+            (Status::Cancelled, 0),
+            (Status::Confirmed, 1),
+            (Status::Denied, 0),
+            (Status::PaymentCompleted, 0),
+        ]),
+    );
+}
+
+#[tokio::test]
+async fn filters_tickets_by_several_statuses() {
+    use db::ticket::Status;
+
+    let client = common::setup().await.auth("alice", "password").await;
+    let db = common::db().await;
+
+    for status in [
+        Status::Requested,
+        Status::Confirmed,
+        Status::Confirmed,
+        Status::Denied,
+        Status::PaymentCompleted,
+    ] {
+        let ticket = TicketBuilder::new(db::user::Id::from(1))
+            .status(status)
+            .build();
+        db.write_ticket(&ticket).await.unwrap();
+    }
+
+    for (statuses, expected) in [
+        (
+            "REQUESTED,CONFIRMED",
+            vec![Status::Requested, Status::Confirmed],
+        ),
+        ("DENIED", vec![Status::Denied]),
+        (
+            "CANCELLED, PAYMENT_COMPLETED",
+            vec![Status::PaymentCompleted],
+        ),
+    ] {
+        let params = [("offset", "0"), ("limit", "10"), ("status", statuses)];
+        let list = client.get_tickets_with(&params).await.unwrap();
+        assert!(
+            list.tickets.iter().all(|t| expected.contains(&t.status)),
+            "{statuses}",
+        );
+        for status in &expected {
+            assert!(
+                list.tickets.iter().any(|t| t.status == *status),
+                "{statuses}",
+            );
+        }
+
+        let count = client
+            .count_tickets_with(&[("status", statuses)])
+            .await
+            .unwrap();
+        assert_eq!(count.count, list.total_count, "{statuses}");
+        assert_eq!(list.tickets.len(), list.total_count, "{statuses}");
+    }
+}
+
+#[tokio::test]
+async fn rejects_invalid_status() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    for statuses in ["REQUESTED,UNKNOWN", "", "REQUESTED,"] {
+        let params = [("offset", "0"), ("limit", "10"), ("status", statuses)];
+        assert_eq!(
+            client.get_tickets_with(&params).await,
+            Err(StatusCode::BAD_REQUEST),
+            "{statuses}",
+        );
+        assert_eq!(
+            client.count_tickets_with(&[("status", statuses)]).await,
+            Err(StatusCode::BAD_REQUEST),
+            "{statuses}",
+        );
+    }
+}
+
+/// Returns `(ticket, initiator, purchasing manager, accounting manager)` ids
+/// and names of the `tickets`, resolving the users by the `users`.
+fn with_user_names(
+    tickets: &[db::Ticket],
+    users: &HashMap<db::user::Id, db::User>,
+) -> Vec<(db::ticket::Id, String, Option<String>, Option<String>)> {
+    let name = |id: &db::user::Id| users[id].name.clone();
+    tickets
+        .iter()
+        .map(|t| {
+            (
+                t.id,
+                name(&t.initiator),
+                t.purchasing_manager.as_ref().map(name),
+                t.accounting_manager.as_ref().map(name),
+            )
+        })
+        .collect()
+}
+
+/// Same as [`with_user_names()`], but for the `tickets` joined with their
+/// users.
+fn joined_user_names(
+    tickets: &[db::ticket::TicketWithUsers],
+) -> Vec<(db::ticket::Id, String, Option<String>, Option<String>)> {
+    tickets
+        .iter()
+        .map(|t| {
+            (
+                t.ticket.id,
+                t.initiator.name.clone(),
+                t.purchasing_manager.as_ref().map(|u| u.name.clone()),
+                t.accounting_manager.as_ref().map(|u| u.name.clone()),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn joins_same_users_as_looked_up_apart() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let created_at = OffsetDateTime::now_utc();
+    for (i, (initiator, purchasing_manager, accounting_manager)) in [
+        (1, None, None),
+        (5, Some(2), None),
+        (1, Some(2), Some(3)),
+        (5, None, None),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let ticket = TicketBuilder::new(db::user::Id::from(initiator))
+            .title(format!("Ticket {i}"))
+            .purchasing_manager(purchasing_manager.map(db::user::Id::from))
+            .accounting_manager(accounting_manager.map(db::user::Id::from))
+            .created_at(created_at + time::Duration::seconds(i as i64))
+            .build();
+        db.write_ticket(&ticket).await.unwrap();
+    }
+    let filter = db::ticket::TicketFilter::default();
+
+    for offset in [0, 1, 3, 4] {
+        let (page, total_count) = db
+            .get_tickets_page_with_count(offset, 2, &filter)
+            .await
+            .unwrap();
+        let (joined, joined_count) = db
+            .get_tickets_page_with_users(offset, 2, &filter, Default::default())
+            .await
+            .unwrap();
+        assert_eq!(joined_count, total_count, "offset {offset}");
+        assert_eq!(
+            joined_user_names(&joined),
+            with_user_names(&page, &users_of(&db, &page).await),
+            "offset {offset}",
+        );
+    }
+
+    let (first, _) =
+        db.get_tickets_page_with_count(0, 1, &filter).await.unwrap();
+    let page = db
+        .get_tickets_before(first[0].created_at, first[0].id, 2, &filter)
+        .await
+        .unwrap();
+    let joined = db
+        .get_tickets_before_with_users(
+            first[0].created_at,
+            first[0].id,
+            2,
+            &filter,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        joined_user_names(&joined),
+        with_user_names(&page, &users_of(&db, &page).await),
+    );
+}
+
+#[tokio::test]
+async fn sorts_tickets_by_initiator() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let eve = common::Client::new().auth("eve", "password").await;
+
+    eve.add_ticket("Eve's 1", "Description", 1).await.unwrap();
+    alice.add_ticket("Alice's", "Description", 1).await.unwrap();
+    eve.add_ticket("Eve's 2", "Description", 1).await.unwrap();
+
+    let initiators = |list: &api::ticket::List| {
+        list.tickets
+            .iter()
+            .map(|t| t.initiator.name.clone())
+            .collect::<Vec<_>>()
+    };
+
+    let asc = alice
+        .get_tickets_with(&[("limit", "10"), ("sort_by", "initiator")])
+        .await
+        .unwrap();
+    assert_eq!(initiators(&asc), ["Alice", "Eve", "Eve"]);
+    assert_eq!(asc.total_count, 3);
+    assert_eq!(asc.next_cursor, None);
+
+    let desc = alice
+        .get_tickets_with(&[
+            ("limit", "10"),
+            ("sort_by", "initiator"),
+            ("order", "desc"),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(initiators(&desc), ["Eve", "Eve", "Alice"]);
+    assert_eq!(desc.total_count, 3);
+
+    // Tickets of the same initiator are ordered by their IDs, so the pages
+    // neither overlap nor skip any.
+    let mut paged = Vec::new();
+    for offset in ["0", "1", "2"] {
+        let page = alice
+            .get_tickets_with(&[
+                ("offset", offset),
+                ("limit", "1"),
+                ("sort_by", "initiator"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(page.total_count, 3);
+        assert_eq!(page.next_cursor, None);
+        paged.extend(page.tickets);
+    }
+    assert_eq!(paged, asc.tickets);
+    assert!(paged[1].id.to_string() < paged[2].id.to_string());
+}
+
+#[tokio::test]
+async fn rejects_invalid_sort_order() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    let res = client
+        .get_tickets_with(&[
+            ("limit", "10"),
+            ("sort_by", "initiator"),
+            ("order", "sideways"),
+        ])
+        .await;
+    assert_eq!(res, Err(StatusCode::BAD_REQUEST));
+
+    let res = client
+        .get_tickets_with(&[("limit", "10"), ("sort_by", "title")])
+        .await;
+    assert_eq!(res, Err(StatusCode::BAD_REQUEST));
+}
+
+#[tokio::test]
+async fn rejects_cursor_with_non_default_order() {
+    let client = common::setup().await.auth("alice", "password").await;
+    client.add_ticket("Ticket", "Description", 1).await.unwrap();
+    let cursor = client.get_tickets(0, 1).await.unwrap().next_cursor.unwrap();
+
+    let res = client
+        .get_tickets_with(&[
+            ("limit", "10"),
+            ("before", &cursor),
+            ("sort_by", "initiator"),
+        ])
+        .await;
+    assert_eq!(res, Err(StatusCode::BAD_REQUEST));
+}
+
+#[tokio::test]
+async fn rejects_malformed_cursor() {
+    let client = common::setup().await.auth("alice", "password").await;
+
+    for cursor in ["", "not a cursor", "AAAA.AAAA"] {
+        let res = client.get_tickets_before_invalid(cursor).await;
+        assert_eq!(res.code, "INVALID_CURSOR");
+    }
+}
+
+#[tokio::test]
+async fn rejects_tampered_cursor() {
+    let client = common::setup().await.auth("alice", "password").await;
+    for i in 1..=2 {
+        client
+            .add_ticket(&format!("Ticket {i}"), "Description", 1)
+            .await
+            .unwrap();
+    }
+    let cursor = client.get_tickets(0, 1).await.unwrap().next_cursor.unwrap();
+
+    let (payload, signature) = cursor.split_once('.').unwrap();
+    let (time, id) = api::cursor::decode(payload).unwrap();
+    let forged = api::cursor::encode(time + Duration::from_secs(1), id);
+    let res = client
+        .get_tickets_before_invalid(&format!("{forged}.{signature}"))
+        .await;
+    assert_eq!(res.code, "INVALID_CURSOR");
+
+    let res = client.get_tickets_before_invalid(payload).await;
+    assert_eq!(res.code, "INVALID_CURSOR");
+}
+
+/// Looks up all the users the `tickets` refer to.
+async fn users_of(
+    db: &db::Client,
+    tickets: &[db::Ticket],
+) -> HashMap<db::user::Id, db::User> {
+    let ids = tickets
+        .iter()
+        .flat_map(|t| {
+            [
+                Some(t.initiator),
+                t.purchasing_manager,
+                t.accounting_manager,
+            ]
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+    db.get_users_by_ids(&ids).await.unwrap()
+}
@@ -0,0 +1,150 @@
+pub mod common;
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use dubna_internship::api;
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
+
+/// Checks whether the `login` is available on the server listening on the
+/// `addr`.
+async fn check(addr: &str, login: &str) -> Response {
+    request(&reqwest::Client::new(), addr, login)
+        .send()
+        .await
+        .unwrap()
+}
+
+/// Builds the check of whether the `login` is available on the server
+/// listening on the `addr`, sent by the `client`.
+fn request(
+    client: &reqwest::Client,
+    addr: &str,
+    login: &str,
+) -> RequestBuilder {
+    client
+        .get(format!("http://{addr}/auth/available"))
+        .query(&[("login", login)])
+}
+
+#[tokio::test]
+async fn is_disabled_by_default() {
+    const ADDR: &str = "127.0.0.1:3022";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(ADDR, "", &[]).await;
+
+    assert_eq!(check(ADDR, "alice").await.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn reports_whether_login_is_taken() {
+    const ADDR: &str = "127.0.0.1:3023";
+
+    let _client = common::setup().await;
+    let _server =
+        common::Server::spawn(ADDR, "[http.login_availability]", &[]).await;
+
+    let taken = check(ADDR, "alice")
+        .await
+        .error_for_status()
+        .unwrap()
+        .json::<api::user::LoginAvailability>()
+        .await
+        .unwrap();
+    assert!(!taken.available);
+
+    let free = check(ADDR, "nobody")
+        .await
+        .error_for_status()
+        .unwrap()
+        .json::<api::user::LoginAvailability>()
+        .await
+        .unwrap();
+    assert!(free.available);
+}
+
+#[tokio::test]
+async fn limits_rate_of_checks() {
+    const ADDR: &str = "127.0.0.1:3024";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(
+        ADDR,
+        "[http.login_availability]\nmax_requests = 2\nperiod = \"1h\"",
+        &[],
+    )
+    .await;
+
+    for _ in 0..2 {
+        assert_eq!(check(ADDR, "alice").await.status(), StatusCode::OK);
+    }
+    let resp = check(ADDR, "alice").await;
+    assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = resp.headers()[RETRY_AFTER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=3600).contains(&retry_after), "{retry_after}");
+}
+
+#[tokio::test]
+async fn limits_rate_of_checks_per_client() {
+    const ADDR: &str = "127.0.0.1:3031";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(
+        ADDR,
+        "[http.login_availability]\nmax_requests = 1\nperiod = \"1h\"",
+        &[],
+    )
+    .await;
+
+    // Whole `127.0.0.0/8` is the loopback, so these connect from different
+    // addresses.
+    let [first, second] = [1, 2].map(|host| {
+        reqwest::Client::builder()
+            .local_address(IpAddr::V4(Ipv4Addr::new(127, 0, 0, host)))
+            .build()
+            .unwrap()
+    });
+
+    let status = |res: reqwest::Result<Response>| res.unwrap().status();
+    let send = |client| request(client, ADDR, "alice").send();
+    assert_eq!(status(send(&first).await), StatusCode::OK);
+    assert_eq!(status(send(&first).await), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(status(send(&second).await), StatusCode::OK);
+    assert_eq!(status(send(&second).await), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn limits_rate_of_checks_per_forwarded_client() {
+    const ADDR: &str = "127.0.0.1:3032";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(
+        ADDR,
+        "[http.login_availability]\n\
+         max_requests = 1\n\
+         period = \"1h\"\n\
+         trust_forwarded_for = true",
+        &[],
+    )
+    .await;
+
+    let client = reqwest::Client::new();
+    // Only the last address is appended by the proxy, so the first one
+    // doesn't tell the client.
+    let send = |forwarded_for| {
+        request(&client, ADDR, "alice")
+            .header("X-Forwarded-For", forwarded_for)
+            .send()
+    };
+    let status = |res: reqwest::Result<Response>| res.unwrap().status();
+    assert_eq!(status(send("10.0.0.1").await), StatusCode::OK);
+    assert_eq!(
+        status(send("10.0.0.2, 10.0.0.1").await),
+        StatusCode::TOO_MANY_REQUESTS,
+    );
+    assert_eq!(status(send("10.0.0.2").await), StatusCode::OK);
+}
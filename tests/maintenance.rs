@@ -0,0 +1,123 @@
+pub mod common;
+
+use std::time::Duration;
+
+use dubna_internship::api;
+use reqwest::{header::RETRY_AFTER, StatusCode};
+use serde_json::json;
+
+/// Signs in as Alice on the server listening on the `addr`, returning the
+/// token.
+async fn auth(addr: &str) -> String {
+    reqwest::Client::new()
+        .post(format!("http://{addr}/auth"))
+        .json(&json!({"login": "alice", "password": "password"}))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json::<api::user::AuthResponse>()
+        .await
+        .unwrap()
+        .token
+}
+
+/// Adds a ticket on the server listening on the `addr`, returning the
+/// response status and its `Retry-After` header, if any.
+async fn add_ticket(addr: &str, token: &str) -> (StatusCode, Option<String>) {
+    let resp = reqwest::Client::new()
+        .post(format!("http://{addr}/ticket"))
+        .header("Authorization", format!("Bearer {token}"))
+        .json(&json!({
+            "title": "Ticket",
+            "description": "Description",
+            "count": 1,
+        }))
+        .send()
+        .await
+        .unwrap();
+    let retry_after = resp
+        .headers()
+        .get(RETRY_AFTER)
+        .map(|v| v.to_str().unwrap().to_owned());
+    (resp.status(), retry_after)
+}
+
+/// Lists the tickets on the server listening on the `addr`, returning the
+/// response status.
+async fn list_tickets(addr: &str, token: &str) -> StatusCode {
+    reqwest::Client::new()
+        .get(format!("http://{addr}/ticket?offset=0&limit=10"))
+        .header("Authorization", format!("Bearer {token}"))
+        .send()
+        .await
+        .unwrap()
+        .status()
+}
+
+/// Keeps adding tickets on the server listening on the `addr` until the
+/// `expected` status is responded with, as the server reloads its config
+/// asynchronously, giving up after a few seconds.
+///
+/// Returns the last response status.
+async fn await_add_ticket_status(
+    addr: &str,
+    token: &str,
+    expected: StatusCode,
+) -> StatusCode {
+    let mut status = add_ticket(addr, token).await.0;
+    for _ in 0..50 {
+        if status == expected {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        status = add_ticket(addr, token).await.0;
+    }
+    status
+}
+
+#[tokio::test]
+async fn rejects_writes_when_read_only() {
+    const ADDR: &str = "127.0.0.1:3017";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(
+        ADDR,
+        "[maintenance]\nread_only = true\nretry_after = \"2m\"",
+        &[],
+    )
+    .await;
+
+    let token = auth(ADDR).await;
+    assert_eq!(
+        add_ticket(ADDR, &token).await,
+        (StatusCode::SERVICE_UNAVAILABLE, Some("120".to_owned())),
+    );
+    assert_eq!(list_tickets(ADDR, &token).await, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn toggles_read_only_on_reload() {
+    const ADDR: &str = "127.0.0.1:3018";
+
+    let _client = common::setup().await;
+    let server = common::Server::spawn(ADDR, "", &[]).await;
+
+    let token = auth(ADDR).await;
+    assert_eq!(add_ticket(ADDR, &token).await.0, StatusCode::OK);
+
+    server.reload("[maintenance]\nread_only = true");
+    assert_eq!(
+        await_add_ticket_status(ADDR, &token, StatusCode::SERVICE_UNAVAILABLE)
+            .await,
+        StatusCode::SERVICE_UNAVAILABLE,
+    );
+    assert_eq!(list_tickets(ADDR, &token).await, StatusCode::OK);
+
+    server.reload("");
+    assert_eq!(
+        await_add_ticket_status(ADDR, &token, StatusCode::OK).await,
+        StatusCode::OK,
+    );
+}
@@ -0,0 +1,152 @@
+pub mod common;
+
+use std::{
+    env, fmt, fs,
+    sync::{Arc, Mutex},
+};
+
+use dubna_internship::db;
+use tokio_postgres::NoTls;
+use tracing::{
+    field::{Field, Visit},
+    Event, Level, Subscriber,
+};
+use tracing_subscriber::{
+    layer::{Context, SubscriberExt as _},
+    Layer, Registry,
+};
+
+#[tokio::test]
+async fn applies_pending_migrations_once() {
+    const SCHEMA: &str = "migrations_spec";
+
+    let (client, connection) =
+        tokio_postgres::connect(&common::database_url(), NoTls)
+            .await
+            .expect("failed to connect to the database");
+    tokio::spawn(connection);
+    client
+        .batch_execute(&format!(
+            "\
+            DROP SCHEMA IF EXISTS {SCHEMA} CASCADE; \
+            CREATE SCHEMA {SCHEMA};",
+        ))
+        .await
+        .expect("failed to create the schema");
+
+    let dir = env::temp_dir().join(format!(
+        "dubna-internship-{}-migrations",
+        std::process::id(),
+    ));
+    _ = fs::remove_dir_all(&dir);
+    for (name, sql) in [
+        (
+            "0001_create_items",
+            "CREATE TABLE items (id INT PRIMARY KEY);",
+        ),
+        ("0002_insert_item", "INSERT INTO items (id) VALUES (1);"),
+    ] {
+        fs::create_dir_all(dir.join(name))
+            .expect("failed to create a directory");
+        fs::write(dir.join(name).join("up.sql"), sql)
+            .expect("failed to write a file");
+    }
+
+    let db = common::db_in_schema(SCHEMA).await;
+
+    let applied = db::migrations::run_pending(&db, &dir).await.unwrap();
+    assert_eq!(applied, 2);
+    let applied = db::migrations::run_pending(&db, &dir).await.unwrap();
+    assert_eq!(applied, 0);
+
+    let count = client
+        .query_one(&format!("SELECT COUNT(*) FROM {SCHEMA}.items"), &[])
+        .await
+        .unwrap()
+        .get::<_, i64>(0);
+    assert_eq!(count, 1);
+
+    let version = db.get_schema_version().await.unwrap();
+    assert_eq!(version.as_deref(), Some("0002_insert_item"));
+
+    _ = fs::remove_dir_all(&dir);
+}
+
+/// [`Layer`] capturing the `notice` field of all the debug events.
+#[derive(Clone, Default)]
+struct Notices(Arc<Mutex<Vec<String>>>);
+
+impl<S: Subscriber> Layer<S> for Notices {
+    fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+        if *event.metadata().level() == Level::DEBUG {
+            event.record(&mut NoticeVisitor(&mut self.0.lock().unwrap()));
+        }
+    }
+}
+
+struct NoticeVisitor<'a>(&'a mut Vec<String>);
+
+impl Visit for NoticeVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "notice" {
+            self.0.push(value.to_owned());
+        }
+    }
+
+    fn record_debug(&mut self, _: &Field, _: &dyn fmt::Debug) {}
+}
+
+#[tokio::test]
+async fn logs_notices_raised_by_migrations() {
+    const SCHEMA: &str = "notices_spec";
+
+    let notices = Notices::default();
+    let _guard = tracing::subscriber::set_default(
+        Registry::default().with(notices.clone()),
+    );
+
+    let (client, connection) =
+        tokio_postgres::connect(&common::database_url(), NoTls)
+            .await
+            .expect("failed to connect to the database");
+    tokio::spawn(connection);
+    client
+        .batch_execute(&format!(
+            "\
+            DROP SCHEMA IF EXISTS {SCHEMA} CASCADE; \
+            CREATE SCHEMA {SCHEMA};",
+        ))
+        .await
+        .expect("failed to create the schema");
+
+    let dir = env::temp_dir()
+        .join(format!("dubna-internship-{}-notices", std::process::id(),));
+    _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("0001_raise_notice"))
+        .expect("failed to create a directory");
+    fs::write(
+        dir.join("0001_raise_notice").join("up.sql"),
+        "DO $$ BEGIN RAISE NOTICE 'hello from migration'; END $$;",
+    )
+    .expect("failed to write a file");
+
+    let db = common::db_in_schema(SCHEMA).await;
+    let applied = db::migrations::run_pending(&db, &dir).await.unwrap();
+    assert_eq!(applied, 1);
+
+    assert!(
+        notices
+            .0
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|n| n == "hello from migration"),
+        "notice isn't logged",
+    );
+
+    client
+        .batch_execute(&format!("DROP SCHEMA {SCHEMA} CASCADE"))
+        .await
+        .expect("failed to drop the schema");
+    _ = fs::remove_dir_all(&dir);
+}
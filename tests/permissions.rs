@@ -0,0 +1,94 @@
+pub mod common;
+
+use dubna_internship::api::Permissions;
+use reqwest::{header::CACHE_CONTROL, StatusCode};
+
+async fn permissions_of(login: &str) -> Permissions {
+    common::setup()
+        .await
+        .auth(login, "password")
+        .await
+        .permissions()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn lets_initiator_create_tickets() {
+    assert_eq!(
+        permissions_of("alice").await,
+        Permissions {
+            can_create_ticket: true,
+            can_confirm_ticket: false,
+            can_deny_ticket: false,
+            can_mark_as_paid: false,
+            can_manage_users: false,
+        },
+    );
+}
+
+#[tokio::test]
+async fn lets_purchasing_manager_confirm_and_deny_tickets() {
+    assert_eq!(
+        permissions_of("bob").await,
+        Permissions {
+            can_create_ticket: false,
+            can_confirm_ticket: true,
+            can_deny_ticket: true,
+            can_mark_as_paid: false,
+            can_manage_users: false,
+        },
+    );
+}
+
+#[tokio::test]
+async fn lets_accounting_manager_mark_tickets_as_paid() {
+    assert_eq!(
+        permissions_of("charlie").await,
+        Permissions {
+            can_create_ticket: false,
+            can_confirm_ticket: false,
+            can_deny_ticket: false,
+            can_mark_as_paid: true,
+            can_manage_users: false,
+        },
+    );
+}
+
+#[tokio::test]
+async fn lets_admin_manage_users() {
+    assert_eq!(
+        permissions_of("dave").await,
+        Permissions {
+            can_create_ticket: false,
+            can_confirm_ticket: false,
+            can_deny_ticket: false,
+            can_mark_as_paid: false,
+            can_manage_users: true,
+        },
+    );
+}
+
+#[tokio::test]
+async fn is_cached_privately() {
+    let res = common::setup()
+        .await
+        .auth("alice", "password")
+        .await
+        .permissions()
+        .await
+        .unwrap();
+    assert_eq!(
+        res.headers()[CACHE_CONTROL].to_str().unwrap(),
+        "private, max-age=300",
+    );
+}
+
+#[tokio::test]
+async fn fails_when_unauthorized() {
+    let status = common::setup().await.permissions().await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,92 @@
+pub mod common;
+
+use std::time::Duration;
+
+use dubna_internship::db;
+
+/// Polls the metrics of the primary pool of the `db` until the `done` ones
+/// are reported, giving up after a second.
+async fn await_metrics(
+    db: &db::Client,
+    done: impl Fn(&db::PoolMetrics) -> bool,
+) -> db::PoolMetrics {
+    let mut metrics = db.pool_metrics()[0];
+    for _ in 0..100 {
+        if done(&metrics) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        metrics = db.pool_metrics()[0];
+    }
+    metrics
+}
+
+#[tokio::test]
+async fn reports_idle_connections() {
+    let _client = common::setup().await;
+    let db = common::db_with_pool(1, None).await;
+
+    let metrics = db.pool_metrics();
+    assert_eq!(metrics.len(), 1);
+    assert_eq!(metrics[0].pool, "primary");
+    assert_eq!(metrics[0].size, 1);
+    assert_eq!(metrics[0].idle, 1);
+    assert_eq!(metrics[0].waiting, 0);
+}
+
+#[tokio::test]
+async fn reports_tasks_waiting_for_connection() {
+    let _client = common::setup().await;
+    let db = common::db_with_pool(1, None).await;
+
+    let conn = db.connection().await.unwrap();
+    let count = tokio::spawn({
+        let db = db.clone();
+        async move { db.get_tickets_count(&Default::default()).await }
+    });
+
+    let metrics = await_metrics(&db, |m| m.waiting > 0).await;
+    assert_eq!(metrics.waiting, 1);
+    assert_eq!(metrics.idle, 0);
+
+    drop(conn);
+    count.await.unwrap().unwrap();
+    let metrics = await_metrics(&db, |m| m.waiting == 0).await;
+    assert_eq!(metrics.waiting, 0);
+    assert_eq!(metrics.idle, 1);
+}
+
+#[tokio::test]
+async fn counts_acquire_timeouts() {
+    let _client = common::setup().await;
+    let db = common::db_with_pool(1, Some(Duration::from_millis(50))).await;
+
+    let conn = db.connection().await.unwrap();
+    let res = db.connection().await;
+    assert!(matches!(res, Err(db::Error::Pool(_))), "{:?}", res.err());
+    drop(conn);
+
+    assert_eq!(db.pool_metrics()[0].acquire_timeouts, 1);
+}
+
+#[tokio::test]
+async fn exposes_metrics_over_http() {
+    const ADDR: &str = "127.0.0.1:3020";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(ADDR, "", &[]).await;
+
+    let resp = reqwest::get(format!("http://{ADDR}/metrics"))
+        .await
+        .unwrap();
+    assert!(resp.status().is_success(), "{}", resp.status());
+    let body = resp.text().await.unwrap();
+    assert!(
+        body.contains("db_pool_connections{pool=\"primary\"}"),
+        "{body}"
+    );
+    assert!(
+        body.contains("db_pool_acquire_timeouts_total{pool=\"primary\"} 0"),
+        "{body}",
+    );
+}
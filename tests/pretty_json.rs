@@ -0,0 +1,36 @@
+pub mod common;
+
+/// Requests the version of the server listening on the `addr`, returning
+/// the response body.
+async fn get_version(addr: &str) -> String {
+    reqwest::get(format!("http://{addr}/version"))
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn responds_with_compact_json_by_default() {
+    const ADDR: &str = "127.0.0.1:3010";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(ADDR, "", &[]).await;
+
+    let body = get_version(ADDR).await;
+    assert!(!body.contains('\n'), "{body}");
+}
+
+#[tokio::test]
+async fn pretty_prints_json_when_enabled() {
+    const ADDR: &str = "127.0.0.1:3011";
+
+    let _client = common::setup().await;
+    let _server =
+        common::Server::spawn(ADDR, "[http]\npretty_json = true", &[]).await;
+
+    let body = get_version(ADDR).await;
+    assert!(body.contains('\n'), "{body}");
+    serde_json::from_str::<serde_json::Value>(&body).unwrap();
+}
@@ -0,0 +1,74 @@
+pub mod common;
+
+use dubna_internship::db::{self, ticket::TicketBuilder};
+use tokio_postgres::NoTls;
+
+fn ticket(price: Option<f64>) -> db::Ticket {
+    TicketBuilder::new(db::user::Id::from(1))
+        .title("Ticket 1")
+        .description("Description 1")
+        .price(price)
+        .build()
+}
+
+/// Creates a schema with a copy of the `tickets` table having its `price`
+/// column migrated to `NUMERIC`, and connects to it.
+async fn numeric_price_db() -> db::Client {
+    const SCHEMA: &str = "numeric_price";
+
+    let (client, connection) =
+        tokio_postgres::connect(&common::database_url(), NoTls)
+            .await
+            .expect("failed to connect to the database");
+    tokio::spawn(connection);
+
+    client
+        .batch_execute(&format!(
+            "\
+            DROP SCHEMA IF EXISTS {SCHEMA} CASCADE; \
+            CREATE SCHEMA {SCHEMA}; \
+            CREATE TABLE {SCHEMA}.tickets (LIKE public.tickets INCLUDING ALL); \
+            ALTER TABLE {SCHEMA}.tickets \
+                ALTER COLUMN price TYPE NUMERIC(12, 2);",
+        ))
+        .await
+        .expect("failed to create the schema");
+
+    common::db_in_schema(SCHEMA).await
+}
+
+#[tokio::test]
+async fn reads_and_writes_float_price() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    for price in [Some(12.5), None] {
+        let ticket = ticket(price);
+        db.write_ticket(&ticket).await.unwrap();
+
+        let found = db
+            .get_ticket_by_id(ticket.id, Default::default())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.price, price);
+    }
+}
+
+#[tokio::test]
+async fn reads_and_writes_numeric_price() {
+    let _client = common::setup().await;
+    let db = numeric_price_db().await;
+
+    for price in [Some(12.5), None] {
+        let ticket = ticket(price);
+        db.write_ticket(&ticket).await.unwrap();
+
+        let found = db
+            .get_ticket_by_id(ticket.id, Default::default())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.price, price);
+    }
+}
@@ -0,0 +1,28 @@
+pub mod common;
+
+use reqwest::StatusCode;
+
+#[tokio::test]
+async fn reports_liveness() {
+    let status = common::Client::new().healthz().await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn reports_readiness() {
+    let status = common::Client::new().readyz().await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn reports_version() {
+    let client = common::Client::new();
+
+    let first = client.version().await;
+    assert_eq!(first.version, env!("CARGO_PKG_VERSION"));
+    assert!(first.started_at <= time::OffsetDateTime::now_utc());
+
+    let second = client.version().await;
+    assert_eq!(second.started_at, first.started_at);
+    assert!(second.uptime_secs >= first.uptime_secs);
+}
@@ -0,0 +1,132 @@
+pub mod common;
+
+use dubna_internship::{
+    api,
+    db::{self, audit::Entity, ticket::Visibility},
+};
+use serde_json::json;
+
+/// Writes the `ticket` and records its `action` within a savepoint, failing
+/// afterwards if `fail` is set.
+async fn apply(
+    tx: &mut db::Transaction<'_>,
+    name: &str,
+    ticket: db::Ticket,
+    action: &'static str,
+    fail: bool,
+) -> Result<(), db::Error> {
+    tx.savepoint(name, |sp| {
+        Box::pin(async move {
+            sp.write_ticket(&ticket).await?;
+            sp.insert_event(
+                api::user::Id::from(1),
+                Entity::Ticket(ticket.id),
+                action,
+                &json!({ "op": action }),
+                &ticket.snapshot(),
+            )
+            .await?;
+            if fail {
+                return Err(db::Error::OutOfRange { param: "count" });
+            }
+            Ok(())
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn rolls_back_only_failed_operation() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let db = common::db().await;
+
+    let id = alice
+        .add_ticket("Ticket", "Description", 1)
+        .await
+        .unwrap()
+        .id;
+    let ticket = db
+        .get_ticket_by_id(id, Visibility::ActiveOnly)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let mut conn = db.connection().await.unwrap();
+    let mut tx = conn.transaction().await.unwrap();
+    let renamed = db::Ticket {
+        title: "Renamed".into(),
+        ..ticket.clone()
+    };
+    apply(&mut tx, "op_1", renamed.clone(), "rename", false)
+        .await
+        .unwrap();
+    let recounted = db::Ticket {
+        count: 5,
+        ..renamed.clone()
+    };
+    let res = apply(&mut tx, "op_2", recounted, "recount", true).await;
+    assert!(res.is_err(), "expected the operation to fail");
+    let described = db::Ticket {
+        description: "Described".into(),
+        ..renamed
+    };
+    apply(&mut tx, "op_3", described, "describe", false)
+        .await
+        .unwrap();
+    tx.commit().await.unwrap();
+    drop(conn);
+
+    let found = db
+        .get_ticket_by_id(id, Visibility::ActiveOnly)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.title, "Renamed");
+    assert_eq!(found.description, "Described");
+    assert_eq!(found.count, 1);
+
+    let actions = db
+        .get_events_for_ticket(id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.action)
+        .collect::<Vec<_>>();
+    assert_eq!(actions, ["create", "rename", "describe"]);
+}
+
+#[tokio::test]
+async fn leaves_ticket_untouched_by_failed_operation() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let db = common::db().await;
+
+    let id = alice
+        .add_ticket("Ticket", "Description", 1)
+        .await
+        .unwrap()
+        .id;
+    let ticket = db
+        .get_ticket_by_id(id, Visibility::ActiveOnly)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let mut conn = db.connection().await.unwrap();
+    let mut tx = conn.transaction().await.unwrap();
+    let renamed = db::Ticket {
+        title: "Renamed".into(),
+        ..ticket
+    };
+    let res = apply(&mut tx, "op", renamed, "rename", true).await;
+    assert!(res.is_err(), "expected the operation to fail");
+    tx.commit().await.unwrap();
+    drop(conn);
+
+    let found = db
+        .get_ticket_by_id(id, Visibility::ActiveOnly)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(found.title, "Ticket");
+    assert_eq!(db.get_events_for_ticket(id).await.unwrap().len(), 1);
+}
@@ -0,0 +1,58 @@
+pub mod common;
+
+use dubna_internship::db::{self, Storage as _};
+use tokio_postgres::NoTls;
+
+#[tokio::test]
+async fn has_expected_schema_version() {
+    let db = common::db().await;
+
+    let version = db.get_schema_version().await.unwrap();
+    assert_eq!(version.as_deref(), Some(db::SCHEMA_VERSION));
+    db.check_schema_version(db::SCHEMA_VERSION).await.unwrap();
+}
+
+#[tokio::test]
+async fn reports_missing_columns_of_unversioned_schema() {
+    const SCHEMA: &str = "schema_spec";
+
+    let (client, connection) =
+        tokio_postgres::connect(&common::database_url(), NoTls)
+            .await
+            .expect("failed to connect to the database");
+    tokio::spawn(connection);
+    // Tables of the schema shadow the complete ones in `public`.
+    client
+        .batch_execute(&format!(
+            "\
+            DROP SCHEMA IF EXISTS {SCHEMA} CASCADE; \
+            CREATE SCHEMA {SCHEMA}; \
+            CREATE TABLE {SCHEMA}.schema_version (\
+                version     TEXT PRIMARY KEY, \
+                applied_at  TIMESTAMPTZ NOT NULL DEFAULT now()\
+            ); \
+            CREATE TABLE {SCHEMA}.tickets (\
+                id     UUID PRIMARY KEY, \
+                title  TEXT NOT NULL\
+            );",
+        ))
+        .await
+        .expect("failed to create the schema");
+
+    let db = common::db_in_schema(SCHEMA).await;
+
+    assert_eq!(db.get_schema_version().await.unwrap(), None);
+    let err = db
+        .check_schema_version(db::SCHEMA_VERSION)
+        .await
+        .unwrap_err();
+    let message = err.to_string();
+    assert!(message.contains("tickets.payment_reference"), "{message}");
+    assert!(!message.contains("tickets.title"), "{message}");
+    assert!(!message.contains("users."), "{message}");
+
+    client
+        .batch_execute(&format!("DROP SCHEMA {SCHEMA} CASCADE"))
+        .await
+        .expect("failed to drop the schema");
+}
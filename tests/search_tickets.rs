@@ -0,0 +1,145 @@
+pub mod common;
+
+use dubna_internship::{
+    api,
+    db::{
+        self,
+        ticket::{TextSearchConfig, TicketBuilder, TicketFilter},
+    },
+};
+use serde_json::json;
+
+async fn write_ticket(db: &db::Client, title: &str, description: &str) {
+    let ticket = TicketBuilder::new(db::user::Id::from(1))
+        .title(title)
+        .description(description)
+        .build();
+    db.write_ticket(&ticket).await.unwrap();
+}
+
+fn titles(list: &api::ticket::List) -> Vec<&str> {
+    list.tickets.iter().map(|t| t.title.as_str()).collect()
+}
+
+#[tokio::test]
+async fn matches_text_literally_without_full_text_search() {
+    let client = common::setup().await.auth("alice", "password").await;
+    let db = common::db().await;
+    write_ticket(&db, "Monitor", "24 inch, 100% sRGB").await;
+    write_ticket(&db, "Keyboard", "For the monitor stand").await;
+    write_ticket(&db, "Mouse", "Wireless").await;
+
+    let found = client
+        .get_tickets_with(&[("limit", "10"), ("q", "MONITOR")])
+        .await
+        .unwrap();
+    assert_eq!(titles(&found), ["Keyboard", "Monitor"]);
+    assert_eq!(found.total_count, 2);
+
+    let found = client
+        .get_tickets_with(&[("limit", "10"), ("q", "100%")])
+        .await
+        .unwrap();
+    assert_eq!(titles(&found), ["Monitor"]);
+
+    let found = client
+        .get_tickets_with(&[("limit", "10"), ("q", "_")])
+        .await
+        .unwrap();
+    assert!(found.tickets.is_empty());
+}
+
+#[tokio::test]
+async fn matches_stems_with_russian_config() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+    write_ticket(&db, "Monitor", "For the design team").await;
+    write_ticket(&db, "Мониторы", "Для отдела закупок").await;
+    write_ticket(&db, "Keyboard", "Wireless").await;
+
+    let search = |query: &'static str, config: TextSearchConfig| {
+        let db = &db;
+        async move {
+            let (tickets, total_count) = db
+                .search_tickets(
+                    query,
+                    config,
+                    0,
+                    10,
+                    &TicketFilter::default(),
+                    Default::default(),
+                )
+                .await
+                .unwrap();
+            let titles = tickets
+                .into_iter()
+                .map(|t| t.ticket.title)
+                .collect::<Vec<_>>();
+            assert_eq!(titles.len(), total_count);
+            titles
+        }
+    };
+
+    assert_eq!(
+        search("monitors", TextSearchConfig::Russian).await,
+        ["Monitor"]
+    );
+    assert_eq!(
+        search("монитор", TextSearchConfig::Russian).await,
+        ["Мониторы"]
+    );
+    assert!(search("monitors", TextSearchConfig::Simple)
+        .await
+        .is_empty());
+    assert_eq!(
+        search("monitor", TextSearchConfig::Simple).await,
+        ["Monitor"]
+    );
+    assert_eq!(
+        search("monitor -design", TextSearchConfig::Russian).await,
+        Vec::<String>::new(),
+    );
+}
+
+#[tokio::test]
+async fn searches_by_q_when_enabled() {
+    const ADDR: &str = "127.0.0.1:3027";
+
+    let _client = common::setup().await;
+    let db = common::db().await;
+    write_ticket(&db, "Monitor", "For the design team").await;
+    write_ticket(&db, "Keyboard", "Wireless").await;
+    let _server = common::Server::spawn(
+        ADDR,
+        "[tickets.full_text_search]\nconfig = \"russian\"",
+        &[],
+    )
+    .await;
+
+    let http = reqwest::Client::new();
+    let token = http
+        .post(format!("http://{ADDR}/auth"))
+        .json(&json!({ "login": "alice", "password": "password" }))
+        .send()
+        .await
+        .unwrap()
+        .json::<api::user::AuthResponse>()
+        .await
+        .unwrap()
+        .token;
+    let found = http
+        .get(format!("http://{ADDR}/ticket"))
+        .bearer_auth(&token)
+        .query(&[("limit", "10"), ("q", "monitors")])
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json::<api::ticket::List>()
+        .await
+        .unwrap();
+    assert_eq!(titles(&found), ["Monitor"]);
+    assert_eq!(found.total_count, 1);
+    assert_eq!(found.next_cursor, None);
+}
@@ -0,0 +1,52 @@
+#![cfg(unix)]
+
+pub mod common;
+
+use std::time::Duration;
+
+use tokio::{
+    io::{AsyncReadExt as _, AsyncWriteExt as _},
+    net::TcpStream,
+    time,
+};
+
+#[tokio::test]
+async fn completes_in_flight_request_on_sigterm() {
+    const ADDR: &str = "127.0.0.1:3001";
+
+    let _client = common::setup().await;
+    let mut server = common::Server::spawn(ADDR, "", &[]).await;
+
+    // Send the request head only, so the request is in flight while its
+    // handler waits for the body.
+    let body = r#"{"login":"alice","password":"password"}"#;
+    let mut stream = TcpStream::connect(ADDR).await.unwrap();
+    stream
+        .write_all(
+            format!(
+                "POST /auth HTTP/1.1\r\n\
+                 Host: localhost\r\n\
+                 Content-Type: application/json\r\n\
+                 Content-Length: {}\r\n\
+                 \r\n",
+                body.len(),
+            )
+            .as_bytes(),
+        )
+        .await
+        .unwrap();
+    time::sleep(Duration::from_millis(100)).await;
+
+    server.terminate();
+    time::sleep(Duration::from_millis(100)).await;
+
+    stream.write_all(body.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    assert!(
+        response.starts_with("HTTP/1.1 200 OK"),
+        "unexpected response: {response}",
+    );
+
+    assert!(server.wait().success());
+}
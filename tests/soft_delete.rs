@@ -0,0 +1,135 @@
+pub mod common;
+
+use dubna_internship::db::{
+    self,
+    ticket::{
+        Status, StatusUpdateFields, TicketBuilder, TicketFilter, Visibility,
+    },
+};
+use futures::TryStreamExt as _;
+
+async fn write_ticket(db: &db::Client) -> db::Ticket {
+    let ticket = TicketBuilder::new(db::user::Id::from(1)).build();
+    db.write_ticket(&ticket).await.unwrap();
+    ticket
+}
+
+fn ids(tickets: &[db::Ticket]) -> Vec<db::ticket::Id> {
+    tickets.iter().map(|t| t.id).collect()
+}
+
+#[tokio::test]
+async fn hides_deleted_ticket_unless_included() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+    let deleted = write_ticket(&db).await;
+    let kept = write_ticket(&db).await;
+
+    assert!(db.delete_ticket(deleted.id).await.unwrap());
+
+    let active = TicketFilter::default();
+    let all = TicketFilter {
+        visibility: Visibility::IncludeDeleted,
+        ..TicketFilter::default()
+    };
+
+    let found = db
+        .get_ticket_by_id(deleted.id, Visibility::ActiveOnly)
+        .await
+        .unwrap();
+    assert!(found.is_none());
+    let found = db
+        .get_ticket_by_id(deleted.id, Visibility::IncludeDeleted)
+        .await
+        .unwrap();
+    assert_eq!(found.map(|t| t.id), Some(deleted.id));
+
+    let page = db
+        .get_tickets_page(0, 10, Visibility::ActiveOnly)
+        .await
+        .unwrap();
+    assert_eq!(ids(&page), [kept.id]);
+    let page = db
+        .get_tickets_page(0, 10, Visibility::IncludeDeleted)
+        .await
+        .unwrap();
+    assert_eq!(ids(&page), [kept.id, deleted.id]);
+
+    let (page, total_count) = db
+        .get_tickets_page_with_count(0, 10, &active)
+        .await
+        .unwrap();
+    assert_eq!((ids(&page), total_count), (vec![kept.id], 1));
+    let (page, total_count) =
+        db.get_tickets_page_with_count(0, 10, &all).await.unwrap();
+    assert_eq!((ids(&page), total_count), (vec![kept.id, deleted.id], 2));
+
+    let (page, total_count) = db
+        .get_tickets_page_with_users(0, 10, &active, Default::default())
+        .await
+        .unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(total_count, 1);
+
+    assert_eq!(db.get_tickets_count(&active).await.unwrap(), 1);
+    assert_eq!(db.get_tickets_count(&all).await.unwrap(), 2);
+
+    let counts = db.get_ticket_counts_by_status(&active).await.unwrap();
+    assert_eq!(counts[&Status::Requested], 1);
+    let counts = db.get_ticket_counts_by_status(&all).await.unwrap();
+    assert_eq!(counts[&Status::Requested], 2);
+
+    let streamed = db
+        .stream_tickets(None, Visibility::ActiveOnly)
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(ids(&streamed), [kept.id]);
+    let streamed = db
+        .stream_tickets(None, Visibility::IncludeDeleted)
+        .await
+        .unwrap()
+        .try_collect::<Vec<_>>()
+        .await
+        .unwrap();
+    assert_eq!(ids(&streamed), [kept.id, deleted.id]);
+}
+
+#[tokio::test]
+async fn deletes_ticket_once() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+    let ticket = write_ticket(&db).await;
+
+    assert!(db.delete_ticket(ticket.id).await.unwrap());
+    assert!(!db.delete_ticket(ticket.id).await.unwrap());
+    assert!(!db.delete_ticket(db::ticket::Id::new()).await.unwrap());
+}
+
+#[tokio::test]
+async fn doesnt_change_status_of_deleted_ticket() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+    let ticket = write_ticket(&db).await;
+    db.delete_ticket(ticket.id).await.unwrap();
+
+    let updated = db
+        .update_ticket_status(
+            ticket.id,
+            Status::Requested,
+            Status::Cancelled,
+            &StatusUpdateFields::default(),
+        )
+        .await
+        .unwrap();
+    assert!(!updated);
+
+    let ticket = db
+        .get_ticket_by_id(ticket.id, Visibility::IncludeDeleted)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(ticket.status, Status::Requested);
+}
@@ -0,0 +1,72 @@
+pub mod common;
+
+use dubna_internship::{
+    api::{
+        self,
+        validation::{Code, Errors, FieldError},
+    },
+    db::{self, ticket::TicketBuilder},
+};
+use reqwest::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn rejects_non_positive_count_in_database() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let ticket = TicketBuilder::new(db::user::Id::from(1)).count(0).build();
+    let e = db.write_ticket(&ticket).await.unwrap_err();
+    assert_eq!(e.violated_check(), Some("count"));
+}
+
+#[tokio::test]
+async fn enforces_configured_count_range() {
+    const ADDR: &str = "127.0.0.1:3026";
+
+    let _client = common::setup().await;
+    let _server = common::Server::spawn(
+        ADDR,
+        "[tickets]\nmin_count = 2\nmax_count = 5",
+        &[],
+    )
+    .await;
+
+    let http = reqwest::Client::new();
+    let token = http
+        .post(format!("http://{ADDR}/auth"))
+        .json(&json!({ "login": "alice", "password": "password" }))
+        .send()
+        .await
+        .unwrap()
+        .json::<api::user::AuthResponse>()
+        .await
+        .unwrap()
+        .token;
+    let add_ticket = |count: usize| {
+        http.post(format!("http://{ADDR}/ticket"))
+            .bearer_auth(&token)
+            .json(&json!({
+                "title": "Ticket",
+                "description": "Description",
+                "count": count,
+            }))
+            .send()
+    };
+
+    for (count, code) in [(1, Code::TooSmall), (6, Code::TooLarge)] {
+        let res = add_ticket(count).await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY, "{count}");
+        assert_eq!(
+            res.json::<Errors>().await.unwrap().errors,
+            [FieldError {
+                field: "count".into(),
+                code,
+            }],
+        );
+    }
+    for count in [2, 5] {
+        let res = add_ticket(count).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK, "{count}");
+    }
+}
@@ -0,0 +1,127 @@
+pub mod common;
+
+use std::collections::HashMap;
+
+use dubna_internship::db::{
+    self,
+    ticket::{Status, TicketBuilder, TicketFilter, TicketStats},
+    user,
+};
+use time::{Duration, OffsetDateTime};
+
+/// Writes the tickets of a known dataset, returning a moment between the
+/// creation of the older tickets and the newer ones.
+async fn seed(db: &db::Client) -> OffsetDateTime {
+    let now = OffsetDateTime::now_utc();
+    let day_ago = now - Duration::days(1);
+    for (i, (status, price, initiator, created_at)) in [
+        (Status::Requested, None, 1, day_ago),
+        (Status::Cancelled, None, 5, day_ago),
+        (Status::Confirmed, Some(100.0), 1, day_ago),
+        (Status::Confirmed, Some(250.0), 5, now),
+        (Status::Denied, None, 1, now),
+        (Status::PaymentCompleted, Some(40.0), 1, now),
+        (Status::PaymentCompleted, Some(60.0), 5, now),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let ticket = TicketBuilder::new(user::Id::from(initiator))
+            .title(format!("Ticket {i}"))
+            .status(status)
+            .price(price)
+            .created_at(created_at)
+            .build();
+        db.write_ticket(&ticket).await.unwrap();
+    }
+    now - Duration::hours(1)
+}
+
+#[tokio::test]
+async fn aggregates_all_tickets() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+    seed(&db).await;
+
+    assert_eq!(
+        db.get_ticket_stats(&TicketFilter::default()).await.unwrap(),
+        TicketStats {
+            counts_by_status: HashMap::from([
+                (Status::Requested, 1),
+                (Status::Cancelled, 1),
+                (Status::Confirmed, 2),
+                (Status::Denied, 1),
+                (Status::PaymentCompleted, 2),
+            ]),
+            confirmed_total_price: 350.0,
+            paid_total_price: 100.0,
+        },
+    );
+}
+
+#[tokio::test]
+async fn aggregates_tickets_of_initiator() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+    seed(&db).await;
+
+    let filter = TicketFilter {
+        initiator: Some(user::Id::from(1)),
+        ..TicketFilter::default()
+    };
+    assert_eq!(
+        db.get_ticket_stats(&filter).await.unwrap(),
+        TicketStats {
+            counts_by_status: HashMap::from([
+                (Status::Requested, 1),
+                (Status::Cancelled, 0),
+                (Status::Confirmed, 1),
+                (Status::Denied, 1),
+                (Status::PaymentCompleted, 1),
+            ]),
+            confirmed_total_price: 100.0,
+            paid_total_price: 40.0,
+        },
+    );
+}
+
+#[tokio::test]
+async fn aggregates_tickets_created_within_period() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+    let hour_ago = seed(&db).await;
+
+    let filter = TicketFilter {
+        created_after: Some(hour_ago),
+        ..TicketFilter::default()
+    };
+    assert_eq!(
+        db.get_ticket_stats(&filter).await.unwrap(),
+        TicketStats {
+            counts_by_status: HashMap::from([
+                (Status::Requested, 0),
+                (Status::Cancelled, 0),
+                (Status::Confirmed, 1),
+                (Status::Denied, 1),
+                (Status::PaymentCompleted, 2),
+            ]),
+            confirmed_total_price: 250.0,
+            paid_total_price: 100.0,
+        },
+    );
+}
+
+#[tokio::test]
+async fn aggregates_no_tickets_as_zeros() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    assert_eq!(
+        db.get_ticket_stats(&TicketFilter::default()).await.unwrap(),
+        TicketStats {
+            counts_by_status: Status::ALL.map(|s| (s, 0)).into(),
+            confirmed_total_price: 0.0,
+            paid_total_price: 0.0,
+        },
+    );
+}
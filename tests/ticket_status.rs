@@ -0,0 +1,120 @@
+pub mod common;
+
+use dubna_internship::db::{
+    self,
+    ticket::{Status, StatusUpdateFields, TicketBuilder},
+};
+
+const PURCHASING_MANAGER: u128 = 2;
+
+async fn write_requested_ticket(db: &db::Client) -> db::Ticket {
+    let ticket = TicketBuilder::new(db::user::Id::from(1)).build();
+    db.write_ticket(&ticket).await.unwrap();
+    ticket
+}
+
+fn confirmation() -> StatusUpdateFields {
+    StatusUpdateFields {
+        price: Some(100.0),
+        purchasing_manager: Some(db::user::Id::from(PURCHASING_MANAGER)),
+        ..StatusUpdateFields::default()
+    }
+}
+
+#[tokio::test]
+async fn updates_status_with_fields() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+    let ticket = write_requested_ticket(&db).await;
+
+    let updated = db
+        .update_ticket_status(
+            ticket.id,
+            Status::Requested,
+            Status::Confirmed,
+            &confirmation(),
+        )
+        .await
+        .unwrap();
+    assert!(updated);
+
+    let ticket = db
+        .get_ticket_by_id(ticket.id, Default::default())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(ticket.status, Status::Confirmed);
+    assert_eq!(ticket.price, Some(100.0));
+    assert_eq!(
+        ticket.purchasing_manager,
+        Some(db::user::Id::from(PURCHASING_MANAGER)),
+    );
+    assert_eq!(ticket.accounting_manager, None);
+}
+
+#[tokio::test]
+async fn doesnt_update_status_if_not_expected() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+    let ticket = write_requested_ticket(&db).await;
+
+    let updated = db
+        .update_ticket_status(
+            ticket.id,
+            Status::Confirmed,
+            Status::PaymentCompleted,
+            &StatusUpdateFields::default(),
+        )
+        .await
+        .unwrap();
+    assert!(!updated);
+
+    let ticket = db
+        .get_ticket_by_id(ticket.id, Default::default())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(ticket.status, Status::Requested);
+}
+
+#[tokio::test]
+async fn lets_one_of_racing_updates_win() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    for _ in 0..20 {
+        let ticket = write_requested_ticket(&db).await;
+        let (confirmation, cancellation) =
+            (confirmation(), StatusUpdateFields::default());
+
+        let (confirmed, cancelled) = tokio::join!(
+            db.update_ticket_status(
+                ticket.id,
+                Status::Requested,
+                Status::Confirmed,
+                &confirmation,
+            ),
+            db.update_ticket_status(
+                ticket.id,
+                Status::Requested,
+                Status::Cancelled,
+                &cancellation,
+            ),
+        );
+        let (confirmed, cancelled) = (confirmed.unwrap(), cancelled.unwrap());
+        assert!(confirmed != cancelled, "exactly one update must win");
+
+        let ticket = db
+            .get_ticket_by_id(ticket.id, Default::default())
+            .await
+            .unwrap()
+            .unwrap();
+        if confirmed {
+            assert_eq!(ticket.status, Status::Confirmed);
+            assert_eq!(ticket.price, Some(100.0));
+        } else {
+            assert_eq!(ticket.status, Status::Cancelled);
+            assert_eq!(ticket.price, None);
+        }
+    }
+}
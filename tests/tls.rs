@@ -0,0 +1,79 @@
+pub mod common;
+
+use reqwest::{Certificate, StatusCode};
+
+/// Spawns a server serving HTTPS on the `addr` with a freshly generated
+/// self-signed certificate, returning a client trusting only it.
+async fn spawn_with_tls(
+    addr: &str,
+    redirect_from_port: u16,
+) -> (common::Server, reqwest::Client) {
+    let cert = rcgen::generate_simple_self_signed(["localhost".to_owned()])
+        .expect("failed to generate a certificate");
+    let cert_pem = cert.cert.pem();
+    let key_pem = cert.key_pair.serialize_pem();
+
+    let server = common::Server::spawn(
+        addr,
+        &format!(
+            "[http.tls]\n\
+             cert_path = \"cert.pem\"\n\
+             key_path = \"key.pem\"\n\
+             redirect_from_port = {redirect_from_port}",
+        ),
+        &[
+            ("cert.pem", cert_pem.as_bytes()),
+            ("key.pem", key_pem.as_bytes()),
+        ],
+    )
+    .await;
+
+    let client = reqwest::Client::builder()
+        .tls_built_in_root_certs(false)
+        .add_root_certificate(
+            Certificate::from_pem(cert_pem.as_bytes()).unwrap(),
+        )
+        .build()
+        .unwrap();
+    (server, client)
+}
+
+#[tokio::test]
+async fn serves_https() {
+    let (_server, client) = spawn_with_tls("127.0.0.1:3002", 3003).await;
+
+    let status = client
+        .get("https://localhost:3002/healthz")
+        .send()
+        .await
+        .expect("failed to send a request")
+        .status();
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn redirects_http_to_https() {
+    let (_server, client) = spawn_with_tls("127.0.0.1:3004", 3005).await;
+
+    let resp = client
+        .get("http://localhost:3005/healthz?probe=1")
+        .send()
+        .await
+        .expect("failed to send a request");
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(
+        resp.url().as_str(),
+        "https://localhost:3004/healthz?probe=1"
+    );
+
+    let resp = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .unwrap()
+        .get("http://localhost:3005/healthz")
+        .send()
+        .await
+        .expect("failed to send a request");
+    assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+    assert_eq!(resp.headers()["location"], "https://localhost:3004/healthz");
+}
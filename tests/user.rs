@@ -1,11 +1,18 @@
 pub mod common;
 
-use dubna_internship::api;
+use dubna_internship::{
+    api::{
+        self,
+        validation::{Code, FieldError},
+    },
+    db,
+};
 use reqwest::StatusCode;
 
 #[tokio::test]
 async fn retreieves_current_user() {
-    let user = common::Client::new()
+    let user = common::setup()
+        .await
         .auth("alice", "password")
         .await
         .user()
@@ -18,6 +25,336 @@ async fn retreieves_current_user() {
 
 #[tokio::test]
 async fn fails_when_unauthorized() {
-    let status = common::Client::new().user().await.unwrap_err();
+    let status = common::setup().await.user().await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn rejects_tokens_issued_before_password_change() {
+    let eve = common::setup().await.auth("eve", "password").await;
+
+    eve.change_password("password", "new_password")
+        .await
+        .unwrap();
+
+    let status = eve.user().await.unwrap_err();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let eve = common::Client::new().auth("eve", "new_password").await;
+    assert_eq!(eve.user().await.unwrap().name, "Eve");
+}
+
+#[tokio::test]
+async fn cant_change_password_when_current_is_wrong() {
+    let status = common::setup()
+        .await
+        .auth("alice", "password")
+        .await
+        .change_password("wrong", "new_password")
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn pages_through_users() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let mut names = Vec::new();
+    for offset in [0, 2, 4] {
+        let page = db.get_users_page(offset, 2, None).await.unwrap();
+        names.extend(page.into_iter().map(|u| u.name));
+    }
+    assert_eq!(names, ["Alice", "Bob", "Charlie", "Dave", "Eve"]);
+    assert_eq!(db.get_users_count(None).await.unwrap(), 5);
+}
+
+#[tokio::test]
+async fn filters_users_by_role() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let page = db
+        .get_users_page(0, 10, Some(api::user::Role::Initiator))
+        .await
+        .unwrap();
+    let names = page.into_iter().map(|u| u.name).collect::<Vec<_>>();
+    assert_eq!(names, ["Alice", "Eve"]);
+
+    let count = db
+        .get_users_count(Some(api::user::Role::Initiator))
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+}
+
+fn user(login: &str) -> db::User {
+    db::User {
+        id: db::user::Id::new(),
+        name: "Frank".into(),
+        role: db::user::Role::Initiator,
+        login: login.into(),
+        password_hash: db::user::PasswordHash::new("password"),
+        password_changed_at: ::time::OffsetDateTime::UNIX_EPOCH,
+        email: None,
+    }
+}
+
+#[tokio::test]
+async fn inserts_user() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let frank = user("frank");
+    db.insert_user(&frank).await.unwrap();
+
+    let found = db.get_user_by_id(frank.id).await.unwrap().unwrap();
+    assert_eq!(found.name, "Frank");
+    assert_eq!(found.login, "frank");
+    assert_eq!(found.role, db::user::Role::Initiator);
+    assert_eq!(found.password_hash, frank.password_hash);
+}
+
+#[tokio::test]
+async fn cant_insert_user_with_taken_login() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let err = db.insert_user(&user("alice")).await.unwrap_err();
+    assert!(
+        matches!(err, db::user::InsertUserError::LoginTaken),
+        "expected login to be taken, found {err:?}",
+    );
+}
+
+#[tokio::test]
+async fn updates_user_name_and_role() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let frank = user("frank");
+    db.insert_user(&frank).await.unwrap();
+    db.update_user_name(frank.id, "Francis").await.unwrap();
+    db.update_user_role(frank.id, db::user::Role::AccountingManager)
+        .await
+        .unwrap();
+
+    let found = db.get_user_by_id(frank.id).await.unwrap().unwrap();
+    assert_eq!(found.name, "Francis");
+    assert_eq!(found.role, db::user::Role::AccountingManager);
+}
+
+#[tokio::test]
+async fn deletes_only_unreferenced_users() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let db = common::db().await;
+
+    alice
+        .add_ticket("Ticket 1", "Description 1", 1)
+        .await
+        .unwrap();
+    let err = db.delete_user(api::user::Id::from(1)).await.unwrap_err();
+    assert!(
+        matches!(err, db::user::DeleteUserError::UserReferenced),
+        "expected user to be referenced, found {err:?}",
+    );
+
+    let frank = user("frank");
+    db.insert_user(&frank).await.unwrap();
+    db.delete_user(frank.id).await.unwrap();
+    assert!(db.get_user_by_id(frank.id).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn refuses_deleting_user_with_tickets() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let dave = common::Client::new().auth("dave", "password").await;
+    let db = common::db().await;
+
+    let first = alice.add_ticket("Ticket 1", "Description 1", 1).await;
+    let second = alice.add_ticket("Ticket 2", "Description 2", 1).await;
+    let alice_id = api::user::Id::from(1);
+    assert_eq!(db.count_tickets_for_user(alice_id).await.unwrap(), 2);
+
+    let refused = dave.delete_user_with_tickets(alice_id).await;
+    assert_eq!(refused.code, "USER_HAS_TICKETS");
+    assert_eq!(refused.ticket_count, 2);
+    let latest_first = [second.unwrap().id, first.unwrap().id];
+    assert_eq!(refused.ticket_ids, latest_first);
+    assert!(db.get_user_by_id(alice_id).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn deletes_user_without_tickets() {
+    let _client = common::setup().await;
+    let dave = common::Client::new().auth("dave", "password").await;
+    let db = common::db().await;
+
+    let frank = user("frank");
+    db.insert_user(&frank).await.unwrap();
+    assert_eq!(db.count_tickets_for_user(frank.id).await.unwrap(), 0);
+
+    dave.delete_user(frank.id).await.unwrap();
+    assert!(db.get_user_by_id(frank.id).await.unwrap().is_none());
+    assert_eq!(dave.delete_user(frank.id).await, Err(StatusCode::NOT_FOUND));
+}
+
+#[tokio::test]
+async fn only_admin_deletes_users() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    let db = common::db().await;
+
+    let frank = user("frank");
+    db.insert_user(&frank).await.unwrap();
+
+    assert_eq!(
+        alice.delete_user(frank.id).await,
+        Err(StatusCode::FORBIDDEN)
+    );
+    assert!(db.get_user_by_id(frank.id).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn reports_unique_violation() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let frank = user("frank");
+    db.insert_user(&frank).await.unwrap();
+
+    let err = db
+        .insert_user(&db::User {
+            login: "francis".into(),
+            ..frank
+        })
+        .await
+        .unwrap_err();
+    match err {
+        db::user::InsertUserError::DbError(
+            ref e @ db::Error::ConstraintViolation { ref constraint },
+        ) => {
+            assert_eq!(constraint, "users_pkey");
+            assert_eq!(e.violated_field(), Some("id"));
+        }
+        found => panic!("expected unique violation, found {found:?}"),
+    }
+}
+
+#[tokio::test]
+async fn updates_own_name() {
+    let eve = common::setup().await.auth("eve", "password").await;
+
+    let user = eve.update_profile("Eve Smith").await.unwrap();
+    assert_eq!(user.id, api::user::Id::from(5));
+    assert_eq!(user.name, "Eve Smith");
+    assert_eq!(user.role, api::user::Role::Initiator);
+
+    assert_eq!(eve.user().await.unwrap().name, "Eve Smith");
+}
+
+#[tokio::test]
+async fn rejects_empty_name() {
+    let errors = common::setup()
+        .await
+        .auth("eve", "password")
+        .await
+        .update_invalid_profile("  ")
+        .await;
+    assert_eq!(
+        errors.errors,
+        [FieldError {
+            field: "name".into(),
+            code: Code::MustNotBeEmpty,
+        }],
+    );
+}
+
+#[tokio::test]
+async fn rejects_too_long_name() {
+    let eve = common::setup().await.auth("eve", "password").await;
+
+    let errors = eve
+        .update_invalid_profile(&"a".repeat(api::user::NAME_MAX_LEN + 1))
+        .await;
+    assert_eq!(
+        errors.errors,
+        [FieldError {
+            field: "name".into(),
+            code: Code::TooLong,
+        }],
+    );
+    assert_eq!(eve.user().await.unwrap().name, "Eve");
+}
+
+#[tokio::test]
+async fn cant_update_profile_when_unauthorized() {
+    let status = common::setup()
+        .await
+        .update_profile("Nobody")
+        .await
+        .unwrap_err();
     assert_eq!(status, StatusCode::UNAUTHORIZED);
 }
+
+#[tokio::test]
+async fn sets_email() {
+    let alice = common::setup().await.auth("alice", "password").await;
+    assert_eq!(alice.user().await.unwrap().email, None);
+
+    let user = alice
+        .set_email(" alice@example.com ", "password")
+        .await
+        .unwrap();
+    assert_eq!(user.email.as_deref(), Some("alice@example.com"));
+    assert_eq!(
+        alice.user().await.unwrap().email.as_deref(),
+        Some("alice@example.com"),
+    );
+
+    let ticket = alice.add_ticket("Ticket", "Description", 1).await.unwrap();
+    let listed = alice.get_tickets(0, 10).await.unwrap();
+    assert_eq!(listed.tickets[0].id, ticket.id);
+    assert_eq!(listed.tickets[0].initiator.email, None);
+}
+
+#[tokio::test]
+async fn refuses_setting_email_with_wrong_password() {
+    let alice = common::setup().await.auth("alice", "password").await;
+
+    let status = alice
+        .set_email("alice@example.com", "wrong")
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert_eq!(alice.user().await.unwrap().email, None);
+}
+
+#[tokio::test]
+async fn rejects_malformed_email() {
+    let alice = common::setup().await.auth("alice", "password").await;
+
+    for email in ["", "alice", "@example.com", "alice@", "al ice@example.com"] {
+        let errors = alice.set_invalid_email(email).await;
+        assert_eq!(
+            errors.errors,
+            [FieldError {
+                field: "email".into(),
+                code: Code::InvalidFormat,
+            }],
+            "{email:?}",
+        );
+    }
+
+    let too_long =
+        format!("{}@example.com", "a".repeat(api::user::EMAIL_MAX_LEN));
+    let errors = alice.set_invalid_email(&too_long).await;
+    assert_eq!(
+        errors.errors,
+        [FieldError {
+            field: "email".into(),
+            code: Code::TooLong,
+        }],
+    );
+    assert_eq!(alice.user().await.unwrap().email, None);
+}
@@ -0,0 +1,65 @@
+pub mod common;
+
+use std::time::Duration;
+
+use dubna_internship::api;
+use serde_json::json;
+
+/// Gets the user authenticated with the `token` from the server listening on
+/// the `addr`.
+async fn user(addr: &str, token: &str) -> api::User {
+    reqwest::Client::new()
+        .get(format!("http://{addr}/user"))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn serves_changes_made_through_same_instance() {
+    const ADDR: &str = "127.0.0.1:3029";
+
+    let alice = common::setup().await.auth("alice", "password").await;
+    let _server =
+        common::Server::spawn(ADDR, "[http.user_cache]\nttl = \"1h\"", &[])
+            .await;
+    let token = alice.auth_token.clone().unwrap();
+
+    assert_eq!(user(ADDR, &token).await.name, "Alice");
+
+    reqwest::Client::new()
+        .patch(format!("http://{ADDR}/me"))
+        .bearer_auth(&token)
+        .json(&json!({ "name": "Alicia" }))
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    assert_eq!(user(ADDR, &token).await.name, "Alicia");
+}
+
+#[tokio::test]
+async fn serves_changes_made_elsewhere_once_expired() {
+    const ADDR: &str = "127.0.0.1:3030";
+
+    let alice = common::setup().await.auth("alice", "password").await;
+    let _server =
+        common::Server::spawn(ADDR, "[http.user_cache]\nttl = \"1s\"", &[])
+            .await;
+    let token = alice.auth_token.clone().unwrap();
+
+    assert_eq!(user(ADDR, &token).await.name, "Alice");
+
+    alice.update_profile("Alicia").await.unwrap();
+    assert_eq!(user(ADDR, &token).await.name, "Alice");
+
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(user(ADDR, &token).await.name, "Alicia");
+}
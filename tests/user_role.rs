@@ -0,0 +1,150 @@
+pub mod common;
+
+use dubna_internship::{
+    api::{self, user::Role},
+    db,
+};
+use reqwest::StatusCode;
+use serde_json::json;
+
+#[tokio::test]
+async fn changes_role() {
+    let dave = common::setup().await.auth("dave", "password").await;
+    let alice = common::Client::new().auth("alice", "password").await;
+    let alice_id = api::user::Id::from(1);
+
+    let changed = dave
+        .change_user_role(alice_id, Role::PurchasingManager)
+        .await
+        .unwrap();
+
+    assert_eq!(changed.id, alice_id);
+    assert_eq!(changed.role, Role::PurchasingManager);
+    assert_eq!(alice.user().await.unwrap().role, Role::PurchasingManager);
+
+    let events = common::db()
+        .await
+        .get_events_for_user(alice_id)
+        .await
+        .unwrap();
+    match events.as_slice() {
+        [changed] => {
+            assert_eq!(changed.actor, api::user::Id::from(4));
+            assert_eq!(changed.entity, db::audit::Entity::User(alice_id));
+            assert_eq!(changed.action, "changeRole");
+            assert_eq!(
+                changed.payload,
+                json!({
+                    "op": "changeRole",
+                    "data": {
+                        "role": "PURCHASING_MANAGER",
+                        "previousRole": "INITIATOR",
+                    },
+                }),
+            );
+        }
+        found => panic!("expected one event, found {found:?}"),
+    }
+}
+
+#[tokio::test]
+async fn changes_nothing_when_role_is_same() {
+    let dave = common::setup().await.auth("dave", "password").await;
+    let bob_id = api::user::Id::from(2);
+
+    for _ in 0..2 {
+        let user = dave
+            .change_user_role(bob_id, Role::PurchasingManager)
+            .await
+            .unwrap();
+        assert_eq!(user.role, Role::PurchasingManager);
+    }
+
+    let events = common::db()
+        .await
+        .get_events_for_user(bob_id)
+        .await
+        .unwrap();
+    assert!(events.is_empty(), "{events:?}");
+}
+
+#[tokio::test]
+async fn forbids_non_admins() {
+    let alice = common::setup().await.auth("alice", "password").await;
+
+    let status = alice
+        .change_user_role(api::user::Id::from(1), Role::Admin)
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn rejects_unknown_user() {
+    let dave = common::setup().await.auth("dave", "password").await;
+
+    let status = dave
+        .change_user_role(api::user::Id::new(), Role::Admin)
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn keeps_last_admin() {
+    let dave = common::setup().await.auth("dave", "password").await;
+    let dave_id = api::user::Id::from(4);
+
+    let status = dave
+        .change_user_role(dave_id, Role::Initiator)
+        .await
+        .unwrap_err();
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    dave.change_user_role(api::user::Id::from(1), Role::Admin)
+        .await
+        .unwrap();
+    let demoted = dave.change_user_role(dave_id, Role::Initiator).await;
+    assert_eq!(demoted.unwrap().role, Role::Initiator);
+}
+
+#[tokio::test]
+async fn keeps_last_admin_when_demoting_concurrently() {
+    let dave = common::setup().await.auth("dave", "password").await;
+    let alice = common::Client::new().auth("alice", "password").await;
+    let (alice_id, dave_id) = (api::user::Id::from(1), api::user::Id::from(4));
+    let db = common::db().await;
+
+    for _ in 0..10 {
+        dave.change_user_role(alice_id, Role::Admin).await.unwrap();
+
+        let (by_dave, by_alice) = tokio::join!(
+            dave.change_user_role(alice_id, Role::Initiator),
+            alice.change_user_role(dave_id, Role::Initiator),
+        );
+        // The loser is either no admin anymore, or demotes the last one.
+        match (by_dave, by_alice) {
+            (Ok(_), Err(loser)) | (Err(loser), Ok(_)) => assert!(
+                [StatusCode::FORBIDDEN, StatusCode::CONFLICT].contains(&loser),
+                "{loser}",
+            ),
+            res => panic!("expected exactly one to win, got {res:?}"),
+        }
+
+        let users = db.get_users_by_ids(&[alice_id, dave_id]).await.unwrap();
+        let admins = users.values().filter(|u| u.role == Role::Admin);
+        assert_eq!(admins.count(), 1);
+
+        // Restores the fixture roles for the next round.
+        let login = match users[&dave_id].role {
+            Role::Admin => "dave",
+            _ => "alice",
+        };
+        let admin = common::Client::new().auth(login, "password").await;
+        admin.change_user_role(dave_id, Role::Admin).await.unwrap();
+        admin
+            .change_user_role(alice_id, Role::Initiator)
+            .await
+            .unwrap();
+    }
+}
@@ -0,0 +1,99 @@
+pub mod common;
+
+use std::time::Instant;
+
+use dubna_internship::db::{
+    self,
+    ticket::{TicketBuilder, WriteTicketsError},
+};
+use time::OffsetDateTime;
+
+const TICKETS: usize = 1000;
+
+fn tickets(count: usize) -> Vec<db::Ticket> {
+    let created_at = OffsetDateTime::now_utc();
+    (0..count)
+        .map(|i| {
+            TicketBuilder::new(db::user::Id::from(1))
+                .title(format!("Ticket {i}"))
+                .price((i % 2 == 0).then_some(100.0))
+                .created_at(created_at)
+                .build()
+        })
+        .collect()
+}
+
+async fn tickets_count(db: &db::Client) -> usize {
+    db.get_tickets_count(&Default::default()).await.unwrap()
+}
+
+#[tokio::test]
+async fn writes_all_tickets() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    // Spans several statements of the default batch size.
+    let tickets = tickets(2500);
+    assert_eq!(db.write_tickets(&tickets).await.unwrap(), tickets.len());
+    assert_eq!(tickets_count(&db).await, tickets.len());
+
+    let written = db
+        .get_ticket_by_id(tickets[0].id, Default::default())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(written.title, "Ticket 0");
+    assert_eq!(written.price, Some(100.0));
+}
+
+#[tokio::test]
+async fn writes_no_tickets_if_one_exists() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let tickets = tickets(10);
+    db.write_ticket(&tickets[7]).await.unwrap();
+
+    match db.write_tickets(&tickets).await {
+        Err(WriteTicketsError::IdTaken(id)) => assert_eq!(id, tickets[7].id),
+        res => panic!("expected `IdTaken`, got {res:?}"),
+    }
+    assert_eq!(tickets_count(&db).await, 1);
+}
+
+#[tokio::test]
+async fn writes_no_tickets_if_one_is_repeated() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let mut tickets = tickets(1500);
+    tickets.push(tickets[0].clone());
+
+    match db.write_tickets(&tickets).await {
+        Err(WriteTicketsError::IdTaken(id)) => assert_eq!(id, tickets[0].id),
+        res => panic!("expected `IdTaken`, got {res:?}"),
+    }
+    assert_eq!(tickets_count(&db).await, 0);
+}
+
+#[tokio::test]
+async fn writes_batch_faster_than_one_by_one() {
+    let _client = common::setup().await;
+    let db = common::db().await;
+
+    let started_at = Instant::now();
+    for ticket in tickets(TICKETS) {
+        db.write_ticket(&ticket).await.unwrap();
+    }
+    let one_by_one = started_at.elapsed();
+
+    let started_at = Instant::now();
+    db.write_tickets(&tickets(TICKETS)).await.unwrap();
+    let batch = started_at.elapsed();
+
+    assert_eq!(tickets_count(&db).await, 2 * TICKETS);
+    assert!(
+        batch * 5 < one_by_one,
+        "batch took {batch:?}, while one by one took {one_by_one:?}",
+    );
+}